@@ -0,0 +1,297 @@
+//! Adaptive translation datasets, files, sentences and the adaptiveMtTranslate method.
+//!
+//! https://cloud.google.com/translate/docs/reference/rest/v3/projects.locations.adaptiveMtDatasets
+//!
+//! Adaptive MT only exists on the v3 surface, so these methods always use `ApiVersion::V3`
+//! regardless of the version the client is configured with.
+
+use serde::{Serialize, Deserialize};
+
+use crate::{ApiVersion, Empty, Result, Timestamp, TranslationClient, create_request, delete_request, get_request, post_request};
+
+/// An Adaptive MT Dataset.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AdaptiveMtDataset {
+    /// Required. The resource name of the dataset, in form of
+    /// projects/{project-number-or-id}/locations/{location_id}/adaptiveMtDatasets/{dataset_id}
+    pub name: String,
+    /// The name of the dataset to show in the interface. The name can be up to 32 characters long and can consist
+    /// only of ASCII Latin letters A-Z and a-z, underscores (_), and ASCII digits 0-9.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// The BCP-47 language code of the source language.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_language_code: Option<String>,
+    /// The BCP-47 language code of the target language.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_language_code: Option<String>,
+    /// The number of examples in the dataset.
+    #[serde(skip_serializing)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub example_count: Option<i32>,
+    /// Output only. Timestamp when this dataset was created.
+    #[serde(skip_serializing)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub create_time: Option<Timestamp>,
+    /// Output only. Timestamp when this dataset was last updated.
+    #[serde(skip_serializing)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_time: Option<Timestamp>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ListAdaptiveMtQueryParams {
+    /// Optional. Requested page size. The server may return fewer results than requested.
+    /// If unspecified, the server picks an appropriate default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<usize>,
+    /// Optional. A token identifying a page of results the server should return. Typically, this is the value of
+    /// next_page_token returned from the previous call. The first page is returned if page_token is empty or missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_token: Option<String>,
+    /// Optional. An expression for filtering the results of the request. Filter is not supported yet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+}
+
+/// A list of AdaptiveMtDatasets.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ListAdaptiveMtDatasetsResponse {
+    /// Output only. A list of Adaptive MT datasets.
+    #[serde(default)]
+    pub adaptive_mt_datasets: Vec<AdaptiveMtDataset>,
+    /// Optional. A token to retrieve a page of results. Pass this value in the page_token field
+    /// in the subsequent call to list to retrieve the next page of results.
+    pub next_page_token: Option<String>,
+}
+
+/// An AdaptiveMtFile.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AdaptiveMtFile {
+    /// Required. The resource name of the file, in form of
+    /// projects/{project-number-or-id}/locations/{location_id}/adaptiveMtDatasets/{dataset}/adaptiveMtFiles/{file}
+    pub name: String,
+    /// The file's display name.
+    pub display_name: Option<String>,
+    /// The number of entries that the file contains.
+    pub entry_count: Option<i32>,
+    /// Output only. Timestamp when this file was created.
+    pub create_time: Option<Timestamp>,
+    /// Output only. Timestamp when this file was last updated.
+    pub update_time: Option<Timestamp>,
+}
+
+/// The response for listing all AdaptiveMt files under a given dataset.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ListAdaptiveMtFilesResponse {
+    /// Output only. The Adaptive MT files.
+    #[serde(default)]
+    pub adaptive_mt_files: Vec<AdaptiveMtFile>,
+    /// Optional. A token to retrieve a page of results.
+    pub next_page_token: Option<String>,
+}
+
+/// An AdaptiveMt sentence entry.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AdaptiveMtSentence {
+    /// Required. The resource name of the file, in form of
+    /// projects/{project-number-or-id}/locations/{location_id}/adaptiveMtDatasets/{dataset}/adaptiveMtFiles/{file}/adaptiveMtSentences/{sentence}
+    pub name: String,
+    /// Required. The source sentence.
+    pub source_sentence: String,
+    /// Required. The target sentence.
+    pub target_sentence: String,
+    /// Output only. Timestamp when this sentence was created.
+    pub create_time: Option<Timestamp>,
+    /// Output only. Timestamp when this sentence was last updated.
+    pub update_time: Option<Timestamp>,
+}
+
+/// List AdaptiveMt sentences response.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ListAdaptiveMtSentencesResponse {
+    /// Output only. The list of AdaptiveMtSentences.
+    #[serde(default)]
+    pub adaptive_mt_sentences: Vec<AdaptiveMtSentence>,
+    /// Optional.
+    pub next_page_token: Option<String>,
+}
+
+/// The request for importing an AdaptiveMt file along with its sentences.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportAdaptiveMtFileRequest {
+    /// Inline file source.
+    FileInputSource(FileInputSource),
+    /// Google Cloud Storage file source.
+    GcsInputSource(GcsInputSource),
+}
+
+/// An inlined file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileInputSource {
+    /// Required. The file's mime type.
+    pub mime_type: String,
+    /// Required. The file's byte contents.
+    #[serde(with = "crate::base64_bytes")]
+    pub content: Vec<u8>,
+    /// Required. The file's display name.
+    pub display_name: String,
+}
+
+/// The Google Cloud Storage location for the input file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GcsInputSource {
+    /// Required. Source data URI. For example, gs://my_bucket/my_object.
+    pub input_uri: String,
+}
+
+/// The response for importing an AdaptiveMtFile.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportAdaptiveMtFileResponse {
+    /// Output only. The Adaptive MT file that was imported.
+    pub adaptive_mt_file: Option<AdaptiveMtFile>,
+}
+
+/// The request for sending an AdaptiveMt translation query.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AdaptiveMtTranslateRequest {
+    /// Required. The resource name for the dataset to use for adaptive MT.
+    /// projects/{project}/locations/{location-id}/adaptiveMtDatasets/{dataset}
+    pub dataset: String,
+    /// Required. The content of the input in string format.
+    /// For now only one sentence per request is supported.
+    pub content: Vec<String>,
+}
+
+/// An AdaptiveMtTranslate response.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AdaptiveMtTranslateResponse {
+    /// Output only. The translation.
+    #[serde(default)]
+    pub translations: Vec<AdaptiveMtTranslation>,
+    /// Output only. The translation's language code.
+    pub language_code: Option<String>,
+}
+
+/// An AdaptiveMt translation.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AdaptiveMtTranslation {
+    /// Output only. The translated text.
+    pub translated_text: String,
+}
+
+impl TranslationClient {
+    fn adaptive_mt_location_url(&self, suffix: &str) -> String {
+        format!("{}/{}/projects/{}/locations/{}{}", self.endpoint, ApiVersion::V3.as_str(), self.project_id, self.location_id, suffix)
+    }
+
+    fn adaptive_mt_resource_url(&self, name: &str, suffix: &str) -> String {
+        format!("{}/{}/{}{}", self.endpoint, ApiVersion::V3.as_str(), name, suffix)
+    }
+
+    /// Creates an Adaptive MT dataset.
+    pub async fn create_adaptive_mt_dataset(&self, dataset: &AdaptiveMtDataset) -> Result<AdaptiveMtDataset> {
+        let access_token = self.access_token().await?;
+        create_request(&self.http, &self.adaptive_mt_location_url("/adaptiveMtDatasets"), &access_token, dataset).await
+    }
+
+    /// Deletes an Adaptive MT dataset, including all its entries and associated metadata.
+    pub async fn delete_adaptive_mt_dataset(&self, name: &str) -> Result<()> {
+        let access_token = self.access_token().await?;
+        delete_request::<Empty>(&self.http, &self.adaptive_mt_resource_url(name, ""), &access_token).await.map(|_| ())
+    }
+
+    /// Gets the Adaptive MT dataset.
+    pub async fn get_adaptive_mt_dataset(&self, name: &str) -> Result<AdaptiveMtDataset> {
+        let access_token = self.access_token().await?;
+        get_request(&self.http, &self.adaptive_mt_resource_url(name, ""), &access_token, &Empty).await
+    }
+
+    /// Lists all Adaptive MT datasets for which the caller has read permission.
+    pub async fn list_adaptive_mt_datasets(&self, params: &ListAdaptiveMtQueryParams) -> Result<ListAdaptiveMtDatasetsResponse> {
+        let access_token = self.access_token().await?;
+        get_request(&self.http, &self.adaptive_mt_location_url("/adaptiveMtDatasets"), &access_token, params).await
+    }
+
+    /// Imports an AdaptiveMtFile and adds all of its sentences into the AdaptiveMtDataset named `dataset`.
+    pub async fn import_adaptive_mt_file(&self, dataset: &str, request_body: &ImportAdaptiveMtFileRequest)
+        -> Result<ImportAdaptiveMtFileResponse>
+    {
+        let access_token = self.access_token().await?;
+        create_request(&self.http, &self.adaptive_mt_resource_url(dataset, ":importAdaptiveMtFile"), &access_token, request_body).await
+    }
+
+    /// Deletes an AdaptiveMtFile along with its sentences.
+    pub async fn delete_adaptive_mt_file(&self, name: &str) -> Result<()> {
+        let access_token = self.access_token().await?;
+        delete_request::<Empty>(&self.http, &self.adaptive_mt_resource_url(name, ""), &access_token).await.map(|_| ())
+    }
+
+    /// Gets an AdaptiveMtFile.
+    pub async fn get_adaptive_mt_file(&self, name: &str) -> Result<AdaptiveMtFile> {
+        let access_token = self.access_token().await?;
+        get_request(&self.http, &self.adaptive_mt_resource_url(name, ""), &access_token, &Empty).await
+    }
+
+    /// Lists all AdaptiveMtFiles associated to the AdaptiveMtDataset named `dataset`.
+    pub async fn list_adaptive_mt_files(&self, dataset: &str, params: &ListAdaptiveMtQueryParams) -> Result<ListAdaptiveMtFilesResponse> {
+        let access_token = self.access_token().await?;
+        get_request(&self.http, &self.adaptive_mt_resource_url(dataset, "/adaptiveMtFiles"), &access_token, params).await
+    }
+
+    /// Lists all AdaptiveMtSentences under a given file or dataset named `parent`.
+    pub async fn list_adaptive_mt_sentences(&self, parent: &str, params: &ListAdaptiveMtQueryParams)
+        -> Result<ListAdaptiveMtSentencesResponse>
+    {
+        let access_token = self.access_token().await?;
+        get_request(&self.http, &self.adaptive_mt_resource_url(parent, "/adaptiveMtSentences"), &access_token, params).await
+    }
+
+    /// Translate text using Adaptive MT.
+    pub async fn adaptive_mt_translate(&self, request_body: &AdaptiveMtTranslateRequest) -> Result<AdaptiveMtTranslateResponse> {
+        let access_token = self.access_token().await?;
+        post_request(&self.http, &self.adaptive_mt_location_url(":adaptiveMtTranslate"), &access_token, request_body).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_mt_urls_always_use_v3() {
+        let client = TranslationClient::new("p", "us-central1", "token");
+        assert_eq!(client.adaptive_mt_location_url(":adaptiveMtTranslate"),
+            "https://translation.googleapis.com/v3/projects/p/locations/us-central1:adaptiveMtTranslate");
+    }
+
+    #[test]
+    fn test_import_request_serialization() {
+        let request = ImportAdaptiveMtFileRequest::GcsInputSource(GcsInputSource {
+            input_uri: "gs://bucket/file.tsv".to_string(),
+        });
+        assert_eq!(serde_json::to_value(&request).unwrap(), serde_json::json!({
+            "gcsInputSource": { "inputUri": "gs://bucket/file.tsv" },
+        }));
+    }
+
+    #[test]
+    fn test_list_datasets_empty_response() {
+        let response: ListAdaptiveMtDatasetsResponse = serde_json::from_str("{}").unwrap();
+        assert!(response.adaptive_mt_datasets.is_empty());
+    }
+}
@@ -0,0 +1,251 @@
+//! Reading the output of `batch_translate_text`.
+//!
+//! https://cloud.google.com/translate/docs/advanced/batch-translation#output_files
+//!
+//! A batch translation writes an `index.csv` file under the output URI prefix, with one row per
+//! input file and target language naming the files the results were written to. `OutputIndex`
+//! parses it. For TSV inputs the translations and errors files are TSV files too, read with
+//! `translation_records` and `error_records`; for text and HTML inputs the translations file is
+//! the translated document itself.
+//!
+//! This module only parses the files. Download them from Cloud Storage with the client of your choice.
+//!
+//! ```
+//! use google_translation::batch::{OutputIndex, translation_records};
+//!
+//! # fn run() -> google_translation::Result<()> {
+//! let index: OutputIndex = "gs://in/a.tsv,de,gs://out/in_a_de_translations.tsv,gs://out/in_a_de_errors.tsv\n".parse()?;
+//! let row = &index.rows()[0];
+//! assert_eq!(row.target_language_code, "de");
+//! assert_eq!(row.translations_uri.as_deref(), Some("gs://out/in_a_de_translations.tsv"));
+//!
+//! let tsv = "0\tHello\tHallo\n";
+//! for record in translation_records(tsv.as_bytes()) {
+//!     let record = record?;
+//!     assert_eq!((record.source.as_str(), record.translation.as_str()), ("Hello", "Hallo"));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::BufRead;
+use std::result::Result as StdResult;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+/// A parsed `index.csv` file.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct OutputIndex {
+    rows: Vec<OutputIndexRow>,
+}
+
+/// A row of `index.csv`: the output files of one input file translated into one target language.
+/// Files that weren't written, for example the errors file of a job without errors, are None.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OutputIndexRow {
+    /// The input file, for example "gs://bucket/input/a.tsv".
+    pub input_file: String,
+    pub target_language_code: String,
+    /// The translations, as TSV for TSV inputs, otherwise the translated document.
+    pub translations_uri: Option<String>,
+    /// The errors, as TSV for TSV inputs.
+    pub errors_uri: Option<String>,
+    /// The translations with the glossary applied, if the request had a glossary for the target language.
+    pub glossary_translations_uri: Option<String>,
+    /// The errors applying the glossary, if the request had a glossary for the target language.
+    pub glossary_errors_uri: Option<String>,
+}
+
+impl OutputIndex {
+    /// Parses the contents of `index.csv`. A header row, if present, is skipped.
+    pub fn parse(csv: &str) -> Result<OutputIndex> {
+        let mut rows = Vec::new();
+        for (i, line) in csv.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields = parse_csv_line(line).map_err(|e| Error::Other(format!("index.csv line {}: {}", i + 1, e)))?;
+            if i == 0 && fields.first().map(String::as_str) == Some("input_file") {
+                continue;
+            }
+            if fields.len() < 3 {
+                return Err(Error::Other(format!("index.csv line {}: expected at least 3 columns, got {}", i + 1, fields.len())));
+            }
+            let mut fields = fields.into_iter();
+            let mut next = || fields.next().filter(|field| !field.is_empty());
+            rows.push(OutputIndexRow {
+                input_file: next().unwrap_or_default(),
+                target_language_code: next().unwrap_or_default(),
+                translations_uri: next(),
+                errors_uri: next(),
+                glossary_translations_uri: next(),
+                glossary_errors_uri: next(),
+            });
+        }
+        Ok(OutputIndex { rows })
+    }
+
+    pub fn rows(&self) -> &[OutputIndexRow] {
+        &self.rows
+    }
+
+    /// The rows of the input file `input_file`, one per target language.
+    pub fn for_input_file<'a>(&'a self, input_file: &'a str) -> impl Iterator<Item = &'a OutputIndexRow> + 'a {
+        self.rows.iter().filter(move |row| row.input_file == input_file)
+    }
+
+    /// The rows of the target language `target_language_code`, one per input file.
+    pub fn for_target_language<'a>(&'a self, target_language_code: &'a str) -> impl Iterator<Item = &'a OutputIndexRow> + 'a {
+        self.rows.iter().filter(move |row| row.target_language_code == target_language_code)
+    }
+}
+
+impl FromStr for OutputIndex {
+    type Err = Error;
+
+    fn from_str(csv: &str) -> Result<OutputIndex> {
+        OutputIndex::parse(csv)
+    }
+}
+
+impl IntoIterator for OutputIndex {
+    type Item = OutputIndexRow;
+    type IntoIter = std::vec::IntoIter<OutputIndexRow>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.into_iter()
+    }
+}
+
+/// Splits a CSV line into fields, unquoting quoted fields ("a ""b""" is `a "b"`).
+fn parse_csv_line(line: &str) -> StdResult<Vec<String>, &'static str> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.is_empty() => quoted = true,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            (c, _) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted field");
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+/// A row of a translations TSV file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TranslationRecord {
+    /// The row id from the input file, or the 0-based row number if the input had none.
+    pub id: String,
+    pub source: String,
+    /// The translation without the glossary. Empty if translating the row failed.
+    pub translation: String,
+    /// The translation with the glossary applied, if the request had a glossary for the target language.
+    pub glossary_translation: Option<String>,
+}
+
+/// A row of an errors TSV file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ErrorRecord {
+    /// The row id from the input file, or the 0-based row number if the input had none.
+    pub id: String,
+    pub source: String,
+    /// The error translating the row. May be empty.
+    pub error: String,
+    /// The error applying the glossary, if the request had a glossary for the target language.
+    pub glossary_error: Option<String>,
+}
+
+/// Reads the rows of a translations TSV file.
+pub fn translation_records<R: BufRead>(reader: R) -> impl Iterator<Item = Result<TranslationRecord>> {
+    tsv_records(reader).map(|fields| {
+        let (id, source, translation, glossary_translation) = fields?;
+        Ok(TranslationRecord { id, source, translation: translation.unwrap_or_default(), glossary_translation })
+    })
+}
+
+/// Reads the rows of an errors TSV file.
+pub fn error_records<R: BufRead>(reader: R) -> impl Iterator<Item = Result<ErrorRecord>> {
+    tsv_records(reader).map(|fields| {
+        let (id, source, error, glossary_error) = fields?;
+        Ok(ErrorRecord { id, source, error: error.unwrap_or_default(), glossary_error })
+    })
+}
+
+/// The columns of a TSV output row: id, source and up to two more.
+type TsvRow = (String, String, Option<String>, Option<String>);
+
+/// Reads the non-empty lines of a TSV file with 2 to 4 columns.
+fn tsv_records<R: BufRead>(reader: R) -> impl Iterator<Item = Result<TsvRow>> {
+    reader.lines().enumerate().filter_map(|(i, line)| {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => return Some(Err(Error::IoError(e))),
+        };
+        let line = line.strip_suffix('\r').unwrap_or(&line);
+        if line.is_empty() {
+            return None;
+        }
+        let mut fields = line.split('\t').map(str::to_string);
+        let (id, source) = match (fields.next(), fields.next()) {
+            (Some(id), Some(source)) => (id, source),
+            _ => return Some(Err(Error::Other(format!("TSV line {}: expected at least 2 columns", i + 1)))),
+        };
+        let row = (id, source, fields.next(), fields.next());
+        if fields.next().is_some() {
+            return Some(Err(Error::Other(format!("TSV line {}: expected at most 4 columns", i + 1))));
+        }
+        Some(Ok(row))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_index() {
+        let csv = "input_file,target_language_code,translations_file,errors_file,glossary_translations_file,glossary_errors_file\n\
+            gs://in/a.tsv,de,gs://out/in_a_de_translations.tsv,,gs://out/in_a_de_glossary_translations.tsv,\n\
+            \"gs://in/b,c.html\",fr,gs://out/in_b_c_fr_translations.html,gs://out/in_b_c_fr_errors.html\n";
+        let index: OutputIndex = csv.parse().unwrap();
+        assert_eq!(index.rows().len(), 2);
+        let row = &index.rows()[0];
+        assert_eq!(row.errors_uri, None);
+        assert_eq!(row.glossary_translations_uri.as_deref(), Some("gs://out/in_a_de_glossary_translations.tsv"));
+        let rows: Vec<_> = index.for_target_language("fr").collect();
+        assert_eq!(rows[0].input_file, "gs://in/b,c.html");
+        assert_eq!(rows[0].glossary_errors_uri, None);
+        assert_eq!(index.for_input_file("gs://in/a.tsv").count(), 1);
+
+        assert!(OutputIndex::parse("gs://in/a.tsv,de\n").is_err());
+        assert!(OutputIndex::parse("\"gs://in/a.tsv,de,x\n").is_err());
+    }
+
+    #[test]
+    fn test_tsv_records() {
+        let tsv = "0\tHello\tHallo\tHallo!\r\n\nrow-2\tBye\t\n";
+        let records: Vec<TranslationRecord> = translation_records(tsv.as_bytes()).collect::<Result<_>>().unwrap();
+        assert_eq!(records[0].glossary_translation.as_deref(), Some("Hallo!"));
+        assert_eq!(records[1], TranslationRecord {
+            id: "row-2".to_string(), source: "Bye".to_string(), translation: String::new(), glossary_translation: None,
+        });
+
+        let errors: Vec<ErrorRecord> = error_records("3\tx\tUnsupported language.\n".as_bytes()).collect::<Result<_>>().unwrap();
+        assert_eq!(errors[0].error, "Unsupported language.");
+        assert!(error_records("only-one-column\n".as_bytes()).next().unwrap().is_err());
+    }
+}
@@ -0,0 +1,465 @@
+//! `gtranslate`, a command-line client for the Cloud Translation API.
+//!
+//! Credentials come from Application Default Credentials, and the project from `--project`,
+//! GOOGLE_CLOUD_PROJECT or the credentials themselves. Text is taken from the arguments, or read from standard input if there
+//! are none. Build with `cargo install google-translation --features cli`.
+//!
+//! ```text
+//! gtranslate translate --to de "Hello, world"
+//! echo "Bonjour" | gtranslate detect
+//! gtranslate languages --display-language en
+//! gtranslate glossary create my-glossary --from en --to de --input gs://bucket/glossary.tsv --wait
+//! gtranslate batch submit --from en --to de --to fr --input gs://bucket/in/* --output gs://bucket/out/
+//! gtranslate batch status projects/my-project/locations/us-central1/operations/123
+//! ```
+
+use std::io::{Read, Write};
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use clap::{Args, Parser, Subcommand};
+use serde::Serialize;
+
+use google_translation::*;
+use google_translation::dry_run::DryRun;
+
+#[derive(Parser)]
+#[command(name = "gtranslate", version, about = "Translate text and manage Cloud Translation resources")]
+struct Cli {
+    /// The Google Cloud project to bill. Defaults to the project of the credentials.
+    #[arg(long, env = "GOOGLE_CLOUD_PROJECT", global = true)]
+    project: Option<String>,
+    /// The location to send requests to. Glossaries and batch translations need a region such as us-central1.
+    #[arg(long, env = "GOOGLE_CLOUD_LOCATION", default_value = GLOBAL_LOCATION, global = true)]
+    location: String,
+    /// Print the API's responses as JSON instead of text.
+    #[arg(long, global = true)]
+    json: bool,
+    /// Act as this service account, impersonating it with the Application Default Credentials.
+    #[arg(long, global = true)]
+    impersonate_service_account: Option<String>,
+    /// Print every HTTP request as a curl command, with credentials redacted, to standard error.
+    #[arg(long, global = true)]
+    curl: bool,
+    /// Validate the request and print it as a curl command without sending it or needing credentials.
+    #[arg(long, global = true)]
+    dry_run: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Translate text, printing one translation per line.
+    Translate {
+        /// The texts to translate. Standard input is translated as one text if there are none.
+        texts: Vec<String>,
+        /// The target language.
+        #[arg(long)]
+        to: String,
+        /// The source language, detected if not given.
+        #[arg(long)]
+        from: Option<String>,
+        /// The model, for example "general/nmt" or a custom model's full name.
+        #[arg(long)]
+        model: Option<String>,
+        /// Treat the text as HTML.
+        #[arg(long)]
+        html: bool,
+    },
+    /// Detect the language of text, printing the candidates with their confidence.
+    Detect {
+        /// The text. Standard input is read if it is missing.
+        text: Option<String>,
+    },
+    /// List the supported languages.
+    Languages {
+        /// Include the languages' names in this language.
+        #[arg(long)]
+        display_language: Option<String>,
+    },
+    /// Manage glossaries.
+    #[command(subcommand)]
+    Glossary(GlossaryCommand),
+    /// Run batch translations of files in Cloud Storage.
+    #[command(subcommand)]
+    Batch(BatchCommand),
+}
+
+#[derive(Subcommand)]
+enum GlossaryCommand {
+    /// Create a unidirectional glossary from a TSV or CSV file in Cloud Storage.
+    Create {
+        /// The glossary id.
+        id: String,
+        /// The source language.
+        #[arg(long)]
+        from: String,
+        /// The target language.
+        #[arg(long)]
+        to: String,
+        /// The gs:// URI of the glossary file.
+        #[arg(long)]
+        input: String,
+        #[command(flatten)]
+        wait: Wait,
+    },
+    /// List the glossaries of the project and location.
+    List,
+    /// Delete a glossary.
+    Delete {
+        /// The glossary id or full name.
+        id: String,
+        #[command(flatten)]
+        wait: Wait,
+    },
+}
+
+#[derive(Subcommand)]
+enum BatchCommand {
+    /// Start a batch translation, printing the name of its operation.
+    Submit {
+        /// The source language.
+        #[arg(long)]
+        from: String,
+        /// A target language. Repeat for several, up to 10.
+        #[arg(long, required = true)]
+        to: Vec<String>,
+        /// A gs:// URI or wildcard of input files. Repeat for several.
+        #[arg(long, required = true)]
+        input: Vec<String>,
+        /// The gs:// prefix to write to, ending with "/". It must be empty.
+        #[arg(long)]
+        output: String,
+        #[command(flatten)]
+        wait: Wait,
+    },
+    /// Show the state and progress of a batch translation.
+    Status {
+        /// The operation name printed by `batch submit`.
+        operation: String,
+    },
+}
+
+#[derive(Args)]
+struct Wait {
+    /// Wait for the operation to finish.
+    #[arg(long)]
+    wait: bool,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build().expect("failed to start the Tokio runtime");
+    let result = runtime.block_on(async {
+        let client = client(&cli).await?;
+        run(&client, cli, &mut std::io::stdin(), &mut std::io::stdout()).await
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("gtranslate: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// The client for the global options: one that prints requests instead of sending them with
+/// `--dry-run`, and one authenticated with Application Default Credentials otherwise.
+async fn client(cli: &Cli) -> Result<TranslationClient> {
+    let mut client = if cli.dry_run {
+        let project = cli.project.as_deref()
+            .ok_or_else(|| Error::InvalidRequest("no project, pass --project or set GOOGLE_CLOUD_PROJECT".to_string()))?;
+        TranslationClient::new(project, &cli.location, "dry-run").with_dry_run(DryRun::new())
+            .with_curl_dump(|command| println!("{}", command))
+    } else {
+        let mut credentials = auth::application_default_credentials()?;
+        if let Some(service_account) = &cli.impersonate_service_account {
+            credentials = Arc::new(auth::ImpersonatedCredentials::new(credentials, service_account));
+        }
+        match &cli.project {
+            Some(project) => TranslationClient::with_token_provider(project, &cli.location, credentials),
+            None => TranslationClient::with_discovered_project(&cli.location, credentials).await?,
+        }
+    };
+    if cli.curl && !cli.dry_run {
+        client = client.with_curl_dump(|command| eprintln!("{}", command));
+    }
+    Ok(client)
+}
+
+/// Runs the command with `client`, reading text from `input` if the command needs some and has
+/// none, and printing the results to `output`.
+async fn run(client: &TranslationClient, cli: Cli, input: &mut dyn Read, output: &mut dyn Write) -> Result<()> {
+    let json = cli.json;
+    match cli.command {
+        Command::Translate { texts, to, from, model, html } => {
+            let texts = if texts.is_empty() { vec![read_text(input)?] } else { texts };
+            let response = client.translate_text(&translate_request(texts, to, from, model, html)).await?;
+            print(output, json, &response, || response.translations.iter().map(|translation| translation.translated_text.clone()).collect())
+        }
+        Command::Detect { text } => {
+            let content = match text {
+                Some(text) => text,
+                None => read_text(input)?,
+            };
+            let response = client.detect_language(&detect_request(content)).await?;
+            print(output, json, &response, || response.languages.iter()
+                .map(|language| format!("{}\t{:.2}", language.language_code, language.confidence))
+                .collect())
+        }
+        Command::Languages { display_language } => {
+            let params = GetSupportedLanguagesQueryParams { display_language_code: display_language, model: None };
+            let response = client.get_supported_languages(&params).await?;
+            print(output, json, &response, || response.languages.iter()
+                .map(|language| {
+                    let usage = match (language.support_source, language.support_target) {
+                        (true, true) => "source,target",
+                        (true, false) => "source",
+                        (false, true) => "target",
+                        (false, false) => "",
+                    };
+                    format!("{}\t{}\t{}", language.language_code, language.display_name.as_deref().unwrap_or_default(), usage)
+                })
+                .collect())
+        }
+        Command::Glossary(GlossaryCommand::Create { id, from, to, input, wait }) => {
+            let operation = client.create_glossary(&glossary(client, &id, from, to, input)?).await?;
+            finish(client, output, json, operation, wait.wait).await
+        }
+        Command::Glossary(GlossaryCommand::List) => {
+            let glossaries = list_glossaries(client).await?;
+            print(output, json, &glossaries, || glossaries.iter()
+                .map(|glossary| format!("{}\t{}", glossary.name, glossary.entry_count.unwrap_or_default()))
+                .collect())
+        }
+        Command::Glossary(GlossaryCommand::Delete { id, wait }) => {
+            let operation = client.delete_glossary(glossary_name(client, id)?).await?;
+            finish(client, output, json, operation, wait.wait).await
+        }
+        Command::Batch(BatchCommand::Submit { from, to, input, output: output_uri_prefix, wait }) => {
+            let operation = client.batch_translate_text(&batch_request(from, to, input, output_uri_prefix)).await?;
+            finish(client, output, json, operation, wait.wait).await
+        }
+        Command::Batch(BatchCommand::Status { operation }) => {
+            let operation = client.get_operation(&operation).await?;
+            if json {
+                return print(output, json, &operation, Vec::new);
+            }
+            let metadata: BatchTranslateMetadata = operation.metadata_as()?;
+            let progress = metadata.progress().map(|progress| format!("{:.0}%", progress * 100.0)).unwrap_or_default();
+            let state = metadata.state.as_ref().map_or("UNKNOWN", |state| state.as_str());
+            print(output, json, &operation, || vec![format!("{}\t{}\t{}", operation.name, state, progress)])?;
+            match &operation.error {
+                Some(status) => Err(Error::Other(format!("operation failed: {}", status.message))),
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+fn translate_request(texts: Vec<String>, to: String, from: Option<String>, model: Option<String>, html: bool) -> TranslateTextRequest {
+    TranslateTextRequest {
+        contents: texts,
+        mime_type: Some(if html { MimeType::Html } else { MimeType::Plain }),
+        source_language_code: from,
+        target_language_code: to,
+        model,
+        glossary_config: None,
+        labels: None,
+        transliteration_config: None,
+    }
+}
+
+fn detect_request(content: String) -> DetectLanguageRequest {
+    DetectLanguageRequest { model: None, mime_type: Some(MimeType::Plain), labels: None, content }
+}
+
+/// A unidirectional glossary named `id` in the client's project and location.
+fn glossary(client: &TranslationClient, id: &str, from: String, to: String, input_uri: String) -> Result<Glossary> {
+    let name = client.project_location()?.glossary(id)?;
+    Ok(Glossary::new(name.to_string(), GlossaryInputConfig { gcs_source: GcsSource { input_uri } }, LanguageCodePair::new(from, to)))
+}
+
+/// `id` if it is a full glossary name, or the name of the glossary `id` in the client's project and location.
+fn glossary_name(client: &TranslationClient, id: String) -> Result<String> {
+    if id.starts_with("projects/") {
+        Ok(id)
+    } else {
+        Ok(client.project_location()?.glossary(&id)?.to_string())
+    }
+}
+
+/// All glossaries in the client's project and location, following the pages of the listing.
+async fn list_glossaries(client: &TranslationClient) -> Result<Vec<Glossary>> {
+    let mut glossaries = Vec::new();
+    let mut page_token = None;
+    loop {
+        let params = ListGlossariesQueryParams { page_size: None, page_token, filter: None };
+        let response = client.list_glossaries(&params).await?;
+        glossaries.extend(response.glossaries);
+        match response.next_page_token.filter(|token| !token.is_empty()) {
+            Some(token) => page_token = Some(token),
+            None => return Ok(glossaries),
+        }
+    }
+}
+
+fn batch_request(from: String, to: Vec<String>, input: Vec<String>, output_uri_prefix: String) -> BatchTranslateTextRequest {
+    BatchTranslateTextRequest {
+        source_language_code: from,
+        target_language_codes: to,
+        models: None,
+        input_configs: input.into_iter()
+            .map(|input_uri| BatchTranslateTextInputConfig { mime_type: None, gcs_source: GcsSource { input_uri } })
+            .collect(),
+        output_config: BatchTranslateTextOutputConfig { gcs_destination: GcsDestination { output_uri_prefix } },
+        glossaries: None,
+        labels: None,
+    }
+}
+
+/// Prints the name of `operation`, or waits for it and prints its result if `wait` is set.
+async fn finish(client: &TranslationClient, output: &mut dyn Write, json: bool, operation: Operation, wait: bool) -> Result<()> {
+    if !wait {
+        return print(output, json, &operation, || vec![operation.name.clone()]);
+    }
+    match operation.wait_until_done(client, &PollConfig::default()).await? {
+        Ok(response) => print(output, json, &response, || vec![format!("{} done", operation.name)]),
+        Err(status) => Err(Error::Other(format!("operation {} failed: {}", operation.name, status.message))),
+    }
+}
+
+/// Prints `value` as JSON, or the lines `text` returns.
+fn print<T: Serialize>(output: &mut dyn Write, json: bool, value: &T, text: impl FnOnce() -> Vec<String>) -> Result<()> {
+    if json {
+        writeln!(output, "{}", serde_json::to_string_pretty(value).map_err(Error::SerdeJsonError)?).map_err(Error::IoError)
+    } else {
+        text().iter().try_for_each(|line| writeln!(output, "{}", line)).map_err(Error::IoError)
+    }
+}
+
+/// Reads a text from `input`, without the final line breaks.
+fn read_text(input: &mut dyn Read) -> Result<String> {
+    let mut text = String::new();
+    input.read_to_string(&mut text).map_err(Error::IoError)?;
+    Ok(text.trim_end_matches('\n').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use google_translation::transport::MockTransport;
+
+    use super::*;
+
+    /// Parses `args` and runs them with a client that sends requests to `transport`, returning
+    /// what the command printed.
+    async fn run_args(transport: &Arc<MockTransport>, args: &[&str], input: &str) -> Result<String> {
+        let cli = Cli::try_parse_from(std::iter::once("gtranslate").chain(args.iter().copied())).unwrap();
+        let client = TranslationClient::new("p", "us-central1", "token").with_transport(transport.clone());
+        let mut output = Vec::new();
+        run(&client, cli, &mut input.as_bytes(), &mut output).await?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_translate() {
+        let transport = Arc::new(MockTransport::with_response(200, r#"{"translations": [{"translatedText": "Hallo"}, {"translatedText": "Welt"}]}"#));
+        let output = run_args(&transport, &["translate", "--to", "de", "--from", "en", "--html", "Hello", "World"], "").await.unwrap();
+        assert_eq!(output, "Hallo\nWelt\n");
+        let request = &transport.requests()[0];
+        assert_eq!(request.uri, "https://translation.googleapis.com/v3beta1/projects/p/locations/us-central1:translateText");
+        assert_eq!(request.json(), serde_json::json!({
+            "contents": ["Hello", "World"], "mimeType": "text/html", "sourceLanguageCode": "en", "targetLanguageCode": "de"
+        }));
+
+        // Without texts, standard input is translated as one text.
+        let output = run_args(&transport, &["translate", "--to", "de", "--json"], "Hello\nWorld\n").await.unwrap();
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&output).unwrap()["translations"][1]["translatedText"], "Welt");
+        assert_eq!(transport.requests()[1].json()["contents"], serde_json::json!(["Hello\nWorld"]));
+    }
+
+    #[tokio::test]
+    async fn test_detect() {
+        let transport = Arc::new(MockTransport::with_response(200,
+            r#"{"languages": [{"languageCode": "fr", "confidence": 0.981}, {"languageCode": "it", "confidence": 0.1}]}"#));
+        assert_eq!(run_args(&transport, &["detect"], "Bonjour\n").await.unwrap(), "fr\t0.98\nit\t0.10\n");
+        assert_eq!(run_args(&transport, &["detect", "Ciao"], "").await.unwrap(), "fr\t0.98\nit\t0.10\n");
+        let requests = transport.requests();
+        assert_eq!(requests[0].uri, "https://translation.googleapis.com/v3beta1/projects/p/locations/us-central1:detectLanguage");
+        assert_eq!(requests[0].json(), serde_json::json!({"content": "Bonjour", "mimeType": "text/plain"}));
+        assert_eq!(requests[1].json()["content"], "Ciao");
+    }
+
+    #[tokio::test]
+    async fn test_glossary() {
+        let transport = Arc::new(MockTransport::new(|request| match (request.method.as_str(), request.uri.contains("pageToken=2")) {
+            ("GET", false) => (200, r#"{"glossaries": [{"name": "projects/p/locations/us-central1/glossaries/a",
+                "inputConfig": {"gcsSource": {"inputUri": "gs://b/a.tsv"}}, "entryCount": 3}], "nextPageToken": "2"}"#.to_string()),
+            ("GET", true) => (200, r#"{"glossaries": [{"name": "projects/p/locations/us-central1/glossaries/b",
+                "inputConfig": {"gcsSource": {"inputUri": "gs://b/b.tsv"}}}]}"#.to_string()),
+            _ => (200, r#"{"name": "projects/p/locations/us-central1/operations/1", "metadata": {}}"#.to_string()),
+        }));
+
+        let output = run_args(&transport, &["glossary", "create", "g", "--from", "en", "--to", "de", "--input", "gs://b/g.tsv"], "").await.unwrap();
+        assert_eq!(output, "projects/p/locations/us-central1/operations/1\n");
+        let request = &transport.requests()[0];
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.uri, "https://translation.googleapis.com/v3beta1/projects/p/locations/us-central1/glossaries");
+        assert_eq!(request.json()["name"], "projects/p/locations/us-central1/glossaries/g");
+        assert_eq!(request.json()["inputConfig"]["gcsSource"]["inputUri"], "gs://b/g.tsv");
+        assert_eq!(request.json()["languagePair"], serde_json::json!({"sourceLanguageCode": "en", "targetLanguageCode": "de"}));
+
+        // Every page of the listing is printed.
+        let output = run_args(&transport, &["glossary", "list"], "").await.unwrap();
+        assert_eq!(output, "projects/p/locations/us-central1/glossaries/a\t3\nprojects/p/locations/us-central1/glossaries/b\t0\n");
+        assert_eq!(transport.requests().len(), 3);
+
+        run_args(&transport, &["glossary", "delete", "g"], "").await.unwrap();
+        run_args(&transport, &["glossary", "delete", "projects/q/locations/us-central1/glossaries/h"], "").await.unwrap();
+        let requests = transport.requests();
+        assert_eq!(requests[3].method, "DELETE");
+        assert_eq!(requests[3].uri, "https://translation.googleapis.com/v3beta1/projects/p/locations/us-central1/glossaries/g");
+        assert_eq!(requests[4].uri, "https://translation.googleapis.com/v3beta1/projects/q/locations/us-central1/glossaries/h");
+    }
+
+    #[tokio::test]
+    async fn test_batch() {
+        let transport = Arc::new(MockTransport::with_response(200, r#"{"name": "projects/p/locations/us-central1/operations/1", "metadata": {}}"#));
+        let output = run_args(&transport, &["batch", "submit", "--from", "en", "--to", "de", "--to", "fr",
+            "--input", "gs://b/in/a.txt", "--input", "gs://b/in/b.txt", "--output", "gs://b/out/"], "").await.unwrap();
+        assert_eq!(output, "projects/p/locations/us-central1/operations/1\n");
+        let request = &transport.requests()[0];
+        assert_eq!(request.uri, "https://translation.googleapis.com/v3beta1/projects/p/locations/us-central1:batchTranslateText");
+        assert_eq!(request.json(), serde_json::json!({
+            "sourceLanguageCode": "en",
+            "targetLanguageCodes": ["de", "fr"],
+            "inputConfigs": [{"gcsSource": {"inputUri": "gs://b/in/a.txt"}}, {"gcsSource": {"inputUri": "gs://b/in/b.txt"}}],
+            "outputConfig": {"gcsDestination": {"outputUriPrefix": "gs://b/out/"}}
+        }));
+
+        let transport = Arc::new(MockTransport::with_response(200, r#"{"name": "projects/p/locations/us-central1/operations/1",
+            "metadata": {"state": "RUNNING", "translatedCharacters": "40", "failedCharacters": "10", "totalCharacters": "200"}}"#));
+        let output = run_args(&transport, &["batch", "status", "projects/p/locations/us-central1/operations/1"], "").await.unwrap();
+        assert_eq!(output, "projects/p/locations/us-central1/operations/1\tRUNNING\t25%\n");
+        assert_eq!(transport.requests()[0].uri, "https://translation.googleapis.com/v3beta1/projects/p/locations/us-central1/operations/1");
+
+        let transport = Arc::new(MockTransport::with_response(200, r#"{"name": "projects/p/locations/us-central1/operations/1", "done": true,
+            "metadata": {"state": "FAILED"}, "error": {"code": 3, "message": "No input files."}}"#));
+        let error = run_args(&transport, &["batch", "status", "projects/p/locations/us-central1/operations/1"], "").await.unwrap_err();
+        assert_eq!(error.to_string(), "operation failed: No input files.");
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_needs_project() {
+        let mut cli = Cli::try_parse_from(["gtranslate", "--dry-run", "detect", "Hallo"]).unwrap();
+        cli.project = None;
+        assert!(matches!(client(&cli).await, Err(Error::InvalidRequest(_))));
+
+        cli.project = Some("p".to_string());
+        let client = client(&cli).await.unwrap();
+        let mut output = Vec::new();
+        run(&client, cli, &mut "".as_bytes(), &mut output).await.unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "und\t0.00\n");
+    }
+}
@@ -0,0 +1,121 @@
+//! A synchronous wrapper around `TranslationClient` for programs without an async runtime.
+//!
+//! Each `BlockingClient` owns a single-threaded tokio runtime and drives the async client on it,
+//! similar to reqwest's blocking module. Don't use it from within an async context.
+
+use std::result::Result as StdResult;
+use std::time::Duration;
+
+use crate::*;
+
+/// A blocking `TranslationClient`.
+pub struct BlockingClient {
+    client: TranslationClient,
+    runtime: tokio::runtime::Runtime,
+}
+
+macro_rules! blocking_methods {
+    ($($(#[$attr:meta])* fn $name:ident(&self $(, $arg:ident: $arg_ty:ty)*) -> $ret:ty;)*) => {
+        $(
+            $(#[$attr])*
+            pub fn $name(&self $(, $arg: $arg_ty)*) -> Result<$ret> {
+                self.runtime.block_on(self.client.$name($($arg),*))
+            }
+        )*
+    };
+}
+
+impl BlockingClient {
+    /// Wraps `client`, creating the runtime used to drive it.
+    pub fn new(client: TranslationClient) -> Result<BlockingClient> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(Error::IoError)?;
+        Ok(BlockingClient { client, runtime })
+    }
+
+    /// The wrapped async client.
+    pub fn client(&self) -> &TranslationClient {
+        &self.client
+    }
+
+    blocking_methods! {
+        /// Detects the language of text within a request.
+        fn detect_language(&self, request_body: &DetectLanguageRequest) -> DetectLanguageResponse;
+        /// Returns a list of supported languages for translation.
+        fn get_supported_languages(&self, query_params: &GetSupportedLanguagesQueryParams) -> SupportedLanguages;
+        /// Translates input text and returns translated text.
+        fn translate_text(&self, request_body: &TranslateTextRequest) -> TranslateTextResponse;
+        /// Translates a large volume of text in asynchronous batch mode.
+        fn batch_translate_text(&self, request_body: &BatchTranslateTextRequest) -> Operation;
+        /// Translates documents in synchronous mode.
+        fn translate_document(&self, request_body: &TranslateDocumentRequest) -> TranslateDocumentResponse;
+        /// Translates a large volume of documents in asynchronous batch mode.
+        fn batch_translate_document(&self, request_body: &BatchTranslateDocumentRequest) -> Operation;
+        /// Starts asynchronous cancellation on a long-running operation.
+        fn cancel_operation(&self, name: impl AsRef<str>) -> ();
+        /// Deletes a long-running operation.
+        fn delete_operation(&self, name: impl AsRef<str>) -> ();
+        /// Gets the latest state of a long-running operation.
+        fn get_operation(&self, name: impl AsRef<str>) -> Operation;
+        /// Lists operations that match the specified filter in the request.
+        fn list_operations(&self, params: &ListOperationsQueryParams) -> ListOperationsResponse;
+        /// Waits for the specified long-running operation until it is done or reaches at most a specified timeout.
+        fn wait_operation(&self, name: impl AsRef<str>, request_body: &WaitOperationRequestBody) -> Operation;
+        /// Creates a glossary and returns the long-running operation.
+        fn create_glossary(&self, glossary: &Glossary) -> Operation;
+        /// Deletes a glossary, or cancels glossary construction if the glossary isn't created yet.
+        fn delete_glossary(&self, name: impl AsRef<str>) -> Operation;
+        /// Gets a glossary.
+        fn get_glossary(&self, name: impl AsRef<str>) -> Glossary;
+        /// Lists glossaries in the client's project and location.
+        fn list_glossaries(&self, params: &ListGlossariesQueryParams) -> ListGlossariesResponse;
+    }
+
+    /// Blocks until `operation` is done, see `Operation::wait_until_done`.
+    pub fn wait_until_done<M, R>(&self, operation: &Operation<M, R>, poll_config: &PollConfig) -> Result<StdResult<R, Status>>
+        where M: serde::de::DeserializeOwned, R: serde::de::DeserializeOwned
+    {
+        self.runtime.block_on(operation.wait_until_done(&self.client, poll_config))
+    }
+
+    /// Cancels `operation` and blocks until it has ended, see `Operation::cancel_and_wait`.
+    pub fn cancel_and_wait<M, R>(&self, operation: &Operation<M, R>, deadline: Duration) -> Result<CancelOutcome<R>>
+        where M: serde::de::DeserializeOwned, R: serde::de::DeserializeOwned
+    {
+        self.runtime.block_on(operation.cancel_and_wait(&self.client, deadline))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocking_detect_language() {
+        use hyper::service::{make_service_fn, service_fn};
+        let server_runtime = tokio::runtime::Runtime::new().unwrap();
+        let endpoint = server_runtime.block_on(async {
+            let make_service = make_service_fn(|_| async {
+                Ok::<_, hyper::Error>(service_fn(|_| async {
+                    Ok::<_, hyper::Error>(hyper::Response::new(hyper::Body::from(
+                        r#"{"languages": [{"languageCode": "zh-CN", "confidence": 1.0}]}"#
+                    )))
+                }))
+            });
+            let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+            let endpoint = format!("http://{}", server.local_addr());
+            tokio::spawn(server);
+            endpoint
+        });
+        let client = BlockingClient::new(TranslationClient::new("p", "global", "token").with_endpoint(&endpoint)).unwrap();
+        let response = client.detect_language(&DetectLanguageRequest {
+            model: None,
+            mime_type: None,
+            labels: None,
+            content: "我是谁是我".to_string(),
+        }).unwrap();
+        assert_eq!(response.languages[0].language_code, "zh-CN");
+    }
+}
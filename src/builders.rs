@@ -0,0 +1,262 @@
+//! Builders for request structs, so optional fields don't have to be spelled out as `None`.
+//!
+//! ```no_run
+//! # use google_translation::*;
+//! let request = TranslateTextRequest::builder()
+//!     .content("Hello world")
+//!     .target("de")
+//!     .mime_type(MimeType::Plain)
+//!     .build()
+//!     .unwrap();
+//! ```
+//!
+//! `build()` returns `Error::InvalidRequest` naming the first required field that wasn't set.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::*;
+use crate::adaptive_mt::{AdaptiveMtTranslateRequest, ListAdaptiveMtQueryParams};
+
+macro_rules! define_builder {
+    ($(
+        $(#[$meta:meta])*
+        $builder:ident => $target:ident {
+            required { $($req:ident: $req_ty:ty),* $(,)? }
+            optional { $($opt:ident: $opt_ty:ty),* $(,)? }
+        }
+    )*) => {
+        $(
+            $(#[$meta])*
+            #[derive(Default)]
+            pub struct $builder {
+                $($req: Option<$req_ty>,)*
+                $($opt: Option<$opt_ty>,)*
+            }
+
+            impl $builder {
+                $(
+                    pub fn $req(mut self, $req: impl Into<$req_ty>) -> Self {
+                        self.$req = Some($req.into());
+                        self
+                    }
+                )*
+                $(
+                    pub fn $opt(mut self, $opt: impl Into<$opt_ty>) -> Self {
+                        self.$opt = Some($opt.into());
+                        self
+                    }
+                )*
+
+                pub fn build(self) -> Result<$target> {
+                    Ok($target {
+                        $($req: self.$req.ok_or_else(|| Error::InvalidRequest(
+                            concat!(stringify!($target), ".", stringify!($req), " is required").to_string()
+                        ))?,)*
+                        $($opt: self.$opt,)*
+                    })
+                }
+            }
+
+            impl $target {
+                pub fn builder() -> $builder {
+                    $builder::default()
+                }
+            }
+        )*
+    };
+}
+
+define_builder! {
+    /// Builds a `DetectLanguageRequest`.
+    DetectLanguageRequestBuilder => DetectLanguageRequest {
+        required { content: String }
+        optional { model: String, mime_type: MimeType, labels: Labels }
+    }
+
+    /// Builds a `GetSupportedLanguagesQueryParams`.
+    GetSupportedLanguagesQueryParamsBuilder => GetSupportedLanguagesQueryParams {
+        required {}
+        optional { display_language_code: String, model: String }
+    }
+
+    /// Builds a `TranslateTextRequest`.
+    TranslateTextRequestBuilder => TranslateTextRequest {
+        required { contents: Vec<String>, target_language_code: String }
+        optional {
+            mime_type: MimeType,
+            source_language_code: String,
+            model: String,
+            glossary_config: TranslateTextGlossaryConfig,
+            labels: Labels,
+            transliteration_config: TransliterationConfig,
+        }
+    }
+
+    /// Builds a `BatchTranslateTextRequest`.
+    BatchTranslateTextRequestBuilder => BatchTranslateTextRequest {
+        required {
+            source_language_code: String,
+            target_language_codes: Vec<String>,
+            input_configs: Vec<BatchTranslateTextInputConfig>,
+            output_config: BatchTranslateTextOutputConfig,
+        }
+        optional {
+            models: HashMap<String, String>,
+            glossaries: HashMap<String, TranslateTextGlossaryConfig>,
+            labels: Labels,
+        }
+    }
+
+    /// Builds a `TranslateDocumentRequest`.
+    TranslateDocumentRequestBuilder => TranslateDocumentRequest {
+        required { target_language_code: String, document_input_config: DocumentInputConfig }
+        optional {
+            source_language_code: String,
+            document_output_config: DocumentOutputConfig,
+            model: String,
+            glossary_config: TranslateTextGlossaryConfig,
+            labels: Labels,
+            customized_attribution: String,
+            is_translate_native_pdf_only: bool,
+            enable_shadow_removal_native_pdf: bool,
+        }
+    }
+
+    /// Builds a `BatchTranslateDocumentRequest`.
+    BatchTranslateDocumentRequestBuilder => BatchTranslateDocumentRequest {
+        required {
+            source_language_code: String,
+            target_language_codes: Vec<String>,
+            input_configs: Vec<BatchDocumentInputConfig>,
+            output_config: BatchDocumentOutputConfig,
+        }
+        optional {
+            models: HashMap<String, String>,
+            glossaries: HashMap<String, TranslateTextGlossaryConfig>,
+            format_conversions: HashMap<String, String>,
+        }
+    }
+
+    /// Builds a `ListOperationsQueryParams`.
+    ListOperationsQueryParamsBuilder => ListOperationsQueryParams {
+        required {}
+        optional { filter: String, page_size: usize, page_token: String }
+    }
+
+    /// Builds a `ListGlossariesQueryParams`.
+    ListGlossariesQueryParamsBuilder => ListGlossariesQueryParams {
+        required {}
+        optional { page_size: usize, page_token: String, filter: String }
+    }
+
+    /// Builds a `WaitOperationRequestBody`.
+    WaitOperationRequestBodyBuilder => WaitOperationRequestBody {
+        required {}
+        optional { timeout: Duration }
+    }
+
+    /// Builds an `AdaptiveMtTranslateRequest`.
+    AdaptiveMtTranslateRequestBuilder => AdaptiveMtTranslateRequest {
+        required { dataset: String, content: Vec<String> }
+        optional {}
+    }
+
+    /// Builds a `ListAdaptiveMtQueryParams`.
+    ListAdaptiveMtQueryParamsBuilder => ListAdaptiveMtQueryParams {
+        required {}
+        optional { page_size: usize, page_token: String, filter: String }
+    }
+}
+
+impl TranslateTextRequestBuilder {
+    /// Appends one string to `contents`.
+    pub fn content(mut self, content: impl Into<String>) -> Self {
+        self.contents.get_or_insert_with(Vec::new).push(content.into());
+        self
+    }
+
+    /// Shorthand for `source_language_code`.
+    pub fn source(self, source_language_code: impl Into<String>) -> Self {
+        self.source_language_code(source_language_code)
+    }
+
+    /// Shorthand for `target_language_code`.
+    pub fn target(self, target_language_code: impl Into<String>) -> Self {
+        self.target_language_code(target_language_code)
+    }
+}
+
+impl BatchTranslateTextRequestBuilder {
+    /// Appends one language code to `target_language_codes`.
+    pub fn target_language(mut self, target_language_code: impl Into<String>) -> Self {
+        self.target_language_codes.get_or_insert_with(Vec::new).push(target_language_code.into());
+        self
+    }
+
+    /// Appends one input config to `input_configs`.
+    pub fn input_config(mut self, input_config: BatchTranslateTextInputConfig) -> Self {
+        self.input_configs.get_or_insert_with(Vec::new).push(input_config);
+        self
+    }
+}
+
+impl BatchTranslateDocumentRequestBuilder {
+    /// Appends one language code to `target_language_codes`.
+    pub fn target_language(mut self, target_language_code: impl Into<String>) -> Self {
+        self.target_language_codes.get_or_insert_with(Vec::new).push(target_language_code.into());
+        self
+    }
+
+    /// Appends one input config to `input_configs`.
+    pub fn input_config(mut self, input_config: BatchDocumentInputConfig) -> Self {
+        self.input_configs.get_or_insert_with(Vec::new).push(input_config);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate_text_request_builder() {
+        let request = TranslateTextRequest::builder()
+            .content("Hello")
+            .content("world")
+            .target("de")
+            .mime_type(MimeType::Plain)
+            .build()
+            .unwrap();
+        assert_eq!(request.contents, vec!["Hello", "world"]);
+        assert_eq!(request.target_language_code, "de");
+        assert!(request.source_language_code.is_none());
+    }
+
+    #[test]
+    fn test_missing_required_field() {
+        match TranslateTextRequest::builder().content("Hello").build() {
+            Err(Error::InvalidRequest(message)) => assert_eq!(message, "TranslateTextRequest.target_language_code is required"),
+            _ => panic!("expected InvalidRequest"),
+        }
+    }
+
+    #[test]
+    fn test_batch_request_builder() {
+        let request = BatchTranslateTextRequest::builder()
+            .source_language_code("en")
+            .target_language("de")
+            .target_language("fr")
+            .input_config(BatchTranslateTextInputConfig {
+                mime_type: None,
+                gcs_source: GcsSource { input_uri: "gs://input/a.tsv".to_string() },
+            })
+            .output_config(BatchTranslateTextOutputConfig {
+                gcs_destination: GcsDestination { output_uri_prefix: "gs://output/".to_string() },
+            })
+            .build()
+            .unwrap();
+        assert_eq!(request.target_language_codes, vec!["de", "fr"]);
+        assert_eq!(request.input_configs.len(), 1);
+    }
+}
@@ -0,0 +1,464 @@
+//! Online translation and language detection of many short strings with bounded parallelism.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::stream::{Stream, StreamExt, TryStreamExt};
+
+use crate::*;
+
+/// Options for `TranslationClient::bulk_translate`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BulkTranslateConfig {
+    pub source_language_code: Option<String>,
+    pub mime_type: Option<MimeType>,
+    pub model: Option<String>,
+    /// The number of translateText requests in flight at once.
+    pub concurrency: usize,
+    /// The maximum number of strings per request.
+    pub max_contents: usize,
+    /// The maximum number of codepoints per request. A single longer string is sent on its own,
+    /// and is split further if the client has chunking enabled.
+    pub max_codepoints: usize,
+    /// How long a request waits for more strings once it has its first. With None, a request is
+    /// sent only when it is full or the input ends, which suits finite inputs; set it for
+    /// continuous streams so strings that arrive slowly are still translated promptly.
+    pub max_delay: Option<Duration>,
+    /// Sends each distinct string of a request once and copies its translation to every position
+    /// it appeared at, so repeated strings are only billed once per request.
+    pub deduplicate: bool,
+}
+
+impl Default for BulkTranslateConfig {
+    fn default() -> Self {
+        BulkTranslateConfig {
+            source_language_code: None,
+            mime_type: None,
+            model: None,
+            concurrency: 4,
+            max_contents: chunking::MAX_CONTENTS,
+            max_codepoints: chunking::MAX_CODEPOINTS,
+            max_delay: None,
+            deduplicate: false,
+        }
+    }
+}
+
+/// Options for `TranslationClient::detect_many`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BulkDetectConfig {
+    pub mime_type: Option<MimeType>,
+    pub model: Option<String>,
+    /// The number of detectLanguage requests in flight at once.
+    pub concurrency: usize,
+}
+
+impl Default for BulkDetectConfig {
+    fn default() -> Self {
+        BulkDetectConfig { mime_type: None, model: None, concurrency: 4 }
+    }
+}
+
+impl TranslationClient {
+    /// Translates every string of `texts` into `target_language_code`, yielding the translations in input order.
+    ///
+    /// Strings are packed into requests of up to `config.max_contents` strings and `config.max_codepoints`
+    /// codepoints, and up to `config.concurrency` requests run at once. The stream ends after the first error.
+    /// For very large volumes stored in Cloud Storage, `batch_translate_text` is cheaper.
+    ///
+    /// ```no_run
+    /// # async fn run(client: google_translation::TranslationClient) -> google_translation::Result<()> {
+    /// use futures::stream::{self, TryStreamExt};
+    /// use google_translation::BulkTranslateConfig;
+    ///
+    /// let texts = vec!["Hello", "Goodbye"];
+    /// let translations: Vec<String> = client
+    ///     .bulk_translate(stream::iter(texts), "de", &BulkTranslateConfig::default())
+    ///     .try_collect()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn bulk_translate<'a, S>(&'a self, texts: S, target_language_code: impl AsRef<str>, config: &BulkTranslateConfig)
+        -> impl Stream<Item = Result<String>> + 'a
+        where S: Stream + 'a, S::Item: Into<String>
+    {
+        self.translate_stream(texts, target_language_code, config).map_ok(|translation| translation.translated_text)
+    }
+
+    /// Like `bulk_translate`, but yields the full `Translation` of every string, for example to
+    /// read the detected source language.
+    ///
+    /// This is the building block for translating a continuous stream such as messages from a
+    /// queue: with `config.max_delay` set, strings are sent once a request is full or its first
+    /// string has waited that long. Translations come out in input order, and no more than
+    /// `config.concurrency` requests are in flight, so a slow consumer slows down reading the input.
+    ///
+    /// ```no_run
+    /// # async fn run(client: google_translation::TranslationClient,
+    /// #     messages: impl futures::Stream<Item = String>) -> google_translation::Result<()> {
+    /// use std::time::Duration;
+    ///
+    /// use futures::stream::TryStreamExt;
+    /// use google_translation::BulkTranslateConfig;
+    ///
+    /// let config = BulkTranslateConfig { max_contents: 100, max_delay: Some(Duration::from_millis(200)), ..BulkTranslateConfig::default() };
+    /// let translations = client.translate_stream(messages, "en", &config);
+    /// futures::pin_mut!(translations);
+    /// while let Some(translation) = translations.try_next().await? {
+    ///     println!("{}", translation.translated_text);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn translate_stream<'a, S>(&'a self, texts: S, target_language_code: impl AsRef<str>, config: &BulkTranslateConfig)
+        -> impl Stream<Item = Result<Translation>> + 'a
+        where S: Stream + 'a, S::Item: Into<String>
+    {
+        assert!(config.concurrency > 0, "concurrency must be positive");
+        let target_language_code = target_language_code.as_ref().to_string();
+        let concurrency = config.concurrency;
+        let config = config.clone();
+        let batches = Batches::new(texts.map(Into::into), config.max_contents, config.max_codepoints, config.max_delay,
+            self.http.runtime.clone());
+        batches
+            .map(move |contents| {
+                let (contents, positions) = if config.deduplicate {
+                    let (unique, positions) = deduplicate(contents);
+                    (unique, Some(positions))
+                } else {
+                    (contents, None)
+                };
+                let request = TranslateTextRequest {
+                    contents,
+                    mime_type: config.mime_type.clone(),
+                    source_language_code: config.source_language_code.clone(),
+                    target_language_code: target_language_code.clone(),
+                    model: config.model.clone(),
+                    glossary_config: None,
+                    labels: None,
+                    transliteration_config: None,
+                };
+                async move {
+                    let response = self.translate_text(&request).await?;
+                    if response.translations.len() != request.contents.len() {
+                        return Err(Error::Other(format!("translateText returned {} translations for {} strings",
+                            response.translations.len(), request.contents.len())));
+                    }
+                    Ok(match positions {
+                        Some(positions) => positions.into_iter().map(|i| response.translations[i].clone()).collect(),
+                        None => response.translations,
+                    })
+                }
+            })
+            .buffered(concurrency)
+            .scan(false, |failed, result| {
+                let item = if *failed {
+                    None
+                } else {
+                    *failed = result.is_err();
+                    Some(match result {
+                        Ok(translations) => futures::stream::iter(translations.into_iter().map(Ok)).left_stream(),
+                        Err(error) => futures::stream::once(async { Err(error) }).right_stream(),
+                    })
+                };
+                async { item }
+            })
+            .flatten()
+    }
+
+    /// Detects the most likely language of every string of `texts`, yielding them in input order.
+    ///
+    /// detectLanguage takes one string per request; up to `config.concurrency` requests run at once.
+    /// The stream ends after the first error.
+    ///
+    /// ```no_run
+    /// # async fn run(client: google_translation::TranslationClient) -> google_translation::Result<()> {
+    /// use futures::stream::{self, TryStreamExt};
+    /// use google_translation::BulkDetectConfig;
+    ///
+    /// let tickets = vec!["My order hasn't arrived", "Mi pedido no ha llegado"];
+    /// let languages: Vec<_> = client
+    ///     .detect_many(stream::iter(tickets), &BulkDetectConfig::default())
+    ///     .try_collect()
+    ///     .await?;
+    /// println!("{}", languages[1].language_code);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn detect_many<'a, S>(&'a self, texts: S, config: &BulkDetectConfig)
+        -> impl Stream<Item = Result<DetectLanguageItem>> + 'a
+        where S: Stream + 'a, S::Item: Into<String>
+    {
+        assert!(config.concurrency > 0, "concurrency must be positive");
+        let config = config.clone();
+        let concurrency = config.concurrency;
+        texts
+            .map(move |text| {
+                let request = DetectLanguageRequest {
+                    model: config.model.clone(),
+                    mime_type: config.mime_type.clone(),
+                    labels: None,
+                    content: text.into(),
+                };
+                async move {
+                    self.detect_language(&request).await?.languages.into_iter().next()
+                        .ok_or_else(|| Error::Other("detectLanguage returned no languages".to_string()))
+                }
+            })
+            .buffered(concurrency)
+            .scan(false, |failed, result| {
+                let item = if *failed {
+                    None
+                } else {
+                    *failed = result.is_err();
+                    Some(result)
+                };
+                async { item }
+            })
+    }
+
+    /// Translates `contents` into every language of `target_language_codes`, returning the
+    /// translations by target language, in the order of `contents`.
+    ///
+    /// Up to `config.concurrency` target languages are translated at once, each with requests
+    /// packed as in `bulk_translate` and sent one after another, so the rate limiter, cache and
+    /// cost tracker see every request. Fails with the first error.
+    ///
+    /// ```no_run
+    /// # async fn run(client: google_translation::TranslationClient) -> google_translation::Result<()> {
+    /// use google_translation::BulkTranslateConfig;
+    ///
+    /// let translations = client
+    ///     .translate_to_many(&["Hello", "Goodbye"], &["de", "fr", "ja"], &BulkTranslateConfig::default())
+    ///     .await?;
+    /// println!("{}", translations["ja"][0]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn translate_to_many<C, T>(&self, contents: &[C], target_language_codes: &[T], config: &BulkTranslateConfig)
+        -> Result<HashMap<String, Vec<String>>>
+        where C: AsRef<str>, T: AsRef<str>
+    {
+        assert!(config.concurrency > 0, "concurrency must be positive");
+        let contents: Vec<String> = contents.iter().map(|content| content.as_ref().to_string()).collect();
+        let per_target = BulkTranslateConfig { concurrency: 1, ..config.clone() };
+        futures::stream::iter(target_language_codes.iter().map(|target| {
+                let target = target.as_ref().to_string();
+                let translations = self.bulk_translate(futures::stream::iter(contents.clone()), target.clone(), &per_target);
+                async move { Ok((target, translations.try_collect::<Vec<String>>().await?)) }
+            }))
+            .buffer_unordered(config.concurrency)
+            .try_collect()
+            .await
+    }
+}
+
+/// The distinct strings of `contents` in order of first appearance, and for each string of
+/// `contents` the index of its distinct string.
+fn deduplicate(contents: Vec<String>) -> (Vec<String>, Vec<usize>) {
+    let mut indices: HashMap<String, usize> = HashMap::new();
+    let mut unique = Vec::new();
+    let positions = contents.into_iter()
+        .map(|content| *indices.entry(content).or_insert_with_key(|content| {
+            unique.push(content.clone());
+            unique.len() - 1
+        }))
+        .collect();
+    (unique, positions)
+}
+
+/// Groups a stream of strings into request-sized batches, ending a batch early once its first
+/// string has waited `max_delay` on `runtime`.
+struct Batches<S> {
+    inner: Pin<Box<S>>,
+    batch: Vec<String>,
+    codepoints: usize,
+    done: bool,
+    max_contents: usize,
+    max_codepoints: usize,
+    max_delay: Option<Duration>,
+    runtime: Arc<dyn Runtime>,
+    /// When the current batch is due, set while it isn't empty and `max_delay` is.
+    deadline: Option<BoxFuture<'static, ()>>,
+}
+
+impl<S: Stream<Item = String>> Batches<S> {
+    fn new(inner: S, max_contents: usize, max_codepoints: usize, max_delay: Option<Duration>, runtime: Arc<dyn Runtime>)
+        -> Batches<S>
+    {
+        assert!(max_contents > 0, "max_contents must be positive");
+        Batches {
+            inner: Box::pin(inner),
+            batch: Vec::new(),
+            codepoints: 0,
+            done: false,
+            max_contents,
+            max_codepoints,
+            max_delay,
+            runtime,
+            deadline: None,
+        }
+    }
+
+    /// Starts a new batch with `text`, returning the previous one.
+    fn start(&mut self, text: Option<String>) -> Vec<String> {
+        self.codepoints = text.as_ref().map_or(0, |text| text.chars().count());
+        self.deadline = match (&text, self.max_delay) {
+            (Some(_), Some(max_delay)) => Some(self.runtime.sleep(max_delay)),
+            _ => None,
+        };
+        std::mem::replace(&mut self.batch, text.into_iter().collect())
+    }
+}
+
+impl<S: Stream<Item = String>> Stream for Batches<S> {
+    type Item = Vec<String>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Vec<String>>> {
+        let this = &mut *self;
+        loop {
+            if this.done {
+                return Poll::Ready(if this.batch.is_empty() { None } else { Some(std::mem::take(&mut this.batch)) });
+            }
+            match this.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(text)) => {
+                    let length = text.chars().count();
+                    if this.batch.is_empty() {
+                        this.start(Some(text));
+                        continue;
+                    }
+                    if this.codepoints + length > this.max_codepoints || this.batch.len() == this.max_contents {
+                        return Poll::Ready(Some(this.start(Some(text))));
+                    }
+                    this.batch.push(text);
+                    this.codepoints += length;
+                }
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending => {
+                    let due = this.deadline.as_mut().is_some_and(|deadline| deadline.as_mut().poll(cx).is_ready());
+                    return if due { Poll::Ready(Some(this.start(None))) } else { Poll::Pending };
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::TokioRuntime;
+    use crate::tests::serve;
+    use crate::transport::MockTransport;
+    use futures::stream::{self, TryStreamExt};
+
+    #[tokio::test]
+    async fn test_batches() {
+        let texts = stream::iter(vec!["aaaa", "bb", "cccccc", "d", "e", "f"].into_iter().map(String::from));
+        let batches: Vec<Vec<String>> = Batches::new(texts, 2, 7, None, Arc::new(TokioRuntime)).collect().await;
+        assert_eq!(batches, vec![vec!["aaaa", "bb"], vec!["cccccc", "d"], vec!["e", "f"]]);
+    }
+
+    #[tokio::test]
+    async fn test_batches_max_delay() {
+        let (sender, receiver) = futures::channel::mpsc::unbounded();
+        let mut batches = Batches::new(receiver, 3, 100, Some(Duration::from_millis(20)), Arc::new(TokioRuntime));
+        sender.unbounded_send("a".to_string()).unwrap();
+        sender.unbounded_send("b".to_string()).unwrap();
+        // The input stays open, so only the delay ends the batch.
+        assert_eq!(batches.next().await.unwrap(), vec!["a", "b"]);
+        for text in ["c", "d", "e", "f"] {
+            sender.unbounded_send(text.to_string()).unwrap();
+        }
+        assert_eq!(batches.next().await.unwrap(), vec!["c", "d", "e"]);
+        drop(sender);
+        assert_eq!(batches.next().await.unwrap(), vec!["f"]);
+        assert_eq!(batches.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_translate() {
+        let (endpoint, requests) = serve(200, r#"{"translations": [{"translatedText": "a"}, {"translatedText": "b"}]}"#).await;
+        let client = TranslationClient::new("p", "global", "token").with_endpoint(&endpoint);
+        let config = BulkTranslateConfig { max_contents: 2, concurrency: 2, ..BulkTranslateConfig::default() };
+        let translations: Vec<String> = client
+            .bulk_translate(stream::iter(vec!["1", "2", "3", "4", "5", "6"]), "de", &config)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(translations, vec!["a", "b", "a", "b", "a", "b"]);
+        assert_eq!(requests.lock().unwrap().len(), 3);
+
+        let results: Vec<Result<String>> = client
+            .bulk_translate(stream::iter(vec!["1", "2", "3"]), "de", &config)
+            .collect()
+            .await;
+        assert_eq!(results.len(), 3);
+        assert!(results[2].is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deduplicate() {
+        let transport = Arc::new(MockTransport::new(|request| {
+            let translations: Vec<_> = request.json()["contents"].as_array().unwrap().iter()
+                .map(|content| serde_json::json!({"translatedText": content.as_str().unwrap().to_uppercase()}))
+                .collect();
+            (200, serde_json::json!({"translations": translations}).to_string())
+        }));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone());
+        let config = BulkTranslateConfig { deduplicate: true, ..BulkTranslateConfig::default() };
+        let translations: Vec<String> = client
+            .bulk_translate(stream::iter(vec!["yes", "no", "yes", "yes", "maybe", "no"]), "de", &config)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(translations, vec!["YES", "NO", "YES", "YES", "MAYBE", "NO"]);
+        assert_eq!(transport.requests()[0].json()["contents"], serde_json::json!(["yes", "no", "maybe"]));
+    }
+
+    #[tokio::test]
+    async fn test_detect_many() {
+        let transport = Arc::new(MockTransport::new(|request| match request.json()["content"].as_str() {
+            Some("fail") => (400, r#"{"error": {"code": 400, "message": "Bad", "status": "INVALID_ARGUMENT"}}"#.to_string()),
+            Some(text) if text.starts_with("Hola") => (200, r#"{"languages": [{"languageCode": "es", "confidence": 1}]}"#.to_string()),
+            _ => (200, r#"{"languages": [{"languageCode": "en", "confidence": 0.9}]}"#.to_string()),
+        }));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone())
+            .with_retry_policy(RetryPolicy { max_attempts: 1, ..RetryPolicy::default() });
+        let config = BulkDetectConfig { concurrency: 2, ..BulkDetectConfig::default() };
+        let languages: Vec<String> = client
+            .detect_many(stream::iter(vec!["Hello", "Hola", "Hi", "Hola amigo"]), &config)
+            .map_ok(|language| language.language_code)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(languages, vec!["en", "es", "en", "es"]);
+        assert_eq!(transport.requests().len(), 4);
+
+        let results: Vec<Result<DetectLanguageItem>> = client
+            .detect_many(stream::iter(vec!["Hello", "fail", "Hola"]), &config)
+            .collect()
+            .await;
+        assert_eq!(results.len(), 2);
+        assert!(results[1].is_err());
+    }
+
+    #[tokio::test]
+    async fn test_translate_to_many() {
+        let transport = Arc::new(MockTransport::new(|request| {
+            let body = request.json();
+            let translations: Vec<_> = body["contents"].as_array().unwrap().iter()
+                .map(|content| serde_json::json!({"translatedText": format!("[{}] {}", body["targetLanguageCode"].as_str().unwrap(), content.as_str().unwrap())}))
+                .collect();
+            (200, serde_json::json!({"translations": translations}).to_string())
+        }));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone());
+        let config = BulkTranslateConfig { max_contents: 2, concurrency: 2, ..BulkTranslateConfig::default() };
+        let translations = client.translate_to_many(&["a", "b", "c"], &["de", "fr", "ja"], &config).await.unwrap();
+        assert_eq!(translations.len(), 3);
+        assert_eq!(translations["de"], vec!["[de] a", "[de] b", "[de] c"]);
+        assert_eq!(translations["ja"], vec!["[ja] a", "[ja] b", "[ja] c"]);
+        assert_eq!(transport.requests().len(), 6);
+    }
+}
@@ -0,0 +1,255 @@
+//! An in-memory cache of text translations.
+//!
+//! With a `TranslationCache` set through `TranslationClient::with_cache`, `translate_text` looks up
+//! every string of the request first and only sends the ones that aren't cached, so translating
+//! the same input twice costs one API call. Entries are keyed by the string together with
+//! everything in the request that affects its translation: the source and target language, MIME
+//! type, model, glossary and transliteration. Labels don't affect the translation and aren't part of the key.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use web_time::Instant;
+
+use crate::*;
+
+/// Limits for a `TranslationCache`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CacheConfig {
+    /// The maximum number of cached strings. The least recently used entry is evicted beyond this.
+    pub max_entries: usize,
+    /// How long an entry stays valid. `None` keeps entries until they are evicted.
+    pub ttl: Option<Duration>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            max_entries: 10_000,
+            ttl: None,
+        }
+    }
+}
+
+/// Counters describing how effective a cache has been.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries dropped because the cache was full. Expired entries aren't counted.
+    pub evictions: u64,
+    pub entries: usize,
+}
+
+/// An LRU cache of translations with an optional time to live.
+///
+/// Clones share the same entries and statistics.
+#[derive(Clone, Debug)]
+pub struct TranslationCache {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    config: CacheConfig,
+    entries: HashMap<Key, Entry>,
+    /// Keys by last use, oldest first.
+    recency: BTreeMap<u64, Key>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Key {
+    text: String,
+    source_language_code: Option<String>,
+    target_language_code: String,
+    mime_type: Option<MimeType>,
+    model: Option<String>,
+    glossary: Option<(String, Option<bool>)>,
+    transliteration: bool,
+}
+
+#[derive(Debug)]
+struct Entry {
+    translation: Translation,
+    glossary_translation: Option<Translation>,
+    inserted_at: Instant,
+    last_used: u64,
+}
+
+impl Key {
+    fn new(request: &TranslateTextRequest, text: &str) -> Key {
+        Key {
+            text: text.to_string(),
+            source_language_code: request.source_language_code.clone(),
+            target_language_code: request.target_language_code.clone(),
+            mime_type: request.mime_type.clone(),
+            model: request.model.clone(),
+            glossary: request.glossary_config.as_ref().map(|config| (config.glossary.clone(), config.ignore_case)),
+            transliteration: request.transliteration_config.as_ref().and_then(|config| config.enable_transliteration) == Some(true),
+        }
+    }
+}
+
+impl TranslationCache {
+    pub fn new(config: CacheConfig) -> TranslationCache {
+        TranslationCache {
+            inner: Arc::new(Mutex::new(Inner {
+                config,
+                entries: HashMap::new(),
+                recency: BTreeMap::new(),
+                tick: 0,
+                stats: CacheStats::default(),
+            })),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let inner = self.inner.lock().unwrap();
+        CacheStats { entries: inner.entries.len(), ..inner.stats }
+    }
+
+    /// Removes every entry. The statistics are kept.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.clear();
+        inner.recency.clear();
+    }
+
+    fn get(&self, key: &Key) -> Option<(Translation, Option<Translation>)> {
+        let mut inner = self.inner.lock().unwrap();
+        let inner = &mut *inner;
+        let expired = match (inner.entries.get(key), inner.config.ttl) {
+            (None, _) => {
+                inner.stats.misses += 1;
+                return None;
+            }
+            (Some(entry), Some(ttl)) => entry.inserted_at.elapsed() >= ttl,
+            (Some(_), None) => false,
+        };
+        if expired {
+            let entry = inner.entries.remove(key).unwrap();
+            inner.recency.remove(&entry.last_used);
+            inner.stats.misses += 1;
+            return None;
+        }
+        inner.tick += 1;
+        let entry = inner.entries.get_mut(key).unwrap();
+        inner.recency.remove(&entry.last_used);
+        entry.last_used = inner.tick;
+        inner.recency.insert(inner.tick, key.clone());
+        inner.stats.hits += 1;
+        Some((entry.translation.clone(), entry.glossary_translation.clone()))
+    }
+
+    fn insert(&self, key: Key, translation: Translation, glossary_translation: Option<Translation>) {
+        let mut inner = self.inner.lock().unwrap();
+        let inner = &mut *inner;
+        if inner.config.max_entries == 0 {
+            return;
+        }
+        if let Some(previous) = inner.entries.remove(&key) {
+            inner.recency.remove(&previous.last_used);
+        }
+        while inner.entries.len() >= inner.config.max_entries {
+            let (&oldest, _) = inner.recency.iter().next().unwrap();
+            let evicted = inner.recency.remove(&oldest).unwrap();
+            inner.entries.remove(&evicted);
+            inner.stats.evictions += 1;
+        }
+        inner.tick += 1;
+        inner.recency.insert(inner.tick, key.clone());
+        inner.entries.insert(key, Entry { translation, glossary_translation, inserted_at: Instant::now(), last_used: inner.tick });
+    }
+}
+
+/// Answers `request` from `cache` where possible and translates the remaining strings with one request.
+pub(crate) async fn translate_text_cached(client: &TranslationClient, cache: &TranslationCache, request: &TranslateTextRequest)
+    -> Result<TranslateTextResponse>
+{
+    let keys: Vec<Key> = request.contents.iter().map(|text| Key::new(request, text)).collect();
+    let mut cached: Vec<Option<(Translation, Option<Translation>)>> = keys.iter().map(|key| cache.get(key)).collect();
+    let misses: Vec<usize> = (0..keys.len()).filter(|&i| cached[i].is_none()).collect();
+
+    if !misses.is_empty() {
+        let miss_request = TranslateTextRequest {
+            contents: misses.iter().map(|&i| request.contents[i].clone()).collect(),
+            mime_type: request.mime_type.clone(),
+            source_language_code: request.source_language_code.clone(),
+            target_language_code: request.target_language_code.clone(),
+            model: request.model.clone(),
+            glossary_config: request.glossary_config.clone(),
+            labels: request.labels.clone(),
+            transliteration_config: request.transliteration_config.clone(),
+        };
+        let response = client.translate_text_from_store(&miss_request).await?;
+        if response.translations.len() != misses.len() {
+            return Err(Error::Other(format!("translateText returned {} translations for {} strings",
+                response.translations.len(), misses.len())));
+        }
+        let mut glossary_translations = response.glossary_translations.map(Vec::into_iter);
+        for (&i, translation) in misses.iter().zip(response.translations) {
+            let glossary_translation = glossary_translations.as_mut().and_then(Iterator::next);
+            cache.insert(keys[i].clone(), translation.clone(), glossary_translation.clone());
+            cached[i] = Some((translation, glossary_translation));
+        }
+    }
+
+    let (translations, glossary_translations): (Vec<Translation>, Vec<Option<Translation>>) =
+        cached.into_iter().map(Option::unwrap).unzip();
+    Ok(TranslateTextResponse {
+        translations,
+        glossary_translations: glossary_translations.into_iter().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::serve;
+
+    fn translation(text: &str) -> Translation {
+        Translation { translated_text: text.to_string(), model: None, detected_language_code: None, glossary_config: None }
+    }
+
+    fn key(text: &str) -> Key {
+        Key::new(&TranslateTextRequest::builder().content(text).target("de").build().unwrap(), text)
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let cache = TranslationCache::new(CacheConfig { max_entries: 2, ttl: None });
+        cache.insert(key("a"), translation("A"), None);
+        cache.insert(key("b"), translation("B"), None);
+        assert!(cache.get(&key("a")).is_some());
+        cache.insert(key("c"), translation("C"), None);
+        assert!(cache.get(&key("b")).is_none());
+        assert_eq!(cache.get(&key("a")).unwrap().0.translated_text, "A");
+        assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 1, evictions: 1, entries: 2 });
+    }
+
+    #[test]
+    fn test_ttl() {
+        let cache = TranslationCache::new(CacheConfig { max_entries: 10, ttl: Some(Duration::from_secs(0)) });
+        cache.insert(key("a"), translation("A"), None);
+        assert!(cache.get(&key("a")).is_none());
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_cached_translate_text() {
+        let (endpoint, requests) = serve(200, r#"{"translations": [{"translatedText": "Hallo"}]}"#).await;
+        let cache = TranslationCache::new(CacheConfig::default());
+        let client = TranslationClient::new("p", "global", "token").with_endpoint(&endpoint).with_cache(cache.clone());
+        let request = TranslateTextRequest::builder().content("Hello").target("de").build().unwrap();
+        for _ in 0..3 {
+            let response = client.translate_text(&request).await.unwrap();
+            assert_eq!(response.translations[0].translated_text, "Hallo");
+            assert!(response.glossary_translations.is_none());
+        }
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 1, evictions: 0, entries: 1 });
+    }
+}
@@ -0,0 +1,331 @@
+//! Recording API traffic to fixture files and replaying it in tests.
+//!
+//! A `CassetteTransport` in record mode sends requests with another transport, usually a
+//! `HyperTransport` talking to the real API, and writes every request and response to a JSON
+//! "cassette" file. In replay mode it answers requests from the cassette without any I/O, so tests
+//! recorded once with live credentials run anywhere afterwards and always see the same responses.
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use google_translation::*;
+//! use google_translation::cassette::CassetteTransport;
+//!
+//! # async fn run() -> Result<()> {
+//! // Records with the real API if GOOGLE_TRANSLATION_RECORD is set, replays the file otherwise.
+//! let project_id = std::env::var("PROJECT_ID").unwrap_or_else(|_| "PROJECT_ID".to_string());
+//! let transport = CassetteTransport::from_env("tests/fixtures/detect.json")?.with_secret(&project_id, "PROJECT_ID");
+//! let client = TranslationClient::new(&project_id, "global", &std::env::var("ACCESS_TOKEN").unwrap_or_default())
+//!     .with_transport(Arc::new(transport));
+//! let response = client.detect_language(&DetectLanguageRequest::builder().content("Hallo").build()?).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Credentials never reach the cassette: request headers, including `Authorization`, aren't
+//! recorded, API keys and access tokens in query strings are replaced with "REDACTED", and each
+//! secret set with `with_secret` is replaced with its placeholder in URLs and bodies. Replayed
+//! requests are scrubbed the same way before they are matched, so a test may run with real or
+//! placeholder values. gzip-compressed bodies are recorded decompressed.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use hyper::{Body, Request, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::{BoxFuture, Error, Result, compression};
+use crate::trace::redact_url;
+use crate::transport::HttpTransport;
+#[cfg(feature = "native")]
+use crate::transport::HyperTransport;
+
+/// The environment variable that makes `CassetteTransport::from_env` record instead of replay.
+pub const RECORD_ENV: &str = "GOOGLE_TRANSLATION_RECORD";
+
+/// The contents of a cassette file.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+/// A request and the response it got.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub uri: String,
+    /// The body as JSON, or as a JSON string if it isn't JSON.
+    pub body: serde_json::Value,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// The body as JSON, or as a JSON string if it isn't JSON.
+    pub body: serde_json::Value,
+}
+
+impl Cassette {
+    pub fn load(path: impl AsRef<Path>) -> Result<Cassette> {
+        let json = std::fs::read_to_string(path).map_err(Error::IoError)?;
+        serde_json::from_str(&json).map_err(Error::SerdeJsonError)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(Error::SerdeJsonError)?;
+        std::fs::write(path, json + "\n").map_err(Error::IoError)
+    }
+}
+
+enum Mode {
+    Record { inner: Arc<dyn HttpTransport> },
+    /// The interactions not replayed yet.
+    Replay { unused: Mutex<Vec<Interaction>> },
+}
+
+/// A transport that records to or replays from a cassette file, see the module documentation.
+pub struct CassetteTransport {
+    path: PathBuf,
+    mode: Mode,
+    secrets: Vec<(String, String)>,
+    recorded: Mutex<Cassette>,
+}
+
+impl CassetteTransport {
+    /// Sends requests with `inner` and records them to a new cassette at `path`. The file is
+    /// rewritten after every response, so it is complete even if a test panics.
+    pub fn record(path: impl Into<PathBuf>, inner: Arc<dyn HttpTransport>) -> CassetteTransport {
+        CassetteTransport::with_mode(path.into(), Mode::Record { inner })
+    }
+
+    /// Answers requests from the cassette at `path`. A request is answered with the first
+    /// interaction not replayed yet with the same method, URL and body, and fails if there is none.
+    pub fn replay(path: impl Into<PathBuf>) -> Result<CassetteTransport> {
+        let path = path.into();
+        let cassette = Cassette::load(&path)?;
+        Ok(CassetteTransport::with_mode(path, Mode::Replay { unused: Mutex::new(cassette.interactions) }))
+    }
+
+    /// Records with a `HyperTransport` if the `RECORD_ENV` environment variable is set, and
+    /// replays otherwise.
+    #[cfg(feature = "native")]
+    pub fn from_env(path: impl Into<PathBuf>) -> Result<CassetteTransport> {
+        match std::env::var_os(RECORD_ENV) {
+            Some(_) => Ok(CassetteTransport::record(path, Arc::new(HyperTransport::new()))),
+            None => CassetteTransport::replay(path),
+        }
+    }
+
+    fn with_mode(path: PathBuf, mode: Mode) -> CassetteTransport {
+        CassetteTransport { path, mode, secrets: Vec::new(), recorded: Mutex::new(Cassette::default()) }
+    }
+
+    /// Replaces `secret`, such as a project id, with `placeholder` in recorded and replayed URLs
+    /// and bodies. Empty secrets are ignored.
+    pub fn with_secret(mut self, secret: &str, placeholder: &str) -> CassetteTransport {
+        if !secret.is_empty() {
+            self.secrets.push((secret.to_string(), placeholder.to_string()));
+        }
+        self
+    }
+
+    /// The number of interactions in the cassette not replayed yet, or 0 when recording. Tests can
+    /// check it is 0 at the end to make sure every recorded request was made.
+    pub fn remaining(&self) -> usize {
+        match &self.mode {
+            Mode::Record { .. } => 0,
+            Mode::Replay { unused } => unused.lock().unwrap().len(),
+        }
+    }
+
+    fn scrub(&self, text: &str) -> String {
+        self.secrets.iter().fold(text.to_string(), |text, (secret, placeholder)| text.replace(secret, placeholder))
+    }
+
+    fn scrub_body(&self, body: &[u8]) -> serde_json::Value {
+        let text = self.scrub(&String::from_utf8_lossy(body));
+        match text.as_str() {
+            "" => serde_json::Value::String(text),
+            _ => serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text)),
+        }
+    }
+
+    async fn recorded_request(&self, request: Request<Body>) -> Result<(RecordedRequest, Request<Body>)> {
+        let (parts, body) = request.into_parts();
+        let body = hyper::body::to_bytes(body).await.map_err(Error::HyperError)?;
+        let decoded = match compression::is_gzip(&parts.headers) {
+            true => compression::gunzip(&body)?,
+            false => body.to_vec(),
+        };
+        let recorded = RecordedRequest {
+            method: parts.method.to_string(),
+            uri: self.scrub(&redact_url(&parts.uri.to_string())),
+            body: self.scrub_body(&decoded),
+        };
+        Ok((recorded, Request::from_parts(parts, Body::from(body))))
+    }
+
+    async fn send_recording(&self, inner: &dyn HttpTransport, request: Request<Body>) -> Result<Response<Body>> {
+        let (recorded_request, request) = self.recorded_request(request).await?;
+        let (mut parts, body) = inner.send(request).await?.into_parts();
+        let mut body = hyper::body::to_bytes(body).await.map_err(Error::HyperError)?;
+        if compression::is_gzip(&parts.headers) {
+            body = compression::gunzip(&body)?.into();
+            parts.headers.remove(hyper::header::CONTENT_ENCODING);
+            parts.headers.remove(hyper::header::CONTENT_LENGTH);
+        }
+        let headers = parts.headers.iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let response = RecordedResponse { status: parts.status.as_u16(), headers, body: self.scrub_body(&body) };
+        let mut cassette = self.recorded.lock().unwrap();
+        cassette.interactions.push(Interaction { request: recorded_request, response });
+        cassette.save(&self.path)?;
+        Ok(Response::from_parts(parts, Body::from(body)))
+    }
+
+    async fn send_replaying(&self, unused: &Mutex<Vec<Interaction>>, request: Request<Body>) -> Result<Response<Body>> {
+        let (request, _) = self.recorded_request(request).await?;
+        let interaction = {
+            let mut unused = unused.lock().unwrap();
+            match unused.iter().position(|interaction| interaction.request == request) {
+                Some(i) => unused.remove(i),
+                None => return Err(Error::Other(format!(
+                    "no interaction left in cassette {} for {} {}", self.path.display(), request.method, request.uri))),
+            }
+        };
+        let response = interaction.response;
+        let mut builder = Response::builder().status(response.status);
+        for (name, value) in &response.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        let body = match response.body {
+            serde_json::Value::String(text) => text,
+            body => body.to_string(),
+        };
+        builder.body(Body::from(body)).map_err(|e| Error::Other(format!("invalid recorded response: {}", e)))
+    }
+}
+
+impl HttpTransport for CassetteTransport {
+    fn send(&self, request: Request<Body>) -> BoxFuture<'_, Result<Response<Body>>> {
+        Box::pin(async move {
+            match &self.mode {
+                Mode::Record { inner } => self.send_recording(inner.as_ref(), request).await,
+                Mode::Replay { unused } => self.send_replaying(unused, request).await,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::CONTENT_ENCODING;
+
+    use super::*;
+    use crate::*;
+    use crate::transport::MockTransport;
+
+    /// Answers every request with a gzip-compressed body.
+    struct GzipTransport(&'static str);
+
+    impl HttpTransport for GzipTransport {
+        fn send(&self, _request: Request<Body>) -> BoxFuture<'_, Result<Response<Body>>> {
+            Box::pin(async move {
+                Ok(Response::builder()
+                    .header(CONTENT_ENCODING, "gzip")
+                    .body(Body::from(compression::gzip(self.0.as_bytes())))
+                    .unwrap())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let path = std::env::temp_dir().join(format!("google-translation-cassette-{}.json", std::process::id()));
+        let live = Arc::new(MockTransport::new(|request| match request.json()["contents"][0].as_str() {
+            Some("fail") => (400, r#"{"error": {"code": 400, "message": "Bad", "status": "INVALID_ARGUMENT"}}"#.to_string()),
+            _ => (200, r#"{"translations": [{"translatedText": "Hallo", "model": "projects/secret-project/locations/global/models/general/nmt"}]}"#.to_string()),
+        }));
+        let request = |text: &str| TranslateTextRequest::builder().content(text).target("de").build().unwrap();
+        let translate = |client: TranslationClient| async move {
+            let ok = client.translate_text(&request("Hello")).await.unwrap();
+            let error = client.translate_text(&request("fail")).await.unwrap_err();
+            (ok, error.code())
+        };
+
+        let recorder = CassetteTransport::record(&path, live.clone()).with_secret("secret-project", "PROJECT_ID");
+        let client = TranslationClient::new("secret-project", "global", "secret-token").with_transport(Arc::new(recorder))
+            .with_retry_policy(RetryPolicy { max_attempts: 1, ..RetryPolicy::default() });
+        let recorded = translate(client).await;
+        assert_eq!(live.requests().len(), 2);
+
+        let json = std::fs::read_to_string(&path).unwrap();
+        assert!(!json.contains("secret"), "{}", json);
+        let cassette = Cassette::load(&path).unwrap();
+        assert_eq!(cassette.interactions.len(), 2);
+        assert_eq!(cassette.interactions[0].request.uri,
+            "https://translation.googleapis.com/v3beta1/projects/PROJECT_ID/locations/global:translateText");
+        assert_eq!(cassette.interactions[0].request.body["contents"][0], "Hello");
+        assert_eq!(cassette.interactions[1].response.status, 400);
+
+        // Replaying works with the real project id or the placeholder, and needs no token.
+        for project_id in ["secret-project", "PROJECT_ID"] {
+            let replayer = Arc::new(CassetteTransport::replay(&path).unwrap().with_secret("secret-project", "PROJECT_ID"));
+            let client = TranslationClient::new(project_id, "global", "").with_transport(replayer.clone())
+                .with_retry_policy(RetryPolicy { max_attempts: 1, ..RetryPolicy::default() });
+            let (ok, code) = translate(client.clone()).await;
+            assert_eq!(ok.translations[0].translated_text, recorded.0.translations[0].translated_text);
+            assert_eq!(code, Some(ErrorCode::InvalidArgument));
+            assert_eq!(replayer.remaining(), 0);
+            let error = client.translate_text(&request("Hello")).await.unwrap_err();
+            assert!(error.to_string().contains("no interaction left"), "{}", error);
+        }
+        assert_eq!(live.requests().len(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_record_redacts_credentials_and_decompresses() {
+        let path = std::env::temp_dir().join(format!("google-translation-cassette-gzip-{}.json", std::process::id()));
+        let request = |body: &str| Request::post(
+                "https://translation.googleapis.com/v3/projects/p:detectLanguage?key=secret-key&access_token=secret-token&alt=json")
+            .header(CONTENT_ENCODING, "gzip")
+            .body(Body::from(compression::gzip(body.as_bytes())))
+            .unwrap();
+
+        let recorder = CassetteTransport::record(&path, Arc::new(GzipTransport(r#"{"languages": [{"languageCode": "de", "confidence": 1.0}]}"#)));
+        let response = recorder.send(request(r#"{"content": "Hallo"}"#)).await.unwrap();
+        assert!(response.headers().get(CONTENT_ENCODING).is_none());
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&body).unwrap()["languages"][0]["languageCode"], "de");
+
+        let json = std::fs::read_to_string(&path).unwrap();
+        assert!(!json.contains("secret"), "{}", json);
+        let cassette = Cassette::load(&path).unwrap();
+        let interaction = &cassette.interactions[0];
+        assert_eq!(interaction.request.uri,
+            "https://translation.googleapis.com/v3/projects/p:detectLanguage?key=REDACTED&access_token=REDACTED&alt=json");
+        assert_eq!(interaction.request.body, serde_json::json!({"content": "Hallo"}));
+        assert_eq!(interaction.response.body["languages"][0]["languageCode"], "de");
+        assert!(!interaction.response.headers.contains_key("content-encoding"));
+
+        // Replayed requests are matched on their decompressed bodies.
+        let replayer = CassetteTransport::replay(&path).unwrap();
+        let error = replayer.send(request(r#"{"content": "Tschüss"}"#)).await.unwrap_err();
+        assert!(error.to_string().contains("no interaction left"), "{}", error);
+        assert_eq!(replayer.remaining(), 1);
+        let response = replayer.send(request(r#"{"content": "Hallo"}"#)).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(replayer.remaining(), 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+//! Splitting content that exceeds the translateText size limit.
+//!
+//! The API rejects requests whose contents add up to more than about 30,000 codepoints with
+//! INVALID_ARGUMENT. With `TranslationClient::with_chunking` enabled, `translate_text` splits
+//! oversized strings with `split`, packs the pieces into as many requests as needed and joins the
+//! translated pieces back together, so the response has one translation per input string as usual.
+//!
+//! Pieces end on paragraph, line, sentence or word boundaries where possible. HTML is split the
+//! same way as plain text, so a piece boundary can fall inside markup if a paragraph is very long.
+
+use crate::*;
+
+/// The recommended maximum number of codepoints in a translateText request.
+pub const MAX_CODEPOINTS: usize = 30_000;
+/// The maximum number of strings in a translateText request.
+pub const MAX_CONTENTS: usize = 1024;
+
+const SENTENCE_TERMINATORS: &[char] = &['.', '!', '?', '…'];
+/// Terminators that end a sentence without being followed by whitespace, as in Chinese and Japanese.
+const CJK_SENTENCE_TERMINATORS: &[char] = &['。', '！', '？'];
+
+/// Splits `text` into consecutive pieces of at most `max_codepoints` codepoints each.
+///
+/// Concatenating the pieces gives back `text`. Each piece ends after the last paragraph break
+/// ("\n\n") that fits, or failing that the last line break, sentence end or whitespace, and
+/// only as a last resort in the middle of a word. Whitespace at a boundary stays with the
+/// preceding piece. A `max_codepoints` of 0 is treated as 1.
+pub fn split(text: &str, max_codepoints: usize) -> Vec<&str> {
+    let max_codepoints = max_codepoints.max(1);
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while rest.chars().count() > max_codepoints {
+        let end = boundary(rest, max_codepoints);
+        pieces.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    if !rest.is_empty() || pieces.is_empty() {
+        pieces.push(rest);
+    }
+    pieces
+}
+
+/// The byte offset at which to end a piece starting at the beginning of `text`.
+fn boundary(text: &str, max_codepoints: usize) -> usize {
+    let (mut paragraph, mut line, mut sentence, mut space) = (0, 0, 0, 0);
+    let mut window_end = text.len();
+    let mut previous = None;
+    for (count, (i, c)) in text.char_indices().enumerate() {
+        if count == max_codepoints {
+            window_end = i;
+            break;
+        }
+        let end = i + c.len_utf8();
+        if c == '\n' {
+            if previous == Some('\n') {
+                paragraph = end;
+            }
+            line = end;
+        }
+        if CJK_SENTENCE_TERMINATORS.contains(&c)
+            || (c.is_whitespace() && previous.is_some_and(|p| SENTENCE_TERMINATORS.contains(&p)))
+        {
+            sentence = end;
+        }
+        if c.is_whitespace() {
+            space = end;
+        }
+        previous = Some(c);
+    }
+    [paragraph, line, sentence, space].iter().copied().find(|&end| end > 0).unwrap_or(window_end)
+}
+
+/// A piece of one of the request's strings, with the whitespace around it kept out of the request.
+struct Piece<'a> {
+    content_index: usize,
+    leading: &'a str,
+    text: &'a str,
+    trailing: &'a str,
+}
+
+impl<'a> Piece<'a> {
+    fn new(content_index: usize, piece: &'a str) -> Piece<'a> {
+        let start = piece.len() - piece.trim_start().len();
+        let end = piece.trim_end().len().max(start);
+        Piece { content_index, leading: &piece[..start], text: &piece[start..end], trailing: &piece[end..] }
+    }
+}
+
+pub(crate) fn needs_chunking(request: &TranslateTextRequest, max_codepoints: usize) -> bool {
+    request.contents.iter().map(|content| content.chars().count()).sum::<usize>() > max_codepoints
+}
+
+/// Translates `request` in several requests of at most `max_codepoints` each and stitches the results.
+pub(crate) async fn translate_text_chunked(client: &TranslationClient, request: &TranslateTextRequest, max_codepoints: usize)
+    -> Result<TranslateTextResponse>
+{
+    let pieces: Vec<Piece> = request.contents.iter().enumerate()
+        .flat_map(|(index, content)| split(content, max_codepoints).into_iter().map(move |piece| Piece::new(index, piece)))
+        .collect();
+    let to_translate: Vec<usize> = (0..pieces.len()).filter(|&i| !pieces[i].text.is_empty()).collect();
+
+    let mut translated: Vec<Option<Translation>> = (0..pieces.len()).map(|_| None).collect();
+    let mut glossary_translated: Vec<Option<Translation>> = (0..pieces.len()).map(|_| None).collect();
+    let mut has_glossary_translations = false;
+    for batch in pack(&pieces, &to_translate, max_codepoints) {
+        let sub_request = TranslateTextRequest {
+            contents: batch.iter().map(|&i| pieces[i].text.to_string()).collect(),
+            mime_type: request.mime_type.clone(),
+            source_language_code: request.source_language_code.clone(),
+            target_language_code: request.target_language_code.clone(),
+            model: request.model.clone(),
+            glossary_config: request.glossary_config.clone(),
+            labels: request.labels.clone(),
+            transliteration_config: request.transliteration_config.clone(),
+        };
+        let response = client.send_translate_text(&sub_request).await?;
+        if response.translations.len() != batch.len() {
+            return Err(Error::Other(format!("translateText returned {} translations for {} strings",
+                response.translations.len(), batch.len())));
+        }
+        for (&i, translation) in batch.iter().zip(response.translations) {
+            translated[i] = Some(translation);
+        }
+        if let Some(glossary_translations) = response.glossary_translations {
+            has_glossary_translations = true;
+            for (&i, translation) in batch.iter().zip(glossary_translations) {
+                glossary_translated[i] = Some(translation);
+            }
+        }
+    }
+
+    Ok(TranslateTextResponse {
+        translations: stitch(&pieces, translated, request.contents.len()),
+        glossary_translations: if has_glossary_translations {
+            Some(stitch(&pieces, glossary_translated, request.contents.len()))
+        } else {
+            None
+        },
+    })
+}
+
+/// Groups the indexes of the pieces to translate into requests within the codepoint and string limits.
+fn pack(pieces: &[Piece], to_translate: &[usize], max_codepoints: usize) -> Vec<Vec<usize>> {
+    let mut batches = Vec::new();
+    let mut batch = Vec::new();
+    let mut codepoints = 0;
+    for &i in to_translate {
+        let length = pieces[i].text.chars().count();
+        if !batch.is_empty() && (codepoints + length > max_codepoints || batch.len() == MAX_CONTENTS) {
+            batches.push(std::mem::take(&mut batch));
+            codepoints = 0;
+        }
+        batch.push(i);
+        codepoints += length;
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+    batches
+}
+
+/// Joins the translated pieces of each string, restoring the whitespace between them.
+/// The other fields of a string's translation are taken from its first translated piece.
+fn stitch(pieces: &[Piece], translated: Vec<Option<Translation>>, content_count: usize) -> Vec<Translation> {
+    let mut translations: Vec<Option<Translation>> = (0..content_count).map(|_| None).collect();
+    let mut texts = vec![String::new(); content_count];
+    for (piece, translation) in pieces.iter().zip(translated) {
+        let text = &mut texts[piece.content_index];
+        text.push_str(piece.leading);
+        if let Some(translation) = translation {
+            text.push_str(&translation.translated_text);
+            translations[piece.content_index].get_or_insert(translation);
+        }
+        text.push_str(piece.trailing);
+    }
+    translations.into_iter().zip(texts)
+        .map(|(translation, translated_text)| match translation {
+            Some(translation) => Translation { translated_text, ..translation },
+            None => Translation { translated_text, model: None, detected_language_code: None, glossary_config: None },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::serve;
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split("short", 10), vec!["short"]);
+        assert_eq!(split("", 10), vec![""]);
+        assert_eq!(split("First para.\n\nSecond para.", 20), vec!["First para.\n\n", "Second para."]);
+        assert_eq!(split("One. Two three. Four", 16), vec!["One. Two three. ", "Four"]);
+        assert_eq!(split("one two three", 9), vec!["one two ", "three"]);
+        assert_eq!(split("abcdefgh", 3), vec!["abc", "def", "gh"]);
+        assert_eq!(split("我是谁。你是谁。", 5), vec!["我是谁。", "你是谁。"]);
+        let text = "é".repeat(10);
+        assert!(split(&text, 4).iter().all(|piece| piece.chars().count() <= 4));
+        assert_eq!(split(&text, 4).concat(), text);
+        assert_eq!(split("abc", 0), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_pack_and_stitch() {
+        let pieces: Vec<Piece> = split("Hello world.\n\nGoodbye.", 14).into_iter().map(|piece| Piece::new(0, piece)).collect();
+        assert_eq!(pieces[0].text, "Hello world.");
+        assert_eq!(pieces[0].trailing, "\n\n");
+        let to_translate = [0, 1];
+        assert_eq!(pack(&pieces, &to_translate, 14), vec![vec![0], vec![1]]);
+        assert_eq!(pack(&pieces, &to_translate, 30), vec![vec![0, 1]]);
+        let translation = |text: &str| Some(Translation {
+            translated_text: text.to_string(),
+            model: None,
+            detected_language_code: Some("en".to_string()),
+            glossary_config: None,
+        });
+        let stitched = stitch(&pieces, vec![translation("Hallo Welt."), translation("Tschüss.")], 1);
+        assert_eq!(stitched[0].translated_text, "Hallo Welt.\n\nTschüss.");
+        assert_eq!(stitched[0].detected_language_code.as_deref(), Some("en"));
+    }
+
+    #[tokio::test]
+    async fn test_chunked_translate_text() {
+        let (endpoint, requests) = serve(200, r#"{"translations": [{"translatedText": "x"}]}"#).await;
+        let client = TranslationClient::new("p", "global", "token").with_endpoint(&endpoint).with_chunking(10);
+        let request = TranslateTextRequest::builder().content("aaaa bbbb cccc").target("de").build().unwrap();
+        let response = client.translate_text(&request).await.unwrap();
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        assert_eq!(response.translations.len(), 1);
+        assert_eq!(response.translations[0].translated_text, "x x");
+    }
+
+    #[tokio::test]
+    async fn test_chunking_zero_codepoints() {
+        let (endpoint, requests) = serve(200, r#"{"translations": [{"translatedText": "x"}]}"#).await;
+        let client = TranslationClient::new("p", "global", "token").with_endpoint(&endpoint).with_chunking(0);
+        let request = TranslateTextRequest::builder().content("ab").target("de").build().unwrap();
+        let response = client.translate_text(&request).await.unwrap();
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        assert_eq!(response.translations[0].translated_text, "xx");
+    }
+}
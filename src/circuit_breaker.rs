@@ -0,0 +1,314 @@
+//! Failing fast while the API is down.
+//!
+//! When the API has an outage, every queued request waits for its own timeouts and retries before
+//! failing. A `CircuitBreaker` wraps a transport and watches the outcome of the last requests:
+//!
+//! - Closed: requests are sent. Once at least `min_requests` of the last `window` requests were
+//!   sent and `failure_rate` of them or more failed, the breaker opens.
+//! - Open: requests fail immediately with `Error::CircuitOpen`, which isn't retried, until
+//!   `cool_down` has passed.
+//! - Half-open: one request at a time is let through as a probe, and the others fail fast. After
+//!   `probes` successful probes in a row the breaker closes; a failed probe opens it again.
+//!
+//! Connection errors and 500, 502, 503 and 504 responses count as failures. Other responses,
+//! including errors such as INVALID_ARGUMENT and RESOURCE_EXHAUSTED, mean the API is up.
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use google_translation::circuit_breaker::CircuitBreakerConfig;
+//!
+//! let client = google_translation::TranslationClient::new("my-project", "global", "token")
+//!     .with_circuit_breaker(CircuitBreakerConfig { cool_down: Duration::from_secs(10), ..CircuitBreakerConfig::default() });
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use hyper::{Body, Request, Response};
+use web_time::Instant;
+
+use crate::{BoxFuture, Error, Result};
+use crate::transport::HttpTransport;
+
+/// When a `CircuitBreaker` opens and how it recovers, see the module documentation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircuitBreakerConfig {
+    /// The number of most recent requests whose outcome is considered.
+    pub window: usize,
+    /// The breaker doesn't open before this many requests are in the window.
+    pub min_requests: usize,
+    /// The share of failed requests in the window, from 0 to 1, at which the breaker opens.
+    pub failure_rate: f64,
+    /// How long the breaker stays open before probing.
+    pub cool_down: Duration,
+    /// The number of successful probes in a row that close the breaker.
+    pub probes: usize,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        CircuitBreakerConfig { window: 20, min_requests: 10, failure_rate: 0.5, cool_down: Duration::from_secs(30), probes: 3 }
+    }
+}
+
+/// The state of a `CircuitBreaker`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug)]
+enum State {
+    /// The outcomes of the last requests, true for failures.
+    Closed { outcomes: VecDeque<bool> },
+    Open { until: Instant },
+    HalfOpen { probing: bool, successes: usize },
+}
+
+/// A transport that stops sending requests through `inner` while they keep failing, see the
+/// module documentation.
+pub struct CircuitBreaker {
+    inner: Arc<dyn HttpTransport>,
+    config: CircuitBreakerConfig,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    pub fn new(inner: Arc<dyn HttpTransport>, config: CircuitBreakerConfig) -> CircuitBreaker {
+        assert!(config.window > 0 && config.probes > 0, "window and probes must be positive");
+        CircuitBreaker { inner, config, state: Mutex::new(State::Closed { outcomes: VecDeque::new() }) }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        match &*self.state.lock().unwrap() {
+            State::Closed { .. } => CircuitState::Closed,
+            State::Open { until } if Instant::now() < *until => CircuitState::Open,
+            State::Open { .. } | State::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Lets a request through, returning whether it is a probe, or fails with `Error::CircuitOpen`.
+    fn admit(&self) -> Result<bool> {
+        let mut state = self.state.lock().unwrap();
+        if let State::Open { until } = *state {
+            if Instant::now() < until {
+                return Err(Error::CircuitOpen);
+            }
+            *state = State::HalfOpen { probing: false, successes: 0 };
+            crate::trace::circuit_state(CircuitState::HalfOpen);
+        }
+        match &mut *state {
+            State::Closed { .. } => Ok(false),
+            State::HalfOpen { probing, .. } if !*probing => {
+                *probing = true;
+                Ok(true)
+            }
+            _ => Err(Error::CircuitOpen),
+        }
+    }
+
+    /// Records the outcome of a request let through by `admit`, or of an abandoned probe if
+    /// `failed` is None.
+    fn record(&self, probe: bool, failed: Option<bool>) {
+        let mut state = self.state.lock().unwrap();
+        let next = match (&mut *state, failed) {
+            (State::Closed { outcomes }, Some(failed)) if !probe => {
+                outcomes.push_back(failed);
+                if outcomes.len() > self.config.window {
+                    outcomes.pop_front();
+                }
+                let failures = outcomes.iter().filter(|failed| **failed).count();
+                if outcomes.len() >= self.config.min_requests
+                    && failures as f64 >= self.config.failure_rate * outcomes.len() as f64
+                {
+                    Some(State::Open { until: Instant::now() + self.config.cool_down })
+                } else {
+                    None
+                }
+            }
+            (State::HalfOpen { probing, successes }, failed) if probe => match failed {
+                None => {
+                    *probing = false;
+                    None
+                }
+                Some(false) if *successes + 1 >= self.config.probes => Some(State::Closed { outcomes: VecDeque::new() }),
+                Some(false) => {
+                    *probing = false;
+                    *successes += 1;
+                    None
+                }
+                Some(true) => Some(State::Open { until: Instant::now() + self.config.cool_down }),
+            },
+            // The outcome of a request sent before the state changed.
+            _ => None,
+        };
+        if let Some(next) = next {
+            crate::trace::circuit_state(match next {
+                State::Closed { .. } => CircuitState::Closed,
+                _ => CircuitState::Open,
+            });
+            *state = next;
+        }
+    }
+}
+
+/// Releases the probe of a request that is dropped before it finishes.
+struct Admission<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+    recorded: bool,
+}
+
+impl Admission<'_> {
+    fn record(mut self, failed: bool) {
+        self.recorded = true;
+        self.breaker.record(self.probe, Some(failed));
+    }
+}
+
+impl Drop for Admission<'_> {
+    fn drop(&mut self) {
+        if !self.recorded && self.probe {
+            self.breaker.record(true, None);
+        }
+    }
+}
+
+impl HttpTransport for CircuitBreaker {
+    fn send(&self, request: Request<Body>) -> BoxFuture<'_, Result<Response<Body>>> {
+        Box::pin(async move {
+            let admission = Admission { breaker: self, probe: self.admit()?, recorded: false };
+            let result = self.inner.send(request).await;
+            let failed = match &result {
+                Ok(response) => matches!(response.status().as_u16(), 500 | 502 | 503 | 504),
+                Err(_) => true,
+            };
+            admission.record(failed);
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+
+    use super::*;
+    use crate::*;
+    use crate::transport::MockTransport;
+
+    /// Answers every request with `status`, or never while `hang` is set.
+    struct StatusTransport {
+        status: AtomicU16,
+        hang: AtomicBool,
+    }
+
+    impl StatusTransport {
+        fn new(status: u16) -> Arc<StatusTransport> {
+            Arc::new(StatusTransport { status: AtomicU16::new(status), hang: AtomicBool::new(false) })
+        }
+    }
+
+    impl HttpTransport for StatusTransport {
+        fn send(&self, _request: Request<Body>) -> BoxFuture<'_, Result<Response<Body>>> {
+            Box::pin(async move {
+                if self.hang.load(Ordering::SeqCst) {
+                    futures::future::pending::<()>().await;
+                }
+                Ok(Response::builder().status(self.status.load(Ordering::SeqCst)).body(Body::empty()).unwrap())
+            })
+        }
+    }
+
+    fn request() -> Request<Body> {
+        Request::get("https://translation.googleapis.com/v3/projects/p/locations/global/supportedLanguages").body(Body::empty()).unwrap()
+    }
+
+    async fn send_status(breaker: &CircuitBreaker, transport: &StatusTransport, status: u16) -> Result<u16> {
+        transport.status.store(status, Ordering::SeqCst);
+        Ok(breaker.send(request()).await?.status().as_u16())
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        let down = Arc::new(AtomicBool::new(true));
+        let transport = Arc::new(MockTransport::new({
+            let down = down.clone();
+            move |_| match down.load(Ordering::SeqCst) {
+                true => (503, r#"{"error": {"code": 503, "message": "Unavailable", "status": "UNAVAILABLE"}}"#.to_string()),
+                false => (200, r#"{"translations": [{"translatedText": "Hallo"}]}"#.to_string()),
+            }
+        }));
+        let config = CircuitBreakerConfig { window: 4, min_requests: 2, cool_down: Duration::from_millis(50), probes: 2, ..CircuitBreakerConfig::default() };
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone())
+            .with_retry_policy(RetryPolicy { max_attempts: 1, ..RetryPolicy::default() })
+            .with_circuit_breaker(config);
+        let request = TranslateTextRequest::builder().content("Hello").target("de").build().unwrap();
+
+        for _ in 0..2 {
+            assert_eq!(client.translate_text(&request).await.unwrap_err().code(), Some(ErrorCode::Unavailable));
+        }
+        assert!(matches!(client.translate_text(&request).await.unwrap_err(), Error::CircuitOpen));
+        assert_eq!(transport.requests().len(), 2);
+
+        // A failed probe opens the breaker again.
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(matches!(client.translate_text(&request).await.unwrap_err(), Error::ResponseError(503, _)));
+        assert!(matches!(client.translate_text(&request).await.unwrap_err(), Error::CircuitOpen));
+        assert_eq!(transport.requests().len(), 3);
+
+        // Two successful probes close it.
+        down.store(false, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        client.translate_text(&request).await.unwrap();
+        client.translate_text(&request).await.unwrap();
+        client.translate_text(&request).await.unwrap();
+        assert_eq!(transport.requests().len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_failures_in_window() {
+        let transport = StatusTransport::new(200);
+        let config = CircuitBreakerConfig { window: 2, min_requests: 2, failure_rate: 1.0, cool_down: Duration::from_secs(3600), probes: 1 };
+        let breaker = CircuitBreaker::new(transport.clone(), config);
+
+        // A single failure is fewer than min_requests.
+        assert_eq!(send_status(&breaker, &transport, 503).await.unwrap(), 503);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        // Client errors and RESOURCE_EXHAUSTED aren't failures.
+        assert_eq!(send_status(&breaker, &transport, 400).await.unwrap(), 400);
+        assert_eq!(send_status(&breaker, &transport, 429).await.unwrap(), 429);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        // The window holds the last 2 outcomes, so the breaker opens once both are failures.
+        assert_eq!(send_status(&breaker, &transport, 500).await.unwrap(), 500);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(send_status(&breaker, &transport, 502).await.unwrap(), 502);
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(matches!(send_status(&breaker, &transport, 200).await, Err(Error::CircuitOpen)));
+    }
+
+    #[tokio::test]
+    async fn test_half_open_probe() {
+        let transport = StatusTransport::new(503);
+        let config = CircuitBreakerConfig { window: 1, min_requests: 1, failure_rate: 1.0, cool_down: Duration::ZERO, probes: 1 };
+        let breaker = CircuitBreaker::new(transport.clone(), config);
+        breaker.send(request()).await.unwrap();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+
+        // Only one probe is let through at a time.
+        transport.hang.store(true, Ordering::SeqCst);
+        let mut probe = breaker.send(request());
+        assert!(futures::poll!(&mut probe).is_pending());
+        assert!(matches!(breaker.send(request()).await, Err(Error::CircuitOpen)));
+
+        // Dropping an unfinished probe lets the next request probe.
+        drop(probe);
+        transport.hang.store(false, Ordering::SeqCst);
+        assert_eq!(send_status(&breaker, &transport, 200).await.unwrap(), 200);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+}
@@ -0,0 +1,97 @@
+//! gzip compression of request and response bodies.
+//!
+//! Every request asks for gzip-compressed responses with `Accept-Encoding: gzip`, and compressed
+//! responses are decompressed before they are parsed or handed to interceptors. Request bodies are
+//! only compressed if the client has a threshold set with `TranslationClient::with_request_compression`.
+
+use std::io::{Read, Write};
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use hyper::header::{CONTENT_ENCODING, HeaderMap};
+
+use crate::{Error, Result};
+
+pub(crate) fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    // Writing to a Vec can't fail.
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+pub(crate) fn gunzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    GzDecoder::new(data).read_to_end(&mut decoded)
+        .map_err(|e| Error::Other(format!("invalid gzip response body: {}", e)))?;
+    Ok(decoded)
+}
+
+/// Returns true if `headers` say the body is gzip-compressed.
+pub(crate) fn is_gzip(headers: &HeaderMap) -> bool {
+    headers.get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case("gzip"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use hyper::{Body, Request, Response};
+
+    use super::*;
+    use crate::*;
+    use crate::transport::{HttpTransport, MockTransport};
+
+    /// Answers every request with a gzip-compressed body.
+    struct GzipTransport(&'static str);
+
+    impl HttpTransport for GzipTransport {
+        fn send(&self, _request: Request<Body>) -> BoxFuture<'_, Result<Response<Body>>> {
+            Box::pin(async move {
+                Ok(Response::builder()
+                    .header("content-encoding", "gzip")
+                    .body(Body::from(gzip(self.0.as_bytes())))
+                    .unwrap())
+            })
+        }
+    }
+
+    #[test]
+    fn test_gzip_round_trip() {
+        let data = "<p>Hello world</p>".repeat(100);
+        let compressed = gzip(data.as_bytes());
+        assert!(compressed.len() < data.len());
+        assert_eq!(gunzip(&compressed).unwrap(), data.as_bytes());
+        assert!(gunzip(b"not gzip").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_request_compression() {
+        let transport = Arc::new(MockTransport::new(|request| {
+            let body = if is_gzip(&request.headers) { gunzip(&request.body).unwrap() } else { request.body.clone() };
+            let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            (200, serde_json::json!({ "languages": [{ "languageCode": request["content"], "confidence": 1.0 }] }).to_string())
+        }));
+        let client = TranslationClient::new("p", "global", "token")
+            .with_transport(transport.clone())
+            .with_request_compression(100);
+        for content in ["short", &"long".repeat(100)] {
+            let request = DetectLanguageRequest::builder().content(content).build().unwrap();
+            assert_eq!(client.detect_language(&request).await.unwrap().languages[0].language_code, content);
+        }
+        let requests = transport.requests();
+        assert_eq!(requests[0].headers["accept-encoding"], "gzip");
+        assert!(!is_gzip(&requests[0].headers));
+        assert!(is_gzip(&requests[1].headers));
+    }
+
+    #[tokio::test]
+    async fn test_response_decompression() {
+        let client = TranslationClient::new("p", "global", "token")
+            .with_transport(Arc::new(GzipTransport(r#"{"translations": [{"translatedText": "Hallo"}]}"#)));
+        let request = TranslateTextRequest::builder().content("Hello").target("de").build().unwrap();
+        assert_eq!(client.translate_text(&request).await.unwrap().translations[0].translated_text, "Hallo");
+    }
+}
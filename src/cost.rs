@@ -0,0 +1,171 @@
+//! Counting billable characters and enforcing a spending budget.
+//!
+//! A `CostTracker` set through `TranslationClient::with_cost_tracker` counts the characters of
+//! every successful translateText and detectLanguage request, which is what the API bills, and
+//! estimates their cost at a price per million characters. With a budget, a request that would
+//! take the estimated cost over it is rejected with `Error::BudgetExceeded` before it is sent.
+//!
+//! ```
+//! use google_translation::cost::{CostTracker, DEFAULT_PRICE_PER_MILLION};
+//!
+//! let tracker = CostTracker::new(DEFAULT_PRICE_PER_MILLION).with_budget(50.0);
+//! let client = google_translation::TranslationClient::new("my-project", "global", "token")
+//!     .with_cost_tracker(tracker.clone());
+//! // ... translate ...
+//! println!("{} characters, about ${:.2}", tracker.stats().characters, tracker.stats().estimated_cost);
+//! ```
+//!
+//! Only the text sent is counted: strings answered by the cache or store cost nothing, and
+//! document and batch translations, billed by page or by the characters of files the client
+//! doesn't read, aren't tracked. The estimate ignores the free tier.
+
+use std::sync::{Arc, Mutex};
+
+use crate::*;
+
+/// The price of translateText and detectLanguage in US dollars per million characters.
+///
+/// https://cloud.google.com/translate/pricing
+pub const DEFAULT_PRICE_PER_MILLION: f64 = 20.0;
+
+/// The characters counted by a `CostTracker`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CostStats {
+    /// The successful requests counted.
+    pub requests: u64,
+    pub characters: u64,
+    /// The cost of `characters` at the tracker's price.
+    pub estimated_cost: f64,
+}
+
+/// Counts billable characters, see the module documentation. Clones share the same counts.
+#[derive(Clone, Debug)]
+pub struct CostTracker {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    price_per_million: f64,
+    budget: Option<f64>,
+    requests: u64,
+    characters: u64,
+    /// The characters of requests in flight, counted against the budget until they finish.
+    reserved: u64,
+}
+
+impl CostTracker {
+    /// A tracker estimating costs at `price_per_million` per million characters, without a budget.
+    pub fn new(price_per_million: f64) -> CostTracker {
+        CostTracker {
+            inner: Arc::new(Mutex::new(Inner { price_per_million, budget: None, requests: 0, characters: 0, reserved: 0 })),
+        }
+    }
+
+    /// Rejects requests that would take the estimated cost over `budget`.
+    pub fn with_budget(self, budget: f64) -> CostTracker {
+        self.inner.lock().unwrap().budget = Some(budget);
+        self
+    }
+
+    pub fn stats(&self) -> CostStats {
+        let inner = self.inner.lock().unwrap();
+        CostStats { requests: inner.requests, characters: inner.characters, estimated_cost: inner.cost(inner.characters) }
+    }
+
+    /// The budget left, or None without a budget.
+    pub fn remaining_budget(&self) -> Option<f64> {
+        let inner = self.inner.lock().unwrap();
+        inner.budget.map(|budget| (budget - inner.cost(inner.characters)).max(0.0))
+    }
+
+    /// Sets the counts back to zero, for example at the start of a billing period.
+    pub fn reset(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.requests = 0;
+        inner.characters = 0;
+    }
+
+    /// Counts `characters` against the budget for a request about to be sent, failing if they
+    /// would exceed it. The characters are counted once the reservation is committed.
+    pub(crate) fn reserve(&self, characters: usize) -> Result<Reservation> {
+        let characters = characters as u64;
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(budget) = inner.budget {
+            let cost = inner.cost(inner.characters + inner.reserved + characters);
+            if cost > budget {
+                return Err(Error::BudgetExceeded(format!(
+                    "sending {} more characters would bring the estimated cost to {:.2}, over the budget of {:.2}",
+                    characters, cost, budget)));
+            }
+        }
+        inner.reserved += characters;
+        Ok(Reservation { tracker: self.clone(), characters, committed: false })
+    }
+}
+
+impl Inner {
+    fn cost(&self, characters: u64) -> f64 {
+        characters as f64 * self.price_per_million / 1_000_000.0
+    }
+}
+
+/// Characters reserved for a request in flight. Released unless committed.
+pub(crate) struct Reservation {
+    tracker: CostTracker,
+    characters: u64,
+    committed: bool,
+}
+
+impl Reservation {
+    /// Counts the characters, once the request succeeded.
+    pub(crate) fn commit(mut self) {
+        let mut inner = self.tracker.inner.lock().unwrap();
+        inner.reserved -= self.characters;
+        inner.characters += self.characters;
+        inner.requests += 1;
+        self.committed = true;
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.tracker.inner.lock().unwrap().reserved -= self.characters;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+
+    #[tokio::test]
+    async fn test_cost_tracker() {
+        let transport = Arc::new(MockTransport::new(|request| match request.json()["contents"][0].as_str() {
+            Some("fail") => (400, r#"{"error": {"code": 400, "message": "Bad", "status": "INVALID_ARGUMENT"}}"#.to_string()),
+            _ => (200, r#"{"translations": [{"translatedText": "Hallo"}]}"#.to_string()),
+        }));
+        // A budget of 10 characters at $1 per million.
+        let tracker = CostTracker::new(1.0).with_budget(0.000_010);
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone())
+            .with_cost_tracker(tracker.clone());
+        let request = |text: &str| TranslateTextRequest::builder().content(text).target("de").build().unwrap();
+
+        client.translate_text(&request("Hello")).await.unwrap();
+        client.translate_text(&request("fail")).await.unwrap_err();
+        assert_eq!(tracker.stats(), CostStats { requests: 1, characters: 5, estimated_cost: 0.000_005 });
+
+        let error = client.translate_text(&request("Hello world")).await.unwrap_err();
+        assert!(matches!(error, Error::BudgetExceeded(_)), "{:?}", error);
+        assert!(error.is_quota());
+        assert_eq!(transport.requests().len(), 2);
+
+        client.translate_text(&request("Hi")).await.unwrap();
+        assert_eq!(tracker.stats().characters, 7);
+        assert!((tracker.remaining_budget().unwrap() - 0.000_003).abs() < 1e-12);
+        tracker.reset();
+        assert_eq!(tracker.stats(), CostStats::default());
+    }
+}
@@ -0,0 +1,150 @@
+//! Rendering requests as curl commands for troubleshooting.
+//!
+//! When a call fails, the quickest way to reproduce it, or to hand it to support, is the exact
+//! HTTP request as a command line. `curl_command` renders a request as an equivalent `curl`
+//! invocation, and `TranslationClient::with_curl_dump` passes the command for every request the
+//! client sends, including each retry, to a callback:
+//!
+//! ```
+//! let client = google_translation::TranslationClient::new("my-project", "global", "token")
+//!     .with_curl_dump(|command| eprintln!("{}", command));
+//! ```
+//!
+//! The access token, API keys and any header marked sensitive are replaced with "REDACTED"; substitute
+//! `$(gcloud auth print-access-token)` to run the command. Compressed request bodies are shown
+//! decompressed.
+
+use std::sync::Arc;
+
+use hyper::{Body, Request, Response};
+use hyper::header::{AUTHORIZATION, HeaderValue};
+
+use crate::{BoxFuture, Error, Result, compression, trace};
+use crate::transport::HttpTransport;
+
+/// Headers that curl sets itself or that `curl_command` renders as options.
+const SKIPPED_HEADERS: &[&str] = &["accept-encoding", "content-encoding", "content-length", "host"];
+
+/// An equivalent `curl` command for `request` with `body`, with credentials redacted.
+pub fn curl_command(request: &Request<()>, body: &[u8]) -> String {
+    let mut command = format!("curl -X {} {}", request.method(), quote(&trace::redact_url(&request.uri().to_string())));
+    for (name, value) in request.headers() {
+        if SKIPPED_HEADERS.contains(&name.as_str()) {
+            continue;
+        }
+        let value = if name == AUTHORIZATION || value.is_sensitive() {
+            redact(name == AUTHORIZATION, value).into()
+        } else {
+            String::from_utf8_lossy(value.as_bytes())
+        };
+        command.push_str(&format!(" \\\n  -H {}", quote(&format!("{}: {}", name, value))));
+    }
+    if request.headers().contains_key(hyper::header::ACCEPT_ENCODING) {
+        command.push_str(" \\\n  --compressed");
+    }
+    if !body.is_empty() {
+        let body = if compression::is_gzip(request.headers()) {
+            compression::gunzip(body).unwrap_or_else(|_| body.to_vec())
+        } else {
+            body.to_vec()
+        };
+        command.push_str(&format!(" \\\n  --data-raw {}", quote(&String::from_utf8_lossy(&body))));
+    }
+    command
+}
+
+/// "REDACTED", keeping the scheme of an Authorization header such as "Bearer" so that the command
+/// shows which kind of credential to substitute.
+fn redact(authorization: bool, value: &HeaderValue) -> String {
+    match value.to_str().ok().and_then(|value| value.split_once(' ')) {
+        Some((scheme, _)) if authorization => format!("{} REDACTED", scheme),
+        _ => "REDACTED".to_string(),
+    }
+}
+
+/// Quotes `text` for a POSIX shell.
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+type Sink = dyn Fn(&str) + Send + Sync;
+
+/// A transport that passes every request through `curl_command` to a callback before sending it
+/// with `inner`. See `TranslationClient::with_curl_dump`.
+pub struct CurlTransport {
+    inner: Arc<dyn HttpTransport>,
+    sink: Box<Sink>,
+}
+
+impl CurlTransport {
+    pub fn new<F>(inner: Arc<dyn HttpTransport>, sink: F) -> CurlTransport
+        where F: Fn(&str) + Send + Sync + 'static
+    {
+        CurlTransport { inner, sink: Box::new(sink) }
+    }
+}
+
+impl HttpTransport for CurlTransport {
+    fn send(&self, request: Request<Body>) -> BoxFuture<'_, Result<Response<Body>>> {
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let body = hyper::body::to_bytes(body).await.map_err(Error::HyperError)?;
+            let request = Request::from_parts(parts, ());
+            (self.sink)(&curl_command(&request, &body));
+            let (parts, ()) = request.into_parts();
+            self.inner.send(Request::from_parts(parts, Body::from(body))).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::*;
+    use crate::transport::MockTransport;
+
+    #[tokio::test]
+    async fn test_curl_dump() {
+        let transport = Arc::new(MockTransport::with_response(200, r#"{"translations": [{"translatedText": "Hallo"}]}"#));
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let client = TranslationClient::new("p", "global", "secret-token").with_transport(transport.clone())
+            .with_curl_dump({
+                let commands = commands.clone();
+                move |command| commands.lock().unwrap().push(command.to_string())
+            });
+        let request = TranslateTextRequest::builder().content("It's").target("de").build().unwrap();
+        client.translate_text(&request).await.unwrap();
+
+        assert_eq!(transport.requests()[0].json()["contents"][0], "It's");
+        let commands = commands.lock().unwrap();
+        assert_eq!(commands.len(), 1);
+        let command = &commands[0];
+        assert!(command.starts_with("curl -X POST 'https://translation.googleapis.com/v3beta1/projects/p/locations/global:translateText'"), "{}", command);
+        assert!(command.contains("-H 'authorization: Bearer REDACTED'"), "{}", command);
+        assert!(command.contains("-H 'content-type: application/json'"), "{}", command);
+        assert!(command.contains("--compressed"), "{}", command);
+        assert!(command.contains(r#"--data-raw '{"contents":["It'\''s"],"targetLanguageCode":"de"}'"#), "{}", command);
+        assert!(!command.contains("secret-token"), "{}", command);
+    }
+
+    #[test]
+    fn test_curl_command_redacts_sensitive_headers() {
+        let mut api_key = HeaderValue::from_static("secret-key");
+        api_key.set_sensitive(true);
+        let request = Request::post("https://translation.googleapis.com/v3/projects/p:translateText")
+            .header("x-goog-api-key", api_key)
+            .header("x-goog-user-project", "billing")
+            .header(hyper::header::CONTENT_ENCODING, "gzip")
+            .header(hyper::header::CONTENT_LENGTH, "42")
+            .body(())
+            .unwrap();
+        let command = curl_command(&request, &compression::gzip(br#"{"contents":["Hi"]}"#));
+        assert!(command.contains("-H 'x-goog-api-key: REDACTED'"), "{}", command);
+        assert!(command.contains("-H 'x-goog-user-project: billing'"), "{}", command);
+        assert!(!command.contains("secret-key"), "{}", command);
+        assert!(!command.contains("content-encoding") && !command.contains("content-length"), "{}", command);
+        assert!(command.ends_with(r#"--data-raw '{"contents":["Hi"]}'"#), "{}", command);
+    }
+}
@@ -0,0 +1,138 @@
+//! Defaults merged into every translateText request.
+//!
+//! Applications that always translate with the same custom models, glossary or tracking labels can
+//! set them once with `TranslationClient::with_request_defaults` instead of on every request.
+//! `translate_text`, and everything built on it such as `Translator`, fills in what a request leaves
+//! unset:
+//!
+//! - `model`: the model set with `with_model_for` for the request's language pair, or else the
+//!   model set with `with_model`. A pair only matches requests that name their source language.
+//! - `glossary_config`: the glossary set with `with_glossary_config`, only for requests that name
+//!   their source language, which the API requires with a glossary.
+//! - `labels`: the default labels are added to the request's, which win if both have a key.
+//!
+//! ```
+//! use google_translation::Labels;
+//! use google_translation::defaults::RequestDefaults;
+//!
+//! # fn run() -> google_translation::Result<()> {
+//! let defaults = RequestDefaults::new()
+//!     .with_model_for("en", "de", "projects/my-project/locations/us-central1/models/my-en-de-model")
+//!     .with_labels(Labels::new().with("team", "localization")?);
+//! let client = google_translation::TranslationClient::new("my-project", "us-central1", "token")
+//!     .with_request_defaults(defaults);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::borrow::Cow;
+
+use crate::*;
+
+/// Values filled into translateText requests, see the module documentation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RequestDefaults {
+    /// (source language, target language, model)
+    pair_models: Vec<(String, String, String)>,
+    model: Option<String>,
+    glossary_config: Option<TranslateTextGlossaryConfig>,
+    labels: Labels,
+}
+
+impl RequestDefaults {
+    pub fn new() -> RequestDefaults {
+        RequestDefaults::default()
+    }
+
+    /// Uses `model` for requests whose language pair has no model of its own.
+    pub fn with_model(mut self, model: impl Into<String>) -> RequestDefaults {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Uses `model` for requests from `source_language_code` to `target_language_code`. Language
+    /// codes are compared case-insensitively.
+    pub fn with_model_for(mut self, source_language_code: &str, target_language_code: &str, model: impl Into<String>) -> RequestDefaults {
+        self.pair_models.push((source_language_code.to_string(), target_language_code.to_string(), model.into()));
+        self
+    }
+
+    pub fn with_glossary_config(mut self, glossary_config: TranslateTextGlossaryConfig) -> RequestDefaults {
+        self.glossary_config = Some(glossary_config);
+        self
+    }
+
+    pub fn with_labels(mut self, labels: Labels) -> RequestDefaults {
+        self.labels = labels;
+        self
+    }
+
+    /// The model used for a request from `source_language_code` to `target_language_code` that
+    /// doesn't set one.
+    pub fn model_for(&self, source_language_code: Option<&str>, target_language_code: &str) -> Option<&str> {
+        source_language_code
+            .and_then(|source| self.pair_models.iter().find(|(s, t, _)|
+                s.eq_ignore_ascii_case(source) && t.eq_ignore_ascii_case(target_language_code)))
+            .map(|(_, _, model)| model.as_str())
+            .or(self.model.as_deref())
+    }
+
+    /// `request` with the defaults filled in, borrowed if there is nothing to fill in. Fails if the
+    /// merged labels are more than `Labels::MAX_LABELS`.
+    pub(crate) fn apply<'a>(&self, request: &'a TranslateTextRequest) -> Result<Cow<'a, TranslateTextRequest>> {
+        let mut request = Cow::Borrowed(request);
+        if request.model.is_none() {
+            if let Some(model) = self.model_for(request.source_language_code.as_deref(), &request.target_language_code) {
+                request.to_mut().model = Some(model.to_string());
+            }
+        }
+        if request.glossary_config.is_none() && request.source_language_code.is_some() && self.glossary_config.is_some() {
+            request.to_mut().glossary_config = self.glossary_config.clone();
+        }
+        if !self.labels.is_empty() {
+            let mut labels = self.labels.clone();
+            for (key, value) in request.labels.iter().flat_map(Labels::iter) {
+                labels.insert(key, value)?;
+            }
+            if request.labels.as_ref() != Some(&labels) {
+                request.to_mut().labels = Some(labels);
+            }
+        }
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+
+    #[tokio::test]
+    async fn test_request_defaults() {
+        let transport = Arc::new(MockTransport::with_response(200, r#"{"translations": [{"translatedText": "Hallo"}]}"#));
+        let defaults = RequestDefaults::new()
+            .with_model("general/nmt")
+            .with_model_for("en", "de", "models/en-de")
+            .with_glossary_config(TranslateTextGlossaryConfig::new("glossaries/g"))
+            .with_labels(Labels::new().with("team", "l10n").unwrap().with("env", "prod").unwrap());
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone())
+            .with_request_defaults(defaults);
+
+        let request = TranslateTextRequest::builder().content("Hello").target("DE").source("en")
+            .labels(Labels::new().with("env", "test").unwrap()).build().unwrap();
+        client.translate_text(&request).await.unwrap();
+        let request = TranslateTextRequest::builder().content("Hello").target("fr").model("models/mine").build().unwrap();
+        client.translate_text(&request).await.unwrap();
+        let request = TranslateTextRequest::builder().content("Hello").target("de").build().unwrap();
+        client.translate_text(&request).await.unwrap();
+
+        let requests: Vec<_> = transport.requests().iter().map(|request| request.json()).collect();
+        assert_eq!(requests[0]["model"], "models/en-de");
+        assert_eq!(requests[0]["glossaryConfig"]["glossary"], "glossaries/g");
+        assert_eq!(requests[0]["labels"], serde_json::json!({"team": "l10n", "env": "test"}));
+        assert_eq!(requests[1]["model"], "models/mine");
+        assert!(requests[1].get("glossaryConfig").is_none());
+        assert_eq!(requests[2]["model"], "general/nmt");
+        assert_eq!(requests[2]["labels"], serde_json::json!({"team": "l10n", "env": "prod"}));
+    }
+}
@@ -0,0 +1,174 @@
+//! Language detection of long and mixed-language documents.
+//!
+//! detectLanguage looks at a whole string at once, which is unreliable for long documents that mix
+//! languages. `TranslationClient::detect_language_of_document` splits the text with
+//! `chunking::split`, detects the language of each chunk and lets the chunks vote: each chunk
+//! whose most likely language reaches `min_confidence` votes for it with its confidence times its
+//! length in codepoints. The overall language is the one with the most votes, and its confidence
+//! is its share of the votes.
+//!
+//! ```no_run
+//! use google_translation::detection::DocumentDetectionConfig;
+//!
+//! # async fn run(client: google_translation::TranslationClient, text: &str) -> google_translation::Result<()> {
+//! let detection = client.detect_language_of_document(text, &DocumentDetectionConfig::default()).await?;
+//! println!("{:?} ({:.0}%)", detection.language_code, detection.confidence * 100.0);
+//! for chunk in &detection.chunks {
+//!     println!("{:?}: {:?}", &text[chunk.range.clone()], chunk.language_code);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::ops::Range;
+
+use futures::stream::{StreamExt, TryStreamExt};
+
+use crate::*;
+
+/// Options for `TranslationClient::detect_language_of_document`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocumentDetectionConfig {
+    /// The maximum number of codepoints per chunk.
+    pub chunk_codepoints: usize,
+    /// Chunks whose most likely language has a lower confidence don't vote.
+    pub min_confidence: f32,
+    /// The number of detectLanguage requests in flight at once.
+    pub concurrency: usize,
+    pub mime_type: Option<MimeType>,
+}
+
+impl Default for DocumentDetectionConfig {
+    fn default() -> Self {
+        DocumentDetectionConfig { chunk_codepoints: 1000, min_confidence: 0.5, concurrency: 4, mime_type: Some(MimeType::Plain) }
+    }
+}
+
+/// The language detected for one chunk of a document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChunkDetection {
+    /// The chunk's byte range in the document.
+    pub range: Range<usize>,
+    /// The chunk's most likely language, None if the API returned none.
+    pub language_code: Option<String>,
+    pub confidence: f32,
+    /// Whether the chunk voted, that is had a language with at least the minimum confidence.
+    pub voted: bool,
+}
+
+/// The result of `TranslationClient::detect_language_of_document`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocumentDetection {
+    /// The language with the most votes, None if no chunk voted.
+    pub language_code: Option<String>,
+    /// The overall language's share of the votes, from 0 to 1.
+    pub confidence: f32,
+    /// Every language voted for with its share of the votes, most votes first.
+    pub languages: Vec<DetectLanguageItem>,
+    /// The chunks in document order. Chunks of only whitespace aren't sent and don't appear.
+    pub chunks: Vec<ChunkDetection>,
+}
+
+impl TranslationClient {
+    /// Detects the language of `text` chunk by chunk, see the module documentation. Fails with the
+    /// first error.
+    pub async fn detect_language_of_document(&self, text: &str, config: &DocumentDetectionConfig)
+        -> Result<DocumentDetection>
+    {
+        assert!(config.concurrency > 0, "concurrency must be positive");
+        let chunks: Vec<Range<usize>> = chunking::split(text, config.chunk_codepoints).into_iter()
+            .filter(|chunk| !chunk.trim().is_empty())
+            .map(|chunk| {
+                let start = chunk.as_ptr() as usize - text.as_ptr() as usize;
+                start..start + chunk.len()
+            })
+            .collect();
+        let chunks: Vec<ChunkDetection> = futures::stream::iter(chunks.into_iter().map(|range| {
+                let request = DetectLanguageRequest {
+                    model: None,
+                    mime_type: config.mime_type.clone(),
+                    labels: None,
+                    content: text[range.clone()].to_string(),
+                };
+                async move {
+                    let top = self.detect_language(&request).await?.languages.into_iter().next();
+                    let confidence = top.as_ref().map_or(0.0, |language| language.confidence);
+                    Ok::<_, Error>(ChunkDetection {
+                        range,
+                        voted: top.is_some() && confidence >= config.min_confidence,
+                        language_code: top.map(|language| language.language_code),
+                        confidence,
+                    })
+                }
+            }))
+            .buffered(config.concurrency)
+            .try_collect()
+            .await?;
+        Ok(tally(text, chunks))
+    }
+}
+
+/// Counts the votes of `chunks` of `text`.
+fn tally(text: &str, chunks: Vec<ChunkDetection>) -> DocumentDetection {
+    let mut votes: Vec<(String, f32)> = Vec::new();
+    for chunk in chunks.iter().filter(|chunk| chunk.voted) {
+        let weight = chunk.confidence * text[chunk.range.clone()].chars().count() as f32;
+        let language_code = chunk.language_code.as_deref().unwrap_or_default();
+        match votes.iter_mut().find(|(code, _)| code == language_code) {
+            Some((_, total)) => *total += weight,
+            None => votes.push((language_code.to_string(), weight)),
+        }
+    }
+    let total: f32 = votes.iter().map(|(_, weight)| weight).sum();
+    let mut languages: Vec<DetectLanguageItem> = votes.into_iter()
+        .map(|(language_code, weight)| DetectLanguageItem {
+            language_code,
+            confidence: if total > 0.0 { weight / total } else { 0.0 },
+        })
+        .collect();
+    languages.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    DocumentDetection {
+        language_code: languages.first().map(|language| language.language_code.clone()),
+        confidence: languages.first().map_or(0.0, |language| language.confidence),
+        languages,
+        chunks,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+
+    #[tokio::test]
+    async fn test_detect_language_of_document() {
+        let transport = Arc::new(MockTransport::new(|request| {
+            let content = request.json()["content"].as_str().unwrap().to_string();
+            let (language_code, confidence) = match content.trim() {
+                text if text.starts_with("Guten") => ("de", 0.9),
+                text if text.starts_with("Bonjour") => ("fr", 0.3),
+                _ => ("en", 1.0),
+            };
+            (200, serde_json::json!({"languages": [{"languageCode": language_code, "confidence": confidence}]}).to_string())
+        }));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone());
+        let text = "Hello there, how are you?\n\nGuten Tag, wie geht es?\n\nBonjour, comment allez-vous?\n\n  \n\nGuten Abend.";
+        let config = DocumentDetectionConfig { chunk_codepoints: 30, ..DocumentDetectionConfig::default() };
+        let detection = client.detect_language_of_document(text, &config).await.unwrap();
+
+        assert_eq!(transport.requests().len(), 4);
+        let chunks: Vec<_> = detection.chunks.iter()
+            .map(|chunk| (text[chunk.range.clone()].trim(), chunk.language_code.as_deref().unwrap(), chunk.voted))
+            .collect();
+        assert_eq!(chunks, vec![
+            ("Hello there, how are you?", "en", true),
+            ("Guten Tag, wie geht es?", "de", true),
+            ("Bonjour, comment allez-vous?", "fr", false),
+            ("Guten Abend.", "de", true),
+        ]);
+        assert_eq!(detection.language_code.as_deref(), Some("de"));
+        let languages: Vec<_> = detection.languages.iter().map(|language| language.language_code.as_str()).collect();
+        assert_eq!(languages, vec!["de", "en"]);
+        assert!(detection.confidence > 0.5 && detection.confidence < 0.6, "{}", detection.confidence);
+    }
+}
@@ -0,0 +1,134 @@
+//! Validating and serializing calls without sending them.
+//!
+//! A client with `TranslationClient::with_dry_run` checks and serializes every call as usual but
+//! hands the request to a `DryRun` instead of the network. The `DryRun` records it and answers
+//! with a synthetic success, so CI can check that translation configs produce valid requests
+//! without credentials or quota:
+//!
+//! - translateText returns every string untranslated, and detectLanguage returns "und".
+//! - Calls that start a long-running operation, such as batchTranslateText or glossaries.create,
+//!   return an operation named "dry-run" that is never done, so don't wait for it.
+//! - Everything else returns an empty response: no languages, glossaries or operations.
+//!
+//! ```
+//! use google_translation::dry_run::DryRun;
+//! use google_translation::TranslateTextRequest;
+//!
+//! # async fn run() -> google_translation::Result<()> {
+//! let dry_run = DryRun::new();
+//! let client = google_translation::TranslationClient::new("my-project", "global", "unused")
+//!     .with_dry_run(dry_run.clone());
+//! let request = TranslateTextRequest::builder().content("Hello").target("de").build()?;
+//! client.translate_text(&request).await?;
+//! println!("{}", dry_run.requests()[0].json());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Only the network is skipped: the client still asks its token provider for a token, so use a
+//! static token rather than Application Default Credentials, and rate limiters and cost trackers
+//! still count the calls. Combine with `with_curl_dump` to print the requests.
+
+use std::sync::{Arc, Mutex};
+
+use hyper::{Body, Request, Response};
+use serde_json::json;
+
+use crate::{BoxFuture, Error, Result, compression, metrics};
+use crate::transport::{HttpTransport, MockRequest};
+
+/// Records the requests of a dry-run client, see the module documentation. Clones share the
+/// recorded requests.
+#[derive(Clone, Debug, Default)]
+pub struct DryRun {
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl DryRun {
+    pub fn new() -> DryRun {
+        DryRun::default()
+    }
+
+    /// The requests received so far, oldest first, with bodies decompressed.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// The synthetic response body for `request`.
+fn respond(request: &MockRequest) -> serde_json::Value {
+    let body = request.json();
+    let path = request.uri.split('?').next().unwrap_or_default();
+    match metrics::endpoint(&request.method, &request.uri).as_str() {
+        "translateText" => json!({
+            "translations": body["contents"].as_array().into_iter().flatten()
+                .map(|content| json!({"translatedText": content}))
+                .collect::<Vec<_>>(),
+        }),
+        "detectLanguage" => json!({"languages": [{"languageCode": "und", "confidence": 0.0}]}),
+        "getSupportedLanguages" => json!({"languages": []}),
+        "translateDocument" => json!({"documentTranslation": {"byteStreamOutputs": []}}),
+        "batchTranslateText" | "batchTranslateDocument" | "glossaries.create" | "glossaries.delete" | "glossaries.patch" => {
+            // projects/{project}/locations/{location}, without a custom method.
+            let location: Vec<&str> = path.find("projects/").map_or("", |i| &path[i..]).split('/').take(4).collect();
+            let location = location.join("/");
+            let location = location.split(':').next().unwrap_or_default();
+            json!({"name": format!("{}/operations/dry-run", location), "metadata": {}, "done": false})
+        }
+        _ => json!({}),
+    }
+}
+
+impl HttpTransport for DryRun {
+    fn send(&self, request: Request<Body>) -> BoxFuture<'_, Result<Response<Body>>> {
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let mut body = hyper::body::to_bytes(body).await.map_err(Error::HyperError)?.to_vec();
+            if compression::is_gzip(&parts.headers) {
+                body = compression::gunzip(&body)?;
+            }
+            let request = MockRequest { method: parts.method, uri: parts.uri.to_string(), headers: parts.headers, body };
+            let response = respond(&request);
+            self.requests.lock().unwrap().push(request);
+            let mut response = Response::new(Body::from(response.to_string()));
+            response.headers_mut().insert(hyper::header::CONTENT_TYPE, hyper::header::HeaderValue::from_static("application/json"));
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[tokio::test]
+    async fn test_dry_run() {
+        let dry_run = DryRun::new();
+        let client = TranslationClient::new("p", "us-central1", "token").with_dry_run(dry_run.clone());
+
+        let request = TranslateTextRequest::builder().content("Hello").content("World").target("de").build().unwrap();
+        let response = client.translate_text(&request).await.unwrap();
+        let translations: Vec<_> = response.translations.iter().map(|translation| translation.translated_text.as_str()).collect();
+        assert_eq!(translations, vec!["Hello", "World"]);
+
+        let glossary = Glossary::new("projects/p/locations/us-central1/glossaries/g",
+            GlossaryInputConfig { gcs_source: GcsSource { input_uri: "gs://bucket/g.tsv".to_string() } },
+            LanguageCodePair::new("en", "de"));
+        let operation = client.create_glossary(&glossary).await.unwrap();
+        assert_eq!(operation.name, "projects/p/locations/us-central1/operations/dry-run");
+        assert_eq!(operation.done, Some(false));
+
+        let params = ListGlossariesQueryParams { page_size: None, page_token: None, filter: None };
+        assert!(client.list_glossaries(&params).await.unwrap().glossaries.is_empty());
+
+        // Invalid requests are rejected before reaching the dry run.
+        let invalid = TranslateTextRequest::builder().contents(Vec::new()).target("de").build().unwrap();
+        assert!(matches!(client.translate_text(&invalid).await.unwrap_err(), Error::InvalidRequest(_)));
+
+        let requests = dry_run.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].json()["contents"], serde_json::json!(["Hello", "World"]));
+        assert_eq!(requests[1].json()["name"], "projects/p/locations/us-central1/glossaries/g");
+    }
+}
@@ -0,0 +1,263 @@
+//! The standard error detail messages of google.rpc, carried in `Status::details`.
+//!
+//! https://cloud.google.com/apis/design/errors#error_details
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// One entry of `Status::details`, decoded according to its "@type".
+///
+/// Details of other types, or that don't match their declared type, are kept as `Unknown`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorDetail {
+    ErrorInfo(ErrorInfo),
+    RetryInfo(RetryInfo),
+    DebugInfo(DebugInfo),
+    QuotaFailure(QuotaFailure),
+    PreconditionFailure(PreconditionFailure),
+    BadRequest(BadRequest),
+    RequestInfo(RequestInfo),
+    ResourceInfo(ResourceInfo),
+    Help(Help),
+    LocalizedMessage(LocalizedMessage),
+    Unknown(serde_json::Value),
+}
+
+const TYPE_URL_PREFIX: &str = "type.googleapis.com/google.rpc.";
+
+impl<'de> Deserialize<'de> for ErrorDetail {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let type_name = value.get("@type")
+            .and_then(serde_json::Value::as_str)
+            .and_then(|type_url| type_url.strip_prefix(TYPE_URL_PREFIX))
+            .unwrap_or("");
+        fn parse<T: serde::de::DeserializeOwned>(value: &serde_json::Value, variant: fn(T) -> ErrorDetail) -> Option<ErrorDetail> {
+            serde_json::from_value(value.clone()).ok().map(variant)
+        }
+        let detail = match type_name {
+            "ErrorInfo" => parse(&value, ErrorDetail::ErrorInfo),
+            "RetryInfo" => parse(&value, ErrorDetail::RetryInfo),
+            "DebugInfo" => parse(&value, ErrorDetail::DebugInfo),
+            "QuotaFailure" => parse(&value, ErrorDetail::QuotaFailure),
+            "PreconditionFailure" => parse(&value, ErrorDetail::PreconditionFailure),
+            "BadRequest" => parse(&value, ErrorDetail::BadRequest),
+            "RequestInfo" => parse(&value, ErrorDetail::RequestInfo),
+            "ResourceInfo" => parse(&value, ErrorDetail::ResourceInfo),
+            "Help" => parse(&value, ErrorDetail::Help),
+            "LocalizedMessage" => parse(&value, ErrorDetail::LocalizedMessage),
+            _ => None,
+        };
+        Ok(detail.unwrap_or(ErrorDetail::Unknown(value)))
+    }
+}
+
+impl Serialize for ErrorDetail {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        fn with_type<T: Serialize>(detail: &T, type_name: &str) -> serde_json::Result<serde_json::Value> {
+            let mut value = serde_json::to_value(detail)?;
+            if let serde_json::Value::Object(fields) = &mut value {
+                fields.insert("@type".to_string(), format!("{}{}", TYPE_URL_PREFIX, type_name).into());
+            }
+            Ok(value)
+        }
+        let value = match self {
+            ErrorDetail::ErrorInfo(detail) => with_type(detail, "ErrorInfo"),
+            ErrorDetail::RetryInfo(detail) => with_type(detail, "RetryInfo"),
+            ErrorDetail::DebugInfo(detail) => with_type(detail, "DebugInfo"),
+            ErrorDetail::QuotaFailure(detail) => with_type(detail, "QuotaFailure"),
+            ErrorDetail::PreconditionFailure(detail) => with_type(detail, "PreconditionFailure"),
+            ErrorDetail::BadRequest(detail) => with_type(detail, "BadRequest"),
+            ErrorDetail::RequestInfo(detail) => with_type(detail, "RequestInfo"),
+            ErrorDetail::ResourceInfo(detail) => with_type(detail, "ResourceInfo"),
+            ErrorDetail::Help(detail) => with_type(detail, "Help"),
+            ErrorDetail::LocalizedMessage(detail) => with_type(detail, "LocalizedMessage"),
+            ErrorDetail::Unknown(value) => return value.serialize(serializer),
+        };
+        value.map_err(serde::ser::Error::custom)?.serialize(serializer)
+    }
+}
+
+/// The reason for an error, for example "SERVICE_DISABLED" or "RATE_LIMIT_EXCEEDED".
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ErrorInfo {
+    /// A constant UPPER_SNAKE_CASE value identifying the proximate cause of the error.
+    pub reason: String,
+    /// The logical grouping of `reason`, typically the service name, such as "googleapis.com".
+    pub domain: String,
+    /// Additional structured details, such as the consumer or quota limit involved.
+    pub metadata: HashMap<String, String>,
+}
+
+/// When the client may retry a failed request.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetryInfo {
+    /// A protobuf Duration in JSON form, for example "3.5s".
+    pub retry_delay: Option<String>,
+}
+
+impl RetryInfo {
+    pub fn delay(&self) -> Option<Duration> {
+        let seconds: f64 = self.retry_delay.as_deref()?.strip_suffix('s')?.parse().ok()?;
+        Duration::try_from_secs_f64(seconds).ok()
+    }
+}
+
+/// Debugging information provided by the server.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DebugInfo {
+    pub stack_entries: Vec<String>,
+    pub detail: String,
+}
+
+/// Which quota checks failed.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QuotaFailure {
+    pub violations: Vec<QuotaViolation>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QuotaViolation {
+    /// The subject on which the quota check failed, for example "project:my-project".
+    pub subject: String,
+    pub description: String,
+    /// The API service the quota belongs to, for example "translate.googleapis.com".
+    pub api_service: String,
+    /// The metric of the violated quota, for example "translate.googleapis.com/default_requests".
+    pub quota_metric: String,
+    /// The id of the violated quota limit.
+    pub quota_id: String,
+    pub quota_dimensions: HashMap<String, String>,
+}
+
+/// Which preconditions failed.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PreconditionFailure {
+    pub violations: Vec<PreconditionViolation>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PreconditionViolation {
+    /// A service-specific type of the violation, for example "TOS".
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub subject: String,
+    pub description: String,
+}
+
+/// Violations of the request's fields.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BadRequest {
+    pub field_violations: Vec<FieldViolation>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FieldViolation {
+    /// A path to the offending field, for example "contents[2]" or "glossary_config.glossary".
+    pub field: String,
+    pub description: String,
+    /// A constant UPPER_SNAKE_CASE reason for the violation.
+    pub reason: String,
+    pub localized_message: Option<LocalizedMessage>,
+}
+
+/// Metadata about the request, to quote when filing a bug or contacting support.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RequestInfo {
+    pub request_id: String,
+    pub serving_data: String,
+}
+
+/// The resource that is being accessed.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResourceInfo {
+    pub resource_type: String,
+    pub resource_name: String,
+    pub owner: String,
+    pub description: String,
+}
+
+/// Links to documentation about the error.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Help {
+    pub links: Vec<Link>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Link {
+    pub description: String,
+    pub url: String,
+}
+
+/// An error message localized for the end user.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LocalizedMessage {
+    /// The locale of `message`, for example "en-US".
+    pub locale: String,
+    pub message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_details() {
+        let details: Vec<ErrorDetail> = serde_json::from_str(r#"[
+            {
+                "@type": "type.googleapis.com/google.rpc.ErrorInfo",
+                "reason": "RATE_LIMIT_EXCEEDED",
+                "domain": "googleapis.com",
+                "metadata": {"service": "translate.googleapis.com"}
+            },
+            {
+                "@type": "type.googleapis.com/google.rpc.QuotaFailure",
+                "violations": [{"quotaMetric": "translate.googleapis.com/default_requests", "quotaId": "DefaultRequestsPerMinutePerProject"}]
+            },
+            {
+                "@type": "type.googleapis.com/google.rpc.BadRequest",
+                "fieldViolations": [{"field": "target_language_code", "description": "Target language is invalid."}]
+            },
+            {"@type": "type.googleapis.com/google.rpc.RetryInfo", "retryDelay": "1.500s"},
+            {"@type": "type.googleapis.com/google.rpc.Custom", "value": 1}
+        ]"#).unwrap();
+        match &details[0] {
+            ErrorDetail::ErrorInfo(info) => {
+                assert_eq!(info.reason, "RATE_LIMIT_EXCEEDED");
+                assert_eq!(info.metadata["service"], "translate.googleapis.com");
+            },
+            detail => panic!("unexpected detail: {:?}", detail),
+        }
+        match &details[1] {
+            ErrorDetail::QuotaFailure(failure) => assert_eq!(failure.violations[0].quota_metric, "translate.googleapis.com/default_requests"),
+            detail => panic!("unexpected detail: {:?}", detail),
+        }
+        match &details[2] {
+            ErrorDetail::BadRequest(bad_request) => assert_eq!(bad_request.field_violations[0].field, "target_language_code"),
+            detail => panic!("unexpected detail: {:?}", detail),
+        }
+        match &details[3] {
+            ErrorDetail::RetryInfo(retry_info) => assert_eq!(retry_info.delay(), Some(Duration::from_millis(1500))),
+            detail => panic!("unexpected detail: {:?}", detail),
+        }
+        assert!(matches!(&details[4], ErrorDetail::Unknown(value) if value["value"] == 1));
+        let round_trip: Vec<ErrorDetail> = serde_json::from_value(serde_json::to_value(&details).unwrap()).unwrap();
+        assert_eq!(round_trip, details);
+    }
+}
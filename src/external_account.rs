@@ -0,0 +1,471 @@
+//! Workload Identity Federation: access tokens for workloads running outside Google Cloud.
+//!
+//! An external account credentials file, created with `gcloud iam workload-identity-pools
+//! create-cred-config`, says where to find a credential issued by another identity provider and
+//! which workload identity pool provider trusts it. `ExternalAccountCredentials` reads that
+//! credential, exchanges it for a Google access token with the Security Token Service and, if the
+//! file names a service account to impersonate, exchanges that token for one of the service
+//! account. The credential is read again at every refresh, so rotated tokens are picked up.
+//!
+//! The credential comes from one of these `credential_source`s:
+//!
+//! - `file`: a file holding an OIDC ID token or SAML assertion, such as a Kubernetes projected
+//!   service account token.
+//! - `url`: an endpoint returning the token, such as a CI system's OIDC token endpoint or Azure's
+//!   instance metadata service.
+//! - `environment_id` "aws1": the AWS credentials from the environment variables or the EC2
+//!   instance metadata, which sign a GetCallerIdentity request that Google verifies with AWS.
+//!
+//! Executable-sourced credentials aren't supported. `auth::credentials_from_file` and
+//! `auth::application_default_credentials` recognize external account files, so pointing
+//! GOOGLE_APPLICATION_CREDENTIALS at the file is usually all a CI job needs.
+//!
+//! https://google.aip.dev/auth/4117
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use base64::Engine;
+use hyper::{Body, Method, Request};
+use hyper::header::{HeaderName, HeaderValue};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+
+use crate::{BoxFuture, Error, HttpsClient, Result, new_https_client};
+use crate::auth::{self, AccessToken, CLOUD_PLATFORM_SCOPE, TokenCache, TokenProvider};
+
+const DEFAULT_STS_URL: &str = "https://sts.googleapis.com/v1/token";
+const DEFAULT_IMPERSONATION_LIFETIME: Duration = Duration::from_secs(3600);
+
+/// The contents of an external account credentials file.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ExternalAccount {
+    /// The workload identity pool provider, for example "//iam.googleapis.com/projects/123/locations/global/workloadIdentityPools/pool/providers/provider".
+    pub audience: String,
+    /// The type of the credential, for example "urn:ietf:params:oauth:token-type:jwt".
+    pub subject_token_type: String,
+    /// The Security Token Service URL, "https://sts.googleapis.com/v1/token" if missing.
+    pub token_url: Option<String>,
+    /// The generateAccessToken URL of the service account to impersonate, if any.
+    pub service_account_impersonation_url: Option<String>,
+    pub service_account_impersonation: Option<ServiceAccountImpersonation>,
+    pub credential_source: CredentialSource,
+    pub quota_project_id: Option<String>,
+    /// The project billed for workforce identity federation, when `audience` is a workforce pool.
+    pub workforce_pool_user_project: Option<String>,
+    /// The OAuth client authenticating to the Security Token Service, if any.
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+impl std::fmt::Debug for ExternalAccount {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ExternalAccount")
+            .field("audience", &self.audience)
+            .field("subject_token_type", &self.subject_token_type)
+            .field("service_account_impersonation_url", &self.service_account_impersonation_url)
+            .field("credential_source", &self.credential_source)
+            .field("quota_project_id", &self.quota_project_id)
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ServiceAccountImpersonation {
+    /// The lifetime of the service account's tokens, an hour if missing.
+    pub token_lifetime_seconds: Option<u64>,
+}
+
+/// Where an `ExternalAccount` finds its credential, see the module documentation.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct CredentialSource {
+    pub file: Option<String>,
+    /// The URL returning the credential, or for AWS the instance metadata URL of the role's security credentials.
+    pub url: Option<String>,
+    /// Headers sent to `url`.
+    pub headers: Option<HashMap<String, String>>,
+    pub format: Option<CredentialSourceFormat>,
+    /// "aws1" for AWS.
+    pub environment_id: Option<String>,
+    /// The instance metadata URL of the availability zone, used if AWS_REGION isn't set.
+    pub region_url: Option<String>,
+    /// The GetCallerIdentity URL signed for Google, with a "{region}" placeholder.
+    pub regional_cred_verification_url: Option<String>,
+    /// The URL of an IMDSv2 session token for the instance metadata requests.
+    pub imdsv2_session_token_url: Option<String>,
+    pub executable: Option<serde_json::Value>,
+}
+
+/// How to read the credential from a file or URL response.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CredentialSourceFormat {
+    /// "text" if the whole content is the credential, "json" if it is a field of a JSON object.
+    #[serde(rename = "type")]
+    pub format_type: String,
+    /// The field holding the credential, for "json".
+    pub subject_token_field_name: Option<String>,
+}
+
+impl CredentialSource {
+    /// The credential in `content`, read from the file or URL.
+    fn parse(&self, content: &str) -> Result<String> {
+        let token = match &self.format {
+            Some(format) if format.format_type == "json" => {
+                let field = format.subject_token_field_name.as_deref()
+                    .ok_or_else(|| Error::AuthError("credential_source.format has no subject_token_field_name".to_string()))?;
+                let value: serde_json::Value = serde_json::from_str(content).map_err(Error::SerdeJsonError)?;
+                value[field].as_str()
+                    .ok_or_else(|| Error::AuthError(format!("the credential has no \"{}\" field", field)))?
+                    .to_string()
+            }
+            _ => content.trim().to_string(),
+        };
+        if token.is_empty() {
+            return Err(Error::AuthError("the credential source returned an empty credential".to_string()));
+        }
+        Ok(token)
+    }
+
+    fn required(&self, value: &Option<String>, field: &str) -> Result<String> {
+        value.clone().ok_or_else(|| Error::AuthError(format!("credential_source has no {}", field)))
+    }
+}
+
+/// Exchanges an external credential for access tokens, see the module documentation.
+pub struct ExternalAccountCredentials {
+    account: ExternalAccount,
+    scopes: Vec<String>,
+    http: HttpsClient,
+    cache: TokenCache,
+}
+
+impl ExternalAccountCredentials {
+    pub fn new(account: ExternalAccount) -> ExternalAccountCredentials {
+        ExternalAccountCredentials {
+            account,
+            scopes: vec![CLOUD_PLATFORM_SCOPE.to_string()],
+            http: new_https_client(),
+            cache: TokenCache::default(),
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<ExternalAccountCredentials> {
+        Ok(ExternalAccountCredentials::new(auth::read_json_file(path.as_ref())?))
+    }
+
+    pub fn account(&self) -> &ExternalAccount {
+        &self.account
+    }
+
+    /// Requests tokens with `scopes` instead of `CLOUD_PLATFORM_SCOPE`.
+    pub fn with_scopes<I>(mut self, scopes: I) -> ExternalAccountCredentials
+        where I: IntoIterator, I::Item: Into<String>
+    {
+        self.scopes = auth::scope_list(scopes);
+        self
+    }
+
+    /// Sends a request without a body and returns the response body.
+    async fn fetch(&self, method: Method, url: &str, headers: &[(&str, &str)]) -> Result<String> {
+        let mut req = Request::new(Body::empty());
+        *req.method_mut() = method;
+        *req.uri_mut() = url.parse().map_err(|_| Error::AuthError(format!("invalid credential source url: {}", url)))?;
+        for (name, value) in headers {
+            match (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+                (Ok(name), Ok(value)) => req.headers_mut().insert(name, value),
+                _ => return Err(Error::AuthError(format!("invalid credential source header: {}", name))),
+            };
+        }
+        let res = self.http.request(req).await?;
+        let status = res.status();
+        let body = hyper::body::to_bytes(res.into_body()).await?;
+        if !status.is_success() {
+            return Err(Error::AuthError(format!("{} returned {}", url, status)));
+        }
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// Reads the external credential.
+    async fn subject_token(&self) -> Result<String> {
+        let source = &self.account.credential_source;
+        match (&source.environment_id, &source.file, &source.url) {
+            (Some(environment_id), _, _) if environment_id == "aws1" => self.aws_subject_token().await,
+            (Some(environment_id), _, _) => Err(Error::AuthError(format!("unsupported environment_id: {}", environment_id))),
+            (None, Some(path), _) => source.parse(&tokio::fs::read_to_string(path).await.map_err(Error::IoError)?),
+            (None, None, Some(url)) => {
+                let headers: Vec<(&str, &str)> = source.headers.iter().flatten()
+                    .map(|(name, value)| (name.as_str(), value.as_str()))
+                    .collect();
+                source.parse(&self.fetch(Method::GET, url, &headers).await?)
+            }
+            _ if source.executable.is_some() => Err(Error::AuthError("executable-sourced credentials are not supported".to_string())),
+            _ => Err(Error::AuthError("credential_source has no file, url or environment_id".to_string())),
+        }
+    }
+
+    /// A GetCallerIdentity request signed with the AWS credentials, serialized the way the
+    /// Security Token Service expects.
+    async fn aws_subject_token(&self) -> Result<String> {
+        let source = &self.account.credential_source;
+        let env = |name| std::env::var(name).ok().filter(|value: &String| !value.is_empty());
+        let region = env("AWS_REGION").or_else(|| env("AWS_DEFAULT_REGION"));
+        let credentials = match (env("AWS_ACCESS_KEY_ID"), env("AWS_SECRET_ACCESS_KEY")) {
+            (Some(access_key_id), Some(secret_access_key)) => {
+                Some(AwsCredentials { access_key_id, secret_access_key, token: env("AWS_SESSION_TOKEN") })
+            }
+            _ => None,
+        };
+        // With IMDSv2, the instance metadata requests need a session token.
+        let mut session_token = None;
+        if region.is_none() || credentials.is_none() {
+            if let Some(url) = &source.imdsv2_session_token_url {
+                session_token = Some(self.fetch(Method::PUT, url, &[("x-aws-ec2-metadata-token-ttl-seconds", "300")]).await?);
+            }
+        }
+        let metadata_headers: Vec<(&str, &str)> = session_token.iter()
+            .map(|token| ("x-aws-ec2-metadata-token", token.as_str()))
+            .collect();
+        let region = match region {
+            Some(region) => region,
+            None => {
+                // The availability zone, such as "us-east-1b".
+                let zone = self.fetch(Method::GET, &source.required(&source.region_url, "region_url")?, &metadata_headers).await?;
+                let zone = zone.trim();
+                zone[..zone.len().saturating_sub(1)].to_string()
+            }
+        };
+        let credentials = match credentials {
+            Some(credentials) => credentials,
+            None => {
+                let url = source.required(&source.url, "url")?;
+                let url = url.trim_end_matches('/');
+                let role = self.fetch(Method::GET, url, &metadata_headers).await?;
+                let credentials = self.fetch(Method::GET, &format!("{}/{}", url, role.trim()), &metadata_headers).await?;
+                serde_json::from_str(&credentials).map_err(Error::SerdeJsonError)?
+            }
+        };
+        let url = source.required(&source.regional_cred_verification_url, "regional_cred_verification_url")?
+            .replace("{region}", &region);
+        let headers = aws_signed_headers(&credentials, &region, "sts", "POST", &url,
+            &[("x-goog-cloud-target-resource", &self.account.audience)], SystemTime::now())?;
+        let request = AwsSignedRequest {
+            headers: headers.into_iter().map(|(key, value)| AwsHeader { key, value }).collect(),
+            method: "POST",
+            url,
+        };
+        Ok(percent_encode(&serde_json::to_string(&request).map_err(Error::SerdeJsonError)?))
+    }
+
+    async fn refresh(&self) -> Result<AccessToken> {
+        let account = &self.account;
+        let mut form = vec![
+            ("grant_type", "urn:ietf:params:oauth:grant-type:token-exchange".to_string()),
+            ("audience", account.audience.clone()),
+            ("scope", self.scopes.join(" ")),
+            ("requested_token_type", "urn:ietf:params:oauth:token-type:access_token".to_string()),
+            ("subject_token", self.subject_token().await?),
+            ("subject_token_type", account.subject_token_type.clone()),
+        ];
+        if let (Some(project), None) = (&account.workforce_pool_user_project, &account.client_id) {
+            form.push(("options", serde_json::json!({"userProject": project}).to_string()));
+        }
+        let mut req = auth::form_request(account.token_url.as_deref().unwrap_or(DEFAULT_STS_URL), &form)?;
+        if let Some(client_id) = &account.client_id {
+            let credentials = format!("{}:{}", client_id, account.client_secret.as_deref().unwrap_or_default());
+            let value = format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(credentials));
+            req.headers_mut().insert(hyper::header::AUTHORIZATION, HeaderValue::from_str(&value)
+                .map_err(|_| Error::AuthError("the client id contains characters not allowed in a header".to_string()))?);
+        }
+        let token = auth::fetch_token(&self.http, req).await?;
+        match &account.service_account_impersonation_url {
+            Some(url) => {
+                let lifetime = account.service_account_impersonation.as_ref()
+                    .and_then(|impersonation| impersonation.token_lifetime_seconds)
+                    .map_or(DEFAULT_IMPERSONATION_LIFETIME, Duration::from_secs);
+                auth::generate_access_token(&self.http, url, &[], &self.scopes, &token.token, lifetime).await
+            }
+            None => Ok(token),
+        }
+    }
+}
+
+impl TokenProvider for ExternalAccountCredentials {
+    fn access_token(&self) -> BoxFuture<'_, Result<AccessToken>> {
+        Box::pin(self.cache.get_or_refresh(self.refresh()))
+    }
+
+    /// The number of the project of the workload identity pool, which the API accepts in place of
+    /// its ID, or the `workforce_pool_user_project` of a workforce pool.
+    fn project_id(&self) -> BoxFuture<'_, Result<Option<String>>> {
+        let project = self.account.audience.split_once("/projects/")
+            .and_then(|(_, rest)| rest.split('/').next())
+            .filter(|project| !project.is_empty())
+            .map(str::to_string)
+            .or_else(|| self.account.workforce_pool_user_project.clone());
+        Box::pin(async move { Ok(project) })
+    }
+}
+
+/// AWS security credentials, as returned by the instance metadata service.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AwsCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    token: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AwsSignedRequest {
+    headers: Vec<AwsHeader>,
+    method: &'static str,
+    url: String,
+}
+
+#[derive(Serialize)]
+struct AwsHeader {
+    key: String,
+    value: String,
+}
+
+/// The headers, "Authorization" included, of a request without a body signed with AWS Signature
+/// Version 4 at `time`. The query of `url` must already be percent-encoded.
+///
+/// https://docs.aws.amazon.com/IAM/latest/UserGuide/create-signed-request.html
+fn aws_signed_headers(credentials: &AwsCredentials, region: &str, service: &str, method: &str, url: &str,
+        extra_headers: &[(&str, &str)], time: SystemTime)
+    -> Result<BTreeMap<String, String>>
+{
+    let uri: hyper::Uri = url.parse().map_err(|_| Error::AuthError(format!("invalid AWS url: {}", url)))?;
+    let host = uri.authority().ok_or_else(|| Error::AuthError(format!("invalid AWS url: {}", url)))?;
+    // The same "YYYYMMDDThhmmssZ" format as TMX.
+    let amz_date = crate::tmx::tmx_date(time);
+    let date = &amz_date[..8];
+
+    let mut headers = BTreeMap::new();
+    headers.insert("host".to_string(), host.to_string());
+    headers.insert("x-amz-date".to_string(), amz_date.clone());
+    if let Some(token) = &credentials.token {
+        headers.insert("x-amz-security-token".to_string(), token.clone());
+    }
+    for (name, value) in extra_headers {
+        headers.insert(name.to_lowercase(), value.to_string());
+    }
+    let signed_headers = headers.keys().map(String::as_str).collect::<Vec<_>>().join(";");
+    let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect();
+    let mut query: Vec<&str> = uri.query().unwrap_or_default().split('&').filter(|param| !param.is_empty()).collect();
+    query.sort_unstable();
+    let canonical_request = format!("{}\n{}\n{}\n{}\n{}\n{}", method, uri.path(), query.join("&"), canonical_headers,
+        signed_headers, hex(&Sha256::digest(b"")));
+
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", amz_date, scope, hex(&Sha256::digest(canonical_request.as_bytes())));
+    let mut key = format!("AWS4{}", credentials.secret_access_key).into_bytes();
+    for part in [date, region, service, "aws4_request"] {
+        key = hmac_sha256(&key, part.as_bytes()).to_vec();
+    }
+    let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+    headers.insert("Authorization".to_string(), format!("AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key_id, scope, signed_headers, signature));
+    Ok(headers)
+}
+
+/// HMAC-SHA256 (RFC 2104).
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > block.len() {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.iter().map(|b| b ^ byte).collect::<Vec<_>>();
+    let inner = Sha256::new().chain_update(pad(0x36)).chain_update(message).finalize();
+    Sha256::new().chain_update(pad(0x5c)).chain_update(inner).finalize().into()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Percent-encodes everything but unreserved characters and '/'.
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::UNIX_EPOCH;
+
+    use hyper::service::{make_service_fn, service_fn};
+
+    use super::*;
+
+    #[test]
+    fn test_aws_signature() {
+        assert_eq!(hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+
+        // The get-vanilla case of the AWS Signature Version 4 test suite.
+        let credentials = AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            token: None,
+        };
+        let time = UNIX_EPOCH + Duration::from_secs(1_440_938_160);
+        let headers = aws_signed_headers(&credentials, "us-east-1", "service", "GET", "https://example.amazonaws.com/", &[], time).unwrap();
+        assert_eq!(headers["x-amz-date"], "20150830T123600Z");
+        assert_eq!(headers["Authorization"], "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+            SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31");
+    }
+
+    #[tokio::test]
+    async fn test_external_account_token_exchange() {
+        let make_service = make_service_fn(|_| async {
+            Ok::<_, hyper::Error>(service_fn(|req: Request<Body>| async move {
+                let path = req.uri().path().to_string();
+                let authorization = req.headers().get(hyper::header::AUTHORIZATION).map(|value| value.to_str().unwrap().to_string());
+                let body = String::from_utf8(hyper::body::to_bytes(req.into_body()).await?.to_vec()).unwrap();
+                let (status, body) = match path.as_str() {
+                    "/token" if body.contains("subject_token=oidc-token") && body.contains("audience=%2F%2Fiam.googleapis.com") => {
+                        (200, r#"{"access_token": "federated-token", "expires_in": 3600}"#)
+                    }
+                    "/impersonate" if authorization.as_deref() == Some("Bearer federated-token") => {
+                        (200, r#"{"accessToken": "service-account-token", "expireTime": "2030-01-01T00:00:00Z"}"#)
+                    }
+                    _ => (400, r#"{"error": "invalid_request"}"#),
+                };
+                Ok::<_, hyper::Error>(hyper::Response::builder().status(status).body(Body::from(body)).unwrap())
+            }))
+        });
+        let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let endpoint = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        let path = std::env::temp_dir().join(format!("google-translation-oidc-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"id_token": "oidc-token"}"#).unwrap();
+        let account: ExternalAccount = serde_json::from_value(serde_json::json!({
+            "type": "external_account",
+            "audience": "//iam.googleapis.com/projects/123/locations/global/workloadIdentityPools/pool/providers/ci",
+            "subject_token_type": "urn:ietf:params:oauth:token-type:jwt",
+            "token_url": format!("{}/token", endpoint),
+            "credential_source": {"file": path, "format": {"type": "json", "subject_token_field_name": "id_token"}},
+        })).unwrap();
+        let credentials = ExternalAccountCredentials::new(account.clone());
+        assert_eq!(credentials.access_token().await.unwrap().token.expose(), "federated-token");
+        assert_eq!(credentials.project_id().await.unwrap().as_deref(), Some("123"));
+
+        let credentials: Arc<dyn TokenProvider> = Arc::new(ExternalAccountCredentials::new(ExternalAccount {
+            service_account_impersonation_url: Some(format!("{}/impersonate", endpoint)),
+            ..account
+        }));
+        assert_eq!(credentials.access_token().await.unwrap().token.expose(), "service-account-token");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,151 @@
+//! Retrying failed custom-model translations with the general NMT model.
+//!
+//! AutoML and other custom models don't support every language pair and are sometimes undeployed
+//! or unavailable. With a `ModelFallback` set through `TranslationClient::with_model_fallback`, a
+//! translateText request that names a model and fails with one of the fallback's error codes is
+//! sent again with the general model, "general/nmt" in the client's project and location unless
+//! `with_model` says otherwise. Errors that aren't the API's, such as rejected requests or
+//! connection failures, are returned as they are.
+//!
+//! The `model` of every translation in a response served by the fallback is set to the fallback
+//! model, so callers can tell which model translated the text:
+//!
+//! ```no_run
+//! use google_translation::fallback::ModelFallback;
+//! use google_translation::TranslateTextRequest;
+//!
+//! # async fn run() -> google_translation::Result<()> {
+//! let client = google_translation::TranslationClient::new("my-project", "us-central1", "token")
+//!     .with_model_fallback(ModelFallback::new());
+//! let model = "projects/my-project/locations/us-central1/models/my-en-de-model";
+//! let request = TranslateTextRequest::builder().content("Hello").source("en").target("de").model(model).build()?;
+//! let response = client.translate_text(&request).await?;
+//! if response.translations[0].model.as_deref() != Some(model) {
+//!     println!("translated by {:?}", response.translations[0].model);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Chunked requests fall back chunk by chunk, so a response may mix models.
+
+use crate::*;
+
+/// The error codes a `ModelFallback` falls back on by default: the model doesn't support the
+/// language pair or request, doesn't exist, isn't deployed, or failed.
+pub const DEFAULT_FALLBACK_CODES: &[ErrorCode] = &[
+    ErrorCode::InvalidArgument,
+    ErrorCode::NotFound,
+    ErrorCode::FailedPrecondition,
+    ErrorCode::Unimplemented,
+    ErrorCode::Internal,
+];
+
+/// When and to which model to fall back, see the module documentation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelFallback {
+    model: Option<String>,
+    codes: Vec<ErrorCode>,
+}
+
+impl Default for ModelFallback {
+    fn default() -> ModelFallback {
+        ModelFallback { model: None, codes: DEFAULT_FALLBACK_CODES.to_vec() }
+    }
+}
+
+impl ModelFallback {
+    /// Falls back to "general/nmt" on `DEFAULT_FALLBACK_CODES`.
+    pub fn new() -> ModelFallback {
+        ModelFallback::default()
+    }
+
+    /// Falls back to `model`, a full model name, instead of "general/nmt".
+    pub fn with_model(mut self, model: impl Into<String>) -> ModelFallback {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Falls back on errors with one of `codes` instead of `DEFAULT_FALLBACK_CODES`.
+    pub fn with_codes(mut self, codes: &[ErrorCode]) -> ModelFallback {
+        self.codes = codes.to_vec();
+        self
+    }
+
+    /// The model to fall back to for requests sent by `client`.
+    pub(crate) fn model(&self, client: &TranslationClient) -> Result<String> {
+        match &self.model {
+            Some(model) => Ok(model.clone()),
+            None => Ok(ModelName::general_nmt(&client.project_location()?).to_string()),
+        }
+    }
+
+    /// Whether `request`, which failed with `error`, should be sent again with `fallback_model`.
+    pub(crate) fn applies(&self, request: &TranslateTextRequest, error: &Error, fallback_model: &str) -> bool {
+        let model = match &request.model {
+            Some(model) => model,
+            None => return false,
+        };
+        model != fallback_model
+            && matches!(error, Error::ResponseError(..) | Error::HttpError(..))
+            && error.code().is_some_and(|code| self.codes.contains(&code))
+    }
+}
+
+/// Sends `request` and, if it fails in a way `fallback` covers, sends it again with the fallback
+/// model, marking the translations of the response with it.
+pub(crate) async fn translate_text_with_fallback(client: &TranslationClient, fallback: &ModelFallback,
+        request: &TranslateTextRequest)
+    -> Result<TranslateTextResponse>
+{
+    let error = match client.post_translate_text(request).await {
+        Err(error) => error,
+        response => return response,
+    };
+    let fallback_model = fallback.model(client)?;
+    if !fallback.applies(request, &error, &fallback_model) {
+        return Err(error);
+    }
+    trace::model_fallback(request.model.as_deref().unwrap_or_default(), &fallback_model, &error);
+    let mut fallback_request = request.clone();
+    fallback_request.model = Some(fallback_model.clone());
+    let mut response = client.post_translate_text(&fallback_request).await?;
+    for translation in response.translations.iter_mut().chain(response.glossary_translations.iter_mut().flatten()) {
+        translation.model = Some(fallback_model.clone());
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+
+    #[tokio::test]
+    async fn test_model_fallback() {
+        let transport = Arc::new(MockTransport::new(|request| {
+            let body = request.json();
+            match (body["model"].as_str(), body["contents"][0].as_str()) {
+                (Some("models/custom"), Some("Hello")) =>
+                    (400, r#"{"error": {"code": 400, "message": "Unsupported language pair", "status": "INVALID_ARGUMENT"}}"#.to_string()),
+                (Some("models/custom"), _) =>
+                    (403, r#"{"error": {"code": 403, "message": "Denied", "status": "PERMISSION_DENIED"}}"#.to_string()),
+                _ => (200, r#"{"translations": [{"translatedText": "Hallo"}]}"#.to_string()),
+            }
+        }));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone())
+            .with_retry_policy(RetryPolicy { max_attempts: 1, ..RetryPolicy::default() })
+            .with_model_fallback(ModelFallback::new());
+        let request = |text: &str| TranslateTextRequest::builder().content(text).target("de").model("models/custom").build().unwrap();
+
+        let response = client.translate_text(&request("Hello")).await.unwrap();
+        assert_eq!(response.translations[0].translated_text, "Hallo");
+        assert_eq!(response.translations[0].model.as_deref(), Some("projects/p/locations/global/models/general/nmt"));
+        let models: Vec<_> = transport.requests().iter().map(|request| request.json()["model"].clone()).collect();
+        assert_eq!(models, vec!["models/custom", "projects/p/locations/global/models/general/nmt"]);
+
+        // PERMISSION_DENIED isn't a fallback code.
+        assert!(client.translate_text(&request("Goodbye")).await.unwrap_err().is_permission_denied());
+        assert_eq!(transport.requests().len(), 3);
+    }
+}
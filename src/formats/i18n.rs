@@ -0,0 +1,266 @@
+//! Nested JSON and YAML locale files, as used by react-i18next, vue-i18n or Rails.
+//!
+//! A file is parsed into an `I18nValue` tree, which keeps the keys in the order of the file.
+//! `translate_values` translates every string leaf and returns the tree for the target locale;
+//! keys, numbers, booleans and nulls are kept as they are. Besides `FormatConfig::placeholders`,
+//! the interpolation syntax of the common libraries is always protected: `{{name}}`, `%{name}`,
+//! `%<name>s`, i18next's nesting `$t(key)` and its `<0>`/`</0>` component tags.
+//!
+//! ```no_run
+//! # async fn run(client: google_translation::TranslationClient) -> google_translation::Result<()> {
+//! use google_translation::Error;
+//! use google_translation::formats::FormatConfig;
+//! use google_translation::formats::i18n::{I18nValue, translate_values};
+//!
+//! let source = I18nValue::from_json(&std::fs::read_to_string("locales/en/translation.json").map_err(Error::IoError)?)?;
+//! let german = translate_values(&client, &source, "de", &FormatConfig::default()).await?;
+//! std::fs::write("locales/de/translation.json", german.to_json()?).map_err(Error::IoError)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Rails locale files have the locale as their root key, which `I18nValue::rename_key` changes
+//! for the target file. YAML files need the `yaml` feature.
+
+use std::fmt;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use crate::{Error, Result, TranslationClient};
+use crate::formats::{FormatConfig, translate_strings};
+
+/// A node of a locale file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum I18nValue {
+    String(String),
+    /// An object or mapping, in the order of the file.
+    Map(Vec<(String, I18nValue)>),
+    List(Vec<I18nValue>),
+    /// A number, boolean or null, which isn't translated.
+    Other(serde_json::Value),
+}
+
+/// Interpolations of i18next, vue-i18n and Rails that the default placeholders don't cover, or
+/// would split: `{{name}}`, `%{name}`, `%<name>s`, `$t(key)` and `<0>`, `</0>` or `<0/>`.
+static INTERPOLATION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\{\{[^{}]*\}\}|%\{\w+\}|%<\w+>[a-zA-Z]|\$t\([^)]*\)|</?\d+\s*/?>").unwrap()
+});
+
+impl I18nValue {
+    pub fn from_json(text: &str) -> Result<I18nValue> {
+        serde_json::from_str(text).map_err(Error::SerdeJsonError)
+    }
+
+    /// The tree as JSON indented by two spaces, with a final newline.
+    pub fn to_json(&self) -> Result<String> {
+        let mut json = serde_json::to_string_pretty(self).map_err(Error::SerdeJsonError)?;
+        json.push('\n');
+        Ok(json)
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(text: &str) -> Result<I18nValue> {
+        serde_yaml::from_str(text).map_err(|e| Error::Other(format!("invalid YAML: {}", e)))
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&self) -> Result<String> {
+        serde_yaml::to_string(self).map_err(|e| Error::Other(format!("failed to write YAML: {}", e)))
+    }
+
+    /// The value of `key` if this is a map.
+    pub fn get(&self, key: &str) -> Option<&I18nValue> {
+        match self {
+            I18nValue::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Renames `from` to `to` if this is a map containing `from`, such as the locale key at the
+    /// root of a Rails file. Returns whether the key was found.
+    pub fn rename_key(&mut self, from: &str, to: &str) -> bool {
+        match self {
+            I18nValue::Map(entries) => match entries.iter_mut().find(|(k, _)| k == from) {
+                Some((key, _)) => {
+                    *key = to.to_string();
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn strings_mut<'a>(&'a mut self, strings: &mut Vec<&'a mut String>) {
+        match self {
+            I18nValue::String(text) => strings.push(text),
+            I18nValue::Map(entries) => entries.iter_mut().for_each(|(_, value)| value.strings_mut(strings)),
+            I18nValue::List(values) => values.iter_mut().for_each(|value| value.strings_mut(strings)),
+            I18nValue::Other(_) => {}
+        }
+    }
+}
+
+/// Translates the string leaves of `value` into `target_language_code`, returning the tree for
+/// the target locale.
+pub async fn translate_values(client: &TranslationClient, value: &I18nValue, target_language_code: impl AsRef<str>,
+        config: &FormatConfig)
+    -> Result<I18nValue>
+{
+    let config = config.protecting_first(INTERPOLATION.as_str())?;
+    let mut translated = value.clone();
+    let mut strings = Vec::new();
+    translated.strings_mut(&mut strings);
+    let translations = translate_strings(client, &strings, target_language_code.as_ref(), &config).await?;
+    for (string, translation) in strings.into_iter().zip(translations) {
+        *string = translation;
+    }
+    Ok(translated)
+}
+
+impl Serialize for I18nValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            I18nValue::String(text) => serializer.serialize_str(text),
+            I18nValue::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            I18nValue::List(values) => serializer.collect_seq(values),
+            I18nValue::Other(value) => value.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for I18nValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<I18nValue, D::Error> {
+        deserializer.deserialize_any(I18nValueVisitor)
+    }
+}
+
+struct I18nValueVisitor;
+
+impl<'de> Visitor<'de> for I18nValueVisitor {
+    type Value = I18nValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a locale file value")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<I18nValue, E> {
+        Ok(I18nValue::String(v.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> std::result::Result<I18nValue, E> {
+        Ok(I18nValue::String(v))
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> std::result::Result<I18nValue, E> {
+        Ok(I18nValue::Other(v.into()))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> std::result::Result<I18nValue, E> {
+        Ok(I18nValue::Other(v.into()))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<I18nValue, E> {
+        Ok(I18nValue::Other(v.into()))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> std::result::Result<I18nValue, E> {
+        Ok(I18nValue::Other(v.into()))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<I18nValue, E> {
+        Ok(I18nValue::Other(serde_json::Value::Null))
+    }
+
+    fn visit_none<E: de::Error>(self) -> std::result::Result<I18nValue, E> {
+        Ok(I18nValue::Other(serde_json::Value::Null))
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> std::result::Result<I18nValue, D::Error> {
+        I18nValue::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<I18nValue, A::Error> {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(I18nValue::List(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<I18nValue, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry::<String, I18nValue>()? {
+            entries.push(entry);
+        }
+        Ok(I18nValue::Map(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::transport::MockTransport;
+
+    const JSON: &str = r#"{
+  "welcome": "Welcome, {{name}}!",
+  "nav": {
+    "home": "Home",
+    "count": 3,
+    "items": [
+      "Open $t(common.file)",
+      "<0>Click</0> here"
+    ]
+  },
+  "empty": "",
+  "beta": true
+}
+"#;
+
+    #[test]
+    fn test_json_round_trip() {
+        let value = I18nValue::from_json(JSON).unwrap();
+        assert_eq!(value.get("nav").and_then(|nav| nav.get("home")), Some(&I18nValue::String("Home".to_string())));
+        assert_eq!(value.get("beta"), Some(&I18nValue::Other(true.into())));
+        assert_eq!(value.to_json().unwrap(), JSON);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_yaml_round_trip() {
+        let yaml = "en:\n  greeting: Hello %{name}\n  zebra: Z\n  apple: A\n  1: one\n";
+        let mut value = I18nValue::from_yaml(yaml).unwrap();
+        assert!(value.rename_key("en", "de"));
+        assert_eq!(value.to_yaml().unwrap(), yaml.replacen("en:", "de:", 1).replace("1: one", "'1': one"));
+    }
+
+    #[tokio::test]
+    async fn test_translate_values() {
+        let transport = Arc::new(MockTransport::new(|request| {
+            let contents = request.json()["contents"].clone();
+            assert_eq!(contents, serde_json::json!([
+                "Welcome, __PH0__!", "Home", "Open __PH0__", "__PH0__Click__PH1__ here",
+            ]));
+            let translations: Vec<_> = contents.as_array().unwrap().iter()
+                .map(|content| serde_json::json!({"translatedText": format!("DE {}", content.as_str().unwrap())}))
+                .collect();
+            (200, serde_json::json!({"translations": translations}).to_string())
+        }));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport);
+        let source = I18nValue::from_json(JSON).unwrap();
+        let german = translate_values(&client, &source, "de", &FormatConfig::default()).await.unwrap();
+        assert_eq!(german.to_json().unwrap(), JSON.replace("\"Welcome", "\"DE Welcome").replace("\"Home", "\"DE Home")
+            .replace("\"Open", "\"DE Open").replace("\"<0>", "\"DE <0>"));
+    }
+}
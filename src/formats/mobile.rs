@@ -0,0 +1,473 @@
+//! Android `strings.xml` and iOS `.strings` files.
+//!
+//! https://developer.android.com/guide/topics/resources/string-resource
+//! https://developer.apple.com/documentation/foundation/bundle/localizing_your_app_s_resources
+//!
+//! `translate_android` and `translate_apple` translate every value of a source strings file and
+//! return the file for the target locale. Values are unescaped before translation and escaped
+//! again for the format afterwards. Placeholders such as `%1$s` or `%@` are protected as
+//! configured in `FormatConfig::placeholders`; in Android values, inline markup such as `<b>` and
+//! character references such as `&amp;` are always kept as they are.
+//!
+//! ```no_run
+//! # async fn run(client: google_translation::TranslationClient) -> google_translation::Result<()> {
+//! use google_translation::Error;
+//! use google_translation::formats::FormatConfig;
+//! use google_translation::formats::mobile::{AndroidStrings, android_values_dir, translate_android};
+//!
+//! let source: AndroidStrings = std::fs::read_to_string("res/values/strings.xml").map_err(Error::IoError)?.parse()?;
+//! let german = translate_android(&client, &source, "de", &FormatConfig::default()).await?;
+//! let dir = format!("res/{}", android_values_dir("de"));
+//! std::fs::create_dir_all(&dir).map_err(Error::IoError)?;
+//! std::fs::write(format!("{}/strings.xml", dir), german.to_string()).map_err(Error::IoError)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+
+use crate::{Error, Result, TranslationClient};
+use crate::formats::{FormatConfig, MARKUP, translate_strings};
+use crate::html::escape;
+
+/// The resources of an Android `strings.xml` file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AndroidStrings {
+    pub resources: Vec<AndroidResource>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AndroidResource {
+    pub name: String,
+    /// False for resources marked `translatable="false"`, which are left out of translated files.
+    pub translatable: bool,
+    pub value: AndroidValue,
+}
+
+/// The value of a resource, as written in the file: XML-escaped and with Android's backslash escapes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AndroidValue {
+    /// A `<string>`.
+    String(String),
+    /// A `<plurals>`, as (quantity, value) pairs such as ("one", "%d file").
+    Plurals(Vec<(String, String)>),
+    /// A `<string-array>`.
+    StringArray(Vec<String>),
+}
+
+impl AndroidStrings {
+    pub fn parse(xml: &str) -> Result<AndroidStrings> {
+        let error = |e: quick_xml::Error| Error::Other(format!("invalid strings.xml: {}", e));
+        let mut reader = Reader::from_str(xml);
+        let mut resources = Vec::new();
+        loop {
+            match reader.read_event().map_err(error)? {
+                Event::Start(element) => {
+                    let value = match element.name().as_ref() {
+                        b"string" => AndroidValue::String(reader.read_text(element.name()).map_err(error)?.into_owned()),
+                        b"plurals" => AndroidValue::Plurals(read_items(&mut reader, &element)?),
+                        b"string-array" => AndroidValue::StringArray(
+                            read_items(&mut reader, &element)?.into_iter().map(|(_, value)| value).collect()
+                        ),
+                        _ => continue,
+                    };
+                    let name = attribute(&element, b"name")
+                        .ok_or_else(|| Error::Other("invalid strings.xml: a resource has no name".to_string()))?;
+                    let translatable = attribute(&element, b"translatable").as_deref() != Some("false");
+                    resources.push(AndroidResource { name, translatable, value });
+                }
+                Event::Empty(element) if element.name().as_ref() == b"string" => {
+                    let name = attribute(&element, b"name")
+                        .ok_or_else(|| Error::Other("invalid strings.xml: a resource has no name".to_string()))?;
+                    let translatable = attribute(&element, b"translatable").as_deref() != Some("false");
+                    resources.push(AndroidResource { name, translatable, value: AndroidValue::String(String::new()) });
+                }
+                Event::Eof => return Ok(AndroidStrings { resources }),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Reads the `<item>` elements up to the end of `parent`, with their quantity, empty for array items.
+fn read_items(reader: &mut Reader<&[u8]>, parent: &BytesStart) -> Result<Vec<(String, String)>> {
+    let error = |e: quick_xml::Error| Error::Other(format!("invalid strings.xml: {}", e));
+    let mut items = Vec::new();
+    loop {
+        match reader.read_event().map_err(error)? {
+            Event::Start(element) if element.name().as_ref() == b"item" => {
+                let value = reader.read_text(element.name()).map_err(error)?.into_owned();
+                items.push((attribute(&element, b"quantity").unwrap_or_default(), value));
+            }
+            Event::Empty(element) if element.name().as_ref() == b"item" => {
+                items.push((attribute(&element, b"quantity").unwrap_or_default(), String::new()));
+            }
+            Event::End(element) if element.name() == parent.name() => return Ok(items),
+            Event::Eof => return Err(Error::Other("invalid strings.xml: unexpected end of file".to_string())),
+            _ => {}
+        }
+    }
+}
+
+fn attribute(element: &BytesStart, name: &[u8]) -> Option<String> {
+    element.attributes()
+        .filter_map(|attribute| attribute.ok())
+        .find(|attribute| attribute.key.as_ref() == name)
+        .and_then(|attribute| attribute.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
+
+impl fmt::Display for AndroidStrings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+        writeln!(f, "<resources>")?;
+        for resource in &self.resources {
+            let translatable = if resource.translatable { "" } else { r#" translatable="false""# };
+            match &resource.value {
+                AndroidValue::String(value) => {
+                    writeln!(f, r#"    <string name="{}"{}>{}</string>"#, escape(&resource.name), translatable, value)?;
+                }
+                AndroidValue::Plurals(items) => {
+                    writeln!(f, r#"    <plurals name="{}"{}>"#, escape(&resource.name), translatable)?;
+                    for (quantity, value) in items {
+                        writeln!(f, r#"        <item quantity="{}">{}</item>"#, escape(quantity), value)?;
+                    }
+                    writeln!(f, "    </plurals>")?;
+                }
+                AndroidValue::StringArray(items) => {
+                    writeln!(f, r#"    <string-array name="{}"{}>"#, escape(&resource.name), translatable)?;
+                    for value in items {
+                        writeln!(f, "        <item>{}</item>", value)?;
+                    }
+                    writeln!(f, "    </string-array>")?;
+                }
+            }
+        }
+        writeln!(f, "</resources>")
+    }
+}
+
+impl FromStr for AndroidStrings {
+    type Err = Error;
+
+    fn from_str(xml: &str) -> Result<AndroidStrings> {
+        AndroidStrings::parse(xml)
+    }
+}
+
+/// Translates the translatable resources of `strings` into `target_language_code`, returning the
+/// resources for the target locale. Plurals keep the quantities of the source file.
+pub async fn translate_android(client: &TranslationClient, strings: &AndroidStrings, target_language_code: impl AsRef<str>,
+        config: &FormatConfig)
+    -> Result<AndroidStrings>
+{
+    let config = config.protecting_first(MARKUP.as_str())?;
+
+    let resources: Vec<&AndroidResource> = strings.resources.iter().filter(|resource| resource.translatable).collect();
+    let texts: Vec<String> = resources.iter()
+        .flat_map(|resource| match &resource.value {
+            AndroidValue::String(value) => vec![value],
+            AndroidValue::Plurals(items) => items.iter().map(|(_, value)| value).collect(),
+            AndroidValue::StringArray(items) => items.iter().collect(),
+        })
+        .map(|value| android_unescape(value))
+        .collect();
+    let mut translations = translate_strings(client, &texts, target_language_code.as_ref(), &config).await?
+        .into_iter()
+        .map(|translation| android_escape(&translation));
+    let mut next = || translations.next().unwrap_or_default();
+    let resources = resources.into_iter().map(|resource| AndroidResource {
+        name: resource.name.clone(),
+        translatable: true,
+        value: match &resource.value {
+            AndroidValue::String(_) => AndroidValue::String(next()),
+            AndroidValue::Plurals(items) => AndroidValue::Plurals(items.iter().map(|(quantity, _)| (quantity.clone(), next())).collect()),
+            AndroidValue::StringArray(items) => AndroidValue::StringArray(items.iter().map(|_| next()).collect()),
+        },
+    }).collect();
+    Ok(AndroidStrings { resources })
+}
+
+/// Decodes Android's backslash escapes, such as `\'` and `\n`. Markup and character references are kept.
+fn android_unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                unescaped.extend(u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32));
+            }
+            Some(c) => unescaped.push(c),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+/// Escapes text for an Android value, leaving markup and character references alone.
+fn android_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut end = 0;
+    for markup in MARKUP.find_iter(text) {
+        escape_text(&text[end..markup.start()], end == 0, &mut escaped);
+        escaped.push_str(markup.as_str());
+        end = markup.end();
+    }
+    escape_text(&text[end..], end == 0, &mut escaped);
+    escaped
+}
+
+fn escape_text(text: &str, at_start: bool, escaped: &mut String) {
+    for (i, c) in text.char_indices() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\'' => escaped.push_str("\\'"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            // A leading @ or ? would make the value a resource or attribute reference.
+            '@' | '?' if at_start && i == 0 => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+}
+
+/// The Android resource directory for `language_code`: "values-de" for "de", "values-zh-rCN" for
+/// "zh-CN", and "values-b+sr+Latn" for codes with a script such as "sr-Latn".
+pub fn android_values_dir(language_code: &str) -> String {
+    let subtags: Vec<&str> = language_code.split(['-', '_']).collect();
+    match subtags.as_slice() {
+        [language] => format!("values-{}", language),
+        [language, region] if region.len() == 2 || (region.len() == 3 && region.bytes().all(|b| b.is_ascii_digit())) => {
+            format!("values-{}-r{}", language, region.to_ascii_uppercase())
+        }
+        subtags => format!("values-b+{}", subtags.join("+")),
+    }
+}
+
+/// The entries of an iOS or macOS `.strings` file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AppleStrings {
+    pub entries: Vec<AppleString>,
+}
+
+/// A `"key" = "value";` entry, with the values unescaped.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppleString {
+    /// The comments before the entry, with their delimiters, such as "/* Title of the main window */".
+    pub comments: Vec<String>,
+    pub key: String,
+    pub value: String,
+}
+
+impl AppleStrings {
+    pub fn parse(text: &str) -> Result<AppleStrings> {
+        let mut entries = Vec::new();
+        let mut comments = Vec::new();
+        let mut rest = text.trim_start_matches('\u{feff}');
+        loop {
+            rest = rest.trim_start();
+            let error = |message: &str| {
+                let line = text[..text.len() - rest.len()].matches('\n').count() + 1;
+                Error::Other(format!(".strings line {}: {}", line, message))
+            };
+            if rest.is_empty() {
+                return Ok(AppleStrings { entries });
+            }
+            if rest.starts_with("/*") {
+                let end = rest.find("*/").ok_or_else(|| error("unterminated comment"))? + 2;
+                comments.push(rest[..end].to_string());
+                rest = &rest[end..];
+                continue;
+            }
+            if rest.starts_with("//") {
+                let end = rest.find('\n').unwrap_or(rest.len());
+                comments.push(rest[..end].trim_end().to_string());
+                rest = &rest[end..];
+                continue;
+            }
+            let (key, after_key) = read_token(rest).ok_or_else(|| error("expected a quoted key"))?;
+            let after_equals = after_key.trim_start().strip_prefix('=').ok_or_else(|| error("expected '='"))?;
+            let (value, after_value) = read_token(after_equals.trim_start()).ok_or_else(|| error("expected a quoted value"))?;
+            rest = after_value.trim_start().strip_prefix(';').ok_or_else(|| error("expected ';'"))?;
+            entries.push(AppleString { comments: std::mem::take(&mut comments), key, value });
+        }
+    }
+}
+
+/// Reads a quoted string or a bare word from the start of `text`, returning it unescaped and the rest.
+fn read_token(text: &str) -> Option<(String, &str)> {
+    let Some(quoted) = text.strip_prefix('"') else {
+        let end = text.find(|c: char| !(c.is_alphanumeric() || "_.-$".contains(c))).unwrap_or(text.len());
+        return if end == 0 { None } else { Some((text[..end].to_string(), &text[end..])) };
+    };
+    let mut value = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &quoted[i + 1..])),
+            '\\' => match chars.next()?.1 {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                'r' => value.push('\r'),
+                'U' | 'u' => {
+                    let hex: String = (0..4).filter_map(|_| chars.next().map(|(_, c)| c)).collect();
+                    value.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                }
+                c => value.push(c),
+            },
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+fn apple_quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl fmt::Display for AppleStrings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, entry) in self.entries.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            for comment in &entry.comments {
+                writeln!(f, "{}", comment)?;
+            }
+            writeln!(f, "{} = {};", apple_quote(&entry.key), apple_quote(&entry.value))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for AppleStrings {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<AppleStrings> {
+        AppleStrings::parse(text)
+    }
+}
+
+/// Translates the values of `strings` into `target_language_code`, keeping keys and comments.
+pub async fn translate_apple(client: &TranslationClient, strings: &AppleStrings, target_language_code: impl AsRef<str>,
+        config: &FormatConfig)
+    -> Result<AppleStrings>
+{
+    let values: Vec<&str> = strings.entries.iter().map(|entry| entry.value.as_str()).collect();
+    let translations = translate_strings(client, &values, target_language_code.as_ref(), config).await?;
+    Ok(AppleStrings {
+        entries: strings.entries.iter().zip(translations)
+            .map(|(entry, value)| AppleString { value, ..entry.clone() })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::transport::MockTransport;
+
+    /// Answers translateText with each content prefixed by "DE ".
+    fn client() -> TranslationClient {
+        let transport = Arc::new(MockTransport::new(|request| {
+            let translations: Vec<_> = request.json()["contents"].as_array().unwrap().iter()
+                .map(|content| serde_json::json!({"translatedText": format!("DE {}", content.as_str().unwrap())}))
+                .collect();
+            (200, serde_json::json!({"translations": translations}).to_string())
+        }));
+        TranslationClient::new("p", "global", "token").with_transport(transport)
+    }
+
+    #[tokio::test]
+    async fn test_translate_android() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<resources>
+    <string name="app_name" translatable="false">Acme</string>
+    <string name="welcome">Don\'t panic, <b>%1$s</b> &amp; co</string>
+    <string name="empty"/>
+    <plurals name="files">
+        <item quantity="one">%d file</item>
+        <item quantity="other">%d files</item>
+    </plurals>
+    <string-array name="planets">
+        <item>Mercury</item>
+    </string-array>
+</resources>
+"#;
+        let strings: AndroidStrings = xml.parse().unwrap();
+        assert_eq!(strings.resources.len(), 5);
+        assert!(!strings.resources[0].translatable);
+        assert_eq!(strings.resources[1].value, AndroidValue::String(r"Don\'t panic, <b>%1$s</b> &amp; co".to_string()));
+        assert_eq!(strings.to_string(), xml.replace(r#"<string name="empty"/>"#, r#"<string name="empty"></string>"#));
+
+        let german = translate_android(&client(), &strings, "de", &FormatConfig::default()).await.unwrap();
+        assert_eq!(german.to_string(), r#"<?xml version="1.0" encoding="utf-8"?>
+<resources>
+    <string name="welcome">DE Don\'t panic, <b>%1$s</b> &amp; co</string>
+    <string name="empty"></string>
+    <plurals name="files">
+        <item quantity="one">DE %d file</item>
+        <item quantity="other">DE %d files</item>
+    </plurals>
+    <string-array name="planets">
+        <item>DE Mercury</item>
+    </string-array>
+</resources>
+"#);
+    }
+
+    #[test]
+    fn test_android_escaping() {
+        assert_eq!(android_unescape(r#"a\'b\"c\\d\neé"#), "a'b\"c\\d\ne\u{e9}");
+        assert_eq!(android_escape("@home <b>it's</b> 1 < 2 & \"x\"\n"), r#"\@home <b>it\'s</b> 1 &lt; 2 &amp; \"x\"\n"#);
+        assert_eq!(android_values_dir("de"), "values-de");
+        assert_eq!(android_values_dir("zh-cn"), "values-zh-rCN");
+        assert_eq!(android_values_dir("es-419"), "values-es-r419");
+        assert_eq!(android_values_dir("sr-Latn"), "values-b+sr+Latn");
+    }
+
+    #[tokio::test]
+    async fn test_translate_apple() {
+        let text = "/* Window title */\n\"title\" = \"Hello \\\"%@\\\"\";\n\n// Button\n\"ok\" = \"OK\\n\";\n";
+        let strings: AppleStrings = text.parse().unwrap();
+        assert_eq!(strings.entries[0].value, "Hello \"%@\"");
+        assert_eq!(strings.entries[1].comments, vec!["// Button"]);
+        assert_eq!(strings.to_string(), text);
+        assert!(AppleStrings::parse("\"a\" = \"b\"").is_err());
+
+        let german = translate_apple(&client(), &strings, "de", &FormatConfig::default()).await.unwrap();
+        assert_eq!(german.to_string(), "/* Window title */\n\"title\" = \"DE Hello \\\"%@\\\"\";\n\n// Button\n\"ok\" = \"DE OK\\n\";\n");
+    }
+}
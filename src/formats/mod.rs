@@ -0,0 +1,119 @@
+//! Machine translation of localization file formats.
+//!
+//! Each module parses one format, translates the strings that need it with `translate_text` and
+//! writes the file back, keeping everything else as it was. Placeholders in the strings are
+//! protected with `FormatConfig::placeholders`, see the `placeholders` module.
+//!
+//! - `po`: gettext PO files.
+//! - `mobile`: Android `strings.xml` and iOS `.strings` files.
+//! - `i18n`: nested JSON and YAML locale files.
+//! - `xliff`: XLIFF 1.2 and 2.0 files.
+//! - `subtitles`: SubRip and WebVTT subtitles.
+
+pub mod i18n;
+pub mod mobile;
+pub mod po;
+pub mod subtitles;
+pub mod xliff;
+
+use std::sync::LazyLock;
+
+use futures::stream::{self, TryStreamExt};
+use regex::Regex;
+
+use crate::*;
+use crate::placeholders::Placeholders;
+
+/// Options for translating the strings of a localization file.
+#[derive(Clone, Debug, Default)]
+pub struct FormatConfig {
+    /// The language of the source strings. Detected by the API if None.
+    pub source_language_code: Option<String>,
+    pub model: Option<String>,
+    /// The placeholders kept out of translation. Defaults to `Placeholders::default()`.
+    pub placeholders: Placeholders,
+}
+
+impl FormatConfig {
+    /// A copy of the config that also protects matches of `pattern`, taking precedence over the
+    /// configured placeholders.
+    pub(crate) fn protecting_first(&self, pattern: &str) -> Result<FormatConfig> {
+        let mut placeholders = Placeholders::empty().with_pattern(pattern)?;
+        for pattern in self.placeholders.patterns() {
+            placeholders = placeholders.with_pattern(pattern)?;
+        }
+        Ok(FormatConfig { placeholders, ..self.clone() })
+    }
+}
+
+/// Inline markup and character references in XML content, which formats embedding XML keep as they
+/// are through translation.
+pub(crate) static MARKUP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"</?[a-zA-Z][^>]*>|&(?:[a-zA-Z]+|#[0-9]+|#x[0-9a-fA-F]+);").unwrap()
+});
+
+/// Escapes the `&` and `<` of translated text for XML content, leaving the matches of `markup`,
+/// such as the restored tags and character references, alone.
+pub(crate) fn escape_outside(text: &str, markup: &Regex) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut end = 0;
+    for found in markup.find_iter(text) {
+        escaped.push_str(&text[end..found.start()].replace('&', "&amp;").replace('<', "&lt;"));
+        escaped.push_str(found.as_str());
+        end = found.end();
+    }
+    escaped.push_str(&text[end..].replace('&', "&amp;").replace('<', "&lt;"));
+    escaped
+}
+
+/// Translates `texts` as plain text, in order, keeping their placeholders and their leading and
+/// trailing whitespace, which translation would otherwise drop. Blank strings aren't sent.
+pub(crate) async fn translate_strings<S: AsRef<str>>(client: &TranslationClient, texts: &[S], target_language_code: &str,
+        config: &FormatConfig)
+    -> Result<Vec<String>>
+{
+    let parts: Vec<(&str, &str, &str)> = texts.iter().map(|text| split_whitespace(text.as_ref())).collect();
+    let protected: Vec<_> = parts.iter().map(|(_, core, _)| config.placeholders.protect(core, false)).collect();
+    let to_translate: Vec<String> = protected.iter().filter(|protected| !protected.text.is_empty())
+        .map(|protected| protected.text.clone())
+        .collect();
+    let bulk_config = BulkTranslateConfig {
+        source_language_code: config.source_language_code.clone(),
+        mime_type: Some(MimeType::Plain),
+        model: config.model.clone(),
+        ..BulkTranslateConfig::default()
+    };
+    let translations: Vec<String> = client.bulk_translate(stream::iter(to_translate), target_language_code, &bulk_config)
+        .try_collect()
+        .await?;
+    let mut translations = translations.into_iter();
+    Ok(parts.iter().zip(&protected).map(|((leading, _, trailing), protected)| {
+        let translation = if protected.text.is_empty() {
+            String::new()
+        } else {
+            protected.restore(translations.next().unwrap_or_default().trim())
+        };
+        format!("{}{}{}", leading, translation, trailing)
+    }).collect())
+}
+
+/// Splits `text` into its leading whitespace, the rest, and its trailing whitespace.
+fn split_whitespace(text: &str) -> (&str, &str, &str) {
+    let core = text.trim();
+    if core.is_empty() {
+        return (text, "", "");
+    }
+    let start = text.len() - text.trim_start().len();
+    (&text[..start], core, &text[start + core.len()..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_whitespace() {
+        assert_eq!(split_whitespace("\n Hello world\n"), ("\n ", "Hello world", "\n"));
+        assert_eq!(split_whitespace("  "), ("  ", "", ""));
+    }
+}
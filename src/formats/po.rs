@@ -0,0 +1,376 @@
+//! Gettext PO files.
+//!
+//! https://www.gnu.org/software/gettext/manual/html_node/PO-Files.html
+//!
+//! `translate_untranslated` fills in the entries whose msgstr is empty and marks them fuzzy, so
+//! translators can review them. Plural entries get the translation of msgid as their first form
+//! and the translation of msgid_plural as the others, for as many forms as the Plural-Forms header
+//! asks for. Comments, flags, contexts and obsolete entries are written back as they were.
+//!
+//! ```no_run
+//! # async fn run(client: google_translation::TranslationClient) -> google_translation::Result<()> {
+//! use google_translation::Error;
+//! use google_translation::formats::FormatConfig;
+//! use google_translation::formats::po::{PoFile, translate_untranslated};
+//!
+//! let mut po: PoFile = std::fs::read_to_string("locale/de.po").map_err(Error::IoError)?.parse()?;
+//! let translated = translate_untranslated(&client, &mut po, "de", &FormatConfig::default()).await?;
+//! std::fs::write("locale/de.po", po.to_string()).map_err(Error::IoError)?;
+//! println!("{} entries translated", translated);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Error, Result, TranslationClient};
+use crate::formats::{FormatConfig, translate_strings};
+
+/// A parsed PO file. The header, if any, is the first entry, the one with an empty msgid.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PoFile {
+    pub entries: Vec<PoEntry>,
+    /// Comments after the last entry, such as obsolete "#~" entries.
+    pub trailing_comments: Vec<String>,
+}
+
+/// A message and its translations.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PoEntry {
+    /// The comment lines other than flags, including the leading '#', such as "#: src/main.c:12".
+    pub comments: Vec<String>,
+    /// The flags of the "#," line, such as "fuzzy" or "c-format".
+    pub flags: Vec<String>,
+    pub msgctxt: Option<String>,
+    pub msgid: String,
+    pub msgid_plural: Option<String>,
+    /// The translation, or one translation per plural form if `msgid_plural` is set.
+    pub msgstr: Vec<String>,
+}
+
+impl PoEntry {
+    pub fn is_header(&self) -> bool {
+        self.msgid.is_empty() && self.msgctxt.is_none()
+    }
+
+    pub fn is_translated(&self) -> bool {
+        self.msgstr.iter().any(|msgstr| !msgstr.is_empty())
+    }
+
+    pub fn is_fuzzy(&self) -> bool {
+        self.flags.iter().any(|flag| flag == "fuzzy")
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Field {
+    Msgctxt,
+    Msgid,
+    MsgidPlural,
+    Msgstr(usize),
+}
+
+impl PoFile {
+    pub fn parse(text: &str) -> Result<PoFile> {
+        let mut file = PoFile::default();
+        let mut entry = PoEntry::default();
+        // Whether `entry` has a msgid yet, so the next comment or keyword starts a new entry.
+        let mut has_msgid = false;
+        let mut field = None;
+        for (i, line) in text.lines().enumerate() {
+            let error = |message: &str| Error::Other(format!("PO line {}: {}", i + 1, message));
+            let line = line.trim();
+            if line.is_empty() {
+                field = None;
+                continue;
+            }
+            let starts_entry = line.starts_with('#') || line.starts_with("msgctxt") || line.starts_with("msgid ");
+            if has_msgid && starts_entry {
+                file.entries.push(std::mem::take(&mut entry));
+                has_msgid = false;
+            }
+            if let Some(flags) = line.strip_prefix("#,") {
+                entry.flags.extend(flags.split(',').map(str::trim).filter(|flag| !flag.is_empty()).map(str::to_string));
+                continue;
+            }
+            if line.starts_with('#') {
+                entry.comments.push(line.to_string());
+                continue;
+            }
+            if line.starts_with('"') {
+                let value = unquote(line).ok_or_else(|| error("invalid string"))?;
+                match field {
+                    Some(field) => field_mut(&mut entry, field).push_str(&value),
+                    None => return Err(error("string outside of a keyword")),
+                }
+                continue;
+            }
+            let (keyword, value) = line.split_once(char::is_whitespace).ok_or_else(|| error("expected a keyword and a string"))?;
+            let value = unquote(value.trim()).ok_or_else(|| error("invalid string"))?;
+            field = Some(match keyword {
+                "msgctxt" => Field::Msgctxt,
+                "msgid" => Field::Msgid,
+                "msgid_plural" => Field::MsgidPlural,
+                "msgstr" => Field::Msgstr(0),
+                _ => {
+                    let index = keyword.strip_prefix("msgstr[").and_then(|rest| rest.strip_suffix(']'))
+                        .and_then(|index| index.parse().ok())
+                        .ok_or_else(|| error(&format!("unknown keyword {:?}", keyword)))?;
+                    Field::Msgstr(index)
+                }
+            });
+            match field.unwrap() {
+                Field::Msgctxt => entry.msgctxt = Some(String::new()),
+                Field::Msgid => has_msgid = true,
+                Field::MsgidPlural => entry.msgid_plural = Some(String::new()),
+                Field::Msgstr(index) => {
+                    if entry.msgstr.len() <= index {
+                        entry.msgstr.resize(index + 1, String::new());
+                    }
+                }
+            }
+            field_mut(&mut entry, field.unwrap()).push_str(&value);
+        }
+        if has_msgid {
+            file.entries.push(entry);
+        } else {
+            file.trailing_comments = entry.comments;
+        }
+        Ok(file)
+    }
+
+    pub fn header(&self) -> Option<&PoEntry> {
+        self.entries.first().filter(|entry| entry.is_header())
+    }
+
+    /// The value of the header field `name`, such as "Language" or "Plural-Forms".
+    pub fn header_field(&self, name: &str) -> Option<&str> {
+        self.header()?.msgstr.first()?.lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(field, _)| field.trim().eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim())
+    }
+
+    /// The number of plural forms, the nplurals of the Plural-Forms header.
+    pub fn plural_forms(&self) -> Option<usize> {
+        self.header_field("Plural-Forms")?.split(';')
+            .filter_map(|part| part.split_once('='))
+            .find(|(name, _)| name.trim() == "nplurals")
+            .and_then(|(_, n)| n.trim().parse().ok())
+    }
+}
+
+fn field_mut(entry: &mut PoEntry, field: Field) -> &mut String {
+    match field {
+        Field::Msgctxt => entry.msgctxt.get_or_insert_with(String::new),
+        Field::Msgid => &mut entry.msgid,
+        Field::MsgidPlural => entry.msgid_plural.get_or_insert_with(String::new),
+        Field::Msgstr(index) => &mut entry.msgstr[index],
+    }
+}
+
+/// Decodes a quoted C string such as "Hello\n".
+fn unquote(quoted: &str) -> Option<String> {
+    let inner = quoted.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => value.push('\n'),
+            't' => value.push('\t'),
+            'r' => value.push('\r'),
+            c => value.push(c),
+        }
+    }
+    Some(value)
+}
+
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Writes `keyword "value"`, or for multi-line values `keyword ""` followed by one string per line.
+fn write_field(f: &mut fmt::Formatter, keyword: &str, value: &str) -> fmt::Result {
+    let lines: Vec<&str> = value.split_inclusive('\n').collect();
+    if lines.len() > 1 {
+        writeln!(f, "{} \"\"", keyword)?;
+        for line in lines {
+            writeln!(f, "{}", quote(line))?;
+        }
+        Ok(())
+    } else {
+        writeln!(f, "{} {}", keyword, quote(value))
+    }
+}
+
+impl fmt::Display for PoFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, entry) in self.entries.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            // Previous-message comments ("#|") follow the flags, the others precede them.
+            for comment in entry.comments.iter().filter(|comment| !comment.starts_with("#|")) {
+                writeln!(f, "{}", comment)?;
+            }
+            if !entry.flags.is_empty() {
+                writeln!(f, "#, {}", entry.flags.join(", "))?;
+            }
+            for comment in entry.comments.iter().filter(|comment| comment.starts_with("#|")) {
+                writeln!(f, "{}", comment)?;
+            }
+            if let Some(msgctxt) = &entry.msgctxt {
+                write_field(f, "msgctxt", msgctxt)?;
+            }
+            write_field(f, "msgid", &entry.msgid)?;
+            match &entry.msgid_plural {
+                Some(msgid_plural) => {
+                    write_field(f, "msgid_plural", msgid_plural)?;
+                    for (i, msgstr) in entry.msgstr.iter().enumerate() {
+                        write_field(f, &format!("msgstr[{}]", i), msgstr)?;
+                    }
+                }
+                None => write_field(f, "msgstr", entry.msgstr.first().map_or("", String::as_str))?,
+            }
+        }
+        if !self.trailing_comments.is_empty() {
+            writeln!(f)?;
+            for comment in &self.trailing_comments {
+                writeln!(f, "{}", comment)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PoFile {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<PoFile> {
+        PoFile::parse(text)
+    }
+}
+
+/// Translates the untranslated entries of `file` into `target_language_code` and marks them fuzzy,
+/// returning the number of entries translated. See the module documentation.
+pub async fn translate_untranslated(client: &TranslationClient, file: &mut PoFile, target_language_code: impl AsRef<str>,
+        config: &FormatConfig)
+    -> Result<usize>
+{
+    let plural_forms = file.plural_forms().unwrap_or(2).max(1);
+    let untranslated: Vec<usize> = (0..file.entries.len())
+        .filter(|&i| !file.entries[i].is_header() && !file.entries[i].is_translated())
+        .collect();
+    let texts: Vec<&str> = untranslated.iter()
+        .flat_map(|&i| std::iter::once(&file.entries[i].msgid).chain(&file.entries[i].msgid_plural))
+        .map(String::as_str)
+        .collect();
+    let mut translations = translate_strings(client, &texts, target_language_code.as_ref(), config).await?.into_iter();
+    for &i in &untranslated {
+        let entry = &mut file.entries[i];
+        let singular = translations.next().unwrap_or_default();
+        entry.msgstr = match entry.msgid_plural {
+            Some(_) => {
+                let plural = translations.next().unwrap_or_default();
+                match plural_forms {
+                    1 => vec![plural],
+                    n => std::iter::once(singular).chain(std::iter::repeat_n(plural, n - 1)).collect(),
+                }
+            }
+            None => vec![singular],
+        };
+        if !entry.is_fuzzy() {
+            entry.flags.insert(0, "fuzzy".to_string());
+        }
+    }
+    Ok(untranslated.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::transport::MockTransport;
+
+    const PO: &str = r#"# German translations.
+msgid ""
+msgstr ""
+"Language: de\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: src/main.c:12
+#, c-format
+msgid "Hello %s!\n"
+msgstr ""
+
+msgctxt "menu"
+msgid "Open"
+msgstr "Öffnen"
+
+#. Shown after deleting.
+msgid "One file"
+msgid_plural "%d files"
+msgstr[0] ""
+msgstr[1] ""
+
+#~ msgid "Old"
+#~ msgstr "Alt"
+"#;
+
+    #[test]
+    fn test_parse_and_write() {
+        let po: PoFile = PO.parse().unwrap();
+        assert_eq!(po.entries.len(), 4);
+        assert_eq!(po.header_field("language"), Some("de"));
+        assert_eq!(po.plural_forms(), Some(2));
+        assert_eq!(po.entries[1].msgid, "Hello %s!\n");
+        assert_eq!(po.entries[1].flags, vec!["c-format"]);
+        assert_eq!(po.entries[2].msgctxt.as_deref(), Some("menu"));
+        assert!(po.entries[2].is_translated());
+        assert_eq!(po.entries[3].msgstr.len(), 2);
+        assert_eq!(po.trailing_comments, vec!["#~ msgid \"Old\"", "#~ msgstr \"Alt\""]);
+        assert_eq!(po.to_string(), PO);
+
+        assert!(PoFile::parse("msgid \"a\nmsgstr \"\"").is_err());
+        assert!(PoFile::parse("\"orphan\"").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_translate_untranslated() {
+        let transport = Arc::new(MockTransport::new(|request| {
+            let json = request.json();
+            assert_eq!(json["contents"], serde_json::json!(["Hello __PH0__!", "One file", "__PH0__ files"]));
+            (200, r#"{"translations": [{"translatedText": "Hallo __PH0__!"}, {"translatedText": "Eine Datei"},
+                {"translatedText": "__PH0__ Dateien"}]}"#.to_string())
+        }));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport);
+        let mut po: PoFile = PO.parse().unwrap();
+        assert_eq!(translate_untranslated(&client, &mut po, "de", &FormatConfig::default()).await.unwrap(), 2);
+
+        assert_eq!(po.entries[1].msgstr, vec!["Hallo %s!\n"]);
+        assert_eq!(po.entries[1].flags, vec!["fuzzy", "c-format"]);
+        assert_eq!(po.entries[2].msgstr, vec!["Öffnen"]);
+        assert!(!po.entries[2].is_fuzzy());
+        assert_eq!(po.entries[3].msgstr, vec!["Eine Datei", "%d Dateien"]);
+        assert!(po.to_string().contains("#, fuzzy, c-format\nmsgid \"Hello %s!\\n\"\nmsgstr \"Hallo %s!\\n\"\n"));
+    }
+}
@@ -0,0 +1,278 @@
+//! SubRip (`.srt`) and WebVTT (`.vtt`) subtitle files.
+//!
+//! https://www.w3.org/TR/webvtt1/
+//!
+//! `translate_subtitles` translates the text of every cue and returns the subtitles for the target
+//! language, with the identifiers, timings and cue settings as they were. The lines of a cue are
+//! translated as one sentence and the translation is broken back into as many lines of similar
+//! length, between words, or between characters for languages written without spaces. Cues whose
+//! lines all start with a dash are dialogue, with one speaker per line, and are translated line
+//! by line. Styling tags such as `<i>` or `<v Speaker>`, ASS overrides such as `{\an8}` and
+//! character references are kept as they are. WebVTT headers, notes, styles and regions are
+//! copied unchanged.
+//!
+//! ```no_run
+//! # async fn run(client: google_translation::TranslationClient) -> google_translation::Result<()> {
+//! use google_translation::Error;
+//! use google_translation::formats::FormatConfig;
+//! use google_translation::formats::subtitles::{Subtitles, translate_subtitles};
+//!
+//! let subtitles: Subtitles = std::fs::read_to_string("movie.en.srt").map_err(Error::IoError)?.parse()?;
+//! let german = translate_subtitles(&client, &subtitles, "de", &FormatConfig::default()).await?;
+//! std::fs::write("movie.de.srt", german.to_string()).map_err(Error::IoError)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::{Error, Result, TranslationClient};
+use crate::formats::{FormatConfig, escape_outside, translate_strings};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Srt,
+    WebVtt,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subtitles {
+    pub format: SubtitleFormat,
+    pub blocks: Vec<SubtitleBlock>,
+}
+
+/// A block of a subtitle file, the blocks being separated by blank lines.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SubtitleBlock {
+    Cue(Cue),
+    /// A WebVTT header, NOTE, STYLE or REGION block, without its final line break.
+    Verbatim(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cue {
+    /// The SRT index or the WebVTT cue identifier.
+    pub identifier: Option<String>,
+    /// The timing line as written, such as "00:00:01,000 --> 00:00:04,000", with any WebVTT cue settings.
+    pub timing: String,
+    pub lines: Vec<String>,
+}
+
+static TIMING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(?:\d+:)?\d{1,2}:\d{2}[,.]\d{3}\s+-->\s+(?:\d+:)?\d{1,2}:\d{2}[,.]\d{3}(?:\s|$)").unwrap()
+});
+
+/// Styling tags, WebVTT timestamp tags, ASS override blocks and character references.
+static CUE_MARKUP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"<[^<>\s][^<>]*>|\{\\[^{}]*\}|&(?:[a-zA-Z]+|#[0-9]+|#x[0-9a-fA-F]+);").unwrap()
+});
+
+impl Subtitles {
+    pub fn parse(text: &str) -> Result<Subtitles> {
+        let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+        let format = if text.starts_with("WEBVTT") { SubtitleFormat::WebVtt } else { SubtitleFormat::Srt };
+        let mut blocks = Vec::new();
+        let mut line_number = 1;
+        for block in text.split("\n\n") {
+            let start_line = line_number;
+            line_number += block.matches('\n').count() + 2;
+            let block = block.trim_matches('\n');
+            if block.trim().is_empty() {
+                continue;
+            }
+            let lines: Vec<&str> = block.lines().collect();
+            let timing_index = match lines.iter().take(2).position(|line| line.contains("-->")) {
+                Some(i) if !(format == SubtitleFormat::WebVtt && lines[0].starts_with("NOTE")) => i,
+                _ if format == SubtitleFormat::WebVtt => {
+                    blocks.push(SubtitleBlock::Verbatim(block.to_string()));
+                    continue;
+                }
+                _ => return Err(Error::Other(format!("SRT line {}: expected a cue timing", start_line + 1))),
+            };
+            if !TIMING.is_match(lines[timing_index]) {
+                return Err(Error::Other(format!("subtitles line {}: invalid cue timing {:?}", start_line + timing_index,
+                    lines[timing_index])));
+            }
+            blocks.push(SubtitleBlock::Cue(Cue {
+                identifier: if timing_index == 1 { Some(lines[0].to_string()) } else { None },
+                timing: lines[timing_index].to_string(),
+                lines: lines[timing_index + 1..].iter().map(|line| line.to_string()).collect(),
+            }));
+        }
+        Ok(Subtitles { format, blocks })
+    }
+
+    pub fn cues(&self) -> impl Iterator<Item = &Cue> {
+        self.blocks.iter().filter_map(|block| match block {
+            SubtitleBlock::Cue(cue) => Some(cue),
+            SubtitleBlock::Verbatim(_) => None,
+        })
+    }
+}
+
+impl fmt::Display for Subtitles {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, block) in self.blocks.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            match block {
+                SubtitleBlock::Verbatim(text) => writeln!(f, "{}", text)?,
+                SubtitleBlock::Cue(cue) => {
+                    if let Some(identifier) = &cue.identifier {
+                        writeln!(f, "{}", identifier)?;
+                    }
+                    writeln!(f, "{}", cue.timing)?;
+                    for line in &cue.lines {
+                        writeln!(f, "{}", line)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Subtitles {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Subtitles> {
+        Subtitles::parse(text)
+    }
+}
+
+/// Whether `cue` is dialogue: more than one line, each starting with a dash.
+fn is_dialogue(cue: &Cue) -> bool {
+    cue.lines.len() > 1 && cue.lines.iter().all(|line| line.trim_start().starts_with('-'))
+}
+
+/// Translates the text of the cues of `subtitles` into `target_language_code`, returning the
+/// subtitles for the target language.
+pub async fn translate_subtitles(client: &TranslationClient, subtitles: &Subtitles, target_language_code: impl AsRef<str>,
+        config: &FormatConfig)
+    -> Result<Subtitles>
+{
+    let config = config.protecting_first(CUE_MARKUP.as_str())?;
+    let texts: Vec<String> = subtitles.cues()
+        .flat_map(|cue| if is_dialogue(cue) { cue.lines.clone() } else { vec![cue.lines.join(" ")] })
+        .collect();
+    let mut translations = translate_strings(client, &texts, target_language_code.as_ref(), &config).await?
+        .into_iter()
+        .map(|translation| match subtitles.format {
+            SubtitleFormat::WebVtt => escape_outside(&translation, &CUE_MARKUP),
+            SubtitleFormat::Srt => translation,
+        });
+    let blocks = subtitles.blocks.iter().map(|block| match block {
+        SubtitleBlock::Verbatim(text) => SubtitleBlock::Verbatim(text.clone()),
+        SubtitleBlock::Cue(cue) => {
+            let lines = if is_dialogue(cue) {
+                cue.lines.iter().map(|_| translations.next().unwrap_or_default()).collect()
+            } else {
+                reflow(&translations.next().unwrap_or_default(), cue.lines.len())
+            };
+            SubtitleBlock::Cue(Cue { identifier: cue.identifier.clone(), timing: cue.timing.clone(), lines })
+        }
+    }).collect();
+    Ok(Subtitles { format: subtitles.format, blocks })
+}
+
+/// Whether `c` is a CJK character, from scripts written without spaces between words.
+fn is_wide(c: char) -> bool {
+    matches!(c, '\u{2E80}'..='\u{9FFF}' | '\u{AC00}'..='\u{D7AF}' | '\u{F900}'..='\u{FAFF}' | '\u{FF00}'..='\u{FFEF}')
+}
+
+/// Breaks `text` into at most `line_count` lines of similar length, between words if it has
+/// spaces and between characters otherwise. Markup isn't broken.
+fn reflow(text: &str, line_count: usize) -> Vec<String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line_count <= 1 || text.is_empty() {
+        return vec![text];
+    }
+    let markup: Vec<(usize, usize)> = CUE_MARKUP.find_iter(&text).map(|m| (m.start(), m.end())).collect();
+    let inside_markup = |i: usize| markup.iter().any(|&(start, end)| start < i && i < end);
+    // The positions lines can break at: after a space, or between two characters of text without spaces.
+    let breaks: Vec<usize> = if text.contains(' ') {
+        text.match_indices(' ').map(|(i, _)| i + 1).filter(|&i| !inside_markup(i)).collect()
+    } else if text.chars().any(is_wide) {
+        text.char_indices().map(|(i, _)| i).filter(|&i| i > 0 && !inside_markup(i))
+            .filter(|&i| !text[i..].starts_with(|c: char| "、。，．！？：；）」』".contains(c)))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let length = text.chars().count();
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for k in 1..line_count {
+        let ideal = length * k / line_count;
+        let best = breaks.iter().copied()
+            .filter(|&i| i > start)
+            .min_by_key(|&i| text[..i].chars().count().abs_diff(ideal));
+        if let Some(i) = best {
+            lines.push(text[start..i].trim_end().to_string());
+            start = i;
+        }
+    }
+    lines.push(text[start..].to_string());
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::transport::MockTransport;
+
+    const SRT: &str = "1\n00:00:01,000 --> 00:00:03,500\nWhere are you\ngoing tonight?\n\n2\n00:00:04,000 --> 00:00:06,000\n- <i>Home.</i>\n- Me too.\n";
+
+    const VTT: &str = "WEBVTT - Example\n\nNOTE a comment\n\nintro\n00:01.000 --> 00:03.000 align:start\n<v Ann>Hello there,</v>\nmy friends & family\n";
+
+    #[test]
+    fn test_parse() {
+        let srt = Subtitles::parse(&SRT.replace('\n', "\r\n")).unwrap();
+        assert_eq!(srt.format, SubtitleFormat::Srt);
+        assert_eq!(srt.cues().count(), 2);
+        assert_eq!(srt.to_string(), SRT);
+
+        let vtt = Subtitles::parse(VTT).unwrap();
+        assert_eq!(vtt.format, SubtitleFormat::WebVtt);
+        assert_eq!(vtt.blocks[1], SubtitleBlock::Verbatim("NOTE a comment".to_string()));
+        let cue = vtt.cues().next().unwrap();
+        assert_eq!(cue.identifier.as_deref(), Some("intro"));
+        assert_eq!(cue.timing, "00:01.000 --> 00:03.000 align:start");
+        assert_eq!(vtt.to_string(), VTT);
+
+        assert!(Subtitles::parse("1\n00:00:01 --> 00:00:02\nHi\n").is_err());
+    }
+
+    #[test]
+    fn test_reflow() {
+        assert_eq!(reflow("Wohin gehst du heute Abend?", 2), vec!["Wohin gehst", "du heute Abend?"]);
+        assert_eq!(reflow("<i>Bonjour</i>", 2), vec!["<i>Bonjour</i>"]);
+        assert_eq!(reflow("你今晚要去哪里？", 2), vec!["你今晚要", "去哪里？"]);
+        assert_eq!(reflow("a  b\n", 1), vec!["a b"]);
+    }
+
+    #[tokio::test]
+    async fn test_translate_subtitles() {
+        let transport = Arc::new(MockTransport::new(|request| {
+            let contents = request.json()["contents"].clone();
+            assert_eq!(contents, serde_json::json!(["Where are you going tonight?", "- __PH0__Home.__PH1__", "- Me too."]));
+            let translations = serde_json::json!([
+                {"translatedText": "Wohin gehst du heute Abend?"},
+                {"translatedText": "- __PH0__Nach Hause.__PH1__"},
+                {"translatedText": "- Ich auch."},
+            ]);
+            (200, serde_json::json!({"translations": translations}).to_string())
+        }));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport);
+        let subtitles = Subtitles::parse(SRT).unwrap();
+        let german = translate_subtitles(&client, &subtitles, "de", &FormatConfig::default()).await.unwrap();
+        assert_eq!(german.to_string(), "1\n00:00:01,000 --> 00:00:03,500\nWohin gehst\ndu heute Abend?\n\n2\n00:00:04,000 --> 00:00:06,000\n- <i>Nach Hause.</i>\n- Ich auch.\n");
+    }
+}
@@ -0,0 +1,534 @@
+//! XLIFF 1.2 and 2.0 files, as exchanged with translation agencies and CAT tools.
+//!
+//! http://docs.oasis-open.org/xliff/v1.2/os/xliff-core.html
+//! https://docs.oasis-open.org/xliff/xliff-core/v2.0/xliff-core-v2.0.html
+//!
+//! A file is parsed into `TranslationUnit`s, one per `<trans-unit>` in XLIFF 1.2 and one per
+//! `<segment>` in XLIFF 2.0. `translate_xliff` machine-translates the sources of the units without
+//! a target and marks the new targets for review: `state="needs-review-translation"` on the
+//! `<target>` in 1.2, and `state="translated"` on the `<segment>` in 2.0, which has no review
+//! state. Inline elements such as `<g>`, `<x/>`, `<ph>` or `<pc>` and character references are
+//! kept as they are.
+//!
+//! A parsed file is written back as it was read, with only the targets, states and target
+//! language that changed rewritten, so notes, metadata and extensions survive. A file built with
+//! `XliffFile::new` is written from scratch.
+//!
+//! ```no_run
+//! # async fn run(client: google_translation::TranslationClient) -> google_translation::Result<()> {
+//! use google_translation::Error;
+//! use google_translation::formats::FormatConfig;
+//! use google_translation::formats::xliff::{XliffFile, translate_xliff};
+//!
+//! let mut xliff: XliffFile = std::fs::read_to_string("messages.de.xlf").map_err(Error::IoError)?.parse()?;
+//! let translated = translate_xliff(&client, &mut xliff, "de", &FormatConfig::default()).await?;
+//! std::fs::write("messages.de.xlf", xliff.to_string()).map_err(Error::IoError)?;
+//! println!("{} units translated", translated);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use regex::Regex;
+
+use crate::{Error, Result, TranslationClient};
+use crate::formats::{FormatConfig, MARKUP, escape_outside, translate_strings};
+use crate::html::escape;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XliffVersion {
+    V1_2,
+    V2_0,
+}
+
+/// An XLIFF document with a single source and target language.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XliffFile {
+    pub version: XliffVersion,
+    pub source_language: String,
+    pub target_language: Option<String>,
+    /// Units added to a parsed file aren't written, since the file is written back as it was read.
+    pub units: Vec<TranslationUnit>,
+    original: Option<Original>,
+}
+
+/// The text of a parsed file and where the values it was parsed with are.
+#[derive(Clone, Debug, PartialEq)]
+struct Original {
+    text: String,
+    target_language: Option<String>,
+    /// The start tags carrying the target language: `<file>` in 1.2, `<xliff>` in 2.0.
+    language_tags: Vec<Range<usize>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TranslationUnit {
+    /// The id of the `<trans-unit>` or `<unit>`. The segments of an XLIFF 2.0 unit share its id.
+    pub id: String,
+    /// The content of `<source>` as XML, with its inline elements and character references.
+    pub source: String,
+    /// The content of `<target>` as XML.
+    pub target: Option<String>,
+    /// The state of the `<target>` in 1.2, or of the `<segment>` in 2.0.
+    pub state: Option<String>,
+    /// False for units marked `translate="no"`, which `translate_xliff` skips.
+    pub translate: bool,
+    location: Option<Location>,
+}
+
+/// Where a parsed unit is in the original text.
+#[derive(Clone, Debug, PartialEq)]
+struct Location {
+    target: Option<String>,
+    state: Option<String>,
+    /// The start tag of the `<segment>`, in 2.0.
+    segment_tag: Option<Range<usize>>,
+    /// The start tag of the `<target>`, if there is one.
+    target_tag: Option<Range<usize>>,
+    /// The `<target>` element, or the empty range after `</source>` where a new one goes.
+    target_element: Range<usize>,
+    /// The whitespace before `<source>`, to indent a new `<target>` the same way.
+    indent: String,
+}
+
+impl TranslationUnit {
+    pub fn new(id: impl Into<String>, source: impl Into<String>) -> TranslationUnit {
+        TranslationUnit { id: id.into(), source: source.into(), target: None, state: None, translate: true, location: None }
+    }
+}
+
+impl XliffFile {
+    /// An empty file, written from scratch by `Display`.
+    pub fn new(version: XliffVersion, source_language: impl Into<String>) -> XliffFile {
+        XliffFile { version, source_language: source_language.into(), target_language: None, units: Vec::new(), original: None }
+    }
+
+    pub fn parse(xml: &str) -> Result<XliffFile> {
+        let error = |e: quick_xml::Error| Error::Other(format!("invalid XLIFF: {}", e));
+        let mut reader = Reader::from_str(xml);
+        let mut file = XliffFile::new(XliffVersion::V1_2, "");
+        let mut original = Original { text: xml.to_string(), target_language: None, language_tags: Vec::new() };
+        // The names of the open elements, and the id and translate attribute of the current unit.
+        let mut stack: Vec<Vec<u8>> = Vec::new();
+        let mut unit_id = String::new();
+        let mut unit_translate = true;
+        let mut pending: Option<TranslationUnit> = None;
+        loop {
+            let start = reader.buffer_position() as usize;
+            let event = reader.read_event().map_err(error)?;
+            let end = reader.buffer_position() as usize;
+            let parent = stack.last().map(Vec::as_slice);
+            let is_segment_parent = parent == Some(b"trans-unit") || parent == Some(b"segment");
+            match event {
+                Event::Start(ref element) | Event::Empty(ref element) => {
+                    let empty = matches!(event, Event::Empty(_));
+                    let name = element.local_name().as_ref().to_vec();
+                    match name.as_slice() {
+                        b"xliff" => {
+                            let version = attribute(element, b"version").unwrap_or_default();
+                            if version.starts_with('2') {
+                                file.version = XliffVersion::V2_0;
+                                file.source_language = attribute(element, b"srcLang").unwrap_or_default();
+                                file.target_language = attribute(element, b"trgLang");
+                                original.language_tags.push(start..end);
+                            } else if !version.starts_with('1') {
+                                return Err(Error::Other(format!("invalid XLIFF: unsupported version {:?}", version)));
+                            }
+                        }
+                        b"file" if file.version == XliffVersion::V1_2 => {
+                            file.source_language = attribute(element, b"source-language").unwrap_or_default();
+                            file.target_language = attribute(element, b"target-language");
+                            original.language_tags.push(start..end);
+                        }
+                        b"trans-unit" | b"unit" => {
+                            unit_id = attribute(element, b"id").unwrap_or_default();
+                            unit_translate = attribute(element, b"translate").as_deref() != Some("no");
+                        }
+                        _ => {}
+                    }
+                    let starts_unit = match file.version {
+                        XliffVersion::V1_2 => name == b"trans-unit",
+                        XliffVersion::V2_0 => name == b"segment",
+                    };
+                    if starts_unit && !empty {
+                        let mut unit = TranslationUnit::new(unit_id.clone(), "");
+                        unit.translate = unit_translate;
+                        if file.version == XliffVersion::V2_0 {
+                            unit.state = attribute(element, b"state");
+                        }
+                        unit.location = Some(Location {
+                            target: None,
+                            state: unit.state.clone(),
+                            segment_tag: if file.version == XliffVersion::V2_0 { Some(start..end) } else { None },
+                            target_tag: None,
+                            target_element: end..end,
+                            indent: String::new(),
+                        });
+                        pending = Some(unit);
+                    }
+                    match (name.as_slice(), pending.as_mut()) {
+                        (b"source", Some(unit)) if is_segment_parent => {
+                            unit.source = if empty { String::new() } else { reader.read_text(element.name()).map_err(error)?.into_owned() };
+                            let source_end = reader.buffer_position() as usize;
+                            let location = unit.location.as_mut().unwrap();
+                            location.target_element = source_end..source_end;
+                            location.indent = indent_before(xml, start).to_string();
+                        }
+                        (b"target", Some(unit)) if is_segment_parent => {
+                            let target = if empty { String::new() } else { reader.read_text(element.name()).map_err(error)?.into_owned() };
+                            if file.version == XliffVersion::V1_2 {
+                                unit.state = attribute(element, b"state");
+                            }
+                            let location = unit.location.as_mut().unwrap();
+                            location.target = Some(target.clone());
+                            location.state = unit.state.clone();
+                            location.target_tag = Some(start..end);
+                            location.target_element = start..reader.buffer_position() as usize;
+                            unit.target = Some(target);
+                        }
+                        _ if !empty => stack.push(name),
+                        _ => {}
+                    }
+                }
+                Event::End(element) => {
+                    let name = element.local_name();
+                    let ends_unit = match file.version {
+                        XliffVersion::V1_2 => name.as_ref() == b"trans-unit",
+                        XliffVersion::V2_0 => name.as_ref() == b"segment",
+                    };
+                    if ends_unit {
+                        file.units.extend(pending.take());
+                    }
+                    stack.pop();
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        if original.language_tags.is_empty() {
+            return Err(Error::Other("invalid XLIFF: no <xliff> or <file> element".to_string()));
+        }
+        original.target_language = file.target_language.clone();
+        file.original = Some(original);
+        Ok(file)
+    }
+
+    /// The name of the attribute holding the target language.
+    fn target_language_attribute(&self) -> &'static str {
+        match self.version {
+            XliffVersion::V1_2 => "target-language",
+            XliffVersion::V2_0 => "trgLang",
+        }
+    }
+
+    /// The original text with the changed targets, states and target language rewritten.
+    fn rewrite(&self, original: &Original) -> String {
+        let text = &original.text;
+        let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+        if self.target_language != original.target_language {
+            for tag in &original.language_tags {
+                let value = self.target_language.as_deref();
+                edits.push((tag.clone(), set_attribute(&text[tag.clone()], self.target_language_attribute(), value)));
+            }
+        }
+        for unit in &self.units {
+            let location = match &unit.location {
+                Some(location) => location,
+                None => continue,
+            };
+            if unit.state != location.state {
+                if let Some(tag) = &location.segment_tag {
+                    edits.push((tag.clone(), set_attribute(&text[tag.clone()], "state", unit.state.as_deref())));
+                }
+            }
+            let state_changed = location.segment_tag.is_none() && unit.state != location.state;
+            if unit.target == location.target && !state_changed {
+                continue;
+            }
+            let element = match (&unit.target, &location.target_tag) {
+                (None, _) => String::new(),
+                (Some(target), Some(tag)) => {
+                    let mut start_tag = text[tag.clone()].to_string();
+                    if location.segment_tag.is_none() {
+                        start_tag = set_attribute(&start_tag, "state", unit.state.as_deref());
+                    }
+                    format!("{}{}</target>", open_tag(&start_tag), target)
+                }
+                (Some(target), None) => {
+                    let state = match (&unit.state, &location.segment_tag) {
+                        (Some(state), None) => format!(r#" state="{}""#, escape(state)),
+                        _ => String::new(),
+                    };
+                    format!("{}<target{}>{}</target>", location.indent, state, target)
+                }
+            };
+            edits.push((location.target_element.clone(), element));
+        }
+        edits.sort_by_key(|(range, _)| range.start);
+        let mut rewritten = String::with_capacity(text.len());
+        let mut end = 0;
+        for (range, replacement) in edits {
+            rewritten.push_str(&text[end..range.start]);
+            rewritten.push_str(&replacement);
+            end = range.end;
+        }
+        rewritten.push_str(&text[end..]);
+        rewritten
+    }
+}
+
+/// The line break and indentation before `position`, if only whitespace precedes it on its line.
+fn indent_before(text: &str, position: usize) -> &str {
+    let line_start = text[..position].rfind('\n').unwrap_or(0);
+    let indent = &text[line_start..position];
+    if indent.trim().is_empty() { indent } else { "" }
+}
+
+/// Sets the attribute `name` of the start tag `tag` to `value`, or removes it if `value` is None.
+fn set_attribute(tag: &str, name: &str, value: Option<&str>) -> String {
+    let existing = Regex::new(&format!(r#"\s{}\s*=\s*(?:"[^"]*"|'[^']*')"#, regex::escape(name))).unwrap();
+    let attribute = value.map(|value| format!(r#" {}="{}""#, name, escape(value))).unwrap_or_default();
+    if let Some(found) = existing.find(tag) {
+        return format!("{}{}{}", &tag[..found.start()], attribute, &tag[found.end()..]);
+    }
+    let end = if tag.ends_with("/>") { tag.len() - 2 } else { tag.len() - 1 };
+    format!("{}{}{}", tag[..end].trim_end(), attribute, &tag[end..])
+}
+
+/// The start tag `tag` without its self-closing slash.
+fn open_tag(tag: &str) -> String {
+    match tag.strip_suffix("/>") {
+        Some(start) => format!("{}>", start.trim_end()),
+        None => tag.to_string(),
+    }
+}
+
+fn attribute(element: &BytesStart, name: &[u8]) -> Option<String> {
+    element.attributes()
+        .filter_map(|attribute| attribute.ok())
+        .find(|attribute| attribute.key.as_ref() == name)
+        .and_then(|attribute| attribute.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
+
+impl fmt::Display for XliffFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(original) = &self.original {
+            return f.write_str(&self.rewrite(original));
+        }
+        let target_language = |name: &str| match &self.target_language {
+            Some(language) => format!(r#" {}="{}""#, name, escape(language)),
+            None => String::new(),
+        };
+        let translate = |unit: &TranslationUnit| if unit.translate { "" } else { r#" translate="no""# };
+        writeln!(f, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        match self.version {
+            XliffVersion::V1_2 => {
+                writeln!(f, r#"<xliff version="1.2" xmlns="urn:oasis:names:tc:xliff:document:1.2">"#)?;
+                writeln!(f, r#"  <file source-language="{}"{} datatype="plaintext" original="messages">"#,
+                    escape(&self.source_language), target_language("target-language"))?;
+                writeln!(f, "    <body>")?;
+                for unit in &self.units {
+                    writeln!(f, r#"      <trans-unit id="{}"{}>"#, escape(&unit.id), translate(unit))?;
+                    writeln!(f, "        <source>{}</source>", unit.source)?;
+                    if let Some(target) = &unit.target {
+                        let state = unit.state.as_ref().map(|state| format!(r#" state="{}""#, escape(state))).unwrap_or_default();
+                        writeln!(f, "        <target{}>{}</target>", state, target)?;
+                    }
+                    writeln!(f, "      </trans-unit>")?;
+                }
+                writeln!(f, "    </body>")?;
+                writeln!(f, "  </file>")?;
+            }
+            XliffVersion::V2_0 => {
+                writeln!(f, r#"<xliff version="2.0" xmlns="urn:oasis:names:tc:xliff:document:2.0" srcLang="{}"{}>"#,
+                    escape(&self.source_language), target_language("trgLang"))?;
+                writeln!(f, r#"  <file id="f1">"#)?;
+                for (i, unit) in self.units.iter().enumerate() {
+                    // Consecutive units with the same id are the segments of one unit.
+                    if i == 0 || self.units[i - 1].id != unit.id {
+                        writeln!(f, r#"    <unit id="{}"{}>"#, escape(&unit.id), translate(unit))?;
+                    }
+                    let state = unit.state.as_ref().map(|state| format!(r#" state="{}""#, escape(state))).unwrap_or_default();
+                    writeln!(f, "      <segment{}>", state)?;
+                    writeln!(f, "        <source>{}</source>", unit.source)?;
+                    if let Some(target) = &unit.target {
+                        writeln!(f, "        <target>{}</target>", target)?;
+                    }
+                    writeln!(f, "      </segment>")?;
+                    if self.units.get(i + 1).is_none_or(|next| next.id != unit.id) {
+                        writeln!(f, "    </unit>")?;
+                    }
+                }
+                writeln!(f, "  </file>")?;
+            }
+        }
+        writeln!(f, "</xliff>")
+    }
+}
+
+impl FromStr for XliffFile {
+    type Err = Error;
+
+    fn from_str(xml: &str) -> Result<XliffFile> {
+        XliffFile::parse(xml)
+    }
+}
+
+/// Machine-translates the translatable units of `xliff` that have no target into
+/// `target_language_code`, marks them for review and sets the file's target language. Returns the
+/// number of units translated.
+pub async fn translate_xliff(client: &TranslationClient, xliff: &mut XliffFile, target_language_code: impl AsRef<str>,
+        config: &FormatConfig)
+    -> Result<usize>
+{
+    let mut config = config.protecting_first(MARKUP.as_str())?;
+    if config.source_language_code.is_none() && !xliff.source_language.is_empty() {
+        config.source_language_code = Some(xliff.source_language.clone());
+    }
+    let state = match xliff.version {
+        XliffVersion::V1_2 => "needs-review-translation",
+        XliffVersion::V2_0 => "translated",
+    };
+    let mut units: Vec<&mut TranslationUnit> = xliff.units.iter_mut()
+        .filter(|unit| unit.translate && !unit.source.trim().is_empty())
+        .filter(|unit| unit.target.as_deref().is_none_or(|target| target.trim().is_empty()))
+        .collect();
+    let sources: Vec<&str> = units.iter().map(|unit| unit.source.as_str()).collect();
+    let translations = translate_strings(client, &sources, target_language_code.as_ref(), &config).await?;
+    for (unit, translation) in units.iter_mut().zip(translations) {
+        unit.target = Some(escape_outside(&translation, &MARKUP));
+        unit.state = Some(state.to_string());
+    }
+    xliff.target_language = Some(target_language_code.as_ref().to_string());
+    Ok(units.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::transport::MockTransport;
+
+    const XLIFF_1_2: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<xliff version="1.2" xmlns="urn:oasis:names:tc:xliff:document:1.2">
+  <file source-language="en" datatype="plaintext" original="app">
+    <body>
+      <trans-unit id="greeting">
+        <source>Hello <g id="1">world</g> &amp; friends</source>
+        <note>Shown on the home page</note>
+      </trans-unit>
+      <trans-unit id="done">
+        <source>Done</source>
+        <target state="final">Fertig</target>
+        <alt-trans><source>Done</source><target>Erledigt</target></alt-trans>
+      </trans-unit>
+      <trans-unit id="brand" translate="no">
+        <source>Acme</source>
+      </trans-unit>
+    </body>
+  </file>
+</xliff>
+"#;
+
+    const XLIFF_2_0: &str = r#"<xliff version="2.0" xmlns="urn:oasis:names:tc:xliff:document:2.0" srcLang="en">
+  <file id="f1">
+    <unit id="u1">
+      <segment id="s1">
+        <source>First sentence.</source>
+      </segment>
+      <ignorable><source> </source></ignorable>
+      <segment id="s2" state="initial">
+        <source>Second <ph id="1"/> sentence.</source>
+        <target/>
+      </segment>
+    </unit>
+  </file>
+</xliff>"#;
+
+    fn mock_client() -> TranslationClient {
+        let transport = Arc::new(MockTransport::new(|request| {
+            let translations: Vec<_> = request.json()["contents"].as_array().unwrap().iter()
+                .map(|content| serde_json::json!({"translatedText": format!("DE {} & co", content.as_str().unwrap())}))
+                .collect();
+            (200, serde_json::json!({"translations": translations}).to_string())
+        }));
+        TranslationClient::new("p", "global", "token").with_transport(transport)
+    }
+
+    #[test]
+    fn test_parse() {
+        let xliff = XliffFile::parse(XLIFF_1_2).unwrap();
+        assert_eq!(xliff.version, XliffVersion::V1_2);
+        assert_eq!(xliff.source_language, "en");
+        let units: Vec<_> = xliff.units.iter().map(|unit| (unit.id.as_str(), unit.source.as_str(), unit.target.as_deref(), unit.translate)).collect();
+        assert_eq!(units, vec![
+            ("greeting", r#"Hello <g id="1">world</g> &amp; friends"#, None, true),
+            ("done", "Done", Some("Fertig"), true),
+            ("brand", "Acme", None, false),
+        ]);
+        assert_eq!(xliff.to_string(), XLIFF_1_2);
+
+        let xliff = XliffFile::parse(XLIFF_2_0).unwrap();
+        assert_eq!(xliff.version, XliffVersion::V2_0);
+        assert_eq!(xliff.units.len(), 2);
+        assert_eq!(xliff.units[1].state.as_deref(), Some("initial"));
+        assert_eq!(xliff.units[1].target.as_deref(), Some(""));
+        assert_eq!(xliff.to_string(), XLIFF_2_0);
+    }
+
+    #[tokio::test]
+    async fn test_translate_xliff_1_2() {
+        let mut xliff = XliffFile::parse(XLIFF_1_2).unwrap();
+        assert_eq!(translate_xliff(&mock_client(), &mut xliff, "de", &FormatConfig::default()).await.unwrap(), 1);
+        let expected = XLIFF_1_2
+            .replace(r#"original="app""#, r#"original="app" target-language="de""#)
+            .replace("friends</source>\n", "friends</source>\n        <target state=\"needs-review-translation\">DE Hello <g id=\"1\">world</g> &amp; friends &amp; co</target>\n");
+        assert_eq!(xliff.to_string(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_translate_xliff_2_0() {
+        let mut xliff = XliffFile::parse(XLIFF_2_0).unwrap();
+        assert_eq!(translate_xliff(&mock_client(), &mut xliff, "de", &FormatConfig::default()).await.unwrap(), 2);
+        let expected = XLIFF_2_0
+            .replace(r#"srcLang="en""#, r#"srcLang="en" trgLang="de""#)
+            .replace(r#"id="s1""#, r#"id="s1" state="translated""#)
+            .replace("sentence.</source>\n      </segment>", "sentence.</source>\n        <target>DE First sentence. &amp; co</target>\n      </segment>")
+            .replace(r#"state="initial""#, r#"state="translated""#)
+            .replace("<target/>", r#"<target>DE Second <ph id="1"/> sentence. &amp; co</target>"#);
+        assert_eq!(xliff.to_string(), expected);
+    }
+
+    #[test]
+    fn test_new_file() {
+        let mut xliff = XliffFile::new(XliffVersion::V2_0, "en");
+        xliff.target_language = Some("fr".to_string());
+        xliff.units.push(TranslationUnit::new("u1", "One."));
+        xliff.units.push(TranslationUnit { target: Some("Deux.".to_string()), ..TranslationUnit::new("u1", "Two.") });
+        let written = xliff.to_string();
+        assert_eq!(written, r#"<?xml version="1.0" encoding="UTF-8"?>
+<xliff version="2.0" xmlns="urn:oasis:names:tc:xliff:document:2.0" srcLang="en" trgLang="fr">
+  <file id="f1">
+    <unit id="u1">
+      <segment>
+        <source>One.</source>
+      </segment>
+      <segment>
+        <source>Two.</source>
+        <target>Deux.</target>
+      </segment>
+    </unit>
+  </file>
+</xliff>
+"#);
+        let parsed = XliffFile::parse(&written).unwrap();
+        assert_eq!(parsed.units.iter().map(|unit| unit.target.clone()).collect::<Vec<_>>(), vec![None, Some("Deux.".to_string())]);
+    }
+}
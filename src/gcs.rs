@@ -0,0 +1,189 @@
+//! A minimal Cloud Storage client for staging batch translation inputs and reading their outputs.
+//!
+//! https://cloud.google.com/storage/docs/json_api/v1/objects
+//!
+//! Objects are named by `gs://bucket/object` URIs, the form the Translation API takes. Requests go
+//! through the same transport, retry policy and credentials as the `TranslationClient` the storage
+//! client was created from; the credentials need a Cloud Storage scope such as cloud-platform.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+use crate::auth::{SecretToken, TokenProvider};
+
+const DEFAULT_ENDPOINT: &str = "https://storage.googleapis.com";
+
+/// Uploads, downloads, lists and deletes Cloud Storage objects.
+#[derive(Clone)]
+pub struct StorageClient {
+    http: HttpClient,
+    token_provider: Arc<dyn TokenProvider>,
+    endpoint: String,
+}
+
+/// An object in a bucket.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageObject {
+    pub bucket: String,
+    /// The object name, without the bucket.
+    pub name: String,
+    /// The size in bytes.
+    #[serde(default, with = "crate::int64")]
+    pub size: i64,
+    pub content_type: Option<String>,
+}
+
+impl StorageObject {
+    /// The `gs://bucket/object` URI of the object.
+    pub fn uri(&self) -> String {
+        format!("gs://{}/{}", self.bucket, self.name)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListObjectsResponse {
+    #[serde(default)]
+    items: Vec<StorageObject>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ListObjectsQueryParams<'a> {
+    prefix: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_token: Option<String>,
+}
+
+impl StorageClient {
+    /// A client sharing `client`'s transport, retry policy, headers and credentials.
+    pub fn new(client: &TranslationClient) -> StorageClient {
+        StorageClient {
+            http: client.http.clone(),
+            token_provider: client.token_provider.clone(),
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+        }
+    }
+
+    /// Sends requests to `endpoint` instead of "https://storage.googleapis.com", for example an emulator.
+    pub fn with_endpoint(mut self, endpoint: &str) -> StorageClient {
+        self.endpoint = endpoint.trim_end_matches('/').to_string();
+        self
+    }
+
+    async fn access_token(&self) -> Result<SecretToken> {
+        Ok(self.http.until_cancelled(self.token_provider.access_token()).await??.token)
+    }
+
+    fn object_url(&self, bucket: &str, object: &str) -> String {
+        format!("{}/storage/v1/b/{}/o/{}", self.endpoint, encode(bucket), encode(object))
+    }
+
+    /// Uploads `data` to the object at `uri`, replacing it if it exists. `data` is sent without
+    /// being copied, and a `Bytes` can be shared with the caller.
+    pub async fn upload(&self, uri: &str, data: impl Into<Bytes>) -> Result<StorageObject> {
+        let (bucket, object) = parse_uri(uri)?;
+        let url = format!("{}/upload/storage/v1/b/{}/o?{}", self.endpoint, encode(bucket),
+            serde_urlencoded::to_string([("uploadType", "media"), ("name", object)]).unwrap());
+        upload_request(&self.http, &url, &self.access_token().await?, data.into()).await
+    }
+
+    /// Downloads the contents of the object at `uri`.
+    pub async fn download(&self, uri: &str) -> Result<Vec<u8>> {
+        let (bucket, object) = parse_uri(uri)?;
+        let url = format!("{}?alt=media", self.object_url(bucket, object));
+        let body: RawBody = get_request(&self.http, &url, &self.access_token().await?, &Empty).await?;
+        // Reuses the response buffer when nothing else holds it.
+        Ok(body.0.into())
+    }
+
+    /// Lists the objects whose URIs start with `uri_prefix`, for example "gs://bucket/output/".
+    pub async fn list(&self, uri_prefix: &str) -> Result<Vec<StorageObject>> {
+        let (bucket, prefix) = parse_uri(uri_prefix)?;
+        let url = format!("{}/storage/v1/b/{}/o", self.endpoint, encode(bucket));
+        let mut objects = Vec::new();
+        let mut params = ListObjectsQueryParams { prefix, page_token: None };
+        loop {
+            let page: ListObjectsResponse = get_request(&self.http, &url, &self.access_token().await?, &params).await?;
+            objects.extend(page.items);
+            match page.next_page_token.filter(|token| !token.is_empty()) {
+                Some(token) => params.page_token = Some(token),
+                None => return Ok(objects),
+            }
+        }
+    }
+
+    /// Deletes the object at `uri`.
+    pub async fn delete(&self, uri: &str) -> Result<()> {
+        let (bucket, object) = parse_uri(uri)?;
+        delete_request::<Empty>(&self.http, &self.object_url(bucket, object), &self.access_token().await?).await?;
+        Ok(())
+    }
+}
+
+/// Splits a `gs://bucket/object` URI into the bucket and the object name, which may be empty.
+pub fn parse_uri(uri: &str) -> Result<(&str, &str)> {
+    let rest = uri.strip_prefix("gs://")
+        .ok_or_else(|| Error::InvalidRequest(format!("invalid Cloud Storage URI {:?}, expected gs://bucket/object", uri)))?;
+    let (bucket, object) = rest.split_once('/').unwrap_or((rest, ""));
+    if bucket.is_empty() {
+        return Err(Error::InvalidRequest(format!("invalid Cloud Storage URI {:?}, the bucket is missing", uri)));
+    }
+    Ok((bucket, object))
+}
+
+/// Percent-encodes everything but unreserved characters, so object names can be used as a path segment.
+fn encode(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+
+    #[test]
+    fn test_parse_uri() {
+        assert_eq!(parse_uri("gs://bucket/a/b c.txt").unwrap(), ("bucket", "a/b c.txt"));
+        assert_eq!(parse_uri("gs://bucket").unwrap(), ("bucket", ""));
+        assert!(parse_uri("gs:///a").is_err());
+        assert!(parse_uri("bucket/a").is_err());
+        assert_eq!(encode("a/b c.txt"), "a%2Fb%20c.txt");
+    }
+
+    #[tokio::test]
+    async fn test_storage_client() {
+        let transport = Arc::new(MockTransport::new(|request| match request.uri.as_str() {
+            uri if uri.contains("pageToken") => (200, r#"{"items": [{"bucket": "b", "name": "out/2.tsv", "size": "7"}]}"#.to_string()),
+            uri if uri.contains("prefix") => (200, r#"{"items": [{"bucket": "b", "name": "out/1.tsv", "size": "5"}], "nextPageToken": "t"}"#.to_string()),
+            uri if uri.contains("alt=media") => (200, "a\tb\n".to_string()),
+            _ => (200, r#"{"bucket": "b", "name": "in/a b.txt", "size": "5"}"#.to_string()),
+        }));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone());
+        let storage = StorageClient::new(&client);
+
+        let object = storage.upload("gs://b/in/a b.txt", b"Hello".to_vec()).await.unwrap();
+        assert_eq!(object.uri(), "gs://b/in/a b.txt");
+        assert_eq!(storage.download("gs://b/out/1.tsv").await.unwrap(), b"a\tb\n");
+        let objects = storage.list("gs://b/out/").await.unwrap();
+        assert_eq!(objects.iter().map(|object| object.size).collect::<Vec<_>>(), vec![5, 7]);
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].uri, "https://storage.googleapis.com/upload/storage/v1/b/b/o?uploadType=media&name=in%2Fa+b.txt");
+        assert_eq!(requests[0].body, b"Hello");
+        assert_eq!(requests[1].uri, "https://storage.googleapis.com/storage/v1/b/b/o/out%2F1.tsv?alt=media");
+        assert_eq!(requests[2].uri, "https://storage.googleapis.com/storage/v1/b/b/o?prefix=out%2F");
+        assert_eq!(requests[3].uri, "https://storage.googleapis.com/storage/v1/b/b/o?prefix=out%2F&pageToken=t");
+    }
+}
@@ -0,0 +1,520 @@
+//! Local checks for glossary files, before they are uploaded to Cloud Storage.
+//!
+//! A malformed glossary file is only reported by the long-running operation started by
+//! `create_glossary`, minutes after the upload. `validate_file` parses the file locally and reports
+//! the same kinds of problems up front.
+//!
+//! Unidirectional glossaries (`Glossary::language_pair`) may be TSV, CSV or TMX files. TSV and CSV
+//! files have two columns, the source and the target term, and no header. Equivalent term set
+//! glossaries (`Glossary::language_codes_set`) must be CSV files whose header row names the language
+//! of each column; the optional "pos" and "description" columns are allowed as well.
+//!
+//! https://cloud.google.com/translate/docs/advanced/glossary#format-glossary
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::*;
+
+/// The maximum total number of codepoints in the terms of a glossary.
+pub const MAX_CODEPOINTS: usize = 10_000_000;
+
+/// Header columns of an equivalent term set glossary that don't hold terms.
+const METADATA_COLUMNS: [&str; 2] = ["pos", "description"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileFormat {
+    Tsv,
+    Csv,
+    Tmx,
+}
+
+impl FileFormat {
+    /// The format of a file with the extension `ext` (without the dot), if it is a glossary format.
+    pub fn from_extension(ext: &str) -> Option<FileFormat> {
+        match ext.to_ascii_lowercase().as_str() {
+            "tsv" => Some(FileFormat::Tsv),
+            "csv" => Some(FileFormat::Csv),
+            "tmx" => Some(FileFormat::Tmx),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The file would be rejected, or the affected entry skipped.
+    Error,
+    /// The file would be accepted, but probably not with the intended result.
+    Warning,
+}
+
+/// A problem found in a glossary file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The 1-based line the problem was found on, if it concerns a single entry.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match self.line {
+            Some(line) => write!(f, "line {}: {}: {}", line, severity, self.message),
+            None => write!(f, "{}: {}", severity, self.message),
+        }
+    }
+}
+
+/// The result of validating a glossary file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// The number of entries found, including the ones with errors.
+    pub entries: usize,
+    /// The total number of codepoints in the terms.
+    pub codepoints: usize,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+    /// True if no diagnostic is an error. Warnings don't make a glossary invalid.
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    fn error(&mut self, line: Option<usize>, message: String) {
+        self.diagnostics.push(Diagnostic { severity: Severity::Error, line, message });
+    }
+
+    fn warning(&mut self, line: Option<usize>, message: String) {
+        self.diagnostics.push(Diagnostic { severity: Severity::Warning, line, message });
+    }
+}
+
+/// Validates the glossary file at `path` against the languages of `glossary`.
+///
+/// The format is taken from the file extension. Returns an error if the file can't be read, its
+/// extension isn't .tsv, .csv or .tmx, or `glossary` itself is invalid (see `Glossary::validate`);
+/// problems in the file's contents are reported in the `Report`.
+///
+/// ```no_run
+/// # use google_translation::*;
+/// # fn run(glossary: &Glossary) -> Result<()> {
+/// let report = glossary::validate_file("terms.csv", glossary)?;
+/// for diagnostic in &report.diagnostics {
+///     eprintln!("terms.csv: {}", diagnostic);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn validate_file<P: AsRef<Path>>(path: P, glossary: &Glossary) -> Result<Report> {
+    let path = path.as_ref();
+    let format = path.extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(FileFormat::from_extension)
+        .ok_or_else(|| Error::InvalidRequest(format!("{} is not a .tsv, .csv or .tmx file", path.display())))?;
+    let contents = std::fs::read_to_string(path).map_err(Error::IoError)?;
+    validate(&contents, format, glossary)
+}
+
+/// Validates glossary file contents in `format` against the languages of `glossary`.
+pub fn validate(contents: &str, format: FileFormat, glossary: &Glossary) -> Result<Report> {
+    glossary.validate()?;
+    let contents = contents.strip_prefix('\u{feff}').unwrap_or(contents);
+    let mut report = Report::default();
+    match (&glossary.language_pair, &glossary.language_codes_set, format) {
+        (Some(_), _, FileFormat::Tsv) => validate_pairs(&mut report, tsv_records(contents)),
+        (Some(_), _, FileFormat::Csv) => validate_pairs(&mut report, csv_records(contents)),
+        (Some(pair), _, FileFormat::Tmx) => validate_tmx(&mut report, contents, pair),
+        (None, Some(set), FileFormat::Csv) => validate_term_sets(&mut report, csv_records(contents), set),
+        (None, _, _) => report.error(None, "equivalent term set glossaries must be CSV files".to_string()),
+    }
+    if report.codepoints > MAX_CODEPOINTS {
+        report.error(None, format!("the glossary has {} codepoints, more than the limit of {}", report.codepoints, MAX_CODEPOINTS));
+    }
+    Ok(report)
+}
+
+/// Creates `glossary`, replacing an existing glossary of the same name.
+///
+/// Glossaries can't be updated in place: this deletes the existing glossary, if any, waits for the
+/// deletion to finish, creates the new glossary and waits for it to be built, polling as configured
+/// by `poll_config`. Returns the created glossary. `glossary` is validated before anything is deleted.
+///
+/// A failed operation is returned as `Error::ResponseError`, with the HTTP status corresponding to
+/// its error code.
+pub async fn replace_glossary(client: &TranslationClient, glossary: &Glossary, poll_config: &PollConfig) -> Result<Glossary> {
+    glossary.validate()?;
+    match client.delete_glossary(&glossary.name).await {
+        Ok(operation) => {
+            let operation: DeleteGlossaryOperation = operation.into_typed()?;
+            match operation.wait_until_done(client, poll_config).await? {
+                Ok(_) => {}
+                Err(status) if status.error_code() == Some(ErrorCode::NotFound) => {}
+                Err(status) => return Err(operation_error(status)),
+            }
+        }
+        Err(e) if e.is_not_found() => {}
+        Err(e) => return Err(e),
+    }
+    let operation: CreateGlossaryOperation = client.create_glossary(glossary).await?.into_typed()?;
+    operation.wait_until_done(client, poll_config).await?.map_err(operation_error)
+}
+
+/// A row of a TSV or CSV file and the line it starts on.
+type Record = (usize, Vec<String>);
+
+fn tsv_records(contents: &str) -> Vec<Record> {
+    contents.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| (i + 1, line.split('\t').map(str::to_string).collect()))
+        .collect()
+}
+
+/// Splits RFC 4180 CSV into records. Quoted fields may contain commas, newlines and doubled quotes.
+fn csv_records(contents: &str) -> Vec<Record> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut line = 1;
+    let mut start_line = 1;
+    let mut quoted = false;
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                fields.push(std::mem::take(&mut field));
+                if fields.len() > 1 || !fields[0].trim().is_empty() {
+                    records.push((start_line, std::mem::take(&mut fields)));
+                }
+                fields.clear();
+                line += 1;
+                start_line = line;
+            }
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    if !fields.is_empty() || !field.trim().is_empty() {
+        fields.push(field);
+        records.push((start_line, fields));
+    }
+    records
+}
+
+fn validate_pairs(report: &mut Report, records: Vec<Record>) {
+    let mut sources: HashMap<String, usize> = HashMap::new();
+    for (line, fields) in records {
+        report.entries += 1;
+        report.codepoints += fields.iter().map(|field| field.chars().count()).sum::<usize>();
+        if fields.len() != 2 {
+            report.error(Some(line), format!("expected 2 columns, found {}", fields.len()));
+            continue;
+        }
+        if fields.iter().any(|field| field.trim().is_empty()) {
+            report.error(Some(line), "empty term".to_string());
+            continue;
+        }
+        if let Some(first) = sources.insert(fields[0].clone(), line) {
+            report.warning(Some(line), format!("duplicate source term {:?}, first defined on line {}", fields[0], first));
+        }
+    }
+}
+
+fn validate_term_sets(report: &mut Report, records: Vec<Record>, set: &LanguageCodesSet) {
+    let mut records = records.into_iter();
+    let (header_line, header) = match records.next() {
+        Some(header) => header,
+        None => return report.error(None, "missing the header row of language codes".to_string()),
+    };
+    let mut term_columns = Vec::new();
+    for (column, name) in header.iter().enumerate() {
+        let name = name.trim();
+        if METADATA_COLUMNS.contains(&name) {
+            continue;
+        }
+        if !is_language_code(name) {
+            report.error(Some(header_line), format!("header {:?} is not a language code", name));
+        } else if !set.language_codes.iter().any(|code| code.eq_ignore_ascii_case(name)) {
+            report.error(Some(header_line), format!("language {:?} is not in the glossary's language codes set", name));
+        }
+        if header[..column].iter().any(|previous| previous.trim() == name) {
+            report.error(Some(header_line), format!("header {:?} appears twice", name));
+        }
+        term_columns.push(column);
+    }
+    for code in &set.language_codes {
+        if !header.iter().any(|name| name.trim().eq_ignore_ascii_case(code)) {
+            report.error(Some(header_line), format!("no column for language {:?} of the language codes set", code));
+        }
+    }
+
+    let mut terms: Vec<HashMap<String, usize>> = vec![HashMap::new(); header.len()];
+    for (line, fields) in records {
+        report.entries += 1;
+        report.codepoints += term_columns.iter()
+            .filter_map(|&column| fields.get(column))
+            .map(|term| term.chars().count())
+            .sum::<usize>();
+        if fields.len() != header.len() {
+            report.error(Some(line), format!("expected {} columns, found {}", header.len(), fields.len()));
+            continue;
+        }
+        if term_columns.iter().filter(|&&column| !fields[column].trim().is_empty()).count() < 2 {
+            report.warning(Some(line), "fewer than two terms, the entry has no effect".to_string());
+        }
+        for &column in &term_columns {
+            let term = &fields[column];
+            if term.trim().is_empty() {
+                continue;
+            }
+            if let Some(first) = terms[column].insert(term.clone(), line) {
+                report.warning(Some(line), format!("duplicate {} term {:?}, first defined on line {}",
+                    header[column].trim(), term, first));
+            }
+        }
+    }
+}
+
+/// A translation unit of a TMX file: its line and the segments by language.
+struct TranslationUnit {
+    line: usize,
+    segments: Vec<(String, String)>,
+}
+
+fn validate_tmx(report: &mut Report, contents: &str, pair: &LanguageCodePair) {
+    let units = match tmx_units(contents) {
+        Ok(units) => units,
+        Err((line, message)) => return report.error(Some(line), message),
+    };
+    let mut sources: HashMap<String, usize> = HashMap::new();
+    for unit in units {
+        report.entries += 1;
+        report.codepoints += unit.segments.iter().map(|(_, segment)| segment.chars().count()).sum::<usize>();
+        let segment = |code: &str| unit.segments.iter()
+            .find(|(lang, _)| lang.eq_ignore_ascii_case(code))
+            .map(|(_, segment)| segment);
+        let (source, target) = match (segment(&pair.source_language_code), segment(&pair.target_language_code)) {
+            (Some(source), Some(target)) => (source, target),
+            _ => {
+                report.error(Some(unit.line), format!("translation unit without both a {:?} and a {:?} segment",
+                    pair.source_language_code, pair.target_language_code));
+                continue;
+            }
+        };
+        if source.trim().is_empty() || target.trim().is_empty() {
+            report.error(Some(unit.line), "empty term".to_string());
+            continue;
+        }
+        if let Some(first) = sources.insert(source.clone(), unit.line) {
+            report.warning(Some(unit.line), format!("duplicate source term {:?}, first defined on line {}", source, first));
+        }
+    }
+}
+
+/// Collects the `<tu>` elements of a TMX document, or returns the line and message of a syntax error.
+fn tmx_units(contents: &str) -> std::result::Result<Vec<TranslationUnit>, (usize, String)> {
+    let line_at = |offset: u64| contents[..(offset as usize).min(contents.len())].matches('\n').count() + 1;
+    let mut reader = Reader::from_str(contents);
+    let mut units = Vec::new();
+    let mut unit: Option<TranslationUnit> = None;
+    let mut lang: Option<String> = None;
+    let mut segment: Option<String> = None;
+    loop {
+        let position = reader.buffer_position();
+        let event = reader.read_event().map_err(|e| (line_at(reader.error_position()), format!("invalid TMX: {}", e)))?;
+        match event {
+            Event::Start(element) => match element.name().as_ref() {
+                b"tu" => unit = Some(TranslationUnit { line: line_at(position), segments: Vec::new() }),
+                b"tuv" => lang = tuv_lang(&element),
+                b"seg" => segment = Some(String::new()),
+                _ => {}
+            },
+            Event::Text(text) => {
+                if let Some(segment) = &mut segment {
+                    let text = text.unescape().map_err(|e| (line_at(position), format!("invalid TMX: {}", e)))?;
+                    segment.push_str(&text);
+                }
+            }
+            Event::CData(text) => {
+                if let Some(segment) = &mut segment {
+                    segment.push_str(&String::from_utf8_lossy(&text));
+                }
+            }
+            Event::End(element) => match element.name().as_ref() {
+                b"tu" => units.extend(unit.take()),
+                b"seg" => {
+                    if let (Some(unit), Some(segment)) = (&mut unit, segment.take()) {
+                        let lang = lang.clone()
+                            .ok_or_else(|| (line_at(position), "<tuv> without an xml:lang attribute".to_string()))?;
+                        unit.segments.push((lang, segment));
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => return Ok(units),
+            _ => {}
+        }
+    }
+}
+
+fn tuv_lang(element: &BytesStart) -> Option<String> {
+    element.attributes()
+        .filter_map(|attribute| attribute.ok())
+        .find(|attribute| matches!(attribute.key.as_ref(), b"xml:lang" | b"lang"))
+        .and_then(|attribute| attribute.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
+
+/// A loose BCP-47 check: a 2-3 letter language subtag followed by alphanumeric subtags.
+fn is_language_code(code: &str) -> bool {
+    let mut subtags = code.split('-');
+    let language = subtags.next().unwrap_or("");
+    (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input_config() -> GlossaryInputConfig {
+        GlossaryInputConfig { gcs_source: GcsSource { input_uri: "gs://bucket/terms".to_string() } }
+    }
+
+    fn pair_glossary() -> Glossary {
+        Glossary::new("g".to_string(), input_config(), LanguageCodePair::new("en", "de"))
+    }
+
+    #[tokio::test]
+    async fn test_replace_glossary() {
+        use crate::tests::serve_fn;
+
+        let (endpoint, requests) = serve_fn(|uri| {
+            let body = if uri.ends_with("/operations/delete") {
+                r#"{"name": "projects/p/locations/global/operations/delete", "metadata": {"name": "projects/p/locations/global/glossaries/g"}, "done": true,
+                    "error": {"code": 5, "message": "Glossary not found."}}"#
+            } else if uri.ends_with("/operations/create") {
+                r#"{"name": "projects/p/locations/global/operations/create", "metadata": {"name": "projects/p/locations/global/glossaries/g"}, "done": true,
+                    "response": {"name": "projects/p/locations/global/glossaries/g", "entryCount": 2,
+                        "inputConfig": {"gcsSource": {"inputUri": "gs://bucket/terms"}},
+                        "languagePair": {"sourceLanguageCode": "en", "targetLanguageCode": "de"}}}"#
+            } else if uri.ends_with("/glossaries/g") {
+                r#"{"name": "projects/p/locations/global/operations/delete", "metadata": {"name": "projects/p/locations/global/glossaries/g"}}"#
+            } else {
+                r#"{"name": "projects/p/locations/global/operations/create", "metadata": {"name": "projects/p/locations/global/glossaries/g"}}"#
+            };
+            (200, body.to_string())
+        }).await;
+        let client = TranslationClient::new("p", "global", "token").with_endpoint(&endpoint);
+        let mut glossary = pair_glossary();
+        glossary.name = "projects/p/locations/global/glossaries/g".to_string();
+        let created = replace_glossary(&client, &glossary, &PollConfig::default()).await.unwrap();
+        assert_eq!(created.entry_count, Some(2));
+        let methods: Vec<String> = requests.lock().unwrap().iter()
+            .map(|request| request.split(' ').next().unwrap().to_string())
+            .collect();
+        assert_eq!(methods, vec!["DELETE", "GET", "POST", "GET"]);
+    }
+
+    #[test]
+    fn test_csv_records() {
+        let records = csv_records("a,b\r\n\"c, \"\"d\"\"\",\"e\nf\"\n\ng,\n");
+        assert_eq!(records, vec![
+            (1, vec!["a".to_string(), "b".to_string()]),
+            (2, vec!["c, \"d\"".to_string(), "e\nf".to_string()]),
+            (5, vec!["g".to_string(), "".to_string()]),
+        ]);
+    }
+
+    #[test]
+    fn test_validate_pairs() {
+        let report = validate("hello\thallo\nworld\tWelt\textra\nhello\tservus\n", FileFormat::Tsv, &pair_glossary()).unwrap();
+        assert_eq!(report.entries, 3);
+        assert_eq!(report.codepoints, 35);
+        assert!(!report.is_valid());
+        assert_eq!(report.diagnostics, vec![
+            Diagnostic { severity: Severity::Error, line: Some(2), message: "expected 2 columns, found 3".to_string() },
+            Diagnostic {
+                severity: Severity::Warning,
+                line: Some(3),
+                message: "duplicate source term \"hello\", first defined on line 1".to_string(),
+            },
+        ]);
+        assert!(validate("hello,hallo\n", FileFormat::Csv, &pair_glossary()).unwrap().is_valid());
+    }
+
+    #[test]
+    fn test_validate_term_sets() {
+        let glossary = Glossary::new_equivalent_term_set("g".to_string(), input_config(), LanguageCodesSet::new(vec!["en", "de", "fr"]));
+        let report = validate("en,de,fr,pos\nhello,hallo,bonjour,interjection\nhello,,salut,\n", FileFormat::Csv, &glossary).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.entries, 2);
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].line, Some(3));
+
+        let report = validate("en,german\nhello,hallo\n", FileFormat::Csv, &glossary).unwrap();
+        let errors: Vec<&str> = report.errors().map(|diagnostic| diagnostic.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "header \"german\" is not a language code",
+            "no column for language \"de\" of the language codes set",
+            "no column for language \"fr\" of the language codes set",
+        ]);
+        assert!(!validate("en\tde\tfr\n", FileFormat::Tsv, &glossary).unwrap().is_valid());
+    }
+
+    #[test]
+    fn test_validate_tmx() {
+        let tmx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<tmx version="1.4">
+  <header srclang="en" datatype="plaintext"/>
+  <body>
+    <tu>
+      <tuv xml:lang="en"><seg>Q&amp;A</seg></tuv>
+      <tuv xml:lang="de"><seg>F&amp;A</seg></tuv>
+    </tu>
+    <tu>
+      <tuv xml:lang="en"><seg>hello</seg></tuv>
+    </tu>
+  </body>
+</tmx>"#;
+        let report = validate(tmx, FileFormat::Tmx, &pair_glossary()).unwrap();
+        assert_eq!(report.entries, 2);
+        assert_eq!(report.codepoints, 11);
+        assert_eq!(report.diagnostics, vec![Diagnostic {
+            severity: Severity::Error,
+            line: Some(9),
+            message: "translation unit without both a \"en\" and a \"de\" segment".to_string(),
+        }]);
+        assert!(!validate("<tmx><body><tu></tuv></body></tmx>", FileFormat::Tmx, &pair_glossary()).unwrap().is_valid());
+    }
+}
@@ -0,0 +1,501 @@
+//! Sending the online calls over gRPC.
+//!
+//! The Translation API is also served over gRPC, whose protobuf messages are smaller and cheaper
+//! to encode and parse than JSON and share one HTTP/2 connection, which adds up at high request
+//! volumes. `GrpcTransport` sends translateText, detectLanguage and supportedLanguages as gRPC
+//! calls to the host of the request and hands every other request to another transport. As it is
+//! a transport, nothing else changes: the client's retries, interceptors and errors work as usual.
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! use google_translation::grpc::GrpcTransport;
+//!
+//! let client = google_translation::TranslationClient::new("my-project", "global", "token")
+//!     .with_transport(Arc::new(GrpcTransport::new()));
+//! ```
+//!
+//! gRPC needs HTTP/2, which Google's servers only speak over TLS when it is negotiated, so the gRPC
+//! connections offer only HTTP/2 with either TLS feature. The messages are declared in this module,
+//! with the fields of this crate's request and response types, rather than generated from the
+//! API's protos. Error details aren't carried over: failed calls become `Error::ResponseError`s
+//! with the status and message.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::result::Result as StdResult;
+use std::sync::Arc;
+
+use hyper::{Body, Client, Request, Response, Uri};
+use hyper::header::{self, HeaderMap, HeaderValue};
+use hyper::http::uri::PathAndQuery;
+#[cfg(feature = "native-tls")]
+use hyper_tls::HttpsConnector;
+#[cfg(feature = "rustls")]
+use hyper_rustls::HttpsConnector;
+use tonic::body::BoxBody;
+use tonic::codec::ProstCodec;
+use tonic::metadata::MetadataMap;
+
+use crate::{BoxFuture, Error, ErrorCode, Result, compression, metrics, new_https_client_with_config};
+use crate::proxy::{Proxy, ProxyConnector};
+use crate::transport::{ConnectionConfig, HttpTransport, HyperTransport};
+
+/// The service the calls of a request to the v3 REST surface go to. Requests to v3beta1 go to
+/// `V3BETA1_SERVICE`; the messages this module declares are the same in both.
+const V3_SERVICE: &str = "google.cloud.translation.v3.TranslationService";
+const V3BETA1_SERVICE: &str = "google.cloud.translation.v3beta1.TranslationService";
+
+/// Request headers that describe the JSON body rather than the call, so aren't sent as metadata.
+const BODY_HEADERS: &[header::HeaderName] = &[
+    header::CONTENT_TYPE, header::CONTENT_LENGTH, header::CONTENT_ENCODING, header::ACCEPT_ENCODING, header::HOST,
+];
+
+/// Sends translateText, detectLanguage and supportedLanguages requests over gRPC and all others
+/// with a fallback transport, see the module documentation.
+#[derive(Clone)]
+pub struct GrpcTransport {
+    client: Client<HttpsConnector<ProxyConnector>, BoxBody>,
+    fallback: Arc<dyn HttpTransport>,
+}
+
+impl GrpcTransport {
+    /// A transport that connects through the proxy named by HTTPS_PROXY, if any, and sends the
+    /// requests it doesn't handle with a `HyperTransport`.
+    pub fn new() -> GrpcTransport {
+        GrpcTransport::with_config(Proxy::from_env(), ConnectionConfig::default())
+    }
+
+    /// A transport that connects through `proxy`, or directly if it is None, with its gRPC and
+    /// fallback connections managed as `config` says. gRPC connections always use HTTP/2.
+    pub fn with_config(proxy: Option<Proxy>, config: ConnectionConfig) -> GrpcTransport {
+        let grpc_config = ConnectionConfig { http2_only: true, ..config.clone() };
+        GrpcTransport {
+            client: new_https_client_with_config(proxy.clone(), &grpc_config),
+            fallback: Arc::new(HyperTransport::with_config(proxy, config)),
+        }
+    }
+
+    /// Sends the requests that don't go over gRPC with `fallback`.
+    pub fn with_fallback(mut self, fallback: Arc<dyn HttpTransport>) -> GrpcTransport {
+        self.fallback = fallback;
+        self
+    }
+
+    async fn call(&self, method: &str, request: Request<Body>) -> Result<Response<Body>> {
+        let (parts, body) = request.into_parts();
+        let mut body = hyper::body::to_bytes(body).await.map_err(Error::HyperError)?;
+        if compression::is_gzip(&parts.headers) {
+            body = compression::gunzip(&body)?.into();
+        }
+        let parent = parent(parts.uri.path());
+        let service = match parts.uri.path().starts_with("/v3/") {
+            true => V3_SERVICE,
+            false => V3BETA1_SERVICE,
+        };
+        let metadata = metadata(&parts.headers, &parent)?;
+        let origin = Uri::builder()
+            .scheme(parts.uri.scheme_str().unwrap_or("https"))
+            .authority(parts.uri.authority().map(|authority| authority.as_str()).unwrap_or_default())
+            .path_and_query("/")
+            .build()
+            .map_err(|e| Error::InvalidUrl(e.to_string()))?;
+        let result = match method {
+            "translateText" => {
+                let request: crate::TranslateTextRequest = serde_json::from_slice(&body).map_err(Error::SerdeJsonError)?;
+                let response: StdResult<proto::TranslateTextResponse, _> = self.unary(origin, service, "TranslateText", metadata,
+                    proto::TranslateTextRequest::new(request, parent)).await;
+                response.map(|response| serde_json::to_vec(&crate::TranslateTextResponse::from(response)))
+            }
+            "detectLanguage" => {
+                let request: crate::DetectLanguageRequest = serde_json::from_slice(&body).map_err(Error::SerdeJsonError)?;
+                let response: StdResult<proto::DetectLanguageResponse, _> = self.unary(origin, service, "DetectLanguage", metadata,
+                    proto::DetectLanguageRequest::new(request, parent)).await;
+                response.map(|response| serde_json::to_vec(&crate::DetectLanguageResponse::from(response)))
+            }
+            _ => {
+                let params: crate::GetSupportedLanguagesQueryParams = serde_urlencoded::from_str(parts.uri.query().unwrap_or_default())
+                    .map_err(|e| Error::InvalidRequest(format!("invalid supportedLanguages query: {}", e)))?;
+                let response: StdResult<proto::SupportedLanguages, _> = self.unary(origin, service, "GetSupportedLanguages", metadata,
+                    proto::GetSupportedLanguagesRequest::new(params, parent)).await;
+                response.map(|response| serde_json::to_vec(&crate::SupportedLanguages::from(response)))
+            }
+        };
+        let (status, body) = match result {
+            Ok(json) => (200, json.map_err(Error::SerdeJsonError)?),
+            Err(status) => error_body(&status),
+        };
+        Response::builder()
+            .status(status)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .map_err(|e| Error::Other(format!("invalid response: {}", e)))
+    }
+
+    async fn unary<M1, M2>(&self, origin: Uri, service: &str, method: &str, metadata: MetadataMap, message: M1)
+        -> StdResult<M2, tonic::Status>
+        where M1: prost::Message + Send + Sync + 'static, M2: prost::Message + Default + Send + Sync + 'static
+    {
+        let mut grpc = tonic::client::Grpc::with_origin(self.client.clone(), origin);
+        grpc.ready().await.map_err(|e| tonic::Status::unavailable(e.to_string()))?;
+        let path = PathAndQuery::try_from(format!("/{}/{}", service, method))
+            .map_err(|e| tonic::Status::internal(e.to_string()))?;
+        let mut request = tonic::Request::new(message);
+        *request.metadata_mut() = metadata;
+        Ok(grpc.unary(request, path, ProstCodec::default()).await?.into_inner())
+    }
+}
+
+impl Default for GrpcTransport {
+    fn default() -> Self {
+        GrpcTransport::new()
+    }
+}
+
+impl HttpTransport for GrpcTransport {
+    fn send(&self, request: Request<Body>) -> BoxFuture<'_, Result<Response<Body>>> {
+        Box::pin(async move {
+            match metrics::endpoint(request.method(), &request.uri().to_string()).as_str() {
+                method @ ("translateText" | "detectLanguage" | "getSupportedLanguages") => self.call(method, request).await,
+                _ => self.fallback.send(request).await,
+            }
+        })
+    }
+}
+
+/// The "projects/{project}/locations/{location}" a request path is for.
+fn parent(path: &str) -> String {
+    let path = path.split(':').next().unwrap_or_default();
+    let segments: Vec<&str> = path.split('/').skip_while(|segment| *segment != "projects").take(4).collect();
+    segments.join("/")
+}
+
+/// The request headers as gRPC metadata, with the routing header Google's frontends expect.
+fn metadata(headers: &HeaderMap, parent: &str) -> Result<MetadataMap> {
+    let mut headers = headers.clone();
+    for name in BODY_HEADERS {
+        headers.remove(name);
+    }
+    let routing = serde_urlencoded::to_string([("parent", parent)]).map_err(|e| Error::Other(e.to_string()))?;
+    let routing = HeaderValue::from_str(&routing).map_err(|e| Error::Other(e.to_string()))?;
+    headers.insert("x-goog-request-params", routing);
+    Ok(MetadataMap::from_headers(headers))
+}
+
+/// The HTTP status and JSON error body the REST API would have answered with.
+fn error_body(status: &tonic::Status) -> (u16, Vec<u8>) {
+    let code = ErrorCode::from_rpc_code(status.code() as i32).unwrap_or(ErrorCode::Unknown);
+    let body = serde_json::json!({
+        "error": {"code": code.http_status(), "message": status.message(), "status": code.name()},
+    });
+    (code.http_status(), body.to_string().into_bytes())
+}
+
+/// The messages of google.cloud.translation.v3beta1 and v3 this transport sends and receives.
+mod proto {
+    use super::*;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub(super) struct TranslateTextRequest {
+        #[prost(string, repeated, tag = "1")]
+        pub contents: Vec<String>,
+        #[prost(string, tag = "3")]
+        pub mime_type: String,
+        #[prost(string, tag = "4")]
+        pub source_language_code: String,
+        #[prost(string, tag = "5")]
+        pub target_language_code: String,
+        #[prost(string, tag = "6")]
+        pub model: String,
+        #[prost(message, optional, tag = "7")]
+        pub glossary_config: Option<TranslateTextGlossaryConfig>,
+        #[prost(string, tag = "8")]
+        pub parent: String,
+        #[prost(map = "string, string", tag = "10")]
+        pub labels: HashMap<String, String>,
+        #[prost(message, optional, tag = "13")]
+        pub transliteration_config: Option<TransliterationConfig>,
+    }
+
+    impl TranslateTextRequest {
+        pub(super) fn new(request: crate::TranslateTextRequest, parent: String) -> TranslateTextRequest {
+            TranslateTextRequest {
+                contents: request.contents,
+                mime_type: request.mime_type.map(|mime_type| mime_type.as_str().to_string()).unwrap_or_default(),
+                source_language_code: request.source_language_code.unwrap_or_default(),
+                target_language_code: request.target_language_code,
+                model: request.model.unwrap_or_default(),
+                glossary_config: request.glossary_config.map(Into::into),
+                parent,
+                labels: labels(request.labels),
+                transliteration_config: request.transliteration_config.map(|config| TransliterationConfig {
+                    enable_transliteration: config.enable_transliteration.unwrap_or_default(),
+                }),
+            }
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub(super) struct TransliterationConfig {
+        #[prost(bool, tag = "1")]
+        pub enable_transliteration: bool,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub(super) struct TranslateTextGlossaryConfig {
+        #[prost(string, tag = "1")]
+        pub glossary: String,
+        #[prost(bool, tag = "2")]
+        pub ignore_case: bool,
+    }
+
+    impl From<crate::TranslateTextGlossaryConfig> for TranslateTextGlossaryConfig {
+        fn from(config: crate::TranslateTextGlossaryConfig) -> Self {
+            TranslateTextGlossaryConfig { glossary: config.glossary, ignore_case: config.ignore_case.unwrap_or_default() }
+        }
+    }
+
+    impl From<TranslateTextGlossaryConfig> for crate::TranslateTextGlossaryConfig {
+        fn from(config: TranslateTextGlossaryConfig) -> Self {
+            crate::TranslateTextGlossaryConfig { glossary: config.glossary, ignore_case: Some(config.ignore_case) }
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub(super) struct TranslateTextResponse {
+        #[prost(message, repeated, tag = "1")]
+        pub translations: Vec<Translation>,
+        #[prost(message, repeated, tag = "3")]
+        pub glossary_translations: Vec<Translation>,
+    }
+
+    impl From<TranslateTextResponse> for crate::TranslateTextResponse {
+        fn from(response: TranslateTextResponse) -> Self {
+            crate::TranslateTextResponse {
+                translations: response.translations.into_iter().map(Into::into).collect(),
+                glossary_translations: Some(response.glossary_translations)
+                    .filter(|translations| !translations.is_empty())
+                    .map(|translations| translations.into_iter().map(Into::into).collect()),
+            }
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub(super) struct Translation {
+        #[prost(string, tag = "1")]
+        pub translated_text: String,
+        #[prost(string, tag = "2")]
+        pub model: String,
+        #[prost(message, optional, tag = "3")]
+        pub glossary_config: Option<TranslateTextGlossaryConfig>,
+        #[prost(string, tag = "4")]
+        pub detected_language_code: String,
+    }
+
+    impl From<Translation> for crate::Translation {
+        fn from(translation: Translation) -> Self {
+            crate::Translation {
+                translated_text: translation.translated_text,
+                model: non_empty(translation.model),
+                detected_language_code: non_empty(translation.detected_language_code),
+                glossary_config: translation.glossary_config.map(Into::into),
+            }
+        }
+    }
+
+    /// `content` is the only member of the `source` oneof, which is encoded like a plain field.
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub(super) struct DetectLanguageRequest {
+        #[prost(string, tag = "1")]
+        pub content: String,
+        #[prost(string, tag = "3")]
+        pub mime_type: String,
+        #[prost(string, tag = "4")]
+        pub model: String,
+        #[prost(string, tag = "5")]
+        pub parent: String,
+        #[prost(map = "string, string", tag = "6")]
+        pub labels: HashMap<String, String>,
+    }
+
+    impl DetectLanguageRequest {
+        pub(super) fn new(request: crate::DetectLanguageRequest, parent: String) -> DetectLanguageRequest {
+            DetectLanguageRequest {
+                content: request.content,
+                mime_type: request.mime_type.map(|mime_type| mime_type.as_str().to_string()).unwrap_or_default(),
+                model: request.model.unwrap_or_default(),
+                parent,
+                labels: labels(request.labels),
+            }
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub(super) struct DetectLanguageResponse {
+        #[prost(message, repeated, tag = "1")]
+        pub languages: Vec<DetectedLanguage>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub(super) struct DetectedLanguage {
+        #[prost(string, tag = "1")]
+        pub language_code: String,
+        #[prost(float, tag = "2")]
+        pub confidence: f32,
+    }
+
+    impl From<DetectLanguageResponse> for crate::DetectLanguageResponse {
+        fn from(response: DetectLanguageResponse) -> Self {
+            crate::DetectLanguageResponse {
+                languages: response.languages.into_iter()
+                    .map(|language| crate::DetectLanguageItem { language_code: language.language_code, confidence: language.confidence })
+                    .collect(),
+            }
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub(super) struct GetSupportedLanguagesRequest {
+        #[prost(string, tag = "1")]
+        pub display_language_code: String,
+        #[prost(string, tag = "2")]
+        pub model: String,
+        #[prost(string, tag = "3")]
+        pub parent: String,
+    }
+
+    impl GetSupportedLanguagesRequest {
+        pub(super) fn new(params: crate::GetSupportedLanguagesQueryParams, parent: String) -> GetSupportedLanguagesRequest {
+            GetSupportedLanguagesRequest {
+                display_language_code: params.display_language_code.unwrap_or_default(),
+                model: params.model.unwrap_or_default(),
+                parent,
+            }
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub(super) struct SupportedLanguages {
+        #[prost(message, repeated, tag = "1")]
+        pub languages: Vec<SupportedLanguage>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub(super) struct SupportedLanguage {
+        #[prost(string, tag = "1")]
+        pub language_code: String,
+        #[prost(string, tag = "2")]
+        pub display_name: String,
+        #[prost(bool, tag = "3")]
+        pub support_source: bool,
+        #[prost(bool, tag = "4")]
+        pub support_target: bool,
+    }
+
+    impl From<SupportedLanguages> for crate::SupportedLanguages {
+        fn from(response: SupportedLanguages) -> Self {
+            crate::SupportedLanguages {
+                languages: response.languages.into_iter()
+                    .map(|language| crate::SupportedLanguage {
+                        language_code: language.language_code,
+                        display_name: non_empty(language.display_name),
+                        support_source: language.support_source,
+                        support_target: language.support_target,
+                    })
+                    .collect(),
+            }
+        }
+    }
+
+    fn labels(labels: Option<crate::Labels>) -> HashMap<String, String> {
+        labels.iter().flat_map(|labels| labels.iter()).map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    /// Proto3 strings are empty when unset.
+    fn non_empty(value: String) -> Option<String> {
+        Some(value).filter(|value| !value.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::service::{make_service_fn, service_fn};
+    use prost::Message;
+
+    use super::*;
+    use crate::*;
+    use crate::transport::MockTransport;
+
+    /// Serves TranslateText of v3beta1 and v3 over cleartext HTTP/2, failing for the target language "xx".
+    async fn serve_grpc() -> String {
+        let make_service = make_service_fn(|_| async {
+            Ok::<_, hyper::Error>(service_fn(|req: Request<Body>| async move {
+                let path = req.uri().path().to_string();
+                assert!(path == "/google.cloud.translation.v3beta1.TranslationService/TranslateText"
+                    || path == "/google.cloud.translation.v3.TranslationService/TranslateText", "{}", path);
+                assert_eq!(req.headers()["authorization"], "Bearer token");
+                assert_eq!(req.headers()["x-goog-request-params"], "parent=projects%2Fp%2Flocations%2Fglobal");
+                let body = hyper::body::to_bytes(req.into_body()).await?;
+                // A gRPC message is framed by a compression flag and its length.
+                let request = proto::TranslateTextRequest::decode(&body[5..]).unwrap();
+                assert_eq!(request.parent, "projects/p/locations/global");
+                // Transliteration only exists in v3.
+                if let Some(config) = &request.transliteration_config {
+                    assert!(path.contains(".v3.") && config.enable_transliteration);
+                }
+                let response = hyper::Response::builder().header("content-type", "application/grpc");
+                if request.target_language_code == "xx" {
+                    let response = response.header("grpc-status", "3").header("grpc-message", "Target language is invalid.");
+                    return Ok::<_, hyper::Error>(response.body(Body::empty()).unwrap());
+                }
+                let translations = request.contents.iter()
+                    .map(|content| proto::Translation { translated_text: content.to_uppercase(), detected_language_code: "en".to_string(), ..Default::default() })
+                    .collect();
+                let message = proto::TranslateTextResponse { translations, glossary_translations: Vec::new() }.encode_to_vec();
+                let mut frame = vec![0];
+                frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+                frame.extend_from_slice(&message);
+                let (mut sender, body) = Body::channel();
+                tokio::spawn(async move {
+                    sender.send_data(frame.into()).await.unwrap();
+                    let mut trailers = HeaderMap::new();
+                    trailers.insert("grpc-status", HeaderValue::from_static("0"));
+                    sender.send_trailers(trailers).await.unwrap();
+                });
+                Ok(response.body(body).unwrap())
+            }))
+        });
+        let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).http2_only(true).serve(make_service);
+        let endpoint = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+        endpoint
+    }
+
+    #[tokio::test]
+    async fn test_grpc_transport() {
+        let endpoint = serve_grpc().await;
+        let fallback = Arc::new(MockTransport::with_response(200, "{}"));
+        let transport = GrpcTransport::with_config(None, ConnectionConfig::default()).with_fallback(fallback.clone());
+        let client = TranslationClient::new("p", "global", "token").with_endpoint(&endpoint).with_transport(Arc::new(transport));
+
+        let request = TranslateTextRequest::builder().content("hello").content("world").target("de").build().unwrap();
+        let response = client.translate_text(&request).await.unwrap();
+        let translations: Vec<_> = response.translations.iter().map(|translation| translation.translated_text.as_str()).collect();
+        assert_eq!(translations, vec!["HELLO", "WORLD"]);
+        assert_eq!(response.translations[0].detected_language_code.as_deref(), Some("en"));
+        assert_eq!(response.translations[0].model, None);
+
+        let request = TranslateTextRequest::builder().content("hello").target("xx").build().unwrap();
+        let error = client.translate_text(&request).await.unwrap_err();
+        assert_eq!(error.code(), Some(ErrorCode::InvalidArgument));
+        assert!(error.to_string().contains("Target language is invalid."));
+
+        // Everything else goes to the fallback transport.
+        client.cancel_operation("projects/p/locations/global/operations/1").await.unwrap();
+        assert_eq!(fallback.requests().len(), 1);
+
+        let request = TranslateTextRequest::builder().content("namaste").target("hi")
+            .transliteration_config(TransliterationConfig::new(true)).build().unwrap();
+        let response = client.with_api_version(ApiVersion::V3).translate_text(&request).await.unwrap();
+        assert_eq!(response.translations[0].translated_text, "NAMASTE");
+    }
+}
@@ -0,0 +1,366 @@
+//! Translating HTML pages without breaking their markup.
+//!
+//! translateText translates HTML content in place and leaves tags and attributes alone, except that
+//! it skips elements marked `translate="no"` or with the class `notranslate`. This module adds what
+//! pages usually need on top of that: `mark_no_translate` marks elements by tag name, class or id,
+//! and `TranslationClient::translate_html` can also translate attributes such as `alt` and `title`,
+//! which the API leaves untranslated. `unescape` decodes the entities the API puts into HTML
+//! translations, for text that is displayed outside a page.
+//!
+//! ```no_run
+//! # async fn run(client: google_translation::TranslationClient) -> google_translation::Result<()> {
+//! use google_translation::html::HtmlConfig;
+//!
+//! let config = HtmlConfig {
+//!     no_translate: vec!["code".to_string(), ".brand".to_string()],
+//!     translate_attributes: vec!["alt".to_string(), "title".to_string()],
+//!     ..HtmlConfig::default()
+//! };
+//! let html = r#"<p>Run <code>make</code> with <img src="a.png" alt="A cat"></p>"#;
+//! let german = client.translate_html(html, "de", &config).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The markup is scanned, not parsed: tags, attributes, comments and the contents of `script` and
+//! `style` elements are recognized, which is enough for well-formed pages.
+
+use std::ops::Range;
+
+use crate::*;
+
+/// Options for `TranslationClient::translate_html`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HtmlConfig {
+    pub source_language_code: Option<String>,
+    pub model: Option<String>,
+    /// Elements to leave untranslated, see `mark_no_translate`.
+    pub no_translate: Vec<String>,
+    /// The names of attributes to translate, such as "alt", "title" or "placeholder".
+    pub translate_attributes: Vec<String>,
+}
+
+impl TranslationClient {
+    /// Translates the HTML document or fragment `html` into `target_language_code`, leaving the
+    /// elements matching `config.no_translate` untranslated and translating the attributes named in
+    /// `config.translate_attributes`. The attributes are sent as extra contents of the same request.
+    pub async fn translate_html(&self, html: &str, target_language_code: impl AsRef<str>, config: &HtmlConfig)
+        -> Result<String>
+    {
+        let html = mark_no_translate(html, &config.no_translate);
+        // Translated attribute values are replaced with tokens, which survive translation because
+        // the API doesn't touch attributes, and are put back into the translated document.
+        let mut values = Vec::new();
+        let html = rewrite_start_tags(&html, |tag| {
+            let replacements: Vec<_> = tag.attributes.iter()
+                .filter(|attribute| config.translate_attributes.iter().any(|name| name.eq_ignore_ascii_case(&attribute.name)))
+                .filter_map(|attribute| attribute.value.clone())
+                .filter(|value| !tag.source[value.clone()].trim().is_empty())
+                .map(|value| {
+                    values.push(tag.source[value.clone()].to_string());
+                    (value, format!("__ATTR{}__", values.len() - 1))
+                })
+                .collect();
+            tag.replace(replacements)
+        });
+
+        let contents: Vec<String> = std::iter::once(html).chain(values).collect();
+        let request = TranslateTextRequest {
+            contents,
+            mime_type: Some(MimeType::Html),
+            source_language_code: config.source_language_code.clone(),
+            target_language_code: target_language_code.as_ref().to_string(),
+            model: config.model.clone(),
+            glossary_config: None,
+            labels: None,
+            transliteration_config: None,
+        };
+        let response = self.translate_text(&request).await?;
+        if response.translations.len() != request.contents.len() {
+            return Err(Error::Other(format!("translateText returned {} translations for {} strings",
+                response.translations.len(), request.contents.len())));
+        }
+        let mut translations = response.translations.into_iter().map(|translation| translation.translated_text);
+        let html = translations.next().unwrap();
+        let values: Vec<String> = translations.map(|value| escape(&unescape(&value))).collect();
+        Ok(rewrite_start_tags(&html, |tag| {
+            let replacements: Vec<_> = tag.attributes.iter()
+                .filter_map(|attribute| {
+                    let range = attribute.value.clone()?;
+                    let i: usize = tag.source[range.clone()].strip_prefix("__ATTR")?.strip_suffix("__")?.parse().ok()?;
+                    let value = values.get(i)?;
+                    Some((range, if attribute.quoted { value.clone() } else { format!("\"{}\"", value) }))
+                })
+                .collect();
+            tag.replace(replacements)
+        }))
+    }
+}
+
+/// Adds `translate="no"` to the elements of `html` matching any of `selectors`: a tag name such as
+/// "code", a class such as ".brand", or an id such as "#logo". Elements that already have a
+/// `translate` attribute are left as they are.
+pub fn mark_no_translate<S: AsRef<str>>(html: &str, selectors: &[S]) -> String {
+    if selectors.is_empty() {
+        return html.to_string();
+    }
+    rewrite_start_tags(html, |tag| {
+        if tag.attribute("translate").is_some() || !selectors.iter().any(|selector| tag.matches(selector.as_ref())) {
+            return None;
+        }
+        let end = tag.source.len() - if tag.source.ends_with("/>") { 2 } else { 1 };
+        Some(format!(r#"{} translate="no"{}"#, tag.source[..end].trim_end(), &tag.source[end..]))
+    })
+}
+
+/// Decodes the character references in `text`: the named references for `&`, `<`, `>`, quotes and
+/// non-breaking spaces, and numeric references such as `&#39;` or `&#x27;`. Others are left as they are.
+pub fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest[1..].find(';').filter(|&end| end <= 10).and_then(|end| {
+            let c = match &rest[1..end + 1] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                reference => {
+                    let number = reference.strip_prefix('#')?;
+                    let code = match number.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => number.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end + 2))
+        });
+        match decoded {
+            Some((c, len)) => {
+                unescaped.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Escapes `&`, `<`, `>` and `"`, so `text` can be used as HTML text or a quoted attribute value.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A start tag, such as `<img src="a.png" alt=cat>`.
+struct StartTag<'a> {
+    source: &'a str,
+    /// The lowercased tag name.
+    name: String,
+    attributes: Vec<Attribute>,
+}
+
+struct Attribute {
+    /// The lowercased attribute name.
+    name: String,
+    /// The byte range of the value in the tag's source, without quotes.
+    value: Option<Range<usize>>,
+    quoted: bool,
+}
+
+impl<'a> StartTag<'a> {
+    /// Parses a complete start tag, from '<' to '>'.
+    fn parse(source: &'a str) -> Option<StartTag<'a>> {
+        let bytes = source.as_bytes();
+        if bytes.len() < 3 || bytes[0] != b'<' || !bytes[1].is_ascii_alphabetic() || *bytes.last().unwrap() != b'>' {
+            return None;
+        }
+        let is_delimiter = |b: u8| b.is_ascii_whitespace() || b == b'/' || b == b'>';
+        let mut i = 1;
+        while !is_delimiter(bytes[i]) {
+            i += 1;
+        }
+        let name = source[1..i].to_ascii_lowercase();
+        let mut attributes = Vec::new();
+        loop {
+            while is_delimiter(bytes[i]) && bytes[i] != b'>' {
+                i += 1;
+            }
+            if bytes[i] == b'>' {
+                break;
+            }
+            let start = i;
+            while !is_delimiter(bytes[i]) && bytes[i] != b'=' {
+                i += 1;
+            }
+            let mut attribute = Attribute { name: source[start..i].to_ascii_lowercase(), value: None, quoted: false };
+            let mut j = i;
+            while bytes[j].is_ascii_whitespace() {
+                j += 1;
+            }
+            if bytes[j] == b'=' {
+                j += 1;
+                while bytes[j].is_ascii_whitespace() {
+                    j += 1;
+                }
+                if bytes[j] == b'"' || bytes[j] == b'\'' {
+                    let end = j + 1 + source[j + 1..].find(bytes[j] as char)?;
+                    attribute.value = Some(j + 1..end);
+                    attribute.quoted = true;
+                    i = end + 1;
+                } else {
+                    let start = j;
+                    while !bytes[j].is_ascii_whitespace() && bytes[j] != b'>' {
+                        j += 1;
+                    }
+                    attribute.value = Some(start..j);
+                    i = j;
+                }
+            }
+            attributes.push(attribute);
+        }
+        Some(StartTag { source, name, attributes })
+    }
+
+    /// The raw value of the attribute `name`, empty for an attribute without a value.
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|attribute| attribute.name == name)
+            .map(|attribute| attribute.value.clone().map_or("", |value| &self.source[value]))
+    }
+
+    fn matches(&self, selector: &str) -> bool {
+        if let Some(class) = selector.strip_prefix('.') {
+            self.attribute("class").is_some_and(|classes| classes.split_whitespace().any(|c| c == class))
+        } else if let Some(id) = selector.strip_prefix('#') {
+            self.attribute("id") == Some(id)
+        } else {
+            self.name.eq_ignore_ascii_case(selector)
+        }
+    }
+
+    /// The source with the given byte ranges replaced, None if there is nothing to replace.
+    fn replace(&self, mut replacements: Vec<(Range<usize>, String)>) -> Option<String> {
+        if replacements.is_empty() {
+            return None;
+        }
+        replacements.sort_by_key(|(range, _)| range.start);
+        let mut source = String::with_capacity(self.source.len());
+        let mut end = 0;
+        for (range, replacement) in replacements {
+            source.push_str(&self.source[end..range.start]);
+            source.push_str(&replacement);
+            end = range.end;
+        }
+        source.push_str(&self.source[end..]);
+        Some(source)
+    }
+}
+
+/// Copies `html`, replacing each start tag for which `rewrite` returns a new source.
+fn rewrite_start_tags(html: &str, mut rewrite: impl FnMut(&StartTag) -> Option<String>) -> String {
+    let mut rewritten = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        rewritten.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map_or(rest.len(), |end| end + 3)
+        } else {
+            tag_end(rest)
+        };
+        let source = &rest[..end];
+        rest = &rest[end..];
+        let tag = match StartTag::parse(source) {
+            Some(tag) => tag,
+            None => {
+                rewritten.push_str(source);
+                continue;
+            }
+        };
+        rewritten.push_str(&rewrite(&tag).unwrap_or_else(|| source.to_string()));
+        if tag.name == "script" || tag.name == "style" {
+            let close = rest.to_ascii_lowercase().find(&format!("</{}", tag.name)).unwrap_or(rest.len());
+            rewritten.push_str(&rest[..close]);
+            rest = &rest[close..];
+        }
+    }
+    rewritten.push_str(rest);
+    rewritten
+}
+
+/// The byte offset just past the '>' ending the tag at the start of `html`, skipping quoted values.
+fn tag_end(html: &str) -> usize {
+    let mut quote = None;
+    for (i, b) in html.bytes().enumerate().skip(1) {
+        match (quote, b) {
+            (None, b'>') => return i + 1,
+            (None, b'"') | (None, b'\'') => quote = Some(b),
+            (Some(q), b) if q == b => quote = None,
+            _ => {}
+        }
+    }
+    html.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::transport::MockTransport;
+
+    #[test]
+    fn test_mark_no_translate() {
+        let html = r#"<p class="x brand">Acme</p><CODE>ls</CODE><br/><span id=logo translate="yes">A</span><script>if (a<b) {}</script>"#;
+        assert_eq!(mark_no_translate(html, &["code", ".brand", "#logo", "br"]),
+            r#"<p class="x brand" translate="no">Acme</p><CODE translate="no">ls</CODE><br translate="no"/><span id=logo translate="yes">A</span><script>if (a<b) {}</script>"#);
+        assert_eq!(mark_no_translate("<!-- <code> --><p>a > b</p>", &["code"]), "<!-- <code> --><p>a > b</p>");
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(unescape("It&#39;s &lt;b&gt; &amp;amp; &#x1F600; &copy; & x"), "It's <b> &amp; 😀 &copy; & x");
+        assert_eq!(escape(r#"<a href="x">&</a>"#), "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;");
+    }
+
+    #[tokio::test]
+    async fn test_translate_html() {
+        let transport = Arc::new(MockTransport::new(|request| {
+            let json = request.json();
+            assert_eq!(json["contents"][0], r#"<p title="__ATTR0__">Cat <code translate="no">cat</code> <img src="a.png" alt=__ATTR1__></p>"#);
+            assert_eq!(json["contents"][1], "A &quot;cat&quot;");
+            (200, r#"{"translations": [
+                {"translatedText": "<p title=\"__ATTR0__\">Katze <code translate=\"no\">cat</code> <img src=\"a.png\" alt=__ATTR1__></p>"},
+                {"translatedText": "Eine &quot;Katze&quot;"},
+                {"translatedText": "Ein Bild"}
+            ]}"#.to_string())
+        }));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport);
+        let config = HtmlConfig {
+            no_translate: vec!["code".to_string()],
+            translate_attributes: vec!["title".to_string(), "alt".to_string()],
+            ..HtmlConfig::default()
+        };
+        let html = r#"<p title="A &quot;cat&quot;">Cat <code>cat</code> <img src="a.png" alt=Picture></p>"#;
+        assert_eq!(client.translate_html(html, "de", &config).await.unwrap(),
+            r#"<p title="Eine &quot;Katze&quot;">Katze <code translate="no">cat</code> <img src="a.png" alt="Ein Bild"></p>"#);
+    }
+}
@@ -0,0 +1,78 @@
+//! Hooks into every HTTP request the client sends.
+//!
+//! Interceptors added with `TranslationClient::with_interceptor` see each request just before it is
+//! sent, after the client has set its headers, and each response once its body has been read. They
+//! run for every attempt, so a retried request is seen once per attempt, and in the order they were
+//! added. Typical uses are audit logging, metrics and injecting headers computed per request.
+//!
+//! The Authorization header is marked sensitive, so logging a request with `{:?}` shows it as
+//! "Sensitive" rather than the access token.
+//!
+//! ```
+//! # use std::sync::Arc;
+//! # use std::sync::atomic::{AtomicUsize, Ordering};
+//! use google_translation::interceptor::Interceptor;
+//!
+//! #[derive(Default)]
+//! struct CountErrors(AtomicUsize);
+//!
+//! impl Interceptor for CountErrors {
+//!     fn on_response(&self, response: &hyper::Response<()>, _body: &[u8]) {
+//!         if !response.status().is_success() {
+//!             self.0.fetch_add(1, Ordering::Relaxed);
+//!         }
+//!     }
+//! }
+//!
+//! let client = google_translation::TranslationClient::new("my-project", "global", "token")
+//!     .with_interceptor(Arc::new(CountErrors::default()));
+//! ```
+
+use hyper::{Body, Request, Response};
+
+/// Callbacks invoked around every HTTP request. Both do nothing by default.
+pub trait Interceptor: Send + Sync {
+    /// Called with each request before it is sent. Changes to the request, such as added
+    /// headers, are sent to the server.
+    fn on_request(&self, _request: &mut Request<Body>) {}
+
+    /// Called with the status and headers of each response and its body. Not called if the request
+    /// failed before a response arrived.
+    fn on_response(&self, _response: &Response<()>, _body: &[u8]) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::TranslationClient;
+    use crate::tests::serve;
+
+    #[derive(Default)]
+    struct Audit(Mutex<Vec<String>>);
+
+    impl Interceptor for Audit {
+        fn on_request(&self, request: &mut Request<Body>) {
+            self.0.lock().unwrap().push(format!("{} {}", request.method(), request.uri().path()));
+        }
+
+        fn on_response(&self, response: &Response<()>, body: &[u8]) {
+            self.0.lock().unwrap().push(format!("{} {}", response.status().as_u16(), String::from_utf8_lossy(body)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interceptor() {
+        let (endpoint, _) = serve(200, "{}").await;
+        let audit = Arc::new(Audit::default());
+        let client = TranslationClient::new("p", "global", "token")
+            .with_endpoint(&endpoint)
+            .with_interceptor(audit.clone());
+        client.cancel_operation("projects/p/locations/global/operations/1").await.unwrap();
+        assert_eq!(*audit.0.lock().unwrap(), vec![
+            "POST /v3beta1/projects/p/locations/global/operations/1:cancel",
+            "200 {}",
+        ]);
+    }
+}
@@ -0,0 +1,251 @@
+//! Tracking long-running batch jobs across process restarts.
+//!
+//! Batch translations can run for hours, longer than the process that started them. A `JobManager`
+//! records the operations it starts, with a fingerprint of the request each one was started for,
+//! in a JSON file. After a restart, opening the same file gives back the tracked jobs, so the
+//! program can resume waiting for them instead of paying for the same translation twice:
+//! submitting a plan again only starts the requests that have no operation yet.
+//!
+//! ```no_run
+//! # async fn run(client: google_translation::TranslationClient, request: google_translation::BatchTranslateTextRequest)
+//! #     -> google_translation::Result<()> {
+//! use google_translation::PollConfig;
+//! use google_translation::jobs::JobManager;
+//! use google_translation::planner::BatchPlan;
+//!
+//! let mut jobs = JobManager::open("translation-jobs.json")?;
+//! jobs.submit(&client, "docs-2024-06", &BatchPlan::from(request)).await?;
+//! for (id, status) in jobs.status(&client).await? {
+//!     println!("{}: {}/{} operations done", id, status.done, status.operations);
+//! }
+//! jobs.wait_until_done(&client, &PollConfig::default()).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::result::Result as StdResult;
+
+use futures::future::try_join_all;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::*;
+use crate::planner::{BatchJobStatus, BatchPlan, operations_status};
+
+/// A job: the operations started for the requests of a `BatchPlan`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackedJob {
+    pub id: String,
+    /// When the job was first submitted, in seconds since the Unix epoch.
+    pub submitted_at: u64,
+    pub operations: Vec<TrackedOperation>,
+}
+
+/// An operation and the fingerprint of the request it was started for, see `fingerprint`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackedOperation {
+    pub name: String,
+    pub fingerprint: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct State {
+    jobs: Vec<TrackedJob>,
+}
+
+/// Starts batch jobs and keeps track of their operations, see the module documentation.
+#[derive(Default)]
+pub struct JobManager {
+    path: Option<PathBuf>,
+    state: State,
+}
+
+impl JobManager {
+    /// A manager that keeps its state in memory only. Use `to_json` to persist it yourself.
+    pub fn new() -> JobManager {
+        JobManager::default()
+    }
+
+    /// A manager whose state is loaded from and saved to the JSON file at `path`. The file is
+    /// created on the first change if it doesn't exist, and rewritten after every change.
+    pub fn open(path: impl AsRef<Path>) -> Result<JobManager> {
+        let path = path.as_ref().to_path_buf();
+        let state = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).map_err(Error::SerdeJsonError)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => State::default(),
+            Err(e) => return Err(Error::IoError(e)),
+        };
+        Ok(JobManager { path: Some(path), state })
+    }
+
+    /// A manager with the state saved by `to_json`.
+    pub fn from_json(json: &str) -> Result<JobManager> {
+        Ok(JobManager { path: None, state: serde_json::from_str(json).map_err(Error::SerdeJsonError)? })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.state).unwrap()
+    }
+
+    pub fn jobs(&self) -> &[TrackedJob] {
+        &self.state.jobs
+    }
+
+    pub fn job(&self, id: &str) -> Option<&TrackedJob> {
+        self.state.jobs.iter().find(|job| job.id == id)
+    }
+
+    /// Starts a batch translation for every request of `plan` that the job `id` has no operation for
+    /// yet, recording each operation as soon as it is started. Submitting the same plan again after
+    /// a restart or a failed submission starts only the missing operations.
+    pub async fn submit(&mut self, client: &TranslationClient, id: &str, plan: &BatchPlan) -> Result<&TrackedJob> {
+        let index = match self.state.jobs.iter().position(|job| job.id == id) {
+            Some(index) => index,
+            None => {
+                let submitted_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+                self.state.jobs.push(TrackedJob { id: id.to_string(), submitted_at, operations: Vec::new() });
+                self.save()?;
+                self.state.jobs.len() - 1
+            }
+        };
+        for request in &plan.requests {
+            let fingerprint = fingerprint(request);
+            if self.state.jobs[index].operations.iter().any(|operation| operation.fingerprint == fingerprint) {
+                continue;
+            }
+            let operation = client.batch_translate_text(request).await?;
+            self.state.jobs[index].operations.push(TrackedOperation { name: operation.name, fingerprint });
+            self.save()?;
+        }
+        Ok(&self.state.jobs[index])
+    }
+
+    /// Stops tracking the job `id`. Its operations keep running.
+    pub fn remove(&mut self, id: &str) -> Result<Option<TrackedJob>> {
+        let job = match self.state.jobs.iter().position(|job| job.id == id) {
+            Some(i) => self.state.jobs.remove(i),
+            None => return Ok(None),
+        };
+        self.save()?;
+        Ok(Some(job))
+    }
+
+    /// Fetches the latest state of the operations of every tracked job, by job id.
+    pub async fn status(&self, client: &TranslationClient) -> Result<Vec<(String, BatchJobStatus)>> {
+        try_join_all(self.state.jobs.iter().map(|job| async move {
+            let status = operations_status(client, job.operations.iter().map(|operation| operation.name.as_str())).await?;
+            Ok::<_, Error>((job.id.clone(), status))
+        })).await
+    }
+
+    /// Waits for every operation of every tracked job, returning the results by job id, in the
+    /// order the operations were started.
+    pub async fn wait_until_done(&self, client: &TranslationClient, poll_config: &PollConfig)
+        -> Result<Vec<(String, Vec<StdResult<BatchTranslateResponse, Status>>)>>
+    {
+        let mut results = Vec::new();
+        for job in &self.state.jobs {
+            let waits = job.operations.iter().map(|operation| async move {
+                let operation: BatchTranslateOperation = client.get_operation(&operation.name).await?.into_typed()?;
+                operation.wait_until_done(client, poll_config).await
+            });
+            results.push((job.id.clone(), try_join_all(waits).await?));
+        }
+        Ok(results)
+    }
+
+    /// Writes the state to the manager's file, if it has one, replacing it atomically.
+    fn save(&self) -> Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, self.to_json()).map_err(Error::IoError)?;
+        std::fs::rename(&tmp, path).map_err(Error::IoError)
+    }
+}
+
+/// The hex SHA-256 of `request`'s JSON, with object keys sorted so maps hash the same every time.
+pub fn fingerprint(request: &BatchTranslateTextRequest) -> String {
+    let json = serde_json::to_value(request).unwrap().to_string();
+    Sha256::digest(json.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::transport::MockTransport;
+
+    fn request(input_uri: &str) -> BatchTranslateTextRequest {
+        BatchTranslateTextRequest::builder()
+            .source_language_code("en")
+            .target_language("de")
+            .input_config(BatchTranslateTextInputConfig { mime_type: None, gcs_source: GcsSource { input_uri: input_uri.to_string() } })
+            .output_config(BatchTranslateTextOutputConfig {
+                gcs_destination: GcsDestination { output_uri_prefix: format!("{}-out/", input_uri) },
+            })
+            .labels(Labels::new().with("team", "docs").unwrap().with("env", "prod").unwrap())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(fingerprint(&request("gs://b/a.tsv")), fingerprint(&request("gs://b/a.tsv")));
+        assert_ne!(fingerprint(&request("gs://b/a.tsv")), fingerprint(&request("gs://b/b.tsv")));
+    }
+
+    #[tokio::test]
+    async fn test_job_manager() {
+        let started = Arc::new(AtomicUsize::new(0));
+        let transport = Arc::new(MockTransport::new({
+            let started = started.clone();
+            move |request| {
+                if request.uri.ends_with(":batchTranslateText") {
+                    let n = started.fetch_add(1, Ordering::SeqCst) + 1;
+                    (200, format!(r#"{{"name": "projects/p/locations/l/operations/{}", "metadata": {{}}}}"#, n))
+                } else {
+                    (200, r#"{"name": "projects/p/locations/l/operations/1", "done": true,
+                        "metadata": {"translatedCharacters": "4", "totalCharacters": "4"}, "response": {}}"#.to_string())
+                }
+            }
+        }));
+        let client = TranslationClient::new("p", "l", "token").with_transport(transport);
+        let path = std::env::temp_dir().join(format!("google-translation-jobs-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut jobs = JobManager::open(&path).unwrap();
+        jobs.submit(&client, "job", &BatchPlan::from(request("gs://b/a.tsv"))).await.unwrap();
+
+        // After a "restart", resubmitting a plan only starts the requests without an operation.
+        let mut jobs = JobManager::open(&path).unwrap();
+        let plan = BatchPlan { requests: vec![request("gs://b/a.tsv"), request("gs://b/b.tsv")] };
+        let job = jobs.submit(&client, "job", &plan).await.unwrap();
+        assert_eq!(job.operations.iter().map(|operation| operation.name.as_str()).collect::<Vec<_>>(),
+            vec!["projects/p/locations/l/operations/1", "projects/p/locations/l/operations/2"]);
+        assert_eq!(started.load(Ordering::SeqCst), 2);
+        assert_eq!(JobManager::from_json(&std::fs::read_to_string(&path).unwrap()).unwrap().jobs(), jobs.jobs());
+
+        let (_, status) = &jobs.status(&client).await.unwrap()[0];
+        assert!(status.is_done());
+        assert_eq!(status.translated_characters, 8);
+        let results = jobs.wait_until_done(&client, &PollConfig::default()).await.unwrap();
+        assert_eq!(results[0].1.len(), 2);
+        assert!(jobs.remove("job").unwrap().is_some());
+        assert!(JobManager::open(&path).unwrap().jobs().is_empty());
+
+        // A new job is saved even if its plan has no requests.
+        jobs.submit(&client, "empty", &BatchPlan { requests: Vec::new() }).await.unwrap();
+        assert!(JobManager::open(&path).unwrap().job("empty").is_some());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
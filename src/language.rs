@@ -0,0 +1,166 @@
+//! BCP-47 language codes, normalized the way the API expects them.
+//!
+//! ```
+//! # use google_translation::*;
+//! # fn run() -> Result<()> {
+//! let code: LanguageCode = "ZH_cn".parse()?;
+//! assert_eq!(code.as_str(), "zh-CN");
+//! assert_eq!(LanguageCode::new("iw")?.as_str(), "he");
+//! assert!(LanguageCode::new("english").is_err());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! `LanguageCode` converts into `String` and implements `AsRef<str>`, so it can be passed wherever
+//! the client and the request builders take a language code.
+
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+/// Deprecated language subtags and the codes that replaced them.
+const DEPRECATED: &[(&str, &str)] = &[("in", "id"), ("iw", "he"), ("ji", "yi"), ("jw", "jv")];
+
+/// A syntactically valid language code such as "de", "zh-CN" or "mni-Mtei".
+///
+/// Underscores become hyphens, the language is lowercased, a script is titlecased, a region is
+/// uppercased, and deprecated languages are replaced: "zh_cn" is "zh-CN" and "iw" is "he".
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(try_from = "String", into = "String")]
+pub struct LanguageCode(String);
+
+impl LanguageCode {
+    pub fn new(code: &str) -> Result<LanguageCode> {
+        normalize(code).map(LanguageCode).ok_or_else(|| Error::InvalidRequest(format!(
+            "invalid language code {:?}, expected a BCP-47 code such as \"de\" or \"zh-CN\"", code)))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The language subtag, "zh" for "zh-CN".
+    pub fn language(&self) -> &str {
+        self.0.split('-').next().unwrap()
+    }
+
+    /// The region subtag, "CN" for "zh-CN".
+    pub fn region(&self) -> Option<&str> {
+        self.0.split('-').skip(1).find(|subtag| is_region(subtag))
+    }
+}
+
+fn normalize(code: &str) -> Option<String> {
+    let mut subtags = code.trim().split(['-', '_']);
+    let language = subtags.next()?.to_ascii_lowercase();
+    if !(2..=3).contains(&language.len()) || !language.bytes().all(|b| b.is_ascii_lowercase()) {
+        return None;
+    }
+    let language = DEPRECATED.iter().find(|(old, _)| *old == language).map_or(language.as_str(), |(_, new)| new);
+    let mut normalized = language.to_string();
+    for subtag in subtags {
+        if subtag.is_empty() || subtag.len() > 8 || !subtag.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            return None;
+        }
+        normalized.push('-');
+        if subtag.len() == 4 && subtag.bytes().all(|b| b.is_ascii_alphabetic()) {
+            normalized.push_str(&subtag[..1].to_ascii_uppercase());
+            normalized.push_str(&subtag[1..].to_ascii_lowercase());
+        } else if is_region(subtag) {
+            normalized.push_str(&subtag.to_ascii_uppercase());
+        } else {
+            normalized.push_str(&subtag.to_ascii_lowercase());
+        }
+    }
+    Some(normalized)
+}
+
+/// A two-letter country code or a three-digit UN M.49 area code such as "419".
+fn is_region(subtag: &str) -> bool {
+    (subtag.len() == 2 && subtag.bytes().all(|b| b.is_ascii_alphabetic()))
+        || (subtag.len() == 3 && subtag.bytes().all(|b| b.is_ascii_digit()))
+}
+
+impl TryFrom<String> for LanguageCode {
+    type Error = Error;
+
+    fn try_from(code: String) -> Result<LanguageCode> {
+        LanguageCode::new(&code)
+    }
+}
+
+impl TryFrom<&str> for LanguageCode {
+    type Error = Error;
+
+    fn try_from(code: &str) -> Result<LanguageCode> {
+        LanguageCode::new(code)
+    }
+}
+
+impl FromStr for LanguageCode {
+    type Err = Error;
+
+    fn from_str(code: &str) -> Result<LanguageCode> {
+        LanguageCode::new(code)
+    }
+}
+
+impl From<LanguageCode> for String {
+    fn from(code: LanguageCode) -> String {
+        code.0
+    }
+}
+
+impl AsRef<str> for LanguageCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for LanguageCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let normalized = |code: &str| LanguageCode::new(code).unwrap().to_string();
+        assert_eq!(normalized("zh_CN"), "zh-CN");
+        assert_eq!(normalized("ZH-cn"), "zh-CN");
+        assert_eq!(normalized(" de "), "de");
+        assert_eq!(normalized("iw"), "he");
+        assert_eq!(normalized("in-ID"), "id-ID");
+        assert_eq!(normalized("mni-mtei"), "mni-Mtei");
+        assert_eq!(normalized("zh-hant-tw"), "zh-Hant-TW");
+        assert_eq!(normalized("es-419"), "es-419");
+
+        let code = LanguageCode::new("zh-Hant-TW").unwrap();
+        assert_eq!((code.language(), code.region()), ("zh", Some("TW")));
+        assert_eq!(LanguageCode::new("de").unwrap().region(), None);
+    }
+
+    #[test]
+    fn test_invalid() {
+        for code in ["", "e", "english", "de-", "de--CH", "d3", "zh-toolongsubtag", "de CH"] {
+            assert!(matches!(LanguageCode::new(code), Err(Error::InvalidRequest(_))), "{:?}", code);
+        }
+    }
+
+    #[test]
+    fn test_serde() {
+        let code: LanguageCode = serde_json::from_str(r#""pt_br""#).unwrap();
+        assert_eq!(serde_json::to_string(&code).unwrap(), r#""pt-BR""#);
+        assert!(serde_json::from_str::<LanguageCode>(r#""x""#).is_err());
+
+        let request = crate::TranslateTextRequest::builder().content("Hello").target(code).build().unwrap();
+        assert_eq!(request.target_language_code, "pt-BR");
+    }
+}
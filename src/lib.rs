@@ -1,40 +1,292 @@
-#[macro_use]
-extern crate log;
-#[macro_use]
-extern crate serde;
+//! https://cloud.google.com/translate/docs/reference/rest/
+
+#[cfg(all(feature = "native-tls", feature = "rustls"))]
+compile_error!("the native-tls and rustls features are mutually exclusive, disable the default features to use rustls");
+#[cfg(all(feature = "native", not(any(feature = "native-tls", feature = "rustls"))))]
+compile_error!("either the native-tls or the rustls feature must be enabled");
+#[cfg(all(feature = "native", feature = "wasm"))]
+compile_error!("the native and wasm features are mutually exclusive, disable the default features to use wasm");
+#[cfg(not(any(feature = "native", feature = "wasm")))]
+compile_error!("either the native or the wasm feature must be enabled");
 
-/**
- * https://cloud.google.com/translate/docs/reference/rest/
- */
+#[cfg_attr(not(feature = "tracing"), macro_use)]
+extern crate log;
 
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::io::{self, Write};
+use std::convert::TryFrom;
+use std::future::Future;
 use std::result::Result as StdResult;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
-use futures::{Future, Stream};
-use futures::future::{loop_fn, Loop};
-use hyper::{Body, Client, Method, Request};
-use hyper::header::HeaderValue;
-use hyper::rt::{self};
+use futures::stream::{Stream, TryStreamExt};
+use hyper::{Body, Method, Request};
+#[cfg(feature = "native")]
+use hyper::Client;
+use hyper::body::Bytes;
+use hyper::header::{HeaderMap, HeaderName, HeaderValue};
+#[cfg(feature = "rustls")]
+use hyper_rustls::HttpsConnector;
+#[cfg(feature = "native-tls")]
 use hyper_tls::HttpsConnector;
 
-use serde::{Serialize, Deserialize, Deserializer};
+use serde::{Serialize, Deserialize};
 use serde::de::DeserializeOwned;
+use web_time::Instant;
+
+pub mod adaptive_mt;
+pub mod auth;
+pub mod batch;
+pub mod cache;
+pub mod cassette;
+pub mod chunking;
+pub mod circuit_breaker;
+pub mod cost;
+pub mod curl;
+pub mod defaults;
+pub mod detection;
+pub mod dry_run;
+pub mod error_details;
+#[cfg(feature = "native")]
+pub mod external_account;
+pub mod fallback;
+pub mod formats;
+pub mod gcs;
+pub mod glossary;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod html;
+pub mod interceptor;
+pub mod jobs;
+pub mod language;
+pub mod markdown;
+pub mod metrics;
+#[cfg(feature = "mock-server")]
+pub mod mock_server;
+pub mod names;
+pub mod operations;
+pub mod placeholders;
+pub mod planner;
+#[cfg(feature = "native")]
+pub mod proxy;
+pub mod rate_limit;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod retry;
+pub mod runtime;
+#[cfg(feature = "native")]
+pub mod staging;
+pub mod store;
+pub mod streaming;
+pub mod tmx;
+pub mod transport;
+#[cfg(feature = "v2")]
+pub mod v2;
+mod builders;
+mod bulk;
+mod compression;
+#[cfg(feature = "opentelemetry")]
+mod otel;
+mod trace;
+mod translator;
+
+pub use builders::*;
+pub use bulk::{BulkDetectConfig, BulkTranslateConfig};
+pub use language::LanguageCode;
+pub use names::{GlossaryName, ModelName, OperationName, ProjectLocation};
+pub use translator::Translator;
+pub use tokio_util::sync::CancellationToken;
+
+use auth::{SecretToken, StaticToken, TokenProvider};
+use cache::TranslationCache;
+use error_details::{BadRequest, ErrorDetail, ErrorInfo, QuotaFailure, RetryInfo};
+use interceptor::Interceptor;
+use placeholders::Placeholders;
+#[cfg(feature = "native")]
+use proxy::{Proxy, ProxyConnector};
+use rate_limit::RateLimiter;
+use retry::RetryPolicy;
+use runtime::Runtime;
+use circuit_breaker::{CircuitBreaker, CircuitBreakerConfig};
+use cost::CostTracker;
+use defaults::RequestDefaults;
+use fallback::ModelFallback;
+use store::TranslationStore;
+use tmx::TmxRecorder;
+use transport::HttpTransport;
+#[cfg(feature = "native")]
+use transport::{ConnectionConfig, HyperTransport};
+
+/// The boxed futures returned by the crate's traits, such as `HttpTransport` and `TokenProvider`.
+/// They are `Send` except with the `wasm` feature, as the browser's futures aren't.
+#[cfg(not(feature = "wasm"))]
+pub type BoxFuture<'a, T> = futures::future::BoxFuture<'a, T>;
+#[cfg(feature = "wasm")]
+pub type BoxFuture<'a, T> = futures::future::LocalBoxFuture<'a, T>;
 
 #[derive(Debug)]
 pub enum Error {
-    HyperError(hyper::error::Error),
+    HyperError(hyper::Error),
+    /// A request sent by `transport::ReqwestTransport` failed.
+    #[cfg(feature = "reqwest-backend")]
+    ReqwestError(reqwest::Error),
+    /// A request sent by `transport::FetchTransport` failed, with the JavaScript error. fetch fails
+    /// like this when the network is unreachable or the request is blocked, for example by CORS.
+    #[cfg(feature = "wasm")]
+    FetchError(String),
     SerdeJsonError(serde_json::Error),
-    ResponseError(u16, serde_json::Value),
+    /// The API answered with a non-OK HTTP status and a google.rpc.Status error body.
+    ResponseError(u16, Status),
+    /// The API answered with a non-OK HTTP status and a body that isn't a google.rpc.Status,
+    /// for example an HTML page from a proxy.
+    HttpError(u16, String),
+    AuthError(String),
+    IoError(std::io::Error),
+    /// A client-side deadline, such as `PollConfig::deadline`, passed.
+    DeadlineExceeded(String),
+    /// A request was rejected before being sent, for example because a required field is missing.
+    InvalidRequest(String),
+    /// A request URL, built from the client's endpoint and ids or from a resource name, isn't a
+    /// valid URI, for example because an id contains a space.
+    InvalidUrl(String),
+    /// The client's `CancellationToken` was cancelled before the call finished.
+    Cancelled,
+    /// A request was rejected before being sent because it would exceed the budget of the
+    /// client's `CostTracker`.
+    BudgetExceeded(String),
+    /// A request was rejected before being sent because the client's circuit breaker is open
+    /// after sustained failures. See the `circuit_breaker` module.
+    CircuitOpen,
     Other(String),
 }
 
-impl From<hyper::error::Error> for Error {
-    fn from(e: hyper::error::Error) -> Self {
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::HyperError(e) => write!(f, "http error: {}", e),
+            #[cfg(feature = "reqwest-backend")]
+            Error::ReqwestError(e) => write!(f, "http error: {}", e),
+            #[cfg(feature = "wasm")]
+            Error::FetchError(message) => write!(f, "http error: {}", message),
+            Error::SerdeJsonError(e) => write!(f, "json error: {}", e),
+            Error::ResponseError(http_status, status) => match &status.status {
+                Some(name) => write!(f, "{} {}: {}", http_status, name, status.message),
+                None => write!(f, "{}: {}", http_status, status.message),
+            },
+            Error::HttpError(http_status, body) => write!(f, "{}: {}", http_status, body),
+            Error::AuthError(message) => write!(f, "authentication error: {}", message),
+            Error::IoError(e) => write!(f, "io error: {}", e),
+            Error::DeadlineExceeded(message) => write!(f, "deadline exceeded: {}", message),
+            Error::InvalidRequest(message) => write!(f, "invalid request: {}", message),
+            Error::InvalidUrl(url) => write!(f, "invalid request URL: {}", url),
+            Error::Cancelled => f.write_str("cancelled"),
+            Error::BudgetExceeded(message) => write!(f, "budget exceeded: {}", message),
+            Error::CircuitOpen => f.write_str("circuit breaker open: the API has been failing"),
+            Error::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl Error {
+    /// Returns true for transient failures worth retrying: RESOURCE_EXHAUSTED (429), INTERNAL (500),
+    /// UNAVAILABLE (503), gateway errors (502, 504) and connection failures.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::HyperError(e) => e.is_connect() || e.is_incomplete_message(),
+            #[cfg(feature = "reqwest-backend")]
+            Error::ReqwestError(e) => e.is_connect() || e.is_timeout(),
+            #[cfg(feature = "wasm")]
+            Error::FetchError(_) => true,
+            Error::ResponseError(http_status, _) | Error::HttpError(http_status, _) => {
+                matches!(*http_status, 429 | 500 | 502 | 503 | 504)
+            },
+            _ => false,
+        }
+    }
+
+    /// The canonical error code of a failed request. `None` for failures that didn't come from
+    /// the API, such as connection, decoding and authentication errors.
+    ///
+    /// The status name in the error body is used if present, otherwise the HTTP status.
+    /// Client-side deadlines, rejected requests and URLs, cancellation, an exceeded budget and an
+    /// open circuit breaker map to DEADLINE_EXCEEDED, INVALID_ARGUMENT, CANCELLED,
+    /// RESOURCE_EXHAUSTED and UNAVAILABLE.
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            Error::ResponseError(http_status, status) => status.status.as_deref()
+                .and_then(ErrorCode::from_name)
+                .or_else(|| ErrorCode::from_http_status(*http_status)),
+            Error::HttpError(http_status, _) => ErrorCode::from_http_status(*http_status),
+            Error::DeadlineExceeded(_) => Some(ErrorCode::DeadlineExceeded),
+            Error::InvalidRequest(_) | Error::InvalidUrl(_) => Some(ErrorCode::InvalidArgument),
+            Error::Cancelled => Some(ErrorCode::Cancelled),
+            Error::BudgetExceeded(_) => Some(ErrorCode::ResourceExhausted),
+            Error::CircuitOpen => Some(ErrorCode::Unavailable),
+            _ => None,
+        }
+    }
+
+    /// The google.rpc.Status the API returned, if the error came with one.
+    pub fn status(&self) -> Option<&Status> {
+        match self {
+            Error::ResponseError(_, status) => Some(status),
+            _ => None,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.code() == Some(ErrorCode::NotFound)
+    }
+
+    pub fn is_already_exists(&self) -> bool {
+        self.code() == Some(ErrorCode::AlreadyExists)
+    }
+
+    pub fn is_permission_denied(&self) -> bool {
+        self.code() == Some(ErrorCode::PermissionDenied)
+    }
+
+    pub fn is_unauthenticated(&self) -> bool {
+        self.code() == Some(ErrorCode::Unauthenticated)
+    }
+
+    /// Returns true if a quota or rate limit was exceeded (RESOURCE_EXHAUSTED).
+    pub fn is_quota(&self) -> bool {
+        self.code() == Some(ErrorCode::ResourceExhausted)
+    }
+
+    /// Returns true if the call was abandoned because the client's `CancellationToken` was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, Error::Cancelled)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::HyperError(e) => Some(e),
+            #[cfg(feature = "reqwest-backend")]
+            Error::ReqwestError(e) => Some(e),
+            Error::SerdeJsonError(e) => Some(e),
+            Error::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<hyper::Error> for Error {
+    fn from(e: hyper::Error) -> Self {
         Error::HyperError(e)
     }
 }
 
+/// The JSON body of a failed request: `{"error": {"code": 404, "message": "...", "status": "NOT_FOUND"}}`.
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: Status,
+}
+
 pub type Result<T> = StdResult<T, Error>;
 
 struct Empty;
@@ -78,65 +330,182 @@ impl ParamsOrEmpty for Empty {
 }
 
 trait ResponseOrEmpty: Sized {
-    const IS_EMPTY: bool;
-    fn from_slice(data: &[u8]) -> StdResult<Self, serde_json::Error>;
+    /// Parses the body `data` of a successful `response`, adding the paths of the fields the type
+    /// doesn't know to `unknown_fields`.
+    fn from_response(response: &hyper::Response<()>, data: &Bytes, unknown_fields: &mut Vec<String>)
+        -> StdResult<Self, serde_json::Error>;
 }
 
 impl<T> ResponseOrEmpty for T where T: DeserializeOwned {
-    const IS_EMPTY: bool = false;
-    fn from_slice(data: &[u8]) -> StdResult<Self, serde_json::Error> {
-        serde_json::from_slice(data)
+    fn from_response(_response: &hyper::Response<()>, data: &Bytes, unknown_fields: &mut Vec<String>)
+        -> StdResult<Self, serde_json::Error>
+    {
+        // The API answers some requests, such as lists with no results, with an empty body.
+        let data: &[u8] = if data.iter().all(u8::is_ascii_whitespace) { b"{}" } else { data };
+        let mut deserializer = serde_json::Deserializer::from_slice(data);
+        let value = serde_ignored::deserialize(&mut deserializer, |path| unknown_fields.push(path.to_string()))?;
+        deserializer.end()?;
+        Ok(value)
     }
 }
 
 impl ResponseOrEmpty for Empty {
-    const IS_EMPTY: bool = true;
-    fn from_slice(_data: &[u8]) -> StdResult<Self, serde_json::Error> {
+    fn from_response(_response: &hyper::Response<()>, _data: &Bytes, _unknown_fields: &mut Vec<String>)
+        -> StdResult<Self, serde_json::Error>
+    {
         Ok(Empty)
     }
 }
 
-fn post_request<IB, OB>(url: &str, access_token: &str, request_body: &IB) -> impl Future<Item=OB, Error=Error>
+/// A response body returned as is, for downloads.
+struct RawBody(Bytes);
+
+impl ResponseOrEmpty for RawBody {
+    fn from_response(_response: &hyper::Response<()>, data: &Bytes, _unknown_fields: &mut Vec<String>)
+        -> StdResult<Self, serde_json::Error>
+    {
+        Ok(RawBody(data.clone()))
+    }
+}
+
+impl<T> ResponseOrEmpty for RawResponse<T> where T: ResponseOrEmpty {
+    fn from_response(response: &hyper::Response<()>, data: &Bytes, unknown_fields: &mut Vec<String>)
+        -> StdResult<Self, serde_json::Error>
+    {
+        Ok(RawResponse {
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: data.clone(),
+            value: T::from_response(response, data, unknown_fields)?,
+        })
+    }
+}
+
+#[cfg(feature = "native")]
+pub(crate) type HttpsClient = Client<HttpsConnector<ProxyConnector>>;
+
+/// A client that connects through the proxy configured in the environment, see `Proxy::from_env`.
+#[cfg(feature = "native")]
+pub(crate) fn new_https_client() -> HttpsClient {
+    new_https_client_with_config(Proxy::from_env(), &ConnectionConfig::default())
+}
+
+#[cfg(feature = "native")]
+pub(crate) fn new_https_client_with_config<B>(proxy: Option<Proxy>, config: &ConnectionConfig)
+    -> Client<HttpsConnector<ProxyConnector>, B>
+    where B: hyper::body::HttpBody + Send + 'static, B::Data: Send
+{
+    let connector = ProxyConnector::new(proxy, config.tcp_keepalive);
+    // hyper-tls doesn't offer HTTP/2 during the TLS handshake, and Google's servers only speak it
+    // when it is negotiated, so an HTTP/2-only client asks for it explicitly.
+    #[cfg(feature = "native-tls")]
+    let https = {
+        let mut tls = native_tls::TlsConnector::builder();
+        if config.http2_only {
+            tls.request_alpns(&["h2"]);
+        }
+        let tls = tls.build().unwrap_or_else(|e| panic!("failed to initialize TLS: {}", e));
+        HttpsConnector::from((connector, tls.into()))
+    };
+    #[cfg(feature = "rustls")]
+    let https = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .https_or_http()
+        .enable_http1()
+        .enable_http2()
+        .wrap_connector(connector);
+    let mut builder = Client::builder();
+    builder.http2_only(config.http2_only)
+        .http2_keep_alive_interval(config.http2_keep_alive_interval)
+        .http2_keep_alive_timeout(config.http2_keep_alive_timeout)
+        .http2_keep_alive_while_idle(config.http2_keep_alive_while_idle)
+        .pool_idle_timeout(config.pool_idle_timeout)
+        .pool_max_idle_per_host(config.pool_max_idle_per_host.unwrap_or(usize::MAX));
+    builder.build(https)
+}
+
+/// The User-Agent sent with every request, optionally followed by a suffix set with `TranslationClient::with_user_agent`.
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// The HTTP client used by the request helpers: the transport requests are sent with plus the
+/// retry policy, headers, interceptors and cancellation token applied to every request sent through it.
+#[derive(Clone)]
+pub(crate) struct HttpClient {
+    transport: Arc<dyn HttpTransport>,
+    retry_policy: RetryPolicy,
+    headers: HeaderMap,
+    interceptors: Vec<Arc<dyn Interceptor>>,
+    cancellation_token: Option<CancellationToken>,
+    /// Request bodies of at least this many bytes are gzip-compressed.
+    compression_threshold: Option<usize>,
+    unknown_fields: UnknownFields,
+    runtime: Arc<dyn Runtime>,
+}
+
+impl HttpClient {
+    /// The client the free functions share, so they reuse one connection pool.
+    fn shared() -> HttpClient {
+        static SHARED: OnceLock<HttpClient> = OnceLock::new();
+        SHARED.get_or_init(HttpClient::new).clone()
+    }
+
+    fn new() -> HttpClient {
+        let mut headers = HeaderMap::new();
+        headers.insert(hyper::header::USER_AGENT, HeaderValue::from_static(USER_AGENT));
+        headers.insert(hyper::header::ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
+        HttpClient {
+            #[cfg(feature = "native")]
+            transport: Arc::new(HyperTransport::new()),
+            #[cfg(feature = "wasm")]
+            transport: Arc::new(transport::FetchTransport),
+            retry_policy: RetryPolicy::default(),
+            headers,
+            interceptors: Vec::new(),
+            cancellation_token: None,
+            compression_threshold: None,
+            unknown_fields: UnknownFields::default(),
+            runtime: Arc::new(runtime::DefaultRuntime::default()),
+        }
+    }
+
+    /// Runs `future` to completion, or returns `Error::Cancelled` as soon as the cancellation token
+    /// is cancelled, dropping the future.
+    async fn until_cancelled<T>(&self, future: impl Future<Output = T>) -> Result<T> {
+        match &self.cancellation_token {
+            Some(token) => token.run_until_cancelled(future).await.ok_or(Error::Cancelled),
+            None => Ok(future.await),
+        }
+    }
+}
+
+async fn post_request<IB, OB>(http: &HttpClient, url: &str, access_token: &SecretToken, request_body: &IB) -> Result<OB>
     where IB: RequestOrEmpty, OB: ResponseOrEmpty
 {
-    let mut req = if IB::IS_EMPTY {
-        Request::default()
+    let body = if IB::IS_EMPTY {
+        None
     } else {
-        Request::new(Body::from(request_body.to_json()))
+        Some(Bytes::from(request_body.to_json()))
     };
-    *req.method_mut() = Method::POST;
-    *req.uri_mut() = url.parse().unwrap();
-    req.headers_mut().insert(
-        hyper::header::CONTENT_TYPE,
-        HeaderValue::from_static("application/json")
-    );
-    req.headers_mut().insert(
-        hyper::header::AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", access_token.trim())).unwrap(),
-    );
-    let https = HttpsConnector::new(4).expect("TLS initialization failed");
-    let client = Client::builder()
-        .build::<_, hyper::Body>(https);
-    client.request(req)
-        .and_then(|res| {
-            let status = res.status();
-            res.into_body().concat2().map(move |body| (status, body))
-        })
-        .map_err(|err| Error::HyperError(err))
-        .and_then(|(status, body)| {
-            info!("POST: {}", status);
-            if status == code::OK {
-                OB::from_slice(body.as_ref()).map_err(|e| Error::SerdeJsonError(e))
-            } else {
-                match serde_json::from_slice(body.as_ref()) {
-                    Ok(body) => Err(Error::ResponseError(status.as_u16(), body)),
-                    Err(e) => Err(Error::SerdeJsonError(e)),
-                }
-            }
-        })
+    send_request(http, Method::POST, url, access_token, body, Some("application/json"), true).await
+}
+
+/// Posts a request that creates a resource or starts an operation. It is only retried if the retry
+/// policy's `retry_non_idempotent` is set, since the first attempt may have succeeded.
+async fn create_request<IB, OB>(http: &HttpClient, url: &str, access_token: &SecretToken, request_body: &IB) -> Result<OB>
+    where IB: RequestOrEmpty, OB: ResponseOrEmpty
+{
+    let body = Some(Bytes::from(request_body.to_json()));
+    send_request(http, Method::POST, url, access_token, body, Some("application/json"), false).await
+}
+
+/// Posts `data` as is, for uploads.
+async fn upload_request<OB>(http: &HttpClient, url: &str, access_token: &SecretToken, data: Bytes) -> Result<OB>
+    where OB: ResponseOrEmpty
+{
+    send_request(http, Method::POST, url, access_token, Some(data), Some("application/octet-stream"), true).await
 }
 
-fn get_request<IB, OB>(url: &str, access_token: &str, params: &IB) -> impl Future<Item=OB, Error=Error>
+async fn get_request<IB, OB>(http: &HttpClient, url: &str, access_token: &SecretToken, params: &IB) -> Result<OB>
     where IB: ParamsOrEmpty, OB: ResponseOrEmpty
 {
     let url = if IB::IS_EMPTY {
@@ -144,240 +513,1075 @@ fn get_request<IB, OB>(url: &str, access_token: &str, params: &IB) -> impl Futur
     } else {
         format!("{}?{}", url, params.to_params())
     };
-    let mut req = Request::default();
-    *req.method_mut() = Method::GET;
-    *req.uri_mut() = url.parse().unwrap();
-    req.headers_mut().insert(
-        hyper::header::CONTENT_TYPE,
-        HeaderValue::from_static("application/json")
-    );
-    req.headers_mut().insert(
-        hyper::header::AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", access_token.trim())).unwrap(),
-    );
-    let https = HttpsConnector::new(4).expect("TLS initialization failed");
-    let client = Client::builder()
-        .build::<_, hyper::Body>(https);
-    client.request(req)
-        .and_then(|res| {
-            let status = res.status();
-            res.into_body().concat2().map(move |body| (status, body))
-        })
-        .map_err(|err| Error::HyperError(err))
-        .and_then(|(status, body)| {
-            info!("GET: {}", status);
-            if status == code::OK {
-                OB::from_slice(body.as_ref()).map_err(|e| Error::SerdeJsonError(e))
-            } else {
-                match serde_json::from_slice(body.as_ref()) {
-                    Ok(body) => Err(Error::ResponseError(status.as_u16(), body)),
-                    Err(e) => Err(Error::SerdeJsonError(e)),
-                }
-            }
-        })
+    send_request(http, Method::GET, &url, access_token, None, Some("application/json"), true).await
 }
 
-fn delete_request<OB>(url: &str, access_token: &str) -> impl Future<Item=OB, Error=Error>
+async fn delete_request<OB>(http: &HttpClient, url: &str, access_token: &SecretToken) -> Result<OB>
     where OB: ResponseOrEmpty
 {
-    let mut req = Request::new(Body::empty());
-    *req.method_mut() = Method::DELETE;
-    *req.uri_mut() = url.parse().unwrap();
-    req.headers_mut().insert(
-        hyper::header::AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", access_token.trim())).unwrap(),
-    );
-    let https = HttpsConnector::new(4).expect("TLS initialization failed");
-    let client = Client::builder()
-        .build::<_, hyper::Body>(https);
-    client.request(req)
-        .and_then(|res| {
-            let status = res.status();
-            res.into_body().concat2().map(move |body| (status, body))
-        })
-        .map_err(|err| Error::HyperError(err))
-        .and_then(|(status, body)| {
-            info!("DELETE: {}", status);
-            if status == code::OK {
-                OB::from_slice(body.as_ref()).map_err(|e| Error::SerdeJsonError(e))
-            } else {
-                match serde_json::from_slice(body.as_ref()) {
-                    Ok(body) => Err(Error::ResponseError(status.as_u16(), body)),
-                    Err(e) => Err(Error::SerdeJsonError(e)),
-                }
-            }
-        })
+    send_request(http, Method::DELETE, url, access_token, None, None, true).await
 }
 
-#[derive(Serialize, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct DetectLanguageRequest {
-    /// Optional. The language detection model to be used.
-    /// 
-    /// Format: projects/{project-id}/locations/{location-id}/models/language-detection/{model-id}
-    /// 
-    /// Only one language detection model is currently supported:
-    /// projects/{project-id}/locations/{location-id}/models/language-detection/default.
-    /// 
-    /// If not specified, the default model is used.
-    /// 
-    /// Authorization requires the following [Google IAM](https://cloud.google.com/iam)
-    /// permission on the specified resource model:
-    /// 
-    /// - cloudtranslate.languageDetectionModels.predict
-    pub model: Option<String>,
-    /// Optional. The format of the source text, for example, "text/html", "text/plain".
-    /// If left blank, the MIME type defaults to "text/html".
-    pub mime_type: Option<MimeType>,
-    /// Optional. The labels with user-defined metadata for the request.
-    /// 
-    /// Label keys and values can be no longer than 63 characters (Unicode codepoints), can only contain lowercase letters, numeric characters, underscores and dashes. International characters are allowed. Label values are optional. Label keys must start with a letter.
-    /// 
-    /// See https://goo.gl/xmQnxf for more information on and examples of labels.
-    pub labels: Option<HashMap<String, String>>,
-    /// The content of the input stored as a string.
-    pub content: String,
+fn build_request(http: &HttpClient, method: &Method, url: &str, access_token: &SecretToken, body: Option<&RequestBody>,
+        content_type: Option<&'static str>)
+    -> Result<Request<Body>>
+{
+    let mut req = match body {
+        Some(body) => Request::new(Body::from(body.data.clone())),
+        None => Request::default(),
+    };
+    *req.method_mut() = method.clone();
+    *req.uri_mut() = url.parse().map_err(|_| Error::InvalidUrl(trace::redact_url(url)))?;
+    *req.headers_mut() = http.headers.clone();
+    if body.is_some_and(|body| body.gzipped) {
+        req.headers_mut().insert(hyper::header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    }
+    if let Some(content_type) = content_type {
+        req.headers_mut().insert(hyper::header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    }
+    // Requests authenticated with an API key in the URL, such as the v2 API's, have no access token.
+    if !access_token.is_empty() {
+        req.headers_mut().insert(hyper::header::AUTHORIZATION, auth::bearer_header(access_token)?);
+    }
+    Ok(req)
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-/// The response message for language detection.
-pub struct DetectLanguageResponse {
-    /// A list of detected languages sorted by detection confidence in descending order. The most probable language first.
-    pub languages: Vec<DetectLanguageItem>,
+/// A request body, compressed once up front so retries don't compress it again.
+struct RequestBody {
+    data: Bytes,
+    gzipped: bool,
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-/// The response message for language detection.
-pub struct DetectLanguageItem {
-    /// The BCP-47 language code of source content in the request, detected automatically.
-    pub language_code: String,
-    /// The confidence of the detection result for this language.
-    pub confidence: f32,
+impl RequestBody {
+    fn new(http: &HttpClient, body: Bytes) -> RequestBody {
+        match http.compression_threshold {
+            Some(threshold) if body.len() >= threshold => RequestBody { data: compression::gzip(&body).into(), gzipped: true },
+            _ => RequestBody { data: body, gzipped: false },
+        }
+    }
 }
 
-/// Detects the language of text within a request.
-pub fn detect_language(project_id: &str, location_id: &str, access_token: &str,
-        request_body: &DetectLanguageRequest)
-    -> impl Future<Item=DetectLanguageResponse, Error=Error> + Send
+/// Sends a request, retrying retryable failures according to the client's retry policy. Requests
+/// that aren't `idempotent` are only retried if the policy allows it.
+async fn send_request<OB>(http: &HttpClient, method: Method, url: &str, access_token: &SecretToken,
+        body: Option<Bytes>, content_type: Option<&'static str>, idempotent: bool)
+    -> Result<OB>
+    where OB: ResponseOrEmpty
 {
-    let url = format!("https://translation.googleapis.com/v3beta1/projects/{}/locations/{}:detectLanguage",
-        project_id, location_id);
-    post_request(&url, access_token, request_body)
+    let max_attempts = match idempotent || http.retry_policy.retry_non_idempotent {
+        true => http.retry_policy.max_attempts,
+        false => 1,
+    };
+    let body = body.map(|body| RequestBody::new(http, body));
+    let send = async {
+        let mut attempt = 1;
+        loop {
+            let mut req = build_request(http, &method, url, access_token, body.as_ref(), content_type)?;
+            #[cfg(feature = "opentelemetry")]
+            otel::inject(req.headers_mut());
+            for interceptor in &http.interceptors {
+                interceptor.on_request(&mut req);
+            }
+            match send_once(http, req, url).await {
+                Ok(response) => return Ok(response),
+                Err((e, retry_after)) => {
+                    if attempt >= max_attempts || !e.is_retryable() {
+                        return Err(e);
+                    }
+                    let delay = http.retry_policy.delay(attempt, retry_after);
+                    trace::retry(&method, url, attempt, &e, delay);
+                    http.runtime.sleep(delay).await;
+                    attempt += 1;
+                    #[cfg(feature = "opentelemetry")]
+                    otel::retry(attempt);
+                }
+            }
+        }
+    };
+    #[cfg(feature = "tracing")]
+    let send = tracing::Instrument::instrument(send, trace::request_span(&method, url));
+    #[cfg(feature = "opentelemetry")]
+    let context = otel::start_span(&method, url);
+    #[cfg(feature = "opentelemetry")]
+    let send = opentelemetry::trace::FutureExt::with_context(send, context.clone());
+    let result = http.until_cancelled(send).await.and_then(|result| result);
+    #[cfg(feature = "opentelemetry")]
+    otel::end_span(&context, result.as_ref().err());
+    result
 }
 
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct GetSupportedLanguagesQueryParams {
-    /// Optional. The language to use to return localized, human readable names of supported languages.
-    /// If missing, then display names are not returned in a response.
-    pub display_language_code: Option<String>,
-    /// Optional. Get supported languages of this model.
-    /// 
-    /// The format depends on model type:
-    /// 
-    /// AutoML Translation models: projects/{project-id}/locations/{location-id}/models/{model-id}
-    /// 
-    /// General (built-in) models: projects/{project-id}/locations/{location-id}/models/general/nmt, projects/{project-id}/locations/{location-id}/models/general/base
-    /// 
-    /// Returns languages supported by the specified model. If missing, we get supported languages of Google general base (PBMT) model.
-    /// 
-    /// Authorization requires one or more of the following [Google IAM](https://cloud.google.com/iam) permissions on the specified resource model:
-    /// 
-    /// - cloudtranslate.generalModels.get
-    /// - automl.models.get
-    pub model: Option<String>,
+/// Sends a request once. On failure also returns the delay requested by a `Retry-After` header, if any.
+async fn send_once<OB>(http: &HttpClient, req: Request<Body>, url: &str) -> StdResult<OB, (Error, Option<Duration>)>
+    where OB: ResponseOrEmpty
+{
+    let method = req.method().clone();
+    let request_bytes = hyper::body::HttpBody::size_hint(req.body()).exact().unwrap_or(0) as usize;
+    let started = Instant::now();
+    let res = match http.transport.send(req).await {
+        Ok(res) => res,
+        Err(e) => {
+            crate::metrics::request(&method, url, None, started.elapsed(), Some(&e));
+            return Err((e, None));
+        }
+    };
+    let (mut parts, body) = res.into_parts();
+    let mut body = hyper::body::to_bytes(body).await.map_err(|e| (Error::HyperError(e), None))?;
+    if compression::is_gzip(&parts.headers) {
+        body = compression::gunzip(&body).map_err(|e| (e, None))?.into();
+        parts.headers.remove(hyper::header::CONTENT_ENCODING);
+        parts.headers.remove(hyper::header::CONTENT_LENGTH);
+    }
+    let res = hyper::Response::from_parts(parts, ());
+    for interceptor in &http.interceptors {
+        interceptor.on_response(&res, &body);
+    }
+    let status = res.status();
+    let retry_after = res.headers().get(hyper::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(retry::parse_retry_after);
+    trace::response(&method, url, status, started.elapsed(), request_bytes, body.len());
+    #[cfg(feature = "opentelemetry")]
+    otel::response(status.as_u16());
+    let result = if status == hyper::StatusCode::OK {
+        parse_response(http, url, &res, &body).map_err(|e| (Error::SerdeJsonError(e), None))
+    } else {
+        Err((response_error(status.as_u16(), body.as_ref()), retry_after))
+    };
+    crate::metrics::request(&method, url, Some(status.as_u16()), started.elapsed(), result.as_ref().err().map(|(e, _)| e));
+    result
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-/// The response message for discovering supported languages.
-pub struct SupportedLanguages {
-    /// A list of supported language responses. This list contains an entry for each language the Translation API supports.
-    pub languages: Vec<SupportedLanguage>,
+/// Parses a successful response, handling fields the response type doesn't know as `http.unknown_fields` says.
+fn parse_response<OB>(http: &HttpClient, url: &str, res: &hyper::Response<()>, body: &Bytes) -> StdResult<OB, serde_json::Error>
+    where OB: ResponseOrEmpty
+{
+    let mut unknown_fields = Vec::new();
+    let response = OB::from_response(res, body, &mut unknown_fields)?;
+    if !unknown_fields.is_empty() {
+        match http.unknown_fields {
+            UnknownFields::Ignore => {}
+            UnknownFields::Warn => trace::unknown_fields(url, &unknown_fields),
+            UnknownFields::Deny => return Err(serde::de::Error::custom(
+                format!("unknown fields in response: {}", unknown_fields.join(", ")))),
+        }
+    }
+    Ok(response)
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-/// A single supported language response corresponds to information related to one supported language.
-pub struct SupportedLanguage {
-    /// Supported language code, generally consisting of its ISO 639-1 identifier,
-    /// for example, 'en', 'ja'. In certain cases, BCP-47 codes including language and region identifiers are returned (for example, 'zh-TW' and 'zh-CN')
-    pub language_code: String,
-    /// Human readable name of the language localized in the display language specified in the request.
-    pub display_name: Option<String>,
-    /// Can be used as source language.
-    pub support_source: bool,
-    /// Can be used as target language.
-    pub support_target: bool,
+fn response_error(http_status: u16, body: &[u8]) -> Error {
+    match serde_json::from_slice::<ErrorResponse>(body) {
+        Ok(response) => Error::ResponseError(http_status, response.error),
+        Err(_) => Error::HttpError(http_status, String::from_utf8_lossy(body).into_owned()),
+    }
 }
 
-/// Returns a list of supported languages for translation.
-pub fn get_supported_languages(project_id: &str, location_id: &str, access_token: &str,
-        query_params: &GetSupportedLanguagesQueryParams)
-    -> impl Future<Item=SupportedLanguages, Error=Error> + Send
-{
-    let url = format!("https://translation.googleapis.com/v3beta1/projects/{}/locations/{}/supportedLanguages",
-        project_id, location_id);
-    get_request(&url, access_token, query_params)
+const DEFAULT_ENDPOINT: &str = "https://translation.googleapis.com";
+
+/// The location that serves translateText, detectLanguage and supportedLanguages without pinning
+/// the request to a region. Glossaries, batch translations and custom models need a regional
+/// location such as "us-central1".
+pub const GLOBAL_LOCATION: &str = "global";
+
+/// The regional endpoints and the location each one serves. Requests sent to a regional endpoint,
+/// and the data in them, are processed within that region.
+///
+/// https://cloud.google.com/translate/docs/advanced/endpoints
+pub const REGIONAL_ENDPOINTS: &[(&str, &str)] = &[
+    ("europe-west1", "https://translate-eu.googleapis.com"),
+    ("us-central1", "https://translate-us.googleapis.com"),
+];
+
+/// The regional endpoint serving `location_id`, if there is one.
+pub fn regional_endpoint(location_id: &str) -> Option<&'static str> {
+    REGIONAL_ENDPOINTS.iter()
+        .find(|(location, _)| *location == location_id)
+        .map(|(_, endpoint)| *endpoint)
 }
 
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct TranslateTextRequest {
-    /// Required. The content of the input in string format. We recommend the total content be less than 30k codepoints.
-    /// Use locations.batchTranslateText for larger text.
-    pub contents: Vec<String>,
-    /// Optional. The format of the source text, for example, "text/html", "text/plain".
-    /// If left blank, the MIME type defaults to "text/html".
-    pub mime_type: Option<MimeType>,
-    /// Optional. The BCP-47 language code of the input text if known,
-    /// for example, "en-US" or "sr-Latn". Supported language codes are listed in Language Support.
-    /// If the source language isn't specified, the API attempts to identify the source language
-    /// automatically and returns the source language within the response.
-    pub source_language_code: Option<String>,
-    /// Required. The BCP-47 language code to use for translation of the input text,
-    /// set to one of the language codes listed in Language Support.
-    pub target_language_code: String,
-    /// Optional. The model type requested for this translation.
-    /// 
-    /// The format depends on model type:
-    ///
-    /// - AutoML Translation models: projects/{project-id}/locations/{location-id}/models/{model-id}
-    /// - General (built-in) models: projects/{project-id}/locations/{location-id}/models/general/nmt,
-    /// projects/{project-id}/locations/{location-id}/models/general/base
-    /// 
-    /// For global (non-regionalized) requests, use location-id global. For example,
-    /// projects/{project-id}/locations/global/models/general/nmt.
-    /// 
-    /// If missing, the system decides which google base model to use.
-    /// 
-    /// Authorization requires one or more of the following [Google IAM](https://cloud.google.com/iam)
-    /// permissions on the specified resource model:
-    /// 
-    /// - cloudtranslate.generalModels.predict
-    /// - automl.models.predict
-    pub model: Option<String>,
-    /// Optional. Glossary to be applied. The glossary must be within the same region (have the same
-    /// location-id) as the model, otherwise an INVALID_ARGUMENT (400) error is returned.
-    /// 
-    /// Authorization requires the following [Google IAM](https://cloud.google.com/iam)
-    /// permission on the specified resource glossaryConfig:
-    /// 
-    /// - cloudtranslate.glossaries.predict
-    pub glossary_config: Option<TranslateTextGlossaryConfig>,
-    /// Optional. The labels with user-defined metadata for the request.
-    /// 
-    /// Label keys and values can be no longer than 63 characters (Unicode codepoints), can only contain
-    /// lowercase letters, numeric characters, underscores and dashes. International characters are allowed.
-    /// Label values are optional. Label keys must start with a letter.
-    /// 
-    /// See https://goo.gl/xmQnxf for more information on and examples of labels.
-    pub labels: Option<Vec<HashMap<String, String>>>,
+/// The version of the REST surface requests are sent to.
+///
+/// Some fields only exist on the v3 surface, such as `Glossary::display_name`. A client using
+/// `V3Beta1` rejects requests that set them with `Error::InvalidRequest` instead of sending fields
+/// the server doesn't know.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ApiVersion {
+    /// https://cloud.google.com/translate/docs/reference/rest/v3beta1
+    #[default]
+    V3Beta1,
+    /// The generally available surface, https://cloud.google.com/translate/docs/reference/rest/v3
+    V3,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl ApiVersion {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiVersion::V3Beta1 => "v3beta1",
+            ApiVersion::V3 => "v3",
+        }
+    }
+}
+
+/// What the client does with fields in responses that the response types don't have, such as
+/// fields added to the API after this crate was released.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum UnknownFields {
+    /// Ignore them, like serde does by default.
+    #[default]
+    Ignore,
+    /// Log a warning naming their paths, for example "translations.0.newField".
+    Warn,
+    /// Fail the call with `Error::SerdeJsonError`, like `#[serde(deny_unknown_fields)]`. Useful in
+    /// CI to notice API changes.
+    Deny,
+}
+
+/// A parsed response along with the HTTP response it was parsed from, returned by
+/// `TranslationClient::call_raw` for debugging.
+#[derive(Clone, Debug)]
+pub struct RawResponse<T> {
+    pub status: hyper::StatusCode,
+    pub headers: HeaderMap,
+    /// The body exactly as the API returned it, after gzip decompression.
+    pub body: Bytes,
+    pub value: T,
+}
+
+impl<T> RawResponse<T> {
+    /// The body as text, with invalid UTF-8 replaced.
+    pub fn text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+
+    /// The value of the header `name`, if present and valid text.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+}
+
+/// A client bound to a project, location and credentials.
+///
+/// The underlying connection pool and TLS connector are created once and shared by
+/// every request made through the client (and its clones), so an application translating
+/// many strings should build one `TranslationClient` and reuse it.
+#[derive(Clone)]
+pub struct TranslationClient {
+    http: HttpClient,
+    project_id: String,
+    location_id: String,
+    token_provider: Arc<dyn TokenProvider>,
+    endpoint: String,
+    api_version: ApiVersion,
+    max_codepoints: Option<usize>,
+    rate_limiter: Option<RateLimiter>,
+    cache: Option<TranslationCache>,
+    store: Option<Arc<dyn TranslationStore>>,
+    placeholders: Option<Placeholders>,
+    tmx_recorder: Option<TmxRecorder>,
+    cost_tracker: Option<CostTracker>,
+    defaults: Option<RequestDefaults>,
+    model_fallback: Option<ModelFallback>,
+}
+
+impl TranslationClient {
+    /// Creates a client for the `GLOBAL_LOCATION` that sends the given access token with every request.
+    pub fn global(project_id: &str, access_token: &str) -> TranslationClient {
+        TranslationClient::new(project_id, GLOBAL_LOCATION, access_token)
+    }
+
+    /// Creates a client that sends the given access token with every request.
+    pub fn new(project_id: &str, location_id: &str, access_token: &str) -> TranslationClient {
+        TranslationClient::with_token_provider(project_id, location_id, Arc::new(StaticToken(access_token.to_string())))
+    }
+
+    /// Creates a client that asks `token_provider` for a token before every request,
+    /// so expired tokens are refreshed transparently.
+    pub fn with_token_provider(project_id: &str, location_id: &str, token_provider: Arc<dyn TokenProvider>) -> TranslationClient {
+        TranslationClient::with_http(HttpClient::new(), project_id, location_id, token_provider)
+    }
+
+    fn with_http(http: HttpClient, project_id: &str, location_id: &str, token_provider: Arc<dyn TokenProvider>) -> TranslationClient {
+        TranslationClient {
+            http,
+            project_id: project_id.to_string(),
+            location_id: location_id.to_string(),
+            token_provider,
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+            api_version: ApiVersion::default(),
+            max_codepoints: None,
+            rate_limiter: None,
+            cache: None,
+            store: None,
+            placeholders: None,
+            tmx_recorder: None,
+            cost_tracker: None,
+            defaults: None,
+            model_fallback: None,
+        }
+    }
+
+    /// Creates a client using Application Default Credentials, see `auth::application_default_credentials`.
+    #[cfg(feature = "native")]
+    pub fn from_application_default_credentials(project_id: &str, location_id: &str) -> Result<TranslationClient> {
+        Ok(TranslationClient::with_token_provider(project_id, location_id, auth::application_default_credentials()?))
+    }
+
+    /// Like `with_token_provider`, with the project found by `auth::discover_project_id`: the
+    /// GOOGLE_CLOUD_PROJECT environment variable, or the project the credentials belong to.
+    pub async fn with_discovered_project(location_id: &str, token_provider: Arc<dyn TokenProvider>) -> Result<TranslationClient> {
+        let project_id = auth::discover_project_id(&*token_provider).await?;
+        Ok(TranslationClient::with_token_provider(&project_id, location_id, token_provider))
+    }
+
+    /// Creates a client using Application Default Credentials and the project they belong to, see
+    /// `with_discovered_project`.
+    #[cfg(feature = "native")]
+    pub async fn from_application_default_project(location_id: &str) -> Result<TranslationClient> {
+        TranslationClient::with_discovered_project(location_id, auth::application_default_credentials()?).await
+    }
+
+    /// Sends requests to `endpoint` instead of "https://translation.googleapis.com".
+    ///
+    /// `endpoint` is a scheme and authority with an optional path prefix, for example a regional endpoint
+    /// ("https://translate-eu.googleapis.com"), a Private Service Connect address
+    /// ("https://translation-myendpoint.p.googleapis.com") or a mock server in tests ("http://127.0.0.1:8080").
+    pub fn with_endpoint(mut self, endpoint: &str) -> TranslationClient {
+        self.endpoint = endpoint.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sends requests to the regional endpoint serving the client's location, see `REGIONAL_ENDPOINTS`,
+    /// so data stays within the region. Returns `Error::InvalidRequest` if the location has no regional
+    /// endpoint; use `with_endpoint` for endpoints not in the list.
+    pub fn with_regional_endpoint(self) -> Result<TranslationClient> {
+        match regional_endpoint(&self.location_id) {
+            Some(endpoint) => Ok(self.with_endpoint(endpoint)),
+            None => Err(Error::InvalidRequest(format!("location {:?} has no regional endpoint", self.location_id))),
+        }
+    }
+
+    /// Retries failed requests according to `retry_policy`. Defaults to `RetryPolicy::default()`.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> TranslationClient {
+        self.http.retry_policy = retry_policy;
+        self
+    }
+
+    /// Appends `suffix` to the User-Agent header, "google-translation/{version}", to identify the application
+    /// in request logs, for example "my-app/1.2".
+    pub fn with_user_agent(mut self, suffix: &str) -> Result<TranslationClient> {
+        let user_agent = HeaderValue::from_str(&format!("{} {}", USER_AGENT, suffix))
+            .map_err(|_| Error::InvalidRequest(format!("invalid User-Agent suffix {:?}", suffix)))?;
+        self.http.headers.insert(hyper::header::USER_AGENT, user_agent);
+        Ok(self)
+    }
+
+    /// Sends the header `name: value` with every request, for example "x-goog-request-params" or
+    /// "x-goog-user-project". Replaces a header of the same name set before, including the User-Agent.
+    /// The Authorization and Content-Type headers are always set by the client.
+    pub fn with_header(mut self, name: &str, value: &str) -> Result<TranslationClient> {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| Error::InvalidRequest(format!("invalid header name {:?}", name)))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|_| Error::InvalidRequest(format!("invalid value {:?} for header {}", value, name)))?;
+        self.http.headers.insert(name, value);
+        Ok(self)
+    }
+
+    /// Sends https requests through `proxy` instead of the proxy named by HTTPS_PROXY, if any.
+    /// Replaces the transport with a new `HyperTransport`; see the `proxy` module.
+    #[cfg(feature = "native")]
+    pub fn with_proxy(self, proxy: Proxy) -> TranslationClient {
+        self.with_transport(Arc::new(HyperTransport::with_proxy(Some(proxy))))
+    }
+
+    /// Tunes the HTTP connections, see `ConnectionConfig`. Replaces the transport with a new
+    /// `HyperTransport` connecting through the proxy named by HTTPS_PROXY, if any; use
+    /// `HyperTransport::with_config` to combine the settings with an explicit proxy.
+    #[cfg(feature = "native")]
+    pub fn with_connection_config(self, config: ConnectionConfig) -> TranslationClient {
+        self.with_transport(Arc::new(HyperTransport::with_config(Proxy::from_env(), config)))
+    }
+
+    /// Sends requests with `transport` instead of the default `HyperTransport`, or `FetchTransport`
+    /// with the `wasm` feature, for example a `MockTransport` in tests. See the `transport` module.
+    pub fn with_transport(mut self, transport: Arc<dyn HttpTransport>) -> TranslationClient {
+        self.http.transport = transport;
+        self
+    }
+
+    /// Wraps the client's transport, as set so far, in a `CircuitBreaker` so requests fail fast with
+    /// `Error::CircuitOpen` while the API is down. See the `circuit_breaker` module. Clones of the
+    /// client share the breaker.
+    pub fn with_circuit_breaker(mut self, config: CircuitBreakerConfig) -> TranslationClient {
+        self.http.transport = Arc::new(CircuitBreaker::new(self.http.transport.clone(), config));
+        self
+    }
+
+    /// Passes every request the client sends, rendered as a `curl` command with credentials
+    /// redacted, to `sink`, for troubleshooting. See the `curl` module.
+    pub fn with_curl_dump<F>(mut self, sink: F) -> TranslationClient
+        where F: Fn(&str) + Send + Sync + 'static
+    {
+        self.http.transport = Arc::new(curl::CurlTransport::new(self.http.transport.clone(), sink));
+        self
+    }
+
+    /// Validates and serializes every call but records it in `dry_run` instead of sending it,
+    /// returning synthetic responses. See the `dry_run` module.
+    pub fn with_dry_run(mut self, dry_run: dry_run::DryRun) -> TranslationClient {
+        self.http.transport = Arc::new(dry_run);
+        self
+    }
+
+    /// Waits between retries, polls and for the rate limiter with `runtime` instead of
+    /// `TokioRuntime`. See the `runtime` module.
+    pub fn with_runtime(mut self, runtime: Arc<dyn Runtime>) -> TranslationClient {
+        self.http.runtime = runtime;
+        self
+    }
+
+    /// Gzip-compresses request bodies of at least `min_bytes` bytes, such as large HTML documents sent to
+    /// `translate_text`. Responses are always requested with `Accept-Encoding: gzip` and decompressed
+    /// transparently, before interceptors see them.
+    pub fn with_request_compression(mut self, min_bytes: usize) -> TranslationClient {
+        self.http.compression_threshold = Some(min_bytes);
+        self
+    }
+
+    /// Sets what happens to response fields the response types don't have, see `UnknownFields`.
+    /// They are ignored by default.
+    pub fn with_unknown_fields(mut self, unknown_fields: UnknownFields) -> TranslationClient {
+        self.http.unknown_fields = unknown_fields;
+        self
+    }
+
+    /// Abandons calls once `token` is cancelled: requests in flight are dropped, waits between retries,
+    /// for the rate limiter and in `Operation::wait_until_done` end early, and every call made through the
+    /// client, including each request of `bulk_translate`, returns `Error::Cancelled`.
+    ///
+    /// Clones of the client share the token. Give a single call its own token with
+    /// `client.clone().with_cancellation_token(token.child_token())`. Cancelling only stops the client
+    /// waiting; use `cancel_operation` to stop a long-running operation on the server.
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> TranslationClient {
+        self.http.cancellation_token = Some(token);
+        self
+    }
+
+    /// Adds an interceptor that sees every request and response, see the `interceptor` module.
+    /// Interceptors run in the order they were added.
+    pub fn with_interceptor(mut self, interceptor: Arc<dyn Interceptor>) -> TranslationClient {
+        self.http.interceptors.push(interceptor);
+        self
+    }
+
+    /// Sends requests to the given API version. Defaults to `ApiVersion::V3Beta1`.
+    pub fn with_api_version(mut self, api_version: ApiVersion) -> TranslationClient {
+        self.api_version = api_version;
+        self
+    }
+
+    /// Makes `translate_text` split requests whose contents exceed `max_codepoints` codepoints,
+    /// usually `chunking::MAX_CODEPOINTS`, into several requests. See the `chunking` module.
+    /// A `max_codepoints` of 0 is treated as 1.
+    pub fn with_chunking(mut self, max_codepoints: usize) -> TranslationClient {
+        self.max_codepoints = Some(max_codepoints.max(1));
+        self
+    }
+
+    /// Delays `translate_text` and `detect_language` requests to stay within `rate_limiter`'s rates.
+    /// Clones of the client share the limiter.
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> TranslationClient {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Answers `translate_text` from `cache` where possible. Clones of the client share the cache.
+    pub fn with_cache(mut self, cache: TranslationCache) -> TranslationClient {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Consults `store` before translating text and records new translations in it, see the `store` module.
+    pub fn with_store(mut self, store: Arc<dyn TranslationStore>) -> TranslationClient {
+        self.store = Some(store);
+        self
+    }
+
+    /// Makes `translate_text` keep the placeholders `placeholders` detects, such as `{name}` or `%s`,
+    /// out of translation. See the `placeholders` module.
+    pub fn with_placeholder_protection(mut self, placeholders: Placeholders) -> TranslationClient {
+        self.placeholders = Some(placeholders);
+        self
+    }
+
+    /// Records every translation `translate_text` returns in `recorder`, see the `tmx` module.
+    /// Clones of the client share the recorder.
+    pub fn with_tmx_recorder(mut self, recorder: TmxRecorder) -> TranslationClient {
+        self.tmx_recorder = Some(recorder);
+        self
+    }
+
+    /// Counts the billable characters of `translate_text` and `detect_language` requests in `tracker`,
+    /// rejecting requests over its budget. See the `cost` module. Clones of the client share the tracker.
+    pub fn with_cost_tracker(mut self, tracker: CostTracker) -> TranslationClient {
+        self.cost_tracker = Some(tracker);
+        self
+    }
+
+    /// Fills the model, glossary and labels that `translate_text` requests leave unset from
+    /// `defaults`. See the `defaults` module.
+    pub fn with_request_defaults(mut self, defaults: RequestDefaults) -> TranslationClient {
+        self.defaults = Some(defaults);
+        self
+    }
+
+    /// Sends translateText requests whose model fails again with the general model. See the
+    /// `fallback` module.
+    pub fn with_model_fallback(mut self, fallback: ModelFallback) -> TranslationClient {
+        self.model_fallback = Some(fallback);
+        self
+    }
+
+    pub fn project_id(&self) -> &str {
+        &self.project_id
+    }
+
+    pub fn location_id(&self) -> &str {
+        &self.location_id
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    pub fn api_version(&self) -> ApiVersion {
+        self.api_version
+    }
+
+    /// The client's project and location. Fails if either id is empty or contains a '/'.
+    pub fn project_location(&self) -> Result<ProjectLocation> {
+        ProjectLocation::new(&self.project_id, &self.location_id)
+    }
+
+    /// The name of the glossary `glossary_id` in the client's project and location.
+    pub fn glossary_name(&self, glossary_id: &str) -> Result<GlossaryName> {
+        self.project_location()?.glossary(glossary_id)
+    }
+
+    /// Replaces the access token used for subsequent requests, keeping the connection pool.
+    pub fn set_access_token(&mut self, access_token: &str) {
+        self.token_provider = Arc::new(StaticToken(access_token.to_string()));
+    }
+
+    async fn access_token(&self) -> Result<SecretToken> {
+        Ok(self.http.until_cancelled(self.token_provider.access_token()).await??.token)
+    }
+
+    /// Reserves `characters` against the cost tracker's budget, if any.
+    fn reserve_characters(&self, characters: usize) -> Result<Option<cost::Reservation>> {
+        self.cost_tracker.as_ref().map(|tracker| tracker.reserve(characters)).transpose()
+    }
+
+    async fn rate_limit(&self, characters: usize) -> Result<()> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            self.http.until_cancelled(rate_limiter.acquire_with(characters, self.http.runtime.as_ref())).await?;
+        }
+        Ok(())
+    }
+
+    /// Calls an API method directly and returns the parsed response with its status, headers and
+    /// body, for debugging. `path` is either a resource name, such as an operation's, or a suffix of
+    /// the client's location like ":translateText" or "/glossaries?pageSize=10". `body`, if any, is
+    /// sent as JSON. Failures are returned as errors, as with the other methods.
+    ///
+    /// Unlike the typed methods, nothing else is applied: no validation, cache, rate limit or cost
+    /// tracking. POST and PATCH calls are treated as not idempotent, so they are only retried if the
+    /// retry policy's `retry_non_idempotent` is set.
+    ///
+    /// ```no_run
+    /// # async fn run(client: google_translation::TranslationClient) -> google_translation::Result<()> {
+    /// use google_translation::TranslateTextResponse;
+    ///
+    /// let body = serde_json::json!({"contents": ["Hello"], "targetLanguageCode": "de"});
+    /// let response = client.call_raw::<TranslateTextResponse>(hyper::Method::POST, ":translateText", Some(body)).await?;
+    /// println!("{} {:?}: {}", response.status, response.headers, response.text());
+    /// println!("{}", response.value.translations[0].translated_text);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn call_raw<T>(&self, method: Method, path: &str, body: Option<serde_json::Value>) -> Result<RawResponse<T>>
+        where T: DeserializeOwned
+    {
+        let url = match path.starts_with("projects/") {
+            true => self.resource_url(path, ""),
+            false => self.location_url(path),
+        };
+        let access_token = self.access_token().await?;
+        let body = body.map(|body| Bytes::from(body.to_string()));
+        let idempotent = method != Method::POST && method != Method::PATCH;
+        send_request(&self.http, method, &url, &access_token, body, Some("application/json"), idempotent).await
+    }
+
+    fn location_url(&self, suffix: &str) -> String {
+        format!("{}/{}/projects/{}/locations/{}{}", self.endpoint, self.api_version.as_str(), self.project_id, self.location_id, suffix)
+    }
+
+    fn resource_url(&self, name: &str, suffix: &str) -> String {
+        format!("{}/{}/{}{}", self.endpoint, self.api_version.as_str(), name, suffix)
+    }
+
+    /// Rejects a request that sets `field`, which only exists on the v3 surface, if the client
+    /// uses v3beta1.
+    fn require_v3(&self, field: &str) -> Result<()> {
+        match self.api_version {
+            ApiVersion::V3 => Ok(()),
+            ApiVersion::V3Beta1 => Err(Error::InvalidRequest(format!("{} is only supported by ApiVersion::V3", field))),
+        }
+    }
+
+    /// Detects the language of text within a request.
+    pub async fn detect_language(&self, request_body: &DetectLanguageRequest)
+        -> Result<DetectLanguageResponse>
+    {
+        let characters = request_body.content.chars().count();
+        let reservation = self.reserve_characters(characters)?;
+        self.rate_limit(characters).await?;
+        let access_token = self.access_token().await?;
+        let response = post_request(&self.http, &self.location_url(":detectLanguage"), &access_token, request_body).await?;
+        if let Some(reservation) = reservation {
+            reservation.commit();
+        }
+        crate::metrics::characters("detectLanguage", characters);
+        Ok(response)
+    }
+
+    /// Returns a list of supported languages for translation.
+    pub async fn get_supported_languages(&self, query_params: &GetSupportedLanguagesQueryParams)
+        -> Result<SupportedLanguages>
+    {
+        let access_token = self.access_token().await?;
+        get_request(&self.http, &self.location_url("/supportedLanguages"), &access_token, query_params).await
+    }
+
+    /// Translates input text and returns translated text.
+    pub async fn translate_text(&self, request_body: &TranslateTextRequest)
+        -> Result<TranslateTextResponse>
+    {
+        if request_body.transliteration_config.is_some() {
+            self.require_v3("TranslateTextRequest.transliteration_config")?;
+        }
+        let request_body = match &self.defaults {
+            Some(defaults) => defaults.apply(request_body)?,
+            None => Cow::Borrowed(request_body),
+        };
+        let response = match &self.cache {
+            Some(cache) => cache::translate_text_cached(self, cache, &request_body).await?,
+            None => self.translate_text_from_store(&request_body).await?,
+        };
+        if let Some(recorder) = &self.tmx_recorder {
+            recorder.record_response(&request_body, &response);
+        }
+        Ok(response)
+    }
+
+    pub(crate) async fn translate_text_from_store(&self, request_body: &TranslateTextRequest)
+        -> Result<TranslateTextResponse>
+    {
+        match &self.store {
+            Some(store) if request_body.glossary_config.is_none() =>
+                store::translate_text_stored(self, store.as_ref(), request_body).await,
+            _ => self.translate_text_from_api(request_body).await,
+        }
+    }
+
+    pub(crate) async fn translate_text_from_api(&self, request_body: &TranslateTextRequest)
+        -> Result<TranslateTextResponse>
+    {
+        match &self.placeholders {
+            Some(placeholders) => placeholders::translate_text_protected(self, placeholders, request_body).await,
+            None => self.translate_text_unprotected(request_body).await,
+        }
+    }
+
+    pub(crate) async fn translate_text_unprotected(&self, request_body: &TranslateTextRequest)
+        -> Result<TranslateTextResponse>
+    {
+        if let Some(max_codepoints) = self.max_codepoints {
+            if chunking::needs_chunking(request_body, max_codepoints) {
+                return chunking::translate_text_chunked(self, request_body, max_codepoints).await;
+            }
+        }
+        self.send_translate_text(request_body).await
+    }
+
+    pub(crate) async fn send_translate_text(&self, request_body: &TranslateTextRequest)
+        -> Result<TranslateTextResponse>
+    {
+        match &self.model_fallback {
+            Some(fallback) => fallback::translate_text_with_fallback(self, fallback, request_body).await,
+            None => self.post_translate_text(request_body).await,
+        }
+    }
+
+    pub(crate) async fn post_translate_text(&self, request_body: &TranslateTextRequest)
+        -> Result<TranslateTextResponse>
+    {
+        request_body.validate()?;
+        let characters = request_body.contents.iter().map(|content| content.chars().count()).sum();
+        let reservation = self.reserve_characters(characters)?;
+        self.rate_limit(characters).await?;
+        let access_token = self.access_token().await?;
+        let response = post_request(&self.http, &self.location_url(":translateText"), &access_token, request_body).await?;
+        if let Some(reservation) = reservation {
+            reservation.commit();
+        }
+        crate::metrics::characters("translateText", characters);
+        Ok(response)
+    }
+
+    /// Translates a large volume of text in asynchronous batch mode.
+    ///
+    /// This call returns immediately and you can use google.longrunning.Operation.name to poll the status of the call.
+    pub async fn batch_translate_text(&self, request_body: &BatchTranslateTextRequest)
+        -> Result<Operation>
+    {
+        request_body.validate()?;
+        let access_token = self.access_token().await?;
+        create_request(&self.http, &self.location_url(":batchTranslateText"), &access_token, request_body).await
+    }
+
+    /// Translates documents in synchronous mode.
+    pub async fn translate_document(&self, request_body: &TranslateDocumentRequest)
+        -> Result<TranslateDocumentResponse>
+    {
+        request_body.validate()?;
+        let access_token = self.access_token().await?;
+        post_request(&self.http, &self.location_url(":translateDocument"), &access_token, request_body).await
+    }
+
+    /// Translates a large volume of documents in asynchronous batch mode.
+    ///
+    /// This call returns immediately and you can use google.longrunning.Operation.name to poll the status of the call.
+    pub async fn batch_translate_document(&self, request_body: &BatchTranslateDocumentRequest)
+        -> Result<Operation>
+    {
+        request_body.validate()?;
+        let access_token = self.access_token().await?;
+        create_request(&self.http, &self.location_url(":batchTranslateDocument"), &access_token, request_body).await
+    }
+
+    /// Starts asynchronous cancellation on a long-running operation.
+    pub async fn cancel_operation(&self, name: impl AsRef<str>) -> Result<()> {
+        let access_token = self.access_token().await?;
+        post_request::<_, Empty>(&self.http, &self.resource_url(name.as_ref(), ":cancel"), &access_token, &Empty).await.map(|_| ())
+    }
+
+    /// Deletes a long-running operation.
+    pub async fn delete_operation(&self, name: impl AsRef<str>) -> Result<()> {
+        let access_token = self.access_token().await?;
+        delete_request::<Empty>(&self.http, &self.resource_url(name.as_ref(), ""), &access_token).await.map(|_| ())
+    }
+
+    /// Gets the latest state of a long-running operation.
+    pub async fn get_operation(&self, name: impl AsRef<str>) -> Result<Operation> {
+        let access_token = self.access_token().await?;
+        get_request(&self.http, &self.resource_url(name.as_ref(), ""), &access_token, &Empty).await
+    }
+
+    /// Lists operations that match the specified filter in the request.
+    pub async fn list_operations(&self, params: &ListOperationsQueryParams)
+        -> Result<ListOperationsResponse>
+    {
+        let access_token = self.access_token().await?;
+        get_request(&self.http, &self.location_url("/operations"), &access_token, params).await
+    }
+
+    /// Like `list_operations`, but yields the operations of every page, fetching the next page when needed.
+    pub fn list_operations_stream(&self, params: &ListOperationsQueryParams) -> impl Stream<Item = Result<Operation>> + '_ {
+        futures::stream::try_unfold(Some(params.clone()), move |params| async move {
+            let mut params = match params {
+                Some(params) => params,
+                None => return Ok::<_, Error>(None),
+            };
+            let page = self.list_operations(&params).await?;
+            params.page_token = page.next_page_token.filter(|token| !token.is_empty());
+            let next = if params.page_token.is_some() { Some(params) } else { None };
+            Ok(Some((futures::stream::iter(page.operations.into_iter().map(Ok)), next)))
+        })
+        .try_flatten()
+    }
+
+    /// Waits for the specified long-running operation until it is done or reaches at most a specified timeout, returning the latest state.
+    pub async fn wait_operation(&self, name: impl AsRef<str>, request_body: &WaitOperationRequestBody)
+        -> Result<Operation>
+    {
+        request_body.validate()?;
+        let access_token = self.access_token().await?;
+        post_request(&self.http, &self.resource_url(name.as_ref(), ":wait"), &access_token, request_body).await
+    }
+
+    /// Creates a glossary and returns the long-running operation.
+    pub async fn create_glossary(&self, glossary: &Glossary) -> Result<Operation> {
+        glossary.validate()?;
+        if glossary.display_name.is_some() {
+            self.require_v3("Glossary.display_name")?;
+        }
+        let access_token = self.access_token().await?;
+        create_request(&self.http, &self.location_url("/glossaries"), &access_token, glossary).await
+    }
+
+    /// Deletes a glossary, or cancels glossary construction if the glossary isn't created yet.
+    pub async fn delete_glossary(&self, name: impl AsRef<str>) -> Result<Operation> {
+        let access_token = self.access_token().await?;
+        delete_request(&self.http, &self.resource_url(name.as_ref(), ""), &access_token).await
+    }
+
+    /// Gets a glossary.
+    pub async fn get_glossary(&self, name: impl AsRef<str>) -> Result<Glossary> {
+        let access_token = self.access_token().await?;
+        get_request(&self.http, &self.resource_url(name.as_ref(), ""), &access_token, &Empty).await
+    }
+
+    /// Lists glossaries in the client's project and location.
+    pub async fn list_glossaries(&self, params: &ListGlossariesQueryParams)
+        -> Result<ListGlossariesResponse>
+    {
+        let access_token = self.access_token().await?;
+        get_request(&self.http, &self.location_url("/glossaries"), &access_token, params).await
+    }
+
+    /// Like `list_glossaries`, but yields the glossaries of every page, fetching the next page when needed.
+    pub fn list_glossaries_stream(&self, params: &ListGlossariesQueryParams) -> impl Stream<Item = Result<Glossary>> + '_ {
+        futures::stream::try_unfold(Some(params.clone()), move |params| async move {
+            let mut params = match params {
+                Some(params) => params,
+                None => return Ok::<_, Error>(None),
+            };
+            let page = self.list_glossaries(&params).await?;
+            params.page_token = page.next_page_token.filter(|token| !token.is_empty());
+            let next = if params.page_token.is_some() { Some(params) } else { None };
+            Ok(Some((futures::stream::iter(page.glossaries.into_iter().map(Ok)), next)))
+        })
+        .try_flatten()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DetectLanguageRequest {
+    /// Optional. The language detection model to be used.
+    /// 
+    /// Format: projects/{project-id}/locations/{location-id}/models/language-detection/{model-id}
+    /// 
+    /// Only one language detection model is currently supported:
+    /// projects/{project-id}/locations/{location-id}/models/language-detection/default.
+    /// 
+    /// If not specified, the default model is used.
+    /// 
+    /// Authorization requires the following [Google IAM](https://cloud.google.com/iam)
+    /// permission on the specified resource model:
+    /// 
+    /// - cloudtranslate.languageDetectionModels.predict
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Optional. The format of the source text, for example, "text/html", "text/plain".
+    /// If left blank, the MIME type defaults to "text/html".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<MimeType>,
+    /// Optional. The labels with user-defined metadata for the request.
+    /// 
+    /// Label keys and values can be no longer than 63 characters (Unicode codepoints), can only contain lowercase letters, numeric characters, underscores and dashes. International characters are allowed. Label values are optional. Label keys must start with a letter.
+    /// 
+    /// See https://goo.gl/xmQnxf for more information on and examples of labels.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Labels>,
+    /// The content of the input stored as a string.
+    pub content: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+/// The response message for language detection.
+pub struct DetectLanguageResponse {
+    /// A list of detected languages sorted by detection confidence in descending order. The most probable language first.
+    pub languages: Vec<DetectLanguageItem>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+/// The response message for language detection.
+pub struct DetectLanguageItem {
+    /// The BCP-47 language code of source content in the request, detected automatically.
+    pub language_code: String,
+    /// The confidence of the detection result for this language.
+    pub confidence: f32,
+}
+
+/// The client behind the free functions: the default endpoint and API version, with a connection
+/// pool shared by every call. Methods taking a resource name don't use the project and location.
+fn default_client(project_id: &str, location_id: &str, access_token: &str) -> TranslationClient {
+    TranslationClient::with_http(HttpClient::shared(), project_id, location_id, Arc::new(StaticToken(access_token.to_string())))
+}
+
+/// Detects the language of text within a request.
+pub async fn detect_language(project_id: &str, location_id: &str, access_token: &str,
+        request_body: &DetectLanguageRequest)
+    -> Result<DetectLanguageResponse>
+{
+    default_client(project_id, location_id, access_token).detect_language(request_body).await
+}
+
+/// `detect_language` in the `GLOBAL_LOCATION`.
+pub async fn detect_language_global(project_id: &str, access_token: &str, request_body: &DetectLanguageRequest)
+    -> Result<DetectLanguageResponse>
+{
+    detect_language(project_id, GLOBAL_LOCATION, access_token, request_body).await
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GetSupportedLanguagesQueryParams {
+    /// Optional. The language to use to return localized, human readable names of supported languages.
+    /// If missing, then display names are not returned in a response.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_language_code: Option<String>,
+    /// Optional. Get supported languages of this model.
+    /// 
+    /// The format depends on model type:
+    /// 
+    /// AutoML Translation models: projects/{project-id}/locations/{location-id}/models/{model-id}
+    /// 
+    /// General (built-in) models: projects/{project-id}/locations/{location-id}/models/general/nmt, projects/{project-id}/locations/{location-id}/models/general/base
+    /// 
+    /// Returns languages supported by the specified model. If missing, we get supported languages of Google general base (PBMT) model.
+    /// 
+    /// Authorization requires one or more of the following [Google IAM](https://cloud.google.com/iam) permissions on the specified resource model:
+    /// 
+    /// - cloudtranslate.generalModels.get
+    /// - automl.models.get
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+/// The response message for discovering supported languages.
+pub struct SupportedLanguages {
+    /// A list of supported language responses. This list contains an entry for each language the Translation API supports.
+    pub languages: Vec<SupportedLanguage>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+/// A single supported language response corresponds to information related to one supported language.
+pub struct SupportedLanguage {
+    /// Supported language code, generally consisting of its ISO 639-1 identifier,
+    /// for example, 'en', 'ja'. In certain cases, BCP-47 codes including language and region identifiers are returned (for example, 'zh-TW' and 'zh-CN')
+    pub language_code: String,
+    /// Human readable name of the language localized in the display language specified in the request.
+    pub display_name: Option<String>,
+    /// Can be used as source language.
+    pub support_source: bool,
+    /// Can be used as target language.
+    pub support_target: bool,
+}
+
+/// Returns a list of supported languages for translation.
+pub async fn get_supported_languages(project_id: &str, location_id: &str, access_token: &str,
+        query_params: &GetSupportedLanguagesQueryParams)
+    -> Result<SupportedLanguages>
+{
+    default_client(project_id, location_id, access_token).get_supported_languages(query_params).await
+}
+
+/// `get_supported_languages` in the `GLOBAL_LOCATION`.
+pub async fn get_supported_languages_global(project_id: &str, access_token: &str,
+        query_params: &GetSupportedLanguagesQueryParams)
+    -> Result<SupportedLanguages>
+{
+    get_supported_languages(project_id, GLOBAL_LOCATION, access_token, query_params).await
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslateTextRequest {
+    /// Required. The content of the input in string format. We recommend the total content be less than 30k codepoints.
+    /// Use locations.batchTranslateText for larger text.
+    pub contents: Vec<String>,
+    /// Optional. The format of the source text, for example, "text/html", "text/plain".
+    /// If left blank, the MIME type defaults to "text/html".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<MimeType>,
+    /// Optional. The BCP-47 language code of the input text if known,
+    /// for example, "en-US" or "sr-Latn". Supported language codes are listed in Language Support.
+    /// If the source language isn't specified, the API attempts to identify the source language
+    /// automatically and returns the source language within the response.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_language_code: Option<String>,
+    /// Required. The BCP-47 language code to use for translation of the input text,
+    /// set to one of the language codes listed in Language Support.
+    pub target_language_code: String,
+    /// Optional. The model type requested for this translation.
+    /// 
+    /// The format depends on model type:
+    ///
+    /// - AutoML Translation models: projects/{project-id}/locations/{location-id}/models/{model-id}
+    /// - General (built-in) models: projects/{project-id}/locations/{location-id}/models/general/nmt,
+    ///   projects/{project-id}/locations/{location-id}/models/general/base
+    /// 
+    /// For global (non-regionalized) requests, use location-id global. For example,
+    /// projects/{project-id}/locations/global/models/general/nmt.
+    /// 
+    /// If missing, the system decides which google base model to use.
+    /// 
+    /// Authorization requires one or more of the following [Google IAM](https://cloud.google.com/iam)
+    /// permissions on the specified resource model:
+    /// 
+    /// - cloudtranslate.generalModels.predict
+    /// - automl.models.predict
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Optional. Glossary to be applied. The glossary must be within the same region (have the same
+    /// location-id) as the model, otherwise an INVALID_ARGUMENT (400) error is returned.
+    /// 
+    /// Authorization requires the following [Google IAM](https://cloud.google.com/iam)
+    /// permission on the specified resource glossaryConfig:
+    /// 
+    /// - cloudtranslate.glossaries.predict
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub glossary_config: Option<TranslateTextGlossaryConfig>,
+    /// Optional. The labels with user-defined metadata for the request.
+    /// 
+    /// Label keys and values can be no longer than 63 characters (Unicode codepoints), can only contain
+    /// lowercase letters, numeric characters, underscores and dashes. International characters are allowed.
+    /// Label values are optional. Label keys must start with a letter.
+    /// 
+    /// See https://goo.gl/xmQnxf for more information on and examples of labels.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Labels>,
+    /// Optional. Transliteration to be applied, for input written in a romanized script such as
+    /// Hindi typed in Latin letters. Only supported by `ApiVersion::V3`; a client using v3beta1
+    /// rejects requests that set it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transliteration_config: Option<TransliterationConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+/// Configures transliteration of the input of a text translation.
+pub struct TransliterationConfig {
+    /// If true, the source text is transliterated from its romanized form before translation.
+    /// Default value is false if missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_transliteration: Option<bool>,
+}
+
+impl TransliterationConfig {
+    pub fn new(enable_transliteration: bool) -> TransliterationConfig {
+        TransliterationConfig { enable_transliteration: Some(enable_transliteration) }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 /// Configures which glossary should be used for a specific target language,
 /// and defines options for applying that glossary.
@@ -386,10 +1590,17 @@ pub struct TranslateTextGlossaryConfig {
     /// Use this format: projects/*/locations/*/glossaries/*
     pub glossary: String,
     /// Optional. Indicates match is case-insensitive. Default value is false if missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ignore_case: Option<bool>,
 }
 
-#[derive(Deserialize, Debug)]
+impl TranslateTextGlossaryConfig {
+    pub fn new(glossary: impl Into<String>) -> TranslateTextGlossaryConfig {
+        TranslateTextGlossaryConfig { glossary: glossary.into(), ignore_case: None }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct TranslateTextResponse {
     /// Text translation responses with no glossary applied.
@@ -400,7 +1611,7 @@ pub struct TranslateTextResponse {
     pub glossary_translations: Option<Vec<Translation>>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 /// A single translation response.
 pub struct Translation {
@@ -417,13 +1628,39 @@ pub struct Translation {
 }
 
 /// Translates input text and returns translated text.
-pub fn translate_text(project_id: &str, location_id: &str, access_token: &str,
+pub async fn translate_text(project_id: &str, location_id: &str, access_token: &str,
         request_body: &TranslateTextRequest)
-    -> impl Future<Item=TranslateTextResponse, Error=Error> + Send
+    -> Result<TranslateTextResponse>
+{
+    default_client(project_id, location_id, access_token).translate_text(request_body).await
+}
+
+/// `translate_text` in the `GLOBAL_LOCATION`.
+pub async fn translate_text_global(project_id: &str, access_token: &str, request_body: &TranslateTextRequest)
+    -> Result<TranslateTextResponse>
 {
-    let url = format!("https://translation.googleapis.com/v3beta1/projects/{}/locations/{}:translateText",
-        project_id, location_id);
-    post_request(&url, access_token, request_body)
+    translate_text(project_id, GLOBAL_LOCATION, access_token, request_body).await
+}
+
+impl TranslateTextRequest {
+    /// Checks that there are between 1 and `chunking::MAX_CONTENTS` strings of at most
+    /// `chunking::MAX_CODEPOINTS` codepoints in total. `translate_text` calls this before sending
+    /// each request, after chunking if it is enabled.
+    pub fn validate(&self) -> Result<()> {
+        if self.contents.is_empty() {
+            return Err(Error::InvalidRequest("TranslateTextRequest.contents is empty".to_string()));
+        }
+        if self.contents.len() > chunking::MAX_CONTENTS {
+            return Err(Error::InvalidRequest(format!(
+                "TranslateTextRequest.contents has {} strings, more than {}", self.contents.len(), chunking::MAX_CONTENTS)));
+        }
+        let codepoints: usize = self.contents.iter().map(|content| content.chars().count()).sum();
+        if codepoints > chunking::MAX_CODEPOINTS {
+            return Err(Error::InvalidRequest(format!(
+                "TranslateTextRequest.contents has {} codepoints, more than {}", codepoints, chunking::MAX_CODEPOINTS)));
+        }
+        Ok(())
+    }
 }
 
 /// Translates a large volume of text in asynchronous batch mode.
@@ -431,7 +1668,7 @@ pub fn translate_text(project_id: &str, location_id: &str, access_token: &str,
 /// This function provides real-time output as the inputs are being processed.
 /// If caller cancels a request, the partial results (for an input file, it's 
 /// all or nothing) may still be available on the specified output location.
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct BatchTranslateTextRequest {
     /// Required. Source language code.
@@ -442,6 +1679,7 @@ pub struct BatchTranslateTextRequest {
     /// 
     /// Map's key is target language code. Map's value is model name.
     /// Value can be a built-in general model, or an AutoML Translation model.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub models: Option<HashMap<String, String>>,
     /// Required. Input configurations.
     /// 
@@ -459,6 +1697,7 @@ pub struct BatchTranslateTextRequest {
     /// Authorization requires the following Google IAM permission on the specified resource glossaries:
     /// 
     /// - cloudtranslate.glossaries.batchPredict
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub glossaries: Option<HashMap<String, TranslateTextGlossaryConfig>>,
     /// Optional. The labels with user-defined metadata for the request.
     /// 
@@ -466,15 +1705,44 @@ pub struct BatchTranslateTextRequest {
     /// numeric characters, underscores and dashes. International characters are allowed. Label values are optional. Label keys must start with a letter.
     /// 
     /// See https://goo.gl/xmQnxf for more information on and examples of labels.
-    pub labels: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Labels>,
+}
+
+impl BatchTranslateTextRequest {
+    /// The maximum number of target languages in a batch request.
+    pub const MAX_TARGET_LANGUAGES: usize = 10;
+
+    /// Checks the number of target languages, that there is an input, and the shape of the Cloud
+    /// Storage URIs. `batch_translate_text` calls this before sending the request.
+    pub fn validate(&self) -> Result<()> {
+        validate_target_languages("BatchTranslateTextRequest", &self.target_language_codes)?;
+        if self.input_configs.is_empty() {
+            return Err(Error::InvalidRequest("BatchTranslateTextRequest.input_configs is empty".to_string()));
+        }
+        for input_config in &self.input_configs {
+            input_config.gcs_source.validate()?;
+        }
+        self.output_config.gcs_destination.validate()
+    }
+}
+
+fn validate_target_languages(request: &str, target_language_codes: &[String]) -> Result<()> {
+    match target_language_codes.len() {
+        0 => Err(Error::InvalidRequest(format!("{}.target_language_codes is empty", request))),
+        n if n > BatchTranslateTextRequest::MAX_TARGET_LANGUAGES => Err(Error::InvalidRequest(format!(
+            "{}.target_language_codes has {} languages, more than {}", request, n, BatchTranslateTextRequest::MAX_TARGET_LANGUAGES))),
+        _ => Ok(()),
+    }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct BatchTranslateTextInputConfig {
     /// Optional. Can be "text/plain" or "text/html". For .tsv,
     /// "text/html" is used if mimeType is missing. For .html,
     /// this field must be "text/html" or empty. For .txt, this field must be "text/plain" or empty.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub mime_type: Option<MimeType>,
     /// Required. Google Cloud Storage location for the source input. This can be a single file (for example,
     /// gs://translation-test/input.tsv) or a wildcard (for example, gs://translation-test/*). If a file extension is .tsv,
@@ -488,7 +1756,7 @@ pub struct BatchTranslateTextInputConfig {
     pub gcs_source: GcsSource,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 /// Output configuration for locations.batchTranslateText reques
 pub struct BatchTranslateTextOutputConfig {
@@ -532,111 +1800,898 @@ pub struct BatchTranslateTextOutputConfig {
     pub gcs_destination: GcsDestination,
 }
 
-#[derive(Serialize, Debug)]
-pub enum MimeType {
-    #[serde(rename="text/plain")]
-    Plain,
-    #[serde(rename="text/html")]
-    Html,
+/// The format of the content to translate.
+///
+/// translateText and batchTranslateText accept "text/plain" and "text/html". Document translation
+/// also takes the MIME types of the supported document formats, which are represented as `Other`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
+pub enum MimeType {
+    Plain,
+    Html,
+    Other(String),
+}
+
+impl MimeType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            MimeType::Plain => "text/plain",
+            MimeType::Html => "text/html",
+            MimeType::Other(mime_type) => mime_type,
+        }
+    }
+
+    /// The MIME type of a file with the given extension ("html" or ".html"), for the text and
+    /// document formats the API supports.
+    pub fn from_extension(extension: &str) -> Option<MimeType> {
+        let mime_type = match extension.trim_start_matches('.').to_ascii_lowercase().as_str() {
+            "txt" => return Some(MimeType::Plain),
+            "html" | "htm" => return Some(MimeType::Html),
+            "pdf" => "application/pdf",
+            "doc" => "application/msword",
+            "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            "ppt" => "application/vnd.ms-powerpoint",
+            "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+            "xls" => "application/vnd.ms-excel",
+            "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            _ => return None,
+        };
+        Some(MimeType::Other(mime_type.to_string()))
+    }
+}
+
+impl From<String> for MimeType {
+    fn from(mime_type: String) -> MimeType {
+        match mime_type.as_str() {
+            "text/plain" => MimeType::Plain,
+            "text/html" => MimeType::Html,
+            _ => MimeType::Other(mime_type),
+        }
+    }
+}
+
+impl From<&str> for MimeType {
+    fn from(mime_type: &str) -> MimeType {
+        MimeType::from(mime_type.to_string())
+    }
+}
+
+impl From<MimeType> for String {
+    fn from(mime_type: MimeType) -> String {
+        match mime_type {
+            MimeType::Other(mime_type) => mime_type,
+            mime_type => mime_type.as_str().to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for MimeType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The labels with user-defined metadata attached to a request.
+///
+/// Label keys and values can be no longer than 63 characters (Unicode codepoints), can only contain
+/// lowercase letters, numeric characters, underscores and dashes. International characters are allowed.
+/// Label keys must start with a letter. A request can have at most 64 labels.
+/// These rules are checked when a label is inserted, so an invalid label never reaches the API.
+///
+/// See https://goo.gl/xmQnxf for more information on and examples of labels.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(try_from = "HashMap<String, String>")]
+pub struct Labels(HashMap<String, String>);
+
+impl Labels {
+    pub const MAX_LABELS: usize = 64;
+    pub const MAX_LENGTH: usize = 63;
+
+    pub fn new() -> Labels {
+        Labels::default()
+    }
+
+    /// Adds a label, replacing any previous value for `key`.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) -> Result<()> {
+        let (key, value) = (key.into(), value.into());
+        Labels::validate(&key, &value)?;
+        if self.0.len() >= Labels::MAX_LABELS && !self.0.contains_key(&key) {
+            return Err(Error::InvalidRequest(format!("more than {} labels", Labels::MAX_LABELS)));
+        }
+        self.0.insert(key, value);
+        Ok(())
+    }
+
+    /// Like `insert`, for chaining.
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<String>) -> Result<Labels> {
+        self.insert(key, value)?;
+        Ok(self)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Checks a single label against the API's rules.
+    pub fn validate(key: &str, value: &str) -> Result<()> {
+        fn allowed(c: char) -> bool {
+            (c.is_alphanumeric() && !c.is_uppercase()) || c == '_' || c == '-'
+        }
+        if !key.chars().next().is_some_and(|c| c.is_alphabetic() && !c.is_uppercase()) {
+            return Err(Error::InvalidRequest(format!("label key {:?} must start with a lowercase letter", key)));
+        }
+        for (what, text) in [("key", key), ("value", value)] {
+            if text.chars().count() > Labels::MAX_LENGTH {
+                return Err(Error::InvalidRequest(format!("label {} {:?} is longer than {} characters", what, text, Labels::MAX_LENGTH)));
+            }
+            if !text.chars().all(allowed) {
+                return Err(Error::InvalidRequest(format!(
+                    "label {} {:?} may only contain lowercase letters, digits, underscores and dashes", what, text)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<HashMap<String, String>> for Labels {
+    type Error = Error;
+
+    fn try_from(map: HashMap<String, String>) -> Result<Labels> {
+        let mut labels = Labels::new();
+        for (key, value) in map {
+            labels.insert(key, value)?;
+        }
+        Ok(labels)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+/// The Google Cloud Storage location for the input content.
+pub struct GcsSource {
+    /// Required. Source data URI. For example, gs://my_bucket/my_object.
+    pub input_uri: String,
+}
+
+impl GcsSource {
+    /// Checks that `input_uri` names an object or a wildcard in a bucket, like "gs://bucket/file.tsv".
+    pub fn validate(&self) -> Result<()> {
+        match gcs::parse_uri(&self.input_uri)? {
+            (_, "") => Err(Error::InvalidRequest(format!("GcsSource.input_uri {:?} names no object", self.input_uri))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+/// The Google Cloud Storage location for the output content.
+pub struct GcsDestination {
+    /// Required. There must be no files under 'outputUriPrefix'.
+    /// 'outputUriPrefix' must end with "/" and start with "gs://", otherwise an INVALID_ARGUMENT (400) error is returned.
+    pub output_uri_prefix: String,
+}
+
+impl GcsDestination {
+    /// Checks that `output_uri_prefix` is a directory in a bucket, like "gs://bucket/output/".
+    pub fn validate(&self) -> Result<()> {
+        gcs::parse_uri(&self.output_uri_prefix)?;
+        if !self.output_uri_prefix.ends_with('/') {
+            return Err(Error::InvalidRequest(format!(
+                "GcsDestination.output_uri_prefix {:?} must end with '/'", self.output_uri_prefix)));
+        }
+        Ok(())
+    }
+}
+
+macro_rules! define_error_codes {
+    ($($name:ident $variant:ident $rpc_code:tt $http_status_code:tt);*;) => {
+        #[deprecated(note = "use `ErrorCode` and `Error::code` instead")]
+        pub mod code {
+            $(pub const $name: u16 = $http_status_code);*;
+        }
+
+        /// The canonical error codes of Google APIs (google.rpc.Code).
+        ///
+        /// Failed requests report them by name in `Status::status`, long-running operations by number
+        /// in `Status::code`. `Error::code` finds the code of any error returned by the API.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum ErrorCode {
+            $($variant),*
+        }
+
+        impl ErrorCode {
+            /// The code with the given google.rpc.Code number.
+            pub fn from_rpc_code(code: i32) -> Option<ErrorCode> {
+                match code {
+                    $($rpc_code => Some(ErrorCode::$variant),)*
+                    _ => None,
+                }
+            }
+
+            /// The code with the given name, for example "NOT_FOUND".
+            pub fn from_name(name: &str) -> Option<ErrorCode> {
+                match name {
+                    $(stringify!($name) => Some(ErrorCode::$variant),)*
+                    _ => None,
+                }
+            }
+
+            pub fn rpc_code(self) -> i32 {
+                match self {
+                    $(ErrorCode::$variant => $rpc_code),*
+                }
+            }
+
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(ErrorCode::$variant => stringify!($name)),*
+                }
+            }
+
+            /// The HTTP status the API responds with for this code.
+            pub fn http_status(self) -> u16 {
+                match self {
+                    $(ErrorCode::$variant => $http_status_code),*
+                }
+            }
+        }
+    };
+}
+
+// https://cloud.google.com/apis/design/errors
+define_error_codes!{
+    OK Ok 0 200;
+    CANCELLED Cancelled 1 499;
+    UNKNOWN Unknown 2 500;
+    INVALID_ARGUMENT InvalidArgument 3 400;
+    DEADLINE_EXCEEDED DeadlineExceeded 4 504;
+    NOT_FOUND NotFound 5 404;
+    ALREADY_EXISTS AlreadyExists 6 409;
+    PERMISSION_DENIED PermissionDenied 7 403;
+    UNAUTHENTICATED Unauthenticated 16 401;
+    RESOURCE_EXHAUSTED ResourceExhausted 8 429;
+    FAILED_PRECONDITION FailedPrecondition 9 400;
+    ABORTED Aborted 10 409;
+    OUT_OF_RANGE OutOfRange 11 400;
+    UNIMPLEMENTED Unimplemented 12 501;
+    INTERNAL Internal 13 500;
+    UNAVAILABLE Unavailable 14 503;
+    DATA_LOSS DataLoss 15 500;
+}
+
+impl ErrorCode {
+    /// The most likely code for a response with the given HTTP status and no status name.
+    /// Several codes share an HTTP status, so this picks the most common one, for example
+    /// INVALID_ARGUMENT for 400.
+    pub fn from_http_status(http_status: u16) -> Option<ErrorCode> {
+        match http_status {
+            200 => Some(ErrorCode::Ok),
+            400 => Some(ErrorCode::InvalidArgument),
+            401 => Some(ErrorCode::Unauthenticated),
+            403 => Some(ErrorCode::PermissionDenied),
+            404 => Some(ErrorCode::NotFound),
+            409 => Some(ErrorCode::AlreadyExists),
+            429 => Some(ErrorCode::ResourceExhausted),
+            499 => Some(ErrorCode::Cancelled),
+            500 => Some(ErrorCode::Internal),
+            501 => Some(ErrorCode::Unimplemented),
+            502 | 503 => Some(ErrorCode::Unavailable),
+            504 => Some(ErrorCode::DeadlineExceeded),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+/// This resource represents a long-running operation that is the result of a network API call.
+///
+/// `M` and `R` are the types of the metadata and response. They default to raw JSON values;
+/// use `Operation::into_typed` to convert them once the kind of operation is known, for example
+/// into a `BatchTranslateOperation`.
+pub struct Operation<M = serde_json::Value, R = serde_json::Value> {
+    /// The server-assigned name, which is only unique within the same service that originally returns it.
+    /// If you use the default HTTP mapping, the name should be a resource name ending with operations/{unique_id}.
+    pub name: String,
+    /// Service-specific metadata associated with the operation.
+    /// It typically contains progress information and common metadata such as create time.
+    /// Some services might not provide such metadata. Any method that returns a long-running operation should document the metadata type, if any.
+    /// 
+    /// An object containing fields of an arbitrary type. An additional field "@type" contains a URI identifying the type.
+    /// Example: { "id": 1234, "@type": "types.example.com/standard/id" }.
+    pub metadata: M,
+    /// If the value is false, it means the operation is still in progress. If true, the operation is completed, and either error or response is available.
+    pub done: Option<bool>,
+    /// The error result of the operation in case of failure or cancellation.
+    pub error: Option<Status>,
+    /// The normal response of the operation in case of success. If the original method returns no data on success, such as Delete, the response
+    /// is google.protobuf.Empty. If the original method is standard Get/Create/Update, the response should be the resource. For other methods,
+    /// the response should have the type XxxResponse, where Xxx is the original method name. For example, if the original method name is TakeSnapshot(),
+    /// the inferred response type is TakeSnapshotResponse.
+    /// 
+    /// An object containing fields of an arbitrary type. An additional field "@type" contains a URI identifying the type.
+    /// Example: { "id": 1234, "@type": "types.example.com/standard/id" }.
+    pub response: Option<R>,
+}
+
+/// The operation returned by `batch_translate_text`.
+pub type BatchTranslateOperation = Operation<BatchTranslateMetadata, BatchTranslateResponse>;
+/// The operation returned by `batch_translate_document`.
+pub type BatchTranslateDocumentOperation = Operation<BatchTranslateDocumentMetadata, BatchTranslateDocumentResponse>;
+/// The operation returned by `create_glossary`.
+pub type CreateGlossaryOperation = Operation<CreateGlossaryMetadata, Glossary>;
+/// The operation returned by `delete_glossary`.
+pub type DeleteGlossaryOperation = Operation<DeleteGlossaryMetadata, DeleteGlossaryResponse>;
+
+impl Operation {
+    /// Deserializes the metadata and, if present, the response into `M` and `R`.
+    pub fn into_typed<M, R>(self) -> Result<Operation<M, R>>
+        where M: DeserializeOwned, R: DeserializeOwned
+    {
+        Ok(Operation {
+            name: self.name,
+            metadata: serde_json::from_value(self.metadata).map_err(Error::SerdeJsonError)?,
+            done: self.done,
+            error: self.error,
+            response: match self.response {
+                Some(response) => Some(serde_json::from_value(response).map_err(Error::SerdeJsonError)?),
+                None => None,
+            },
+        })
+    }
+
+    /// Deserializes a copy of the metadata into `M`.
+    pub fn metadata_as<M: DeserializeOwned>(&self) -> Result<M> {
+        M::deserialize(&self.metadata).map_err(Error::SerdeJsonError)
+    }
+
+    /// Deserializes a copy of the response into `R`. Returns None while the operation is running or if it failed.
+    pub fn response_as<R: DeserializeOwned>(&self) -> Result<Option<R>> {
+        match &self.response {
+            Some(response) => R::deserialize(response).map(Some).map_err(Error::SerdeJsonError),
+            None => Ok(None),
+        }
+    }
+
+    #[deprecated(note = "use wait_until_done, which takes a client and a PollConfig")]
+    pub async fn wait_util_done(&self, access_token: &str) -> Result<StdResult<serde_json::Value, Status>> {
+        self.wait_until_done(&TranslationClient::new("", "", access_token), &PollConfig::default()).await
+    }
+}
+
+impl<M, R> Operation<M, R> where M: DeserializeOwned, R: DeserializeOwned {
+    /// Polls the operation with `client` until it is done, returning its response or error.
+    ///
+    /// Returns `Error::DeadlineExceeded` if the operation is still running when `poll_config.deadline` passes,
+    /// and `Error::Cancelled` as soon as the client's cancellation token is cancelled.
+    pub async fn wait_until_done(&self, client: &TranslationClient, poll_config: &PollConfig) -> Result<StdResult<R, Status>> {
+        let started = Instant::now();
+        let mut interval = poll_config.initial_interval;
+        loop {
+            let operation = client.get_operation(&self.name).await?;
+            if operation.done == Some(true) {
+                return match operation.into_typed::<M, R>()? {
+                    Operation { response: Some(response), .. } => Ok(Ok(response)),
+                    Operation { error: Some(error), .. } => Ok(Err(error)),
+                    Operation { name, .. } => Err(Error::Other(format!("operation {} is done without response or error", name))),
+                };
+            }
+            if let Some(deadline) = poll_config.deadline {
+                let elapsed = started.elapsed();
+                if elapsed >= deadline {
+                    return Err(Error::DeadlineExceeded(format!("operation {} is not done after {:?}", self.name, elapsed)));
+                }
+                interval = interval.min(deadline - elapsed);
+            }
+            client.http.until_cancelled(client.http.runtime.sleep(interval)).await?;
+            interval = interval.mul_f64(poll_config.multiplier).min(poll_config.max_interval);
+        }
+    }
+
+    /// Requests cancellation of the operation with `client`, which is best-effort, then polls it like
+    /// `wait_until_done` to see whether it took: the operation either ends as cancelled or finishes
+    /// with its response or another error first.
+    ///
+    /// Returns `Error::DeadlineExceeded` if the operation is still running after `deadline`.
+    pub async fn cancel_and_wait(&self, client: &TranslationClient, deadline: Duration) -> Result<CancelOutcome<R>> {
+        match client.cancel_operation(&self.name).await {
+            // The operation finished before it could be cancelled.
+            Err(e) if e.code() == Some(ErrorCode::FailedPrecondition) => {}
+            result => result?,
+        }
+        let poll_config = PollConfig { deadline: Some(deadline), ..PollConfig::default() };
+        Ok(match self.wait_until_done(client, &poll_config).await? {
+            Err(status) if status.error_code() == Some(ErrorCode::Cancelled) => CancelOutcome::Cancelled(status),
+            result => CancelOutcome::Finished(result),
+        })
+    }
+
+    /// Polls the operation with `client` like `wait_until_done`, yielding a snapshot of its metadata
+    /// after every poll, so progress can be shown while a batch job runs. The first snapshot is
+    /// fetched right away and the last is that of the finished operation, after which the stream ends.
+    ///
+    /// Yields `Error::DeadlineExceeded` and ends if the operation is still running when
+    /// `poll_config.deadline` passes. Get the result with `wait_until_done` once the stream has ended.
+    ///
+    /// ```no_run
+    /// # async fn run(client: google_translation::TranslationClient, operation: google_translation::BatchTranslateOperation)
+    /// #     -> google_translation::Result<()> {
+    /// use futures::stream::TryStreamExt;
+    /// use google_translation::PollConfig;
+    ///
+    /// let mut progress = Box::pin(operation.progress_stream(&client, &PollConfig::default()));
+    /// while let Some(metadata) = progress.try_next().await? {
+    ///     println!("{:.0}% translated", metadata.progress().unwrap_or(0.0) * 100.0);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn progress_stream<'a>(&'a self, client: &'a TranslationClient, poll_config: &PollConfig)
+        -> impl Stream<Item = Result<M>> + 'a
+    {
+        let poll_config = poll_config.clone();
+        let started = Instant::now();
+        // The state is the wait before the next poll, None before the first one, and whether the operation is done.
+        futures::stream::try_unfold((None, false), move |(interval, done): (Option<Duration>, bool)| {
+            let poll_config = poll_config.clone();
+            async move {
+                if done {
+                    return Ok(None);
+                }
+                if let Some(mut interval) = interval {
+                    if let Some(deadline) = poll_config.deadline {
+                        let elapsed = started.elapsed();
+                        if elapsed >= deadline {
+                            return Err(Error::DeadlineExceeded(format!("operation {} is not done after {:?}", self.name, elapsed)));
+                        }
+                        interval = interval.min(deadline - elapsed);
+                    }
+                    client.http.until_cancelled(client.http.runtime.sleep(interval)).await?;
+                }
+                let operation = client.get_operation(&self.name).await?;
+                let done = operation.done == Some(true);
+                let metadata = serde_json::from_value(operation.metadata).map_err(Error::SerdeJsonError)?;
+                let next = match interval {
+                    Some(interval) => interval.mul_f64(poll_config.multiplier).min(poll_config.max_interval),
+                    None => poll_config.initial_interval,
+                };
+                Ok(Some((metadata, (Some(next), done))))
+            }
+        })
+    }
+}
+
+/// How `Operation::wait_until_done` polls a long-running operation.
+///
+/// The first poll waits `initial_interval`, and every following wait is `multiplier` times longer,
+/// up to `max_interval`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PollConfig {
+    pub initial_interval: Duration,
+    pub multiplier: f64,
+    pub max_interval: Duration,
+    /// Gives up after this long. None waits forever.
+    pub deadline: Option<Duration>,
+}
+
+impl Default for PollConfig {
+    fn default() -> Self {
+        PollConfig {
+            initial_interval: Duration::from_secs(1),
+            multiplier: 1.5,
+            max_interval: Duration::from_secs(30),
+            deadline: Some(Duration::from_secs(60 * 60)),
+        }
+    }
+}
+
+/// How an operation ended after `Operation::cancel_and_wait`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CancelOutcome<R> {
+    /// The operation was cancelled, with this CANCELLED status.
+    Cancelled(Status),
+    /// The operation finished before the cancellation took effect, with its response or error.
+    Finished(StdResult<R, Status>),
+}
+
+/// The state an operation's metadata reports. States this crate doesn't know are kept as
+/// `Unknown`, so new states the API adds still deserialize.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
+pub enum OperationState {
+    /// STATE_UNSPECIFIED.
+    Unspecified,
+    Running,
+    Succeeded,
+    Failed,
+    /// The operation is being cancelled.
+    Cancelling,
+    Cancelled,
+    /// A state this crate doesn't know, as sent by the API.
+    Unknown(String),
+}
+
+impl OperationState {
+    /// The name the API uses for the state, for example "RUNNING".
+    pub fn as_str(&self) -> &str {
+        match self {
+            OperationState::Unspecified => "STATE_UNSPECIFIED",
+            OperationState::Running => "RUNNING",
+            OperationState::Succeeded => "SUCCEEDED",
+            OperationState::Failed => "FAILED",
+            OperationState::Cancelling => "CANCELLING",
+            OperationState::Cancelled => "CANCELLED",
+            OperationState::Unknown(state) => state,
+        }
+    }
+
+    /// Whether the operation has stopped, successfully or not.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, OperationState::Succeeded | OperationState::Failed | OperationState::Cancelled)
+    }
+}
+
+impl From<&str> for OperationState {
+    fn from(state: &str) -> OperationState {
+        match state {
+            "STATE_UNSPECIFIED" => OperationState::Unspecified,
+            "RUNNING" => OperationState::Running,
+            "SUCCEEDED" => OperationState::Succeeded,
+            "FAILED" => OperationState::Failed,
+            "CANCELLING" => OperationState::Cancelling,
+            "CANCELLED" => OperationState::Cancelled,
+            _ => OperationState::Unknown(state.to_string()),
+        }
+    }
+}
+
+impl From<String> for OperationState {
+    fn from(state: String) -> OperationState {
+        match OperationState::from(state.as_str()) {
+            OperationState::Unknown(_) => OperationState::Unknown(state),
+            known => known,
+        }
+    }
+}
+
+impl From<OperationState> for String {
+    fn from(state: OperationState) -> String {
+        match state {
+            OperationState::Unknown(state) => state,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+impl AsRef<str> for OperationState {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl std::fmt::Display for OperationState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// State metadata for the batch translation operation.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
-/// The Google Cloud Storage location for the input content.
-pub struct GcsSource {
-    /// Required. Source data URI. For example, gs://my_bucket/my_object.
-    pub input_uri: String,
+pub struct BatchTranslateMetadata {
+    /// The state of the operation.
+    pub state: Option<OperationState>,
+    /// Number of successfully translated characters so far (Unicode codepoints).
+    #[serde(default, with = "int64")]
+    pub translated_characters: i64,
+    /// Number of characters that have failed to process so far (Unicode codepoints).
+    #[serde(default, with = "int64")]
+    pub failed_characters: i64,
+    /// Total number of characters (Unicode codepoints). This is the total number of codepoints from input files
+    /// times the number of target languages and appears here shortly after the call is submitted.
+    #[serde(default, with = "int64")]
+    pub total_characters: i64,
+    /// Time when the operation was submitted.
+    pub submit_time: Option<Timestamp>,
+}
+
+impl BatchTranslateMetadata {
+    /// The fraction of characters processed so far, translated or failed, between 0 and 1.
+    /// None until the total is known.
+    pub fn progress(&self) -> Option<f64> {
+        progress(self.translated_characters + self.failed_characters, self.total_characters)
+    }
+}
+
+fn progress(processed: i64, total: i64) -> Option<f64> {
+    if total > 0 {
+        Some((processed as f64 / total as f64).clamp(0.0, 1.0))
+    } else {
+        None
+    }
 }
 
-#[derive(Serialize)]
+/// Stored in the Operation.response field returned by BatchTranslateText if at least one sentence is translated successfully.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
-/// The Google Cloud Storage location for the output content.
-pub struct GcsDestination {
-    /// Required. There must be no files under 'outputUriPrefix'.
-    /// 'outputUriPrefix' must end with "/" and start with "gs://", otherwise an INVALID_ARGUMENT (400) error is returned.
-    pub output_uri_prefix: String,
+pub struct BatchTranslateResponse {
+    /// Total number of characters (Unicode codepoints).
+    #[serde(default, with = "int64")]
+    pub total_characters: i64,
+    /// Number of successfully translated characters (Unicode codepoints).
+    #[serde(default, with = "int64")]
+    pub translated_characters: i64,
+    /// Number of characters that have failed to process (Unicode codepoints).
+    #[serde(default, with = "int64")]
+    pub failed_characters: i64,
+    /// Time when the operation was submitted.
+    pub submit_time: Option<Timestamp>,
+    /// The time when the operation is finished and Operation.done is set to true.
+    pub end_time: Option<Timestamp>,
 }
 
-macro_rules! define_error_codes {
-    ($($name:ident $http_status_code:tt);*;) => {
-        pub mod code {
-            $(pub const $name: u16 = $http_status_code);*;
-        }
-    };
+/// State metadata for the batch translate document operation.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchTranslateDocumentMetadata {
+    /// The state of the operation.
+    pub state: Option<OperationState>,
+    /// Total number of pages to translate in all documents so far. Documents without clear page definition
+    /// (such as XLSX) are not counted.
+    #[serde(default, with = "int64")]
+    pub total_pages: i64,
+    /// Number of successfully translated pages in all documents so far.
+    #[serde(default, with = "int64")]
+    pub translated_pages: i64,
+    /// Number of pages that failed to process in all documents so far.
+    #[serde(default, with = "int64")]
+    pub failed_pages: i64,
+    /// Number of billable pages in documents with clear page definition (such as PDF, DOCX, PPTX) so far.
+    #[serde(default, with = "int64")]
+    pub total_billable_pages: i64,
+    /// Total number of characters (Unicode codepoints) in all documents so far.
+    #[serde(default, with = "int64")]
+    pub total_characters: i64,
+    /// Number of successfully translated characters (Unicode codepoints) in all documents so far.
+    #[serde(default, with = "int64")]
+    pub translated_characters: i64,
+    /// Number of characters that have failed to process (Unicode codepoints) in all documents so far.
+    #[serde(default, with = "int64")]
+    pub failed_characters: i64,
+    /// Number of billable characters (Unicode codepoints) in documents without clear page definition (such as XLSX) so far.
+    #[serde(default, with = "int64")]
+    pub total_billable_characters: i64,
+    /// Time when the operation was submitted.
+    pub submit_time: Option<Timestamp>,
 }
 
-// https://cloud.google.com/apis/design/errors
-define_error_codes!{
-    OK 200;
-    CANCELLED 499;
-    UNKNOWN 500;
-    INVALID_ARGUMENT 400;
-    DEADLINE_EXCEEDED 504;
-    NOT_FOUND 404;
-    ALREADY_EXISTS 409;
-    PERMISSION_DENIED 403;
-    UNAUTHENTICATED 401;
-    RESOURCE_EXHAUSTED 429;
-    FAILED_PRECONDITION 400;
-    ABORTED 409;
-    OUT_OF_RANGE 400;
-    UNIMPLEMENTED 501;
-    INTERNAL 500;
-    UNAVAILABLE 503;
-    DATA_LOSS 500;
-}
-
-#[derive(Deserialize, Debug)]
+impl BatchTranslateDocumentMetadata {
+    /// The fraction of characters processed so far, translated or failed, between 0 and 1.
+    /// None until the total is known.
+    pub fn progress(&self) -> Option<f64> {
+        progress(self.translated_characters + self.failed_characters, self.total_characters)
+    }
+}
+
+/// Stored in the Operation.response field returned by BatchTranslateDocument if at least one document is translated successfully.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
-/// This resource represents a long-running operation that is the result of a network API call.
-pub struct Operation {
-    /// The server-assigned name, which is only unique within the same service that originally returns it.
-    /// If you use the default HTTP mapping, the name should be a resource name ending with operations/{unique_id}.
+pub struct BatchTranslateDocumentResponse {
+    /// Total number of pages to translate in all documents.
+    #[serde(default, with = "int64")]
+    pub total_pages: i64,
+    /// Number of successfully translated pages in all documents.
+    #[serde(default, with = "int64")]
+    pub translated_pages: i64,
+    /// Number of pages that failed to process in all documents.
+    #[serde(default, with = "int64")]
+    pub failed_pages: i64,
+    /// Total number of characters (Unicode codepoints) in all documents.
+    #[serde(default, with = "int64")]
+    pub total_characters: i64,
+    /// Number of successfully translated characters (Unicode codepoints) in all documents.
+    #[serde(default, with = "int64")]
+    pub translated_characters: i64,
+    /// Number of characters that have failed to process (Unicode codepoints) in all documents.
+    #[serde(default, with = "int64")]
+    pub failed_characters: i64,
+    /// Time when the operation was submitted.
+    pub submit_time: Option<Timestamp>,
+    /// The time when the operation is finished and Operation.done is set to true.
+    pub end_time: Option<Timestamp>,
+}
+
+/// Stored in the Operation.metadata field returned by CreateGlossary.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateGlossaryMetadata {
+    /// The name of the glossary that is being created.
     pub name: String,
-    /// Service-specific metadata associated with the operation.
-    /// It typically contains progress information and common metadata such as create time.
-    /// Some services might not provide such metadata. Any method that returns a long-running operation should document the metadata type, if any.
-    /// 
-    /// An object containing fields of an arbitrary type. An additional field "@type" contains a URI identifying the type.
-    /// Example: { "id": 1234, "@type": "types.example.com/standard/id" }.
-    pub metadata: serde_json::Value,
-    /// If the value is false, it means the operation is still in progress. If true, the operation is completed, and either error or response is available.
-    pub done: Option<bool>,
-    /// The error result of the operation in case of failure or cancellation.
-    pub error: Option<Status>,
-    /// The normal response of the operation in case of success. If the original method returns no data on success, such as Delete, the response
-    /// is google.protobuf.Empty. If the original method is standard Get/Create/Update, the response should be the resource. For other methods,
-    /// the response should have the type XxxResponse, where Xxx is the original method name. For example, if the original method name is TakeSnapshot(),
-    /// the inferred response type is TakeSnapshotResponse.
-    /// 
-    /// An object containing fields of an arbitrary type. An additional field "@type" contains a URI identifying the type.
-    /// Example: { "id": 1234, "@type": "types.example.com/standard/id" }.
-    pub response: Option<serde_json::Value>,
+    /// The current state of the glossary creation operation.
+    pub state: Option<OperationState>,
+    /// The time when the operation was submitted to the server.
+    pub submit_time: Option<Timestamp>,
 }
 
-impl Operation {
-    pub fn wait_util_done(&self, access_token: &str) -> impl Future<Item=StdResult<serde_json::Value, Status>, Error=Error> {
-        let name = self.name.to_string();
-        let access_token = access_token.to_string();
-        loop_fn((), move |_| {
-            wait_operation(&name, &access_token, &WaitOperationRequestBody { timeout: Some("1s".to_string()) })
-            .and_then(|new_operation| {
-                match new_operation.done {
-                    None | Some(false) => Ok(Loop::Continue(())),
-                    Some(true) => {
-                        match new_operation.response {
-                            Some(response) => Ok(Loop::Break(Ok(response))),
-                            _ => {
-                                match new_operation.error {
-                                    Some(error) => Ok(Loop::Break(Err(error))),
-                                    None => Err(Error::Other(format!("wait_operation should return one of response or error : {:?}", new_operation))),
-                                }
-                            }
-                        }
-                    }
-                }
-            })
-        })        
+/// Stored in the Operation.metadata field returned by DeleteGlossary.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteGlossaryMetadata {
+    /// The name of the glossary that is being deleted.
+    pub name: String,
+    /// The current state of the glossary deletion operation.
+    pub state: Option<OperationState>,
+    /// The time when the operation was submitted to the server.
+    pub submit_time: Option<Timestamp>,
+}
+
+/// Stored in the Operation.response field returned by DeleteGlossary.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteGlossaryResponse {
+    /// The name of the deleted glossary.
+    pub name: String,
+    /// The time when the operation was submitted to the server.
+    pub submit_time: Option<Timestamp>,
+    /// The time when the glossary deletion is finished and Operation.done is set to true.
+    pub end_time: Option<Timestamp>,
+}
+
+/// A timestamp in RFC3339 UTC "Zulu" format, such as "2014-10-02T15:01:23.045123456Z". With the `chrono`
+/// feature it is parsed into a `chrono::DateTime<Utc>`, otherwise it is the string the API returned.
+#[cfg(feature = "chrono")]
+pub type Timestamp = chrono::DateTime<chrono::Utc>;
+/// A timestamp in RFC3339 UTC "Zulu" format, such as "2014-10-02T15:01:23.045123456Z". With the `chrono`
+/// feature it is parsed into a `chrono::DateTime<Utc>`, otherwise it is the string the API returned.
+#[cfg(not(feature = "chrono"))]
+pub type Timestamp = String;
+
+/// The time from `start` to `end`, None if either is missing or `end` is before `start`.
+#[cfg(feature = "chrono")]
+fn duration_between(start: Option<&Timestamp>, end: Option<&Timestamp>) -> Option<Duration> {
+    end?.signed_duration_since(*start?).to_std().ok()
+}
+
+#[cfg(feature = "chrono")]
+impl BatchTranslateMetadata {
+    /// The time since the operation was submitted.
+    pub fn elapsed(&self) -> Option<Duration> {
+        duration_between(self.submit_time.as_ref(), Some(&chrono::Utc::now()))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl BatchTranslateResponse {
+    /// The time from submitting the operation to its end.
+    pub fn duration(&self) -> Option<Duration> {
+        duration_between(self.submit_time.as_ref(), self.end_time.as_ref())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl BatchTranslateDocumentMetadata {
+    /// The time since the operation was submitted.
+    pub fn elapsed(&self) -> Option<Duration> {
+        duration_between(self.submit_time.as_ref(), Some(&chrono::Utc::now()))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl BatchTranslateDocumentResponse {
+    /// The time from submitting the operation to its end.
+    pub fn duration(&self) -> Option<Duration> {
+        duration_between(self.submit_time.as_ref(), self.end_time.as_ref())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl DeleteGlossaryResponse {
+    /// The time from submitting the deletion to its end.
+    pub fn duration(&self) -> Option<Duration> {
+        duration_between(self.submit_time.as_ref(), self.end_time.as_ref())
+    }
+}
+
+/// (De)serializes optional protobuf Duration fields, which the JSON mapping encodes as seconds with
+/// up to nine fractional digits followed by 's', such as "3.5s".
+mod duration {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    /// The largest number of seconds a protobuf Duration can hold, about 10,000 years.
+    pub const MAX_SECONDS: u64 = 315_576_000_000;
+
+    pub fn serialize<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.collect_str(&format(*duration).map_err(serde::ser::Error::custom)?),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(s) => parse(&s).map(Some).map_err(serde::de::Error::custom),
+            None => Ok(None),
+        }
+    }
+
+    /// Formats with 0, 3, 6 or 9 fractional digits, like the protobuf JSON encoder.
+    pub fn format(duration: Duration) -> Result<String, String> {
+        let (seconds, nanos) = (duration.as_secs(), duration.subsec_nanos());
+        if seconds > MAX_SECONDS {
+            return Err(format!("duration of {} seconds is out of range", seconds));
+        }
+        Ok(match nanos {
+            0 => format!("{}s", seconds),
+            _ if nanos % 1_000_000 == 0 => format!("{}.{:03}s", seconds, nanos / 1_000_000),
+            _ if nanos % 1_000 == 0 => format!("{}.{:06}s", seconds, nanos / 1_000),
+            _ => format!("{}.{:09}s", seconds, nanos),
+        })
+    }
+
+    pub fn parse(s: &str) -> Result<Duration, String> {
+        let invalid = || format!("invalid duration {:?}, expected seconds followed by 's', such as \"3.5s\"", s);
+        let number = s.strip_suffix('s').ok_or_else(invalid)?;
+        let (seconds, fraction) = match number.split_once('.') {
+            Some((_, "")) => return Err(invalid()),
+            Some((seconds, fraction)) => (seconds, fraction),
+            None => (number, ""),
+        };
+        if seconds.is_empty() || !seconds.bytes().all(|b| b.is_ascii_digit()) || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        if fraction.len() > 9 {
+            return Err(format!("invalid duration {:?}, at most nine fractional digits are allowed", s));
+        }
+        let seconds: u64 = seconds.parse().map_err(|_| invalid())?;
+        if seconds > MAX_SECONDS {
+            return Err(format!("duration of {} seconds is out of range", seconds));
+        }
+        let nanos = format!("{:0<9}", fraction).parse().map_err(|_| invalid())?;
+        Ok(Duration::new(seconds, nanos))
+    }
+}
+
+/// Deserializes protobuf int64 fields, which the JSON mapping encodes as strings.
+mod int64 {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrNumber {
+        String(String),
+        Number(i64),
+    }
+
+    pub fn serialize<S: Serializer>(n: &i64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(n)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+        match StringOrNumber::deserialize(deserializer)? {
+            StringOrNumber::String(s) => s.parse().map_err(serde::de::Error::custom),
+            StringOrNumber::Number(n) => Ok(n),
+        }
     }
 }
 
@@ -645,40 +2700,46 @@ impl Operation {
 /// or other methods to check whether the cancellation succeeded or whether the operation completed despite cancellation. On successful cancellation,
 /// the operation is not deleted; instead, it becomes an operation with an Operation.error value with a google.rpc.Status.code of 1, corresponding to
 /// Code.CANCELLED.
-pub fn cancel_operation(name: &str, access_token: &str) -> impl Future<Item=(), Error=Error> + Send {
-    let url = format!("https://translation.googleapis.com/v3beta1/{}:cancel", name);
-    post_request(&url, access_token, &())
+pub async fn cancel_operation(name: impl AsRef<str>, access_token: &str) -> Result<()> {
+    default_client("", "", access_token).cancel_operation(name).await
 }
 
 /// Deletes a long-running operation. This method indicates that the client is no longer interested in the operation result.
 /// It does not cancel the operation. If the server doesn't support this method, it returns google.rpc.Code.UNIMPLEMENTED.
-pub fn delete_operation(name: &str, access_token: &str) -> impl Future<Item=(), Error=Error> + Send {
-    let url = format!("https://translation.googleapis.com/v3beta1/{}:cancel", name);
-    delete_request::<Empty>(&url, access_token).map(|_| ())
+pub async fn delete_operation(name: impl AsRef<str>, access_token: &str) -> Result<()> {
+    default_client("", "", access_token).delete_operation(name).await
 }
 
 /// Gets the latest state of a long-running operation. Clients can use this method to poll the operation
 /// result at intervals as recommended by the API service.
-pub fn get_opertion(name: &str, access_token: &str) -> impl Future<Item=Operation, Error=Error> + Send {
-    let url = format!("https://translation.googleapis.com/v3beta1/{}", name);
-    get_request(&url, access_token, &Empty)
+pub async fn get_operation(name: impl AsRef<str>, access_token: &str) -> Result<Operation> {
+    default_client("", "", access_token).get_operation(name).await
 }
 
-#[derive(Serialize)]
+#[deprecated(note = "renamed to get_operation")]
+pub async fn get_opertion(name: &str, access_token: &str) -> Result<Operation> {
+    get_operation(name, access_token).await
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ListOperationsQueryParams {
-    /// The standard list filter.
+    /// The standard list filter, which `operations::OperationFilter` composes.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub filter: Option<String>,
     /// The standard list page size.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub page_size: Option<usize>,
     /// The standard list page token.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub page_token: Option<String>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ListOperationsResponse {
     /// A list of operations that matches the specified filter in the request.
+    #[serde(default)]
     pub operations: Vec<Operation>,
     /// The standard List next-page token.
     pub next_page_token: Option<String>,
@@ -690,68 +2751,460 @@ pub struct ListOperationsResponse {
 /// To override the binding, API services can add a binding such as "/v1/{name=users/*}/operations" to their service configuration.
 /// For backwards compatibility, the default name includes the operations collection id, however overriding users must ensure the name binding
 /// is the parent resource, without the operations collection id.
-pub fn list_operations(project_id: &str, location_id: &str, access_token: &str, params: &ListOperationsQueryParams)
-    -> impl Future<Item=ListOperationsResponse, Error=Error> + Send
+pub async fn list_operations(project_id: &str, location_id: &str, access_token: &str, params: &ListOperationsQueryParams)
+    -> Result<ListOperationsResponse>
 {
-    let url = format!("https://translation.googleapis.com/v3beta1/projects/{}/locations/{}/operations", project_id, location_id);
-    get_request(&url, access_token, params)
+    default_client(project_id, location_id, access_token).list_operations(params).await
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 #[serde(rename_all="camelCase")]
 pub struct WaitOperationRequestBody {
     /// The maximum duration to wait before timing out. If left blank, the wait will be at most the time permitted by the underlying HTTP/RPC protocol.
     /// If RPC context deadline is also specified, the shorter one will be used.
     /// 
-    /// A duration in seconds with up to nine fractional digits, terminated by 's'. Example: "3.5s".
-    pub timeout: Option<String>,
+    /// Sent as a duration in seconds with up to nine fractional digits, terminated by 's', for example "3.5s".
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "duration")]
+    pub timeout: Option<Duration>,
+}
+
+impl WaitOperationRequestBody {
+    /// Checks that `timeout` is within the range of a protobuf Duration. `wait_operation` calls this
+    /// before sending the request.
+    pub fn validate(&self) -> Result<()> {
+        match self.timeout {
+            Some(timeout) if timeout.as_secs() > duration::MAX_SECONDS => Err(Error::InvalidRequest(format!(
+                "WaitOperationRequestBody.timeout must be at most {} seconds", duration::MAX_SECONDS
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Waits for the specified long-running operation until it is done or reaches at most a specified timeout, returning the latest state.
+/// If the operation is already done, the latest state is immediately returned. If the timeout specified is greater than the default HTTP/RPC timeout,
+/// the HTTP/RPC timeout is used. If the server does not support this method, it returns google.rpc.Code.UNIMPLEMENTED. Note that this method is on a
+/// best-effort basis. It may return the latest state before the specified timeout (including immediately), meaning even an immediate response is no
+/// guarantee that the operation is done.
+pub async fn wait_operation(name: impl AsRef<str>, access_token: &str, request_body: &WaitOperationRequestBody)
+    -> Result<Operation>
+{
+    default_client("", "", access_token).wait_operation(name, request_body).await
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Status {
+    /// The status code, which should be an enum value of google.rpc.Code.
+    ///
+    /// In the body of a failed HTTP request this is the HTTP status code instead.
+    pub code: i32,
+    /// A developer-facing error message, which should be in English. Any user-facing error message should be localized
+    /// and sent in the google.rpc.Status.details field, or localized by the client.
+    #[serde(default)]
+    pub message: String,
+    /// The name of the google.rpc.Code, for example "NOT_FOUND". Only present in the body of a failed HTTP request.
+    pub status: Option<String>,
+    /// A list of messages that carry the error details. There is a common set of message types for APIs to use,
+    /// which are decoded by their "@type"; see the `error_details` module.
+    pub details: Option<Vec<ErrorDetail>>,
+}
+
+/// The error of a failed long-running operation as an `Error::ResponseError`.
+pub(crate) fn operation_error(status: Status) -> Error {
+    let http_status = status.error_code().map_or(500, ErrorCode::http_status);
+    Error::ResponseError(http_status, status)
+}
+
+impl Status {
+    /// The canonical error code, from `status` if present and otherwise from the google.rpc.Code in `code`.
+    /// For errors returned by a request, `Error::code` also takes the HTTP status into account.
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        match &self.status {
+            Some(name) => ErrorCode::from_name(name),
+            None => ErrorCode::from_rpc_code(self.code),
+        }
+    }
+
+    fn details(&self) -> impl Iterator<Item = &ErrorDetail> {
+        self.details.iter().flatten()
+    }
+
+    /// The first ErrorInfo detail, which names the reason for the error.
+    pub fn error_info(&self) -> Option<&ErrorInfo> {
+        self.details().find_map(|detail| match detail {
+            ErrorDetail::ErrorInfo(info) => Some(info),
+            _ => None,
+        })
+    }
+
+    /// The first BadRequest detail, which lists the invalid fields of the request.
+    pub fn bad_request(&self) -> Option<&BadRequest> {
+        self.details().find_map(|detail| match detail {
+            ErrorDetail::BadRequest(bad_request) => Some(bad_request),
+            _ => None,
+        })
+    }
+
+    /// The first QuotaFailure detail, which names the exceeded quotas.
+    pub fn quota_failure(&self) -> Option<&QuotaFailure> {
+        self.details().find_map(|detail| match detail {
+            ErrorDetail::QuotaFailure(failure) => Some(failure),
+            _ => None,
+        })
+    }
+
+    /// The first RetryInfo detail, which says how long to wait before retrying.
+    pub fn retry_info(&self) -> Option<&RetryInfo> {
+        self.details().find_map(|detail| match detail {
+            ErrorDetail::RetryInfo(retry_info) => Some(retry_info),
+            _ => None,
+        })
+    }
+}
+
+/// Translates a large volume of text in asynchronous batch mode.
+/// 
+/// This function provides real-time output as the inputs are being processed. If caller
+/// cancels a request, the partial results (for an input file, it's all or nothing) may
+/// still be available on the specified output location.
+/// 
+/// This call returns immediately and you can use google.longrunning.Operation.name to poll the status of the call.
+pub async fn batch_translate_text(project_id: &str, location_id: &str, access_token: &str,
+        request_body: &BatchTranslateTextRequest)
+    -> Result<Operation>
+{
+    default_client(project_id, location_id, access_token).batch_translate_text(request_body).await
+}
+
+/// A document translation request.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslateDocumentRequest {
+    /// Optional. The BCP-47 language code of the input document if known, for example, "en-US" or "sr-Latn".
+    /// If the source language isn't specified, the API attempts to identify the source language automatically
+    /// and returns the source language within the response. Source language must be specified if the request
+    /// contains a glossary or a custom model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_language_code: Option<String>,
+    /// Required. The BCP-47 language code to use for translation of the input document,
+    /// set to one of the language codes listed in Language Support.
+    pub target_language_code: String,
+    /// Required. Input configurations.
+    pub document_input_config: DocumentInputConfig,
+    /// Optional. Output configurations. Defines if the output file should be stored within Cloud Storage
+    /// as well as the desired output format. If not provided the translated file will only be returned
+    /// through a byte-stream and its output mime type will be the same as the input file's mime type.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_output_config: Option<DocumentOutputConfig>,
+    /// Optional. The model type requested for this translation.
+    ///
+    /// The format depends on model type:
+    ///
+    /// - AutoML Translation models: projects/{project-number-or-id}/locations/{location-id}/models/{model-id}
+    /// - General (built-in) models: projects/{project-number-or-id}/locations/{location-id}/models/general/nmt,
+    ///
+    /// If not provided, the default Google model (NMT) will be used for translation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Optional. Glossary to be applied. The glossary must be within the same region (have the same location-id)
+    /// as the model, otherwise an INVALID_ARGUMENT (400) error is returned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub glossary_config: Option<TranslateTextGlossaryConfig>,
+    /// Optional. The labels with user-defined metadata for the request.
+    ///
+    /// See https://goo.gl/xmQnxf for more information on and examples of labels.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Labels>,
+    /// Optional. An attribution shown in the translated document instead of the default
+    /// "Machine Translated by Google". At most 32 characters.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub customized_attribution: Option<String>,
+    /// Optional. If true, native PDF files are translated as PDFs without being converted, which
+    /// is faster but only supports PDFs with a text layer. Default value is false if missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_translate_native_pdf_only: Option<bool>,
+    /// Optional. If true, removes the shadow text left on background images when translating
+    /// native PDFs. Can't be combined with `is_translate_native_pdf_only`. Default value is false if missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_shadow_removal_native_pdf: Option<bool>,
+}
+
+impl TranslateDocumentRequest {
+    /// Checks the shape of the Cloud Storage URIs, if any, and that the PDF options don't conflict.
+    /// `translate_document` calls this before sending the request.
+    pub fn validate(&self) -> Result<()> {
+        if self.is_translate_native_pdf_only == Some(true) && self.enable_shadow_removal_native_pdf == Some(true) {
+            return Err(Error::InvalidRequest(
+                "TranslateDocumentRequest.enable_shadow_removal_native_pdf requires is_translate_native_pdf_only to be false".to_string()));
+        }
+        if let DocumentSource::GcsSource(gcs_source) = &self.document_input_config.source {
+            gcs_source.validate()?;
+        }
+        match self.document_output_config.as_ref().and_then(|config| config.gcs_destination.as_ref()) {
+            Some(gcs_destination) => gcs_destination.validate(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A document translation request input config.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentInputConfig {
+    /// Specifies the input document's mimeType.
+    ///
+    /// If not specified it will be determined using the file extension for gcs_source provided files.
+    /// For a file provided through bytes content the mimeType must be provided. Currently supported mime types are:
+    ///
+    /// - application/pdf
+    /// - application/vnd.openxmlformats-officedocument.wordprocessingml.document
+    /// - application/vnd.openxmlformats-officedocument.presentationml.presentation
+    /// - application/vnd.openxmlformats-officedocument.spreadsheetml.sheet
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// Specifies the source for the document's content.
+    #[serde(flatten)]
+    pub source: DocumentSource,
+}
+
+/// The source of a document to translate.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DocumentSource {
+    /// Document's content represented as a stream of bytes.
+    Content(#[serde(with = "base64_bytes")] Vec<u8>),
+    /// Google Cloud Storage location. This must be a single file. For example: gs://example_bucket/example_file.pdf
+    GcsSource(GcsSource),
+}
+
+/// A document translation request output config.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentOutputConfig {
+    /// Optional. Google Cloud Storage destination for the translation output, e.g., gs://my_bucket/my_directory/.
+    ///
+    /// The destination directory provided does not have to be empty, but the bucket must exist.
+    /// If a file with the same name as the output file already exists in the destination an error will be returned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gcs_destination: Option<GcsDestination>,
+    /// Optional. Specifies the translated document's mimeType. If not specified, the translated file's
+    /// mime type will be the same as the input file's mime type. Currently only support the output mime type
+    /// to be the same as input mime type.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// A translated document response message.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslateDocumentResponse {
+    /// Translated document.
+    pub document_translation: DocumentTranslation,
+    /// The document's translation output if a glossary is provided in the request.
+    /// This can be the same as `document_translation` if no glossary terms apply.
+    pub glossary_document_translation: Option<DocumentTranslation>,
+    /// Only present when 'model' is present in the request. 'model' is normalized to have a project number.
+    pub model: Option<String>,
+    /// The glossaryConfig used for this translation.
+    pub glossary_config: Option<TranslateTextGlossaryConfig>,
+}
+
+/// A translated document message.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentTranslation {
+    /// The array of translated documents. It is expected to be size 1 for now.
+    /// We may produce multiple translated documents in the future for other type of file formats.
+    ///
+    /// Empty when the translation was written to `DocumentOutputConfig.gcs_destination`.
+    #[serde(default, with = "base64_bytes_vec")]
+    pub byte_stream_outputs: Vec<Vec<u8>>,
+    /// The translated document's mime type.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// The detected language for the input document. If the user did not provide the source language
+    /// for the input document, this field will have the language code automatically detected.
+    /// If the source language was passed, auto-detection of the language does not occur and this field is empty.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detected_language_code: Option<String>,
+}
+
+/// The BatchTranslateDocument request.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchTranslateDocumentRequest {
+    /// Required. The BCP-47 language code of the input document if known, for example, "en-US" or "sr-Latn".
+    /// Supported language codes are listed in Language Support (https://cloud.google.com/translate/docs/languages).
+    pub source_language_code: String,
+    /// Required. The BCP-47 language code to use for translation of the input document. Specify up to 10 language codes here.
+    pub target_language_codes: Vec<String>,
+    /// Required. Input configurations. The total number of files matched should be <= 100.
+    /// The total content size to translate should be <= 100M Unicode codepoints.
+    /// The files must use UTF-8 encoding.
+    pub input_configs: Vec<BatchDocumentInputConfig>,
+    /// Required. Output configuration. If 2 input configs match to the same file (that is, same input path),
+    /// we don't generate output for duplicate inputs.
+    pub output_config: BatchDocumentOutputConfig,
+    /// Optional. The models to use for translation. Map's key is target language code.
+    /// Map's value is the model name. Value can be a built-in general model, or an AutoML Translation model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub models: Option<HashMap<String, String>>,
+    /// Optional. Glossaries to be applied. It's keyed by target language code.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub glossaries: Option<HashMap<String, TranslateTextGlossaryConfig>>,
+    /// Optional. File format conversion map to be applied to all input files. Map's key is the original mime_type.
+    /// Map's value is the target mime_type of translated documents.
+    ///
+    /// Supported file format conversion includes:
+    ///
+    /// - application/pdf to application/vnd.openxmlformats-officedocument.wordprocessingml.document
+    ///
+    /// If nothing specified, output files will be in the same format as the original file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format_conversions: Option<HashMap<String, String>>,
+}
+
+impl BatchTranslateDocumentRequest {
+    /// Checks the number of target languages, that there is an input, and the shape of the Cloud
+    /// Storage URIs. `batch_translate_document` calls this before sending the request.
+    pub fn validate(&self) -> Result<()> {
+        validate_target_languages("BatchTranslateDocumentRequest", &self.target_language_codes)?;
+        if self.input_configs.is_empty() {
+            return Err(Error::InvalidRequest("BatchTranslateDocumentRequest.input_configs is empty".to_string()));
+        }
+        for input_config in &self.input_configs {
+            input_config.gcs_source.validate()?;
+        }
+        self.output_config.gcs_destination.validate()
+    }
+}
+
+/// Input configuration for BatchTranslateDocument request.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchDocumentInputConfig {
+    /// Google Cloud Storage location for the source input. This can be a single file (for example,
+    /// gs://translation-test/input.docx) or a wildcard (for example, gs://translation-test/*).
+    ///
+    /// File mime type is determined based on extension. Supported mime type includes:
+    ///
+    /// - pdf, application/pdf
+    /// - docx, application/vnd.openxmlformats-officedocument.wordprocessingml.document
+    /// - pptx, application/vnd.openxmlformats-officedocument.presentationml.presentation
+    /// - xlsx, application/vnd.openxmlformats-officedocument.spreadsheetml.sheet
+    ///
+    /// The max file size to support for .docx, .pptx and .xlsx is 100MB. The max file size to support for .pdf is 1GB
+    /// and the max page limit is 1000 pages. The max file size to support for all input documents is 1GB.
+    pub gcs_source: GcsSource,
+}
+
+/// Output configuration for BatchTranslateDocument request.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchDocumentOutputConfig {
+    /// Google Cloud Storage destination for output content. For every single input document (for example,
+    /// gs://a/b/c.[extension]), we generate at most 2 * n output files. (n is the # of targetLanguageCodes
+    /// in the BatchTranslateDocumentRequest).
+    ///
+    /// While the input documents are being processed, we write/update an index file index.csv under
+    /// gcsDestination.output_uri_prefix (for example, gs://translation_output/index.csv) The index file is
+    /// generated/updated as new files are being translated. The format is:
+    ///
+    /// input_document,target_language_code,translation_output,error_output, glossary_translation_output,glossary_error_output
+    ///
+    /// The format of translation_output (for target language code 'trg') is:
+    /// gs://translation_test/a_b_c_'trg'_translation.[extension]
+    pub gcs_destination: GcsDestination,
+}
+
+/// Translates a large volume of documents in asynchronous batch mode.
+///
+/// This function provides real-time output as the inputs are being processed. If caller cancels a request,
+/// the partial results (for an input file, it's all or nothing) may still be available on the specified output location.
+///
+/// This call returns immediately and you can use google.longrunning.Operation.name to poll the status of the call.
+pub async fn batch_translate_document(project_id: &str, location_id: &str, access_token: &str,
+        request_body: &BatchTranslateDocumentRequest)
+    -> Result<Operation>
+{
+    default_client(project_id, location_id, access_token).batch_translate_document(request_body).await
 }
 
-/// Waits for the specified long-running operation until it is done or reaches at most a specified timeout, returning the latest state.
-/// If the operation is already done, the latest state is immediately returned. If the timeout specified is greater than the default HTTP/RPC timeout,
-/// the HTTP/RPC timeout is used. If the server does not support this method, it returns google.rpc.Code.UNIMPLEMENTED. Note that this method is on a
-/// best-effort basis. It may return the latest state before the specified timeout (including immediately), meaning even an immediate response is no
-/// guarantee that the operation is done.
-pub fn wait_operation(name: &str, access_token: &str, request_body: &WaitOperationRequestBody)
-    -> impl Future<Item=Operation, Error=Error> + Send
-{
-    let url = format!("https://translation.googleapis.com/v3beta1/{}:wait", name);
-    post_request(&url, access_token, request_body) 
+/// Serializes bytes fields as the base64 strings used by the JSON mapping of protobuf `bytes`.
+///
+/// Documents can be several megabytes, so the base64 text is written straight into the JSON and
+/// decoded straight from it, without a `String` holding it in between.
+mod base64_bytes {
+    use std::fmt;
+
+    use base64::Engine;
+    use base64::display::Base64Display;
+    use base64::engine::general_purpose::STANDARD;
+    use serde::{Deserializer, Serializer};
+    use serde::de::{Error, Visitor};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&Base64Display::new(bytes, &STANDARD))
+    }
+
+    struct Base64Visitor;
+
+    impl Visitor<'_> for Base64Visitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a base64 string")
+        }
+
+        fn visit_str<E: Error>(self, s: &str) -> Result<Vec<u8>, E> {
+            STANDARD.decode(s).map_err(E::custom)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        deserializer.deserialize_str(Base64Visitor)
+    }
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct Status {
-    /// The status code, which should be an enum value of google.rpc.Code.
-    pub code: i32,
-    /// A developer-facing error message, which should be in English. Any user-facing error message should be localized
-    /// and sent in the google.rpc.Status.details field, or localized by the client.
-    pub message: String,
-    /// A list of messages that carry the error details. There is a common set of message types for APIs to use.
-    /// 
-    /// An object containing fields of an arbitrary type. An additional field "@type" contains a URI identifying the type.
-    /// Example: { "id": 1234, "@type": "types.example.com/standard/id" }.
-    pub details: Option<Vec<serde_json::Value>>,
+mod base64_bytes_vec {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    struct Encoded<'a>(&'a [u8]);
+
+    impl Serialize for Encoded<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            super::base64_bytes::serialize(self.0, serializer)
+        }
+    }
+
+    struct Decoded(Vec<u8>);
+
+    impl<'de> Deserialize<'de> for Decoded {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Decoded, D::Error> {
+            super::base64_bytes::deserialize(deserializer).map(Decoded)
+        }
+    }
+
+    pub fn serialize<S: Serializer>(items: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(items.iter().map(|bytes| Encoded(bytes)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Vec<u8>>, D::Error> {
+        Ok(Vec::<Decoded>::deserialize(deserializer)?.into_iter().map(|decoded| decoded.0).collect())
+    }
 }
 
-/// Translates a large volume of text in asynchronous batch mode.
-/// 
-/// This function provides real-time output as the inputs are being processed. If caller
-/// cancels a request, the partial results (for an input file, it's all or nothing) may
-/// still be available on the specified output location.
-/// 
-/// This call returns immediately and you can use google.longrunning.Operation.name to poll the status of the call.
-pub fn batch_translate_text(project_id: &str, location_id: &str, access_token: &str,
-        request_body: &BatchTranslateTextRequest)
-    -> impl Future<Item=Operation, Error=Error> + Send
+/// Translates documents in synchronous mode.
+pub async fn translate_document(project_id: &str, location_id: &str, access_token: &str,
+        request_body: &TranslateDocumentRequest)
+    -> Result<TranslateDocumentResponse>
 {
-    let url = format!("https://translation.googleapis.com/v3beta1/projects/{}/locations/{}:batchTranslateText",
-        project_id, location_id);
-    post_request(&url, access_token, request_body)
+    default_client(project_id, location_id, access_token).translate_document(request_body).await
 }
 
 /// Represents a glossary built from user provided data.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Glossary {
     /// Required. The resource name of the glossary.Glossary names have the form
@@ -761,45 +3214,96 @@ pub struct Glossary {
     /// Total glossary must not exceed 10M Unicode codepoints.
     pub input_config: GlossaryInputConfig,
     /// Output only. The number of entries defined in the glossary.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub entry_count: Option<usize>,
     /// Output only. When glossaries.create was called.
     /// 
     /// A timestamp in RFC3339 UTC "Zulu" format, accurate to nanoseconds.
     /// Example: "2014-10-02T15:01:23.045123456Z".
-    pub submit_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submit_time: Option<Timestamp>,
     /// Output only. When the glossary creation was finished.
     /// 
     /// A timestamp in RFC3339 UTC "Zulu" format, accurate to nanoseconds.
     /// Example: "2014-10-02T15:01:23.045123456Z".
-    pub end_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_time: Option<Timestamp>,
     /// Used with unidirectional glossaries.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub language_pair: Option<LanguageCodePair>,
     /// Used with equivalent term set glossaries.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub language_codes_set: Option<LanguageCodesSet>,
+    /// Optional. The display name of the glossary. Only supported by `ApiVersion::V3`; a client using
+    /// v3beta1 rejects glossaries that set it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
 }
 
 impl Glossary {
-    pub fn new(name: String, input_config: GlossaryInputConfig, language_pair: LanguageCodePair) -> Glossary {
+    pub fn new(name: impl Into<String>, input_config: GlossaryInputConfig, language_pair: LanguageCodePair) -> Glossary {
         Glossary {
-            name,
+            name: name.into(),
             input_config,
             entry_count: None,
             submit_time: None,
             end_time: None,
             language_pair: Some(language_pair),
             language_codes_set: None,
+            display_name: None,
+        }
+    }
+
+    /// A glossary whose entries are sets of equivalent terms in each of `language_codes_set`.
+    pub fn new_equivalent_term_set(name: impl Into<String>, input_config: GlossaryInputConfig, language_codes_set: LanguageCodesSet) -> Glossary {
+        Glossary {
+            name: name.into(),
+            input_config,
+            entry_count: None,
+            submit_time: None,
+            end_time: None,
+            language_pair: None,
+            language_codes_set: Some(language_codes_set),
+            display_name: None,
+        }
+    }
+
+    /// How long building the glossary took, from `submit_time` to `end_time`.
+    #[cfg(feature = "chrono")]
+    pub fn build_duration(&self) -> Option<Duration> {
+        duration_between(self.submit_time.as_ref(), self.end_time.as_ref())
+    }
+
+    pub fn with_display_name(mut self, display_name: impl Into<String>) -> Glossary {
+        self.display_name = Some(display_name.into());
+        self
+    }
+
+    /// Checks that exactly one of `language_pair` and `language_codes_set` is set, and that the
+    /// language codes set has at least two distinct entries. `create_glossary` calls this before
+    /// sending the glossary.
+    pub fn validate(&self) -> Result<()> {
+        match (&self.language_pair, &self.language_codes_set) {
+            (Some(_), None) => Ok(()),
+            (None, Some(set)) => set.validate(),
+            (Some(_), Some(_)) => Err(Error::InvalidRequest(
+                "Glossary.language_pair and Glossary.language_codes_set are mutually exclusive".to_string()
+            )),
+            (None, None) => Err(Error::InvalidRequest(
+                "one of Glossary.language_pair and Glossary.language_codes_set is required".to_string()
+            )),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct GlossaryInputConfig {
     pub gcs_source: GcsSource,
 }
 
 /// Used with unidirectional glossaries.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct LanguageCodePair {
     /// Required. The BCP-47 language code of the input text, for example,
@@ -810,8 +3314,17 @@ pub struct LanguageCodePair {
     pub target_language_code: String,
 }
 
+impl LanguageCodePair {
+    pub fn new(source_language_code: impl Into<String>, target_language_code: impl Into<String>) -> LanguageCodePair {
+        LanguageCodePair {
+            source_language_code: source_language_code.into(),
+            target_language_code: target_language_code.into(),
+        }
+    }
+}
+
 /// Used with equivalent term set glossaries.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct LanguageCodesSet {
     /// The BCP-47 language code(s) for terms defined in the glossary. All entries are unique.
@@ -819,51 +3332,71 @@ pub struct LanguageCodesSet {
     pub language_codes: Vec<String>,
 }
 
+impl LanguageCodesSet {
+    pub fn new<I>(language_codes: I) -> LanguageCodesSet
+        where I: IntoIterator, I::Item: Into<String>
+    {
+        LanguageCodesSet { language_codes: language_codes.into_iter().map(Into::into).collect() }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.language_codes.len() < 2 {
+            return Err(Error::InvalidRequest("Glossary.language_codes_set needs at least two language codes".to_string()));
+        }
+        for (i, code) in self.language_codes.iter().enumerate() {
+            if self.language_codes[..i].contains(code) {
+                return Err(Error::InvalidRequest(format!("Glossary.language_codes_set contains {:?} twice", code)));
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Creates a glossary and returns the long-running operation. Returns NOT_FOUND, if the project doesn't exist.
-pub fn create_glossary(project_id: &str, location_id: &str, access_token: &str, glossary: &Glossary)
-    -> impl Future<Item=Operation, Error=Error> + Send
+pub async fn create_glossary(project_id: &str, location_id: &str, access_token: &str, glossary: &Glossary)
+    -> Result<Operation>
 {
-    let url = format!("https://translation.googleapis.com/v3beta1/projects/{}/locations/{}/glossaries",
-        project_id, location_id);
-    post_request(&url, access_token, glossary)
+    default_client(project_id, location_id, access_token).create_glossary(glossary).await
 }
 
 /// Deletes a glossary, or cancels glossary construction if the glossary isn't created yet.
 /// Returns NOT_FOUND, if the glossary doesn't exist.
-pub fn delete_glossary(name: &str, access_token: &str)
-    -> impl Future<Item=Operation, Error=Error> + Send
+pub async fn delete_glossary(name: impl AsRef<str>, access_token: &str)
+    -> Result<Operation>
 {
-    let url = format!("https://translation.googleapis.com/v3beta1/{}", name);
-    delete_request(&url, access_token)
+    default_client("", "", access_token).delete_glossary(name).await
 }
 
 /// Gets a glossary. Returns NOT_FOUND, if the glossary doesn't exist.
-pub fn get_glossary(name: &str, access_token: &str)
-    -> impl Future<Item=Operation, Error=Error> + Send
+pub async fn get_glossary(name: impl AsRef<str>, access_token: &str)
+    -> Result<Glossary>
 {
-    let url = format!("https://translation.googleapis.com/v3beta1/{}", name);
-    get_request(&url, access_token, &Empty)
+    default_client("", "", access_token).get_glossary(name).await
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ListGlossariesQueryParams {
     /// Optional. Requested page size. The server may return fewer glossaries than requested.
     /// If unspecified, the server picks an appropriate default.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub page_size: Option<usize>,
     /// Optional. A token identifying a page of results the server should return. Typically,
     /// this is the value of [ListGlossariesResponse.next_page_token] returned from the previous call to
     /// glossaries.list method. The first page is returned if pageTokenis empty or missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub page_token: Option<String>,
     /// Optional. Filter specifying constraints of a list operation. Filtering is not supported yet, and
     /// the parameter currently has no effect. If missing, no filtering is performed.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub filter: Option<String>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ListGlossariesResponse {
     /// The list of glossaries for a project.
+    #[serde(default)]
     pub glossaries: Vec<Glossary>,
     /// A token to retrieve a page of results. Pass this value in the [ListGlossariesRequest.page_token] field
     /// in the subsequent call to glossaries.list method to retrieve the next page of results.
@@ -871,19 +3404,15 @@ pub struct ListGlossariesResponse {
 }
 
 /// Lists glossaries in a project. Returns NOT_FOUND, if the project doesn't exist.
-pub fn list_glossaries(project_id: &str, location_id: &str, access_token: &str, params: &ListGlossariesQueryParams)
-    -> impl Future<Item=ListGlossariesResponse, Error=Error> + Send
+pub async fn list_glossaries(project_id: &str, location_id: &str, access_token: &str, params: &ListGlossariesQueryParams)
+    -> Result<ListGlossariesResponse>
 {
-    let url = format!("https://translation.googleapis.com/v3beta1/projects/{}/locations/{}/glossaries",
-        project_id, location_id);
-    get_request(&url, access_token, params)
+    default_client(project_id, location_id, access_token).list_glossaries(params).await
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::rc::Rc;
-    use futures::future::{loop_fn, Loop};
 
     #[test]
     fn it_works() {
@@ -891,7 +3420,314 @@ mod tests {
     }
 
     #[test]
-    fn test_detect_language() {
+    fn test_api_version_urls() {
+        let client = TranslationClient::new("p", "global", "token");
+        assert_eq!(client.location_url(":translateText"),
+            "https://translation.googleapis.com/v3beta1/projects/p/locations/global:translateText");
+        let client = client.with_api_version(ApiVersion::V3);
+        assert_eq!(client.location_url(":translateText"),
+            "https://translation.googleapis.com/v3/projects/p/locations/global:translateText");
+        assert_eq!(client.resource_url("projects/p/locations/global/operations/1", ":wait"),
+            "https://translation.googleapis.com/v3/projects/p/locations/global/operations/1:wait");
+    }
+
+    #[tokio::test]
+    async fn test_v3_only_fields() {
+        let transport = Arc::new(transport::MockTransport::with_response(200, r#"{"name": "projects/p/locations/global/operations/1", "metadata": {}}"#));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone());
+        let glossary: Glossary = serde_json::from_str(r#"{"name": "projects/p/locations/global/glossaries/g",
+            "inputConfig": {"gcsSource": {"inputUri": "gs://b/g.tsv"}}, "languagePair": {"sourceLanguageCode": "en", "targetLanguageCode": "de"}}"#).unwrap();
+        let glossary = glossary.with_display_name("Products");
+        assert!(matches!(client.create_glossary(&glossary).await, Err(Error::InvalidRequest(_))));
+        assert!(transport.requests().is_empty());
+        client.with_api_version(ApiVersion::V3).create_glossary(&glossary).await.unwrap();
+        assert_eq!(transport.requests()[0].uri, "https://translation.googleapis.com/v3/projects/p/locations/global/glossaries");
+
+        let transport = Arc::new(transport::MockTransport::with_response(200, r#"{"translations": [{"translatedText": "नमस्ते"}]}"#));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone());
+        let request = TranslateTextRequest::builder().content("namaste").target("hi")
+            .transliteration_config(TransliterationConfig::new(true)).build().unwrap();
+        assert!(matches!(client.translate_text(&request).await, Err(Error::InvalidRequest(_))));
+        assert!(transport.requests().is_empty());
+        client.with_api_version(ApiVersion::V3).translate_text(&request).await.unwrap();
+        assert_eq!(transport.requests()[0].uri, "https://translation.googleapis.com/v3/projects/p/locations/global:translateText");
+    }
+
+    #[test]
+    fn test_default_client() {
+        let client = default_client("p", "global", "token");
+        assert_eq!(client.location_url(":translateText"),
+            "https://translation.googleapis.com/v3beta1/projects/p/locations/global:translateText");
+        assert!(Arc::ptr_eq(&client.http.transport, &default_client("q", "global", "token").http.transport));
+    }
+
+    #[tokio::test]
+    async fn test_global_location() {
+        let transport = Arc::new(transport::MockTransport::new(|request| match request.uri.as_str() {
+            uri if uri.ends_with(":translateText") => (200, r#"{"translations": [{"translatedText": "Hallo"}]}"#.to_string()),
+            uri if uri.ends_with(":detectLanguage") => (200, r#"{"languages": [{"languageCode": "en", "confidence": 1.0}]}"#.to_string()),
+            _ => (200, r#"{"languages": []}"#.to_string()),
+        }));
+        let client = TranslationClient::global("p", "token").with_transport(transport.clone());
+        client.translate_text(&TranslateTextRequest::builder().content("Hello").target("de").build().unwrap()).await.unwrap();
+        client.detect_language(&DetectLanguageRequest::builder().content("Hello").build().unwrap()).await.unwrap();
+        client.get_supported_languages(&GetSupportedLanguagesQueryParams::builder().build().unwrap()).await.unwrap();
+        let uris: Vec<String> = transport.requests().into_iter()
+            .map(|request| request.uri.split('?').next().unwrap().to_string())
+            .collect();
+        assert_eq!(uris, vec![
+            "https://translation.googleapis.com/v3beta1/projects/p/locations/global:translateText",
+            "https://translation.googleapis.com/v3beta1/projects/p/locations/global:detectLanguage",
+            "https://translation.googleapis.com/v3beta1/projects/p/locations/global/supportedLanguages",
+        ]);
+    }
+
+    #[test]
+    fn test_regional_endpoint() {
+        let client = TranslationClient::new("p", "europe-west1", "token").with_regional_endpoint().unwrap();
+        assert_eq!(client.location_url(":translateText"),
+            "https://translate-eu.googleapis.com/v3beta1/projects/p/locations/europe-west1:translateText");
+        assert_eq!(regional_endpoint("us-central1"), Some("https://translate-us.googleapis.com"));
+        assert!(matches!(TranslationClient::new("p", "global", "token").with_regional_endpoint(), Err(Error::InvalidRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_default_headers() {
+        use hyper::service::{make_service_fn, service_fn};
+        let headers = Arc::new(std::sync::Mutex::new(HeaderMap::new()));
+        let recorded = headers.clone();
+        let make_service = make_service_fn(move |_| {
+            let recorded = recorded.clone();
+            async move {
+                Ok::<_, hyper::Error>(service_fn(move |req: Request<Body>| {
+                    *recorded.lock().unwrap() = req.headers().clone();
+                    async { Ok::<_, hyper::Error>(hyper::Response::new(Body::from("{}"))) }
+                }))
+            }
+        });
+        let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let endpoint = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        let client = TranslationClient::new("p", "global", "token")
+            .with_endpoint(&endpoint)
+            .with_user_agent("my-app/1.2").unwrap()
+            .with_header("x-goog-request-params", "parent=projects/p").unwrap();
+        client.cancel_operation("projects/p/locations/global/operations/1").await.unwrap();
+        let headers = headers.lock().unwrap();
+        assert_eq!(headers["user-agent"], format!("google-translation/{} my-app/1.2", env!("CARGO_PKG_VERSION")));
+        assert_eq!(headers["x-goog-request-params"], "parent=projects/p");
+        assert_eq!(headers["authorization"], "Bearer token");
+        assert!(TranslationClient::new("p", "global", "token").with_header("bad header", "x").is_err());
+        assert!(TranslationClient::new("p", "global", "token").with_user_agent("bad\n").is_err());
+    }
+
+    /// Serves `body` with `status` for every request on a local port, recording the request lines it receives.
+    pub(crate) async fn serve(status: u16, body: &'static str) -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+        serve_fn(move |_| (status, body.to_string())).await
+    }
+
+    /// Like `serve`, but responds with whatever `respond` returns for the request's path and query.
+    pub(crate) async fn serve_fn<F>(respond: F) -> (String, Arc<std::sync::Mutex<Vec<String>>>)
+        where F: Fn(&str) -> (u16, String) + Send + Sync + 'static
+    {
+        use hyper::service::{make_service_fn, service_fn};
+        let respond = Arc::new(respond);
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        let make_service = make_service_fn(move |_| {
+            let recorded = recorded.clone();
+            let respond = respond.clone();
+            async move {
+                Ok::<_, hyper::Error>(service_fn(move |req: Request<Body>| {
+                    recorded.lock().unwrap().push(format!("{} {}", req.method(), req.uri()));
+                    let (status, body) = respond(&req.uri().to_string());
+                    async move {
+                        Ok::<_, hyper::Error>(hyper::Response::builder().status(status).body(Body::from(body)).unwrap())
+                    }
+                }))
+            }
+        });
+        let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let endpoint = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+        (endpoint, requests)
+    }
+
+    #[tokio::test]
+    async fn test_list_glossaries_stream() {
+        let (endpoint, requests) = serve_fn(|uri| {
+            let body = if uri.contains("pageToken=2") {
+                r#"{"glossaries": [{"name": "g3", "inputConfig": {"gcsSource": {"inputUri": "gs://b/g3.tsv"}}}]}"#
+            } else {
+                r#"{"glossaries": [
+                    {"name": "g1", "inputConfig": {"gcsSource": {"inputUri": "gs://b/g1.tsv"}}},
+                    {"name": "g2", "inputConfig": {"gcsSource": {"inputUri": "gs://b/g2.tsv"}}}
+                ], "nextPageToken": "2"}"#
+            };
+            (200, body.to_string())
+        }).await;
+        let client = TranslationClient::new("p", "global", "token").with_endpoint(&endpoint);
+        let params = ListGlossariesQueryParams { page_size: Some(2), page_token: None, filter: None };
+        let names: Vec<String> = client.list_glossaries_stream(&params)
+            .map_ok(|glossary| glossary.name)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(names, vec!["g1", "g2", "g3"]);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        assert!(requests.lock().unwrap()[1].ends_with("?pageSize=2&pageToken=2"));
+    }
+
+    #[tokio::test]
+    async fn test_custom_endpoint() {
+        let (endpoint, requests) = serve(200, r#"{"translations": [{"translatedText": "你好"}]}"#).await;
+        let client = TranslationClient::new("p", "global", "token").with_endpoint(&format!("{}/", endpoint));
+        let request = TranslateTextRequest {
+            contents: vec!["hello".to_string()],
+            mime_type: None,
+            source_language_code: None,
+            target_language_code: "zh".to_string(),
+            model: None,
+            glossary_config: None,
+            labels: None,
+            transliteration_config: None,
+        };
+        let response = client.translate_text(&request).await.unwrap();
+        assert_eq!(response.translations[0].translated_text, "你好");
+        assert_eq!(*requests.lock().unwrap(), vec!["POST /v3beta1/projects/p/locations/global:translateText"]);
+    }
+
+    #[test]
+    fn test_response_error() {
+        let body = br#"{"error": {"code": 404, "message": "Glossary not found.", "status": "NOT_FOUND"}}"#;
+        match response_error(404, body) {
+            Error::ResponseError(404, status) => {
+                assert_eq!(status.status.as_deref(), Some("NOT_FOUND"));
+                assert_eq!(status.message, "Glossary not found.");
+            },
+            e => panic!("unexpected error: {:?}", e),
+        }
+        assert_eq!(response_error(404, body).to_string(), "404 NOT_FOUND: Glossary not found.");
+        match response_error(502, b"<html>Bad Gateway</html>") {
+            Error::HttpError(502, body) => assert_eq!(body, "<html>Bad Gateway</html>"),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_error_code() {
+        let not_found = response_error(404, br#"{"error": {"code": 404, "message": "Glossary not found.", "status": "NOT_FOUND"}}"#);
+        assert_eq!(not_found.code(), Some(ErrorCode::NotFound));
+        assert!(not_found.is_not_found());
+        let precondition = response_error(400, br#"{"error": {"code": 400, "status": "FAILED_PRECONDITION"}}"#);
+        assert_eq!(precondition.code(), Some(ErrorCode::FailedPrecondition));
+        assert!(response_error(429, b"Too Many Requests").is_quota());
+        assert_eq!(Error::Other("error".to_string()).code(), None);
+        assert_eq!(ErrorCode::from_rpc_code(16), Some(ErrorCode::Unauthenticated));
+        assert_eq!(ErrorCode::from_name("ABORTED").map(ErrorCode::rpc_code), Some(10));
+        assert_eq!(ErrorCode::DataLoss.to_string(), "DATA_LOSS");
+        assert_eq!(ErrorCode::Unavailable.http_status(), 503);
+        let operation_error: Status = serde_json::from_str(r#"{"code": 7, "message": "Permission denied."}"#).unwrap();
+        assert_eq!(operation_error.error_code(), Some(ErrorCode::PermissionDenied));
+        let quota = response_error(429, br#"{"error": {"code": 429, "status": "RESOURCE_EXHAUSTED", "details": [
+            {"@type": "type.googleapis.com/google.rpc.ErrorInfo", "reason": "RATE_LIMIT_EXCEEDED", "domain": "googleapis.com"}
+        ]}}"#);
+        let status = quota.status().unwrap();
+        assert_eq!(status.error_info().unwrap().reason, "RATE_LIMIT_EXCEEDED");
+        assert!(status.quota_failure().is_none());
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(response_error(429, br#"{"error": {"code": 429, "status": "RESOURCE_EXHAUSTED"}}"#).is_retryable());
+        assert!(response_error(503, b"Service Unavailable").is_retryable());
+        assert!(!response_error(400, br#"{"error": {"code": 400, "status": "INVALID_ARGUMENT"}}"#).is_retryable());
+        assert!(!Error::Other("error".to_string()).is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_empty_list_responses() {
+        for body in ["empty list", "missing field", "empty body"] {
+            let transport = Arc::new(crate::transport::MockTransport::new(move |request| {
+                let collection = request.uri.split('?').next().unwrap().rsplit('/').next().unwrap().to_string();
+                match body {
+                    "empty list" => (200, format!(r#"{{"{}": []}}"#, collection)),
+                    "missing field" => (200, "{}".to_string()),
+                    _ => (200, String::new()),
+                }
+            }));
+            let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone());
+            let operations = client.list_operations(&ListOperationsQueryParams::default()).await.unwrap();
+            assert!(operations.operations.is_empty() && operations.next_page_token.is_none(), "{}", body);
+            let params = ListGlossariesQueryParams { page_size: None, page_token: None, filter: None };
+            let glossaries: Vec<Glossary> = client.list_glossaries_stream(&params).try_collect().await.unwrap();
+            assert!(glossaries.is_empty(), "{}", body);
+            let params = adaptive_mt::ListAdaptiveMtQueryParams { page_size: None, page_token: None, filter: None };
+            assert!(client.list_adaptive_mt_datasets(&params).await.unwrap().adaptive_mt_datasets.is_empty(), "{}", body);
+            let dataset = "projects/p/locations/global/adaptiveMtDatasets/d";
+            assert!(client.list_adaptive_mt_files(dataset, &params).await.unwrap().adaptive_mt_files.is_empty(), "{}", body);
+            assert!(client.list_adaptive_mt_sentences(dataset, &params).await.unwrap().adaptive_mt_sentences.is_empty(), "{}", body);
+            assert_eq!(transport.requests().len(), 5, "{}", body);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_url_and_token() {
+        let transport = Arc::new(crate::transport::MockTransport::with_response(200, r#"{"languages": []}"#));
+        let request = DetectLanguageRequest::builder().content("Hallo").build().unwrap();
+        let client = TranslationClient::new("my project", "global", "token").with_transport(transport.clone());
+        let error = client.detect_language(&request).await.unwrap_err();
+        assert!(matches!(error, Error::InvalidUrl(_)), "{:?}", error);
+        assert_eq!(error.code(), Some(ErrorCode::InvalidArgument));
+        let client = TranslationClient::new("p", "global", "to\nken").with_transport(transport.clone());
+        let error = client.detect_language(&request).await.unwrap_err();
+        assert!(matches!(error, Error::AuthError(_)), "{:?}", error);
+        assert!(!error.to_string().contains("to\nken"));
+        assert!(transport.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_call_raw() {
+        let transport = Arc::new(crate::transport::MockTransport::new(|request| match request.uri.contains("operations") {
+            true => (200, r#"{"name": "projects/p/locations/global/operations/1", "metadata": {}, "done": true}"#.to_string()),
+            false => (200, r#"{"translations": [{"translatedText": "Hallo"}]}"#.to_string()),
+        }));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone());
+        let body = serde_json::json!({"contents": ["Hello"], "targetLanguageCode": "de"});
+        let response = client.call_raw::<TranslateTextResponse>(Method::POST, ":translateText", Some(body.clone())).await.unwrap();
+        assert_eq!(response.status, hyper::StatusCode::OK);
+        assert_eq!(response.text(), r#"{"translations": [{"translatedText": "Hallo"}]}"#);
+        assert_eq!(response.value.translations[0].translated_text, "Hallo");
+        let operation = client.call_raw::<Operation>(Method::GET, "projects/p/locations/global/operations/1", None).await.unwrap();
+        assert_eq!(operation.value.done, Some(true));
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].uri, "https://translation.googleapis.com/v3beta1/projects/p/locations/global:translateText");
+        assert_eq!(requests[0].json(), body);
+        assert_eq!(requests[1].method, Method::GET);
+        assert_eq!(requests[1].uri, "https://translation.googleapis.com/v3beta1/projects/p/locations/global/operations/1");
+        assert!(requests[1].body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_fields() {
+        let transport = Arc::new(crate::transport::MockTransport::with_response(200,
+            r#"{"translations": [{"translatedText": "Hallo", "newField": 1}], "newList": []}"#));
+        let request = TranslateTextRequest::builder().content("Hello").target("de").build().unwrap();
+        for mode in [UnknownFields::Ignore, UnknownFields::Warn] {
+            let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone()).with_unknown_fields(mode);
+            assert_eq!(client.translate_text(&request).await.unwrap().translations[0].translated_text, "Hallo");
+        }
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport).with_unknown_fields(UnknownFields::Deny);
+        let error = client.translate_text(&request).await.unwrap_err();
+        assert!(matches!(error, Error::SerdeJsonError(_)), "{:?}", error);
+        assert_eq!(error.to_string(), "json error: unknown fields in response: translations.0.newField, newList");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_detect_language() {
         let project_id = std::env::var("PROJECT_ID").unwrap();
         let location_id = std::env::var("LOCATION_ID").unwrap();
         let access_token = std::env::var("ACCESS_TOKEN").unwrap();
@@ -901,16 +3737,33 @@ mod tests {
             labels: None,
             content: "我是谁是我".to_string(),
         };
-        tokio::runtime::current_thread::block_on_all(hyper::rt::lazy(move || {
-            detect_language(&project_id, &location_id, &access_token, &request_body)
-            .map(|response_body| {
-                println!("{:?}", response_body);
-            })
-        })).unwrap();
+        let response_body = detect_language(&project_id, &location_id, &access_token, &request_body).await.unwrap();
+        println!("{:?}", response_body);
     }
 
-    #[test]
-    fn test_get_supported_languages() {
+    #[tokio::test]
+    #[ignore]
+    async fn test_client_reuse() {
+        let project_id = std::env::var("PROJECT_ID").unwrap();
+        let location_id = std::env::var("LOCATION_ID").unwrap();
+        let access_token = std::env::var("ACCESS_TOKEN").unwrap();
+        let client = TranslationClient::new(&project_id, &location_id, &access_token);
+        let request_body = DetectLanguageRequest {
+            model: None,
+            mime_type: None,
+            labels: None,
+            content: "我是谁是我".to_string(),
+        };
+        let response_bodies = futures::future::try_join(
+            client.detect_language(&request_body),
+            client.detect_language(&request_body),
+        ).await.unwrap();
+        println!("{:?}", response_bodies);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_get_supported_languages() {
         let project_id = std::env::var("PROJECT_ID").unwrap();
         let location_id = std::env::var("LOCATION_ID").unwrap();
         let access_token = std::env::var("ACCESS_TOKEN").unwrap();
@@ -918,19 +3771,15 @@ mod tests {
             display_language_code: None,
             model: None,
         };
-        tokio::runtime::current_thread::block_on_all(hyper::rt::lazy(move || {
-            get_supported_languages(&project_id, &location_id, &access_token, &query_params)
-            .map(|response_body| {
-                println!("{:?}", response_body);
-            })
-            .map_err(|e| {
-                panic!("{:?}", e);
-            })
-        })).unwrap();
+        match get_supported_languages(&project_id, &location_id, &access_token, &query_params).await {
+            Ok(response_body) => println!("{:?}", response_body),
+            Err(e) => panic!("{:?}", e),
+        }
     }
 
-    #[test]
-    fn test_translate_text() {
+    #[tokio::test]
+    #[ignore]
+    async fn test_translate_text() {
         let project_id = std::env::var("PROJECT_ID").unwrap();
         let location_id = std::env::var("LOCATION_ID").unwrap();
         let access_token = std::env::var("ACCESS_TOKEN").unwrap();
@@ -947,21 +3796,17 @@ mod tests {
             source_language_code: Some("en".to_string()),
             target_language_code: "zh".to_string(),
             model: None,
+            transliteration_config: None,
         };
-        tokio::runtime::current_thread::block_on_all(hyper::rt::lazy(move || {
-            translate_text(&project_id, &location_id, &access_token, &request)
-            .map(|response_body| {
-                println!("{:?}", response_body);
-            })
-            .map_err(|e| {
-                panic!("{:?}", e);
-            })
-        })).unwrap();
+        match translate_text(&project_id, &location_id, &access_token, &request).await {
+            Ok(response_body) => println!("{:?}", response_body),
+            Err(e) => panic!("{:?}", e),
+        }
     }
 
-    #[test]
+    #[tokio::test]
     #[ignore]
-    fn test_batch_translate_text() {
+    async fn test_batch_translate_text() {
         let project_id = std::env::var("PROJECT_ID").unwrap();
         let location_id = std::env::var("LOCATION_ID").unwrap();
         let access_token = std::env::var("ACCESS_TOKEN").unwrap();
@@ -990,24 +3835,218 @@ mod tests {
             })).collect()),
             labels: None,
         };
-        tokio::runtime::current_thread::block_on_all(hyper::rt::lazy(move || {
-            batch_translate_text(&project_id, &location_id, &access_token, &request)
-            .and_then(move |operation| {
-                operation.wait_util_done(&access_token).map(|r| {
-                    match r {
-                        Ok(_) => (),
-                        Err(e) => panic!("wait_operation error: {:?}", e),
-                    }
-                })
-            })
-            .map_err(|e| {
-                panic!("{:?}", e);
-            })
-        })).unwrap();
+        let operation = match batch_translate_text(&project_id, &location_id, &access_token, &request).await {
+            Ok(operation) => operation,
+            Err(e) => panic!("{:?}", e),
+        };
+        match operation.wait_until_done(&TranslationClient::new(&project_id, &location_id, &access_token), &PollConfig::default()).await {
+            Ok(Ok(_)) => (),
+            Ok(Err(e)) => panic!("wait_operation error: {:?}", e),
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_document_input_config_serialization() {
+        let config = DocumentInputConfig {
+            mime_type: Some("application/pdf".to_string()),
+            source: DocumentSource::Content(b"%PDF".to_vec()),
+        };
+        assert_eq!(serde_json::to_value(&config).unwrap(), serde_json::json!({
+            "mimeType": "application/pdf",
+            "content": "JVBERg==",
+        }));
+        let config = DocumentInputConfig {
+            mime_type: None,
+            source: DocumentSource::GcsSource(GcsSource { input_uri: "gs://bucket/a.docx".to_string() }),
+        };
+        assert_eq!(serde_json::to_value(&config).unwrap(), serde_json::json!({
+            "gcsSource": { "inputUri": "gs://bucket/a.docx" },
+        }));
+        let response: TranslateDocumentResponse = serde_json::from_str(
+            r#"{"documentTranslation": {"byteStreamOutputs": ["JVBERg=="], "mimeType": "application/pdf"}}"#
+        ).unwrap();
+        assert_eq!(response.document_translation.byte_stream_outputs, vec![b"%PDF".to_vec()]);
+        // Escaped strings are unescaped before decoding.
+        let response: TranslateDocumentResponse = serde_json::from_str(
+            r#"{"documentTranslation": {"byteStreamOutputs": ["JVBERg\u003d\u003d"]}}"#
+        ).unwrap();
+        assert_eq!(response.document_translation.byte_stream_outputs, vec![b"%PDF".to_vec()]);
+        let config = DocumentInputConfig { mime_type: None, source: DocumentSource::Content(b"%PDF".to_vec()) };
+        assert_eq!(serde_json::to_string(&config).unwrap(), r#"{"content":"JVBERg=="}"#);
+    }
+
+    #[test]
+    fn test_translate_document_pdf_options() {
+        let input_config = DocumentInputConfig { mime_type: None, source: DocumentSource::Content(b"%PDF".to_vec()) };
+        let builder = || TranslateDocumentRequest::builder().target_language_code("de").document_input_config(input_config.clone())
+            .customized_attribution("Translated by Example").is_translate_native_pdf_only(true);
+        let request = builder().build().unwrap();
+        request.validate().unwrap();
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["customizedAttribution"], "Translated by Example");
+        assert_eq!(json["isTranslateNativePdfOnly"], true);
+        assert!(json.get("enableShadowRemovalNativePdf").is_none());
+        match builder().enable_shadow_removal_native_pdf(true).build().unwrap().validate() {
+            Err(Error::InvalidRequest(_)) => (),
+            r => panic!("expected InvalidRequest, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_labels() {
+        let labels = Labels::new().with("team", "localization").unwrap().with("état", "prêt-2").unwrap();
+        assert_eq!(labels.get("team"), Some("localization"));
+        assert!(Labels::new().with("Team", "x").is_err());
+        assert!(Labels::new().with("1team", "x").is_err());
+        assert!(Labels::new().with("team", "Localization").is_err());
+        assert!(Labels::new().with("team", "a".repeat(64)).is_err());
+        assert!(Labels::new().with("team", "").is_ok());
+
+        let request = TranslateTextRequest::builder().content("Hello").target("de").labels(labels).build().unwrap();
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["labels"], serde_json::json!({ "team": "localization", "état": "prêt-2" }));
+
+        let mut map = HashMap::new();
+        map.insert("ok".to_string(), "fine".to_string());
+        assert_eq!(Labels::try_from(map).unwrap().len(), 1);
+        let mut labels = Labels::new();
+        for i in 0..Labels::MAX_LABELS {
+            labels.insert(format!("k{}", i), "v").unwrap();
+        }
+        assert!(labels.insert("one_more", "v").is_err());
+        assert!(labels.insert("k0", "replaced").is_ok());
+    }
+
+    #[test]
+    fn test_mime_type() {
+        assert_eq!(serde_json::to_string(&MimeType::Html).unwrap(), r#""text/html""#);
+        assert_eq!(serde_json::from_str::<MimeType>(r#""text/plain""#).unwrap(), MimeType::Plain);
+        let pdf: MimeType = serde_json::from_str(r#""application/pdf""#).unwrap();
+        assert_eq!(pdf, MimeType::Other("application/pdf".to_string()));
+        assert_eq!(serde_json::to_string(&pdf).unwrap(), r#""application/pdf""#);
+        assert_eq!(MimeType::from_extension(".HTM"), Some(MimeType::Html));
+        assert_eq!(MimeType::from_extension("docx").unwrap().as_str(),
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document");
+        assert_eq!(MimeType::from_extension("exe"), None);
+        assert_eq!(MimeType::from("text/html"), MimeType::Html);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let request = TranslateTextRequest::builder()
+            .content("Hello")
+            .target("de")
+            .mime_type(MimeType::Html)
+            .labels(Labels::new().with("team", "docs").unwrap())
+            .build()
+            .unwrap();
+        let round_trip: TranslateTextRequest = serde_json::from_str(&serde_json::to_string(&request).unwrap()).unwrap();
+        assert_eq!(round_trip, request);
+
+        let response: BatchTranslateResponse = serde_json::from_str(
+            r#"{"totalCharacters": "12", "translatedCharacters": 12, "submitTime": "2020-01-01T00:00:00Z"}"#
+        ).unwrap();
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["totalCharacters"], "12");
+        assert_eq!(json["failedCharacters"], "0");
+        assert_eq!(serde_json::from_value::<BatchTranslateResponse>(json).unwrap(), response);
+
+        assert!(serde_json::from_str::<Labels>(r#"{"Team": "docs"}"#).is_err());
+    }
+
+    #[test]
+    fn test_wait_operation_timeout() {
+        let body = WaitOperationRequestBody::builder().timeout(Duration::from_millis(3500)).build().unwrap();
+        assert_eq!(serde_json::to_string(&body).unwrap(), r#"{"timeout":"3.500s"}"#);
+        assert_eq!(serde_json::to_string(&WaitOperationRequestBody::default()).unwrap(), "{}");
+        assert_eq!(duration::format(Duration::from_secs(3)).unwrap(), "3s");
+        assert_eq!(duration::format(Duration::new(0, 1)).unwrap(), "0.000000001s");
+
+        let body: WaitOperationRequestBody = serde_json::from_str(r#"{"timeout": "1.000000001s"}"#).unwrap();
+        assert_eq!(body.timeout, Some(Duration::new(1, 1)));
+        assert_eq!(duration::parse("0.5s"), Ok(Duration::from_millis(500)));
+        assert!(duration::parse("1.0000000001s").is_err());
+        assert!(duration::parse("-1s").is_err());
+        assert!(duration::parse("1.s").is_err());
+        assert!(duration::parse("1").is_err());
+
+        let body = WaitOperationRequestBody { timeout: Some(Duration::from_secs(duration::MAX_SECONDS + 1)) };
+        assert!(matches!(body.validate(), Err(Error::InvalidRequest(_))));
     }
 
     #[test]
-    fn test_list_operations() {
+    fn test_request_serialization_omits_none() {
+        let request = TranslateTextRequest::builder().content("Hello").target("de").build().unwrap();
+        assert_eq!(serde_json::to_value(&request).unwrap(), serde_json::json!({
+            "contents": ["Hello"],
+            "targetLanguageCode": "de",
+        }));
+        let request = DetectLanguageRequest::builder().content("Hello").build().unwrap();
+        assert_eq!(serde_json::to_string(&request).unwrap(), r#"{"content":"Hello"}"#);
+
+        let glossary = Glossary::new(
+            "projects/p/locations/us-central1/glossaries/g".to_string(),
+            GlossaryInputConfig { gcs_source: GcsSource { input_uri: "gs://bucket/g.tsv".to_string() } },
+            LanguageCodePair { source_language_code: "en".to_string(), target_language_code: "de".to_string() },
+        );
+        let json = serde_json::to_value(&glossary).unwrap();
+        assert_eq!(json, serde_json::json!({
+            "name": "projects/p/locations/us-central1/glossaries/g",
+            "inputConfig": { "gcsSource": { "inputUri": "gs://bucket/g.tsv" } },
+            "languagePair": { "sourceLanguageCode": "en", "targetLanguageCode": "de" },
+        }));
+        let round_trip: Glossary = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&round_trip).unwrap(), json);
+
+        let config = DocumentInputConfig {
+            mime_type: None,
+            source: DocumentSource::Content(b"%PDF".to_vec()),
+        };
+        let round_trip: DocumentInputConfig = serde_json::from_value(serde_json::to_value(&config).unwrap()).unwrap();
+        assert!(round_trip.mime_type.is_none());
+        assert!(matches!(round_trip.source, DocumentSource::Content(content) if content == b"%PDF"));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_batch_translate_document() {
+        let project_id = std::env::var("PROJECT_ID").unwrap();
+        let location_id = std::env::var("LOCATION_ID").unwrap();
+        let access_token = std::env::var("ACCESS_TOKEN").unwrap();
+        let request = BatchTranslateDocumentRequest {
+            source_language_code: "en".to_string(),
+            target_language_codes: vec!["zh".to_string()],
+            input_configs: vec![
+                BatchDocumentInputConfig {
+                    gcs_source: GcsSource {
+                        input_uri: "gs://mb_input/test.docx".to_string(),
+                    },
+                }
+            ],
+            output_config: BatchDocumentOutputConfig {
+                gcs_destination: GcsDestination {
+                    output_uri_prefix: "gs://mb_output/".to_string(),
+                }
+            },
+            models: None,
+            glossaries: None,
+            format_conversions: None,
+        };
+        let operation = match batch_translate_document(&project_id, &location_id, &access_token, &request).await {
+            Ok(operation) => operation,
+            Err(e) => panic!("{:?}", e),
+        };
+        match operation.wait_until_done(&TranslationClient::new(&project_id, &location_id, &access_token), &PollConfig::default()).await {
+            Ok(Ok(_)) => (),
+            Ok(Err(e)) => panic!("wait_operation error: {:?}", e),
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_list_operations() {
         let project_id = std::env::var("PROJECT_ID").unwrap();
         let location_id = std::env::var("LOCATION_ID").unwrap();
         let access_token = std::env::var("ACCESS_TOKEN").unwrap();
@@ -1016,20 +4055,162 @@ mod tests {
             page_size: None,
             page_token: None,
         };
-        tokio::runtime::current_thread::block_on_all(hyper::rt::lazy(move || {
-            list_operations(&project_id, &location_id, &access_token, &params)
-            .map(|list_operations| {
-                println!("{:?}", list_operations);
-                
-            })
-            .map_err(|e| {
-                panic!("{:?}", e);
-            })
-        })).unwrap();
+        match list_operations(&project_id, &location_id, &access_token, &params).await {
+            Ok(list_operations) => println!("{:?}", list_operations),
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancel_and_wait() {
+        let (endpoint, requests) = serve_fn(|uri| match uri {
+            "/v3beta1/projects/p/locations/global/operations/1:cancel" => (200, "{}".to_string()),
+            "/v3beta1/projects/p/locations/global/operations/1" => (200,
+                r#"{"name": "projects/p/locations/global/operations/1", "metadata": {}, "done": true,
+                    "error": {"code": 1, "message": "Operation cancelled."}}"#.to_string()),
+            "/v3beta1/projects/p/locations/global/operations/2:cancel" => (400,
+                r#"{"error": {"code": 400, "message": "Operation is done.", "status": "FAILED_PRECONDITION"}}"#.to_string()),
+            _ => (200, r#"{"name": "projects/p/locations/global/operations/2", "metadata": {}, "done": true, "response": {}}"#.to_string()),
+        }).await;
+        let client = TranslationClient::new("p", "global", "token").with_endpoint(&endpoint);
+        let operation = Operation { name: "projects/p/locations/global/operations/1".to_string(), metadata: serde_json::Value::Null,
+            done: Some(false), error: None, response: None };
+        match operation.cancel_and_wait(&client, Duration::from_secs(1)).await.unwrap() {
+            CancelOutcome::Cancelled(status) => assert_eq!(status.message, "Operation cancelled."),
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+        let operation = Operation { name: "projects/p/locations/global/operations/2".to_string(), ..operation };
+        assert_eq!(operation.cancel_and_wait(&client, Duration::from_secs(1)).await.unwrap(), CancelOutcome::Finished(Ok(serde_json::json!({}))));
+        assert_eq!(requests.lock().unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_operation_urls() {
+        let (endpoint, requests) = serve(200, "{}").await;
+        let client = TranslationClient::new("p", "global", "token").with_endpoint(&endpoint);
+        let name = "projects/p/locations/global/operations/42";
+        client.cancel_operation(name).await.unwrap();
+        client.delete_operation(name).await.unwrap();
+        assert_eq!(*requests.lock().unwrap(), vec![
+            "POST /v3beta1/projects/p/locations/global/operations/42:cancel",
+            "DELETE /v3beta1/projects/p/locations/global/operations/42",
+        ]);
+    }
+
+    #[test]
+    fn test_typed_operation() {
+        let operation: Operation = serde_json::from_str(r#"{
+            "name": "projects/p/locations/us-central1/operations/1",
+            "metadata": {
+                "@type": "type.googleapis.com/google.cloud.translation.v3beta1.BatchTranslateMetadata",
+                "state": "SUCCEEDED",
+                "translatedCharacters": "12",
+                "totalCharacters": "12",
+                "submitTime": "2019-10-02T15:01:23.045123456Z"
+            },
+            "done": true,
+            "response": {
+                "@type": "type.googleapis.com/google.cloud.translation.v3beta1.BatchTranslateResponse",
+                "totalCharacters": "12",
+                "translatedCharacters": "12",
+                "endTime": "2019-10-02T15:02:23.045123456Z"
+            }
+        }"#).unwrap();
+        assert_eq!(operation.metadata_as::<BatchTranslateMetadata>().unwrap().state, Some(OperationState::Succeeded));
+        let operation: BatchTranslateOperation = operation.into_typed().unwrap();
+        assert_eq!(operation.metadata.translated_characters, 12);
+        let response = operation.response.unwrap();
+        assert_eq!(response.total_characters, 12);
+        assert_eq!(response.failed_characters, 0);
     }
 
     #[test]
-    fn test_list_glossaries() {
+    fn test_operation_state() {
+        let states: Vec<OperationState> = serde_json::from_str(r#"["CANCELLING", "PAUSED"]"#).unwrap();
+        assert_eq!(states, vec![OperationState::Cancelling, OperationState::Unknown("PAUSED".to_string())]);
+        assert!(!states[0].is_terminal());
+        assert_eq!(serde_json::to_string(&states).unwrap(), r#"["CANCELLING","PAUSED"]"#);
+    }
+
+    #[tokio::test]
+    async fn test_wait_until_done_deadline() {
+        let (endpoint, requests) = serve(200, r#"{"name": "projects/p/locations/global/operations/1", "metadata": {}, "done": false}"#).await;
+        let client = TranslationClient::new("p", "global", "token").with_endpoint(&endpoint);
+        let operation = client.get_operation("projects/p/locations/global/operations/1").await.unwrap();
+        let poll_config = PollConfig {
+            initial_interval: Duration::from_millis(10),
+            multiplier: 2.0,
+            max_interval: Duration::from_millis(20),
+            deadline: Some(Duration::from_millis(100)),
+        };
+        match operation.wait_until_done(&client, &poll_config).await {
+            Err(Error::DeadlineExceeded(_)) => (),
+            r => panic!("expected DeadlineExceeded, got {:?}", r),
+        }
+        assert!(requests.lock().unwrap().len() > 2);
+    }
+
+    #[tokio::test]
+    async fn test_progress_stream() {
+        let polls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let (endpoint, _) = serve_fn({
+            let polls = polls.clone();
+            move |_| {
+                let poll = polls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) as i64;
+                let body = serde_json::json!({
+                    "name": "projects/p/locations/global/operations/1",
+                    "metadata": { "state": if poll < 2 { "RUNNING" } else { "SUCCEEDED" },
+                        "translatedCharacters": (poll * 50).to_string(), "totalCharacters": "100" },
+                    "done": poll >= 2,
+                });
+                (200, body.to_string())
+            }
+        }).await;
+        let client = TranslationClient::new("p", "global", "token").with_endpoint(&endpoint);
+        let operation = BatchTranslateOperation {
+            name: "projects/p/locations/global/operations/1".to_string(),
+            metadata: BatchTranslateMetadata {
+                state: None, translated_characters: 0, failed_characters: 0, total_characters: 0, submit_time: None,
+            },
+            done: None,
+            error: None,
+            response: None,
+        };
+        assert_eq!(operation.metadata.progress(), None);
+        let poll_config = PollConfig { initial_interval: Duration::from_millis(10), ..PollConfig::default() };
+        let snapshots: Vec<BatchTranslateMetadata> = operation.progress_stream(&client, &poll_config).try_collect().await.unwrap();
+        let progress: Vec<Option<f64>> = snapshots.iter().map(BatchTranslateMetadata::progress).collect();
+        assert_eq!(progress, vec![Some(0.0), Some(0.5), Some(1.0)]);
+        assert_eq!(snapshots[2].state, Some(OperationState::Succeeded));
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_token() {
+        let (endpoint, requests) = serve(200, r#"{"name": "projects/p/locations/global/operations/1", "metadata": {}, "done": false}"#).await;
+        let token = CancellationToken::new();
+        let client = TranslationClient::new("p", "global", "token")
+            .with_endpoint(&endpoint)
+            .with_cancellation_token(token.clone());
+        let operation = client.get_operation("projects/p/locations/global/operations/1").await.unwrap();
+        let poll_config = PollConfig { initial_interval: Duration::from_secs(60), ..PollConfig::default() };
+        let cancel = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            token.cancel();
+        });
+        let started = Instant::now();
+        let error = operation.wait_until_done(&client, &poll_config).await.unwrap_err();
+        assert!(error.is_cancelled());
+        assert_eq!(error.code(), Some(ErrorCode::Cancelled));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        cancel.await.unwrap();
+
+        assert!(client.cancel_operation("projects/p/locations/global/operations/1").await.unwrap_err().is_cancelled());
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_list_glossaries() {
         let project_id = std::env::var("PROJECT_ID").unwrap();
         let location_id = std::env::var("LOCATION_ID").unwrap();
         let access_token = std::env::var("ACCESS_TOKEN").unwrap();
@@ -1038,73 +4219,123 @@ mod tests {
             page_size: None,
             page_token: None,
         };
-        tokio::runtime::current_thread::block_on_all(hyper::rt::lazy(move || {
-            list_glossaries(&project_id, &location_id, &access_token, &params)
-            .map(|list_glossaries_response| {
-                println!("{:?}", list_glossaries_response);
-                
-            })
-            .map_err(|e| {
-                panic!("{:?}", e);
-            })
-        })).unwrap();
+        match list_glossaries(&project_id, &location_id, &access_token, &params).await {
+            Ok(list_glossaries_response) => println!("{:?}", list_glossaries_response),
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn test_timestamps() {
+        let glossary: Glossary = serde_json::from_str(r#"{
+            "name": "projects/p/locations/us-central1/glossaries/g",
+            "inputConfig": {"gcsSource": {"inputUri": "gs://bucket/terms.csv"}},
+            "submitTime": "2014-10-02T15:01:23.045123456Z",
+            "endTime": "2014-10-02T15:03:23.045123456Z"
+        }"#).unwrap();
+        assert_eq!(glossary.build_duration(), Some(Duration::from_secs(120)));
+        assert_eq!(serde_json::to_value(&glossary).unwrap()["endTime"], "2014-10-02T15:03:23.045123456Z");
+
+        let response: BatchTranslateResponse = serde_json::from_str(r#"{"submitTime": "2020-01-01T00:00:00Z"}"#).unwrap();
+        assert_eq!(response.duration(), None);
+        assert!(serde_json::from_str::<BatchTranslateResponse>(r#"{"submitTime": "yesterday"}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_glossary() {
+        let transport = Arc::new(transport::MockTransport::with_response(200, r#"{"name": "projects/p/locations/global/glossaries/g",
+            "inputConfig": {"gcsSource": {"inputUri": "gs://b/g.tsv"}}, "languagePair": {"sourceLanguageCode": "en", "targetLanguageCode": "de"},
+            "entryCount": 42}"#));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone());
+        let glossary = client.get_glossary("projects/p/locations/global/glossaries/g").await.unwrap();
+        assert_eq!(glossary.name, "projects/p/locations/global/glossaries/g");
+        assert_eq!(glossary.entry_count, Some(42));
+        let requests = transport.requests();
+        assert_eq!(requests[0].method, Method::GET);
+        assert_eq!(requests[0].uri, "https://translation.googleapis.com/v3beta1/projects/p/locations/global/glossaries/g");
     }
 
     #[test]
+    fn test_glossary_validate() {
+        let input_config = GlossaryInputConfig { gcs_source: GcsSource { input_uri: "gs://bucket/terms.csv".to_string() } };
+        let glossary = Glossary::new_equivalent_term_set("g".to_string(), input_config.clone(), LanguageCodesSet::new(vec!["en", "de", "fr"]));
+        assert!(glossary.validate().is_ok());
+        assert_eq!(serde_json::to_value(&glossary).unwrap()["languageCodesSet"]["languageCodes"], serde_json::json!(["en", "de", "fr"]));
+        assert!(serde_json::to_value(&glossary).unwrap().get("languagePair").is_none());
+
+        let duplicate = Glossary::new_equivalent_term_set("g".to_string(), input_config.clone(), LanguageCodesSet::new(vec!["en", "en"]));
+        assert!(matches!(duplicate.validate(), Err(Error::InvalidRequest(_))));
+        let single = Glossary::new_equivalent_term_set("g".to_string(), input_config.clone(), LanguageCodesSet::new(vec!["en"]));
+        assert!(matches!(single.validate(), Err(Error::InvalidRequest(_))));
+
+        let mut both = Glossary::new("g".to_string(), input_config, LanguageCodePair::new("en", "de"));
+        assert!(both.validate().is_ok());
+        both.language_codes_set = Some(LanguageCodesSet::new(vec!["en", "de"]));
+        assert!(matches!(both.validate(), Err(Error::InvalidRequest(_))));
+        both.language_pair = None;
+        both.language_codes_set = None;
+        assert!(matches!(both.validate(), Err(Error::InvalidRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_request_validation() {
+        let transport = Arc::new(crate::transport::MockTransport::with_response(200, "{}"));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone());
+        let invalid = |result: Result<_>| match result {
+            Err(Error::InvalidRequest(message)) => message,
+            other => panic!("expected InvalidRequest, got {:?}", other),
+        };
+
+        let mut request = TranslateTextRequest::builder().contents(Vec::new()).target("de").build().unwrap();
+        assert_eq!(invalid(request.validate()), "TranslateTextRequest.contents is empty");
+        request.contents = vec!["a".to_string(); chunking::MAX_CONTENTS + 1];
+        assert_eq!(invalid(client.translate_text(&request).await.map(|_| ())), "TranslateTextRequest.contents has 1025 strings, more than 1024");
+        request.contents = vec!["é".repeat(chunking::MAX_CODEPOINTS + 1)];
+        assert_eq!(invalid(request.validate()), "TranslateTextRequest.contents has 30001 codepoints, more than 30000");
+        request.contents = vec!["é".repeat(chunking::MAX_CODEPOINTS)];
+        assert!(request.validate().is_ok());
+
+        let mut batch = BatchTranslateTextRequest::builder()
+            .source_language_code("en")
+            .target_language_codes((0..11).map(|i| format!("l{}", i)).collect::<Vec<_>>())
+            .input_config(BatchTranslateTextInputConfig { mime_type: None, gcs_source: GcsSource { input_uri: "gs://in/a.tsv".to_string() } })
+            .output_config(BatchTranslateTextOutputConfig { gcs_destination: GcsDestination { output_uri_prefix: "gs://out/".to_string() } })
+            .build()
+            .unwrap();
+        assert_eq!(invalid(client.batch_translate_text(&batch).await.map(|_| ())),
+            "BatchTranslateTextRequest.target_language_codes has 11 languages, more than 10");
+        batch.target_language_codes.truncate(10);
+        assert!(batch.validate().is_ok());
+        batch.output_config.gcs_destination.output_uri_prefix = "gs://out/prefix".to_string();
+        assert_eq!(invalid(batch.validate()), r#"GcsDestination.output_uri_prefix "gs://out/prefix" must end with '/'"#);
+        batch.output_config.gcs_destination.output_uri_prefix = "gs://out/".to_string();
+        batch.input_configs[0].gcs_source.input_uri = "s3://in/a.tsv".to_string();
+        assert!(invalid(batch.validate()).contains("expected gs://bucket/object"));
+        batch.input_configs[0].gcs_source.input_uri = "gs://in".to_string();
+        assert_eq!(invalid(batch.validate()), r#"GcsSource.input_uri "gs://in" names no object"#);
+        assert!(transport.requests().is_empty());
+    }
+
+    #[tokio::test]
     #[ignore]
-    fn test_glossaries() {
-        let project_id = Rc::new(std::env::var("PROJECT_ID").unwrap());
-        let location_id = Rc::new(std::env::var("LOCATION_ID").unwrap());
-        let access_token = Rc::new(std::env::var("ACCESS_TOKEN").unwrap());
-        let glossary_bucket_id = Rc::new(std::env::var("GLOSSARY_BUCKET_ID").unwrap());
+    async fn test_glossaries() {
+        let project_id = std::env::var("PROJECT_ID").unwrap();
+        let location_id = std::env::var("LOCATION_ID").unwrap();
+        let access_token = std::env::var("ACCESS_TOKEN").unwrap();
+        let glossary_bucket_id = std::env::var("GLOSSARY_BUCKET_ID").unwrap();
         let test_glossary_name = format!("projects/{}/locations/{}/glossaries/test", project_id, location_id);
         let test_glossary_gs = format!("gs://{}/test.tsv", glossary_bucket_id);
-        tokio::runtime::current_thread::block_on_all(hyper::rt::lazy(move || {
-            let access_token2 = access_token.clone();
-            let access_token3 = access_token.clone();
-            delete_glossary(&test_glossary_name, &access_token)
-            .then(move |r| -> Box<dyn Future<Item=(), Error=Error>> {
-                match r {
-                    Ok(operation) => {
-                        println!("{:?}", operation);
-                        Box::new(operation.wait_util_done(&access_token2).map(|r| {
-                            match r {
-                                Ok(_) => (),
-                                Err(e) => panic!("wait_operation error: {:?}", e),
-                            }
-                        }))
-                    },
-                    Err(Error::ResponseError(code, _)) if code == code::NOT_FOUND => {
-                        // nothing to do
-                        Box::new(futures::future::ok::<(), Error>(()))
-                    },
-                    Err(e) => panic!("{:?}", e),
-                }
-            })
-            .map_err(|e| {
-                panic!("{:?}", e);
-            })
-            .and_then(move |_| {
-                let glossary = Glossary::new(
-                    test_glossary_name,
-                    GlossaryInputConfig { gcs_source: GcsSource { input_uri: test_glossary_gs }},
-                    LanguageCodePair { source_language_code: "en".to_string(), target_language_code: "zh".to_string()}
-                );
-                create_glossary(&project_id, &location_id, &access_token, &glossary)
-            })
-            .and_then(move |operation| {
-                println!("{:?}", operation);
-                operation.wait_util_done(&access_token3).map(|r| {
-                    match r {
-                        Ok(_) => (),
-                        Err(e) => panic!("wait_operation error: {:?}", e),
-                    }
-                })
-            })
-            .map_err(|e| {
-                panic!("{:?}", e);
-            })
-        })).unwrap();
+        let glossary = Glossary::new(
+            test_glossary_name,
+            GlossaryInputConfig { gcs_source: GcsSource { input_uri: test_glossary_gs }},
+            LanguageCodePair { source_language_code: "en".to_string(), target_language_code: "zh".to_string()}
+        );
+        let client = TranslationClient::new(&project_id, &location_id, &access_token);
+        match glossary::replace_glossary(&client, &glossary, &PollConfig::default()).await {
+            Ok(glossary) => println!("{:?}", glossary),
+            Err(e) => panic!("{:?}", e),
+        }
     }
 
     #[test]
@@ -0,0 +1,275 @@
+//! Translating Markdown documents.
+//!
+//! Neither text/plain nor text/html mode understands Markdown: code blocks get translated, link
+//! URLs get mangled and list markers move around. `TranslationClient::translate_markdown` splits a
+//! document into blocks instead, keeps code blocks, front matter, tables' separator rows, thematic
+//! breaks and link reference definitions as they are, and translates only the prose of
+//! paragraphs, headings, list items and block quotes. Inline code, link and image URLs, HTML tags,
+//! bare URLs and table cell delimiters inside the prose are replaced with tokens before translation
+//! and put back afterwards.
+//!
+//! ```no_run
+//! # async fn run(client: google_translation::TranslationClient) -> google_translation::Result<()> {
+//! use google_translation::markdown::MarkdownConfig;
+//!
+//! let markdown = "# Install\n\nRun `cargo build`, see [the guide](https://example.com/guide).\n";
+//! let german = client.translate_markdown(markdown, "de", &MarkdownConfig::default()).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The lines of a paragraph are translated together and written back as one line, since the
+//! translation rarely breaks where the original did.
+
+use std::sync::LazyLock;
+
+use futures::stream::{self, TryStreamExt};
+use regex::{Captures, Regex};
+
+use crate::*;
+
+/// Options for `TranslationClient::translate_markdown`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MarkdownConfig {
+    pub source_language_code: Option<String>,
+    pub model: Option<String>,
+}
+
+impl TranslationClient {
+    /// Translates the prose of `markdown` into `target_language_code`, see the module documentation.
+    pub async fn translate_markdown(&self, markdown: &str, target_language_code: impl AsRef<str>, config: &MarkdownConfig)
+        -> Result<String>
+    {
+        let segments = parse(markdown);
+        let protected: Vec<(String, Vec<String>)> = segments.iter()
+            .filter_map(|segment| match segment {
+                Segment::Prose { text, .. } => Some(protect(text)),
+                Segment::Verbatim(_) => None,
+            })
+            .collect();
+        // Only prose with something left to translate once the tokens are taken out is sent.
+        let texts: Vec<String> = protected.iter().filter(|(text, _)| has_words(text)).map(|(text, _)| text.clone()).collect();
+        let bulk_config = BulkTranslateConfig {
+            source_language_code: config.source_language_code.clone(),
+            mime_type: Some(MimeType::Plain),
+            model: config.model.clone(),
+            ..BulkTranslateConfig::default()
+        };
+        let translations: Vec<String> = self.bulk_translate(stream::iter(texts), target_language_code, &bulk_config)
+            .try_collect()
+            .await?;
+
+        let mut translations = translations.into_iter();
+        let mut protected = protected.into_iter();
+        let mut translated = String::with_capacity(markdown.len());
+        for segment in &segments {
+            match segment {
+                Segment::Verbatim(text) => translated.push_str(text),
+                Segment::Prose { prefix, text } => {
+                    let (protected_text, tokens) = protected.next().unwrap();
+                    translated.push_str(prefix);
+                    if has_words(&protected_text) {
+                        translated.push_str(&restore(&translations.next().unwrap_or_default(), &tokens));
+                    } else {
+                        translated.push_str(text);
+                    }
+                }
+            }
+        }
+        Ok(translated)
+    }
+}
+
+/// A part of a Markdown document: either copied as is, or prose following a block prefix such
+/// as "## " or "> - ".
+#[derive(Clone, Debug, PartialEq)]
+enum Segment<'a> {
+    Verbatim(&'a str),
+    Prose { prefix: &'a str, text: String },
+}
+
+/// The block markers before the prose of a line: indentation, block quotes, a heading or a list item.
+static PREFIX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\s*(?:>\s?)*(?:#{1,6}\s+|[-*+]\s+(?:\[[ xX]\]\s+)?|\d{1,9}[.)]\s+)?)(.*)$").unwrap()
+});
+
+/// Lines kept as they are: thematic breaks, setext heading underlines, table separator rows and
+/// link reference definitions.
+static VERBATIM_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"^\s{0,3}(?:(?:-\s*){3,}|(?:\*\s*){3,}|(?:_\s*){3,}|=+\s*)$",
+        r"|^\s*\|?\s*:?-+:?\s*(?:\|\s*:?-+:?\s*)*\|?\s*$",
+        r"|^\s{0,3}\[[^\]]+\]:\s*\S+",
+    )).unwrap()
+});
+
+/// Inline code, link destinations, reference link labels, HTML tags and autolinks, bare URLs, and
+/// table cell delimiters.
+static INLINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"``.*?``|`[^`]*`",
+        r#"|\]\([^)\s]*(?:\s+"[^"]*")?\)"#,
+        r"|\]\[[^\]]*\]",
+        r"|</?[a-zA-Z][^>]*>",
+        r"|https?://[^\s)<>\]]+",
+        r"|\|",
+    )).unwrap()
+});
+
+static TOKEN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)__\s*MD\s*(\d+)\s*__").unwrap());
+
+fn parse(markdown: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = markdown;
+    if let Some(front_matter) = front_matter(markdown) {
+        segments.push(Segment::Verbatim(front_matter));
+        rest = &markdown[front_matter.len()..];
+    }
+    let mut lines = rest.split_inclusive('\n').peekable();
+    let mut fence: Option<&str> = None;
+    let mut previous_blank = true;
+    while let Some(line) = lines.next() {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let trimmed = content.trim_start();
+        if let Some(marker) = fence {
+            segments.push(Segment::Verbatim(line));
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            segments.push(Segment::Verbatim(line));
+            continue;
+        }
+        let indented_code = previous_blank && (content.starts_with("    ") || content.starts_with('\t'));
+        if trimmed.is_empty() || indented_code || VERBATIM_LINE.is_match(content) {
+            segments.push(Segment::Verbatim(line));
+            previous_blank = trimmed.is_empty() || indented_code;
+            continue;
+        }
+        previous_blank = false;
+
+        let captures = PREFIX.captures(content).unwrap();
+        let prefix = captures.get(1).unwrap().as_str();
+        let mut text = captures[2].trim_end().to_string();
+        let mut ending = &line[content.len()..];
+        // A paragraph continues on the following lines until a blank line or another block.
+        let joinable = !prefix.contains('#') && !trimmed.starts_with('|');
+        while let Some(next) = lines.peek().filter(|_| joinable) {
+            let next_content = next.trim_end_matches(['\n', '\r']);
+            if next_content.trim().is_empty() || starts_block(next_content) {
+                break;
+            }
+            text.push(' ');
+            text.push_str(next_content.trim());
+            ending = &next[next_content.len()..];
+            lines.next();
+        }
+        segments.push(Segment::Prose { prefix, text });
+        segments.push(Segment::Verbatim(ending));
+    }
+    segments
+}
+
+/// The YAML front matter at the start of `markdown`, including its closing "---" line.
+fn front_matter(markdown: &str) -> Option<&str> {
+    let body = markdown.strip_prefix("---\n").or_else(|| markdown.strip_prefix("---\r\n"))?;
+    let mut end = markdown.len() - body.len();
+    for line in body.split_inclusive('\n') {
+        end += line.len();
+        if line.trim_end() == "---" {
+            return Some(&markdown[..end]);
+        }
+    }
+    None
+}
+
+/// Whether `line` starts a new block rather than continuing a paragraph.
+fn starts_block(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.starts_with("```") || trimmed.starts_with("~~~") || trimmed.starts_with('|')
+        || VERBATIM_LINE.is_match(line)
+        || !PREFIX.captures(line).unwrap()[1].trim().is_empty()
+}
+
+/// Replaces the inline code, URLs and tags in `text` with tokens, returning the text and what the tokens stand for.
+fn protect(text: &str) -> (String, Vec<String>) {
+    let mut tokens = Vec::new();
+    let protected = INLINE.replace_all(text, |captures: &Captures| {
+        tokens.push(captures[0].to_string());
+        format!("__MD{}__", tokens.len() - 1)
+    }).into_owned();
+    (protected, tokens)
+}
+
+fn restore(translated: &str, tokens: &[String]) -> String {
+    TOKEN.replace_all(translated, |captures: &Captures| {
+        captures[1].parse::<usize>().ok().and_then(|i| tokens.get(i)).cloned().unwrap_or_else(|| captures[0].to_string())
+    }).into_owned()
+}
+
+fn has_words(protected: &str) -> bool {
+    TOKEN.replace_all(protected, "").chars().any(char::is_alphabetic)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::transport::MockTransport;
+
+    const MARKDOWN: &str = "---\ntitle: Guide\n---\n# Install the tool\n\nRun `cargo build`, then read\n[the guide](https://example.com/a \"Guide\").\n\n```sh\necho hello\n```\n\n- [x] Done\n> Quoted *text*\n\n---\n| Name | Value |\n|------|------:|\n\n[guide]: https://example.com\n";
+
+    #[test]
+    fn test_parse() {
+        let prose: Vec<(&str, String)> = parse(MARKDOWN).into_iter()
+            .filter_map(|segment| match segment {
+                Segment::Prose { prefix, text } => Some((prefix, text)),
+                Segment::Verbatim(_) => None,
+            })
+            .collect();
+        assert_eq!(prose, vec![
+            ("# ", "Install the tool".to_string()),
+            ("", r#"Run `cargo build`, then read [the guide](https://example.com/a "Guide")."#.to_string()),
+            ("- [x] ", "Done".to_string()),
+            ("> ", "Quoted *text*".to_string()),
+            ("", "| Name | Value |".to_string()),
+        ]);
+        let verbatim: String = parse(MARKDOWN).into_iter()
+            .map(|segment| match segment {
+                Segment::Verbatim(text) => text.to_string(),
+                Segment::Prose { prefix, text } => format!("{}{}", prefix, text),
+            })
+            .collect();
+        assert!(verbatim.starts_with("---\ntitle: Guide\n---\n"));
+        assert!(verbatim.contains("```sh\necho hello\n```\n"));
+    }
+
+    #[test]
+    fn test_protect() {
+        let (text, tokens) = protect("See `a` and [b](http://x/y) or <b>https://z.com</b>.");
+        assert_eq!(text, "See __MD0__ and [b__MD1__ or __MD2____MD3____MD4__.");
+        assert_eq!(restore("Siehe __MD0__ und [b__ md1__ oder __MD2____MD3____MD4__.", &tokens),
+            "Siehe `a` und [b](http://x/y) oder <b>https://z.com</b>.");
+        assert!(!has_words("__MD0__, __MD1__"));
+    }
+
+    #[tokio::test]
+    async fn test_translate_markdown() {
+        let transport = Arc::new(MockTransport::new(|request| {
+            let contents = request.json()["contents"].clone();
+            assert_eq!(contents[0], "Install the tool");
+            assert_eq!(contents[1], "Run __MD0__, then read [the guide__MD1__.");
+            let translations: Vec<_> = contents.as_array().unwrap().iter()
+                .map(|content| serde_json::json!({"translatedText": format!("DE {}", content.as_str().unwrap())}))
+                .collect();
+            (200, serde_json::json!({"translations": translations}).to_string())
+        }));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport);
+        let translated = client.translate_markdown(MARKDOWN, "de", &MarkdownConfig::default()).await.unwrap();
+        assert_eq!(translated, "---\ntitle: Guide\n---\n# DE Install the tool\n\nDE Run `cargo build`, then read [the guide](https://example.com/a \"Guide\").\n\n```sh\necho hello\n```\n\n- [x] DE Done\n> DE Quoted *text*\n\n---\nDE | Name | Value |\n|------|------:|\n\n[guide]: https://example.com\n");
+    }
+}
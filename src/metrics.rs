@@ -0,0 +1,167 @@
+//! Metrics recorded with the `metrics` crate.
+//!
+//! With the `metrics` feature, the client records the following with the recorder the application
+//! installs, for example `metrics-exporter-prometheus`:
+//!
+//! - `REQUESTS`: a counter of HTTP requests, one per attempt, labelled with the `endpoint`, the
+//!   API method such as "translateText" or "glossaries.get", and the HTTP `status`, or "none" if
+//!   no response arrived.
+//! - `ERRORS`: a counter of failed requests, labelled with the `endpoint` and the `code`, the name
+//!   of the canonical error code such as "RESOURCE_EXHAUSTED", or "UNKNOWN" for connection
+//!   failures and unreadable responses.
+//! - `LATENCY`: a histogram of the time to receive each response in seconds, by `endpoint`.
+//! - `CHARACTERS`: a counter of the characters sent to translateText and detectLanguage and
+//!   their v2 counterparts, which is what the API bills, by `endpoint`. Only successful requests
+//!   are counted.
+//!
+//! Call `describe` once after installing the recorder to register the metrics' descriptions and
+//! units. Without the feature nothing is recorded.
+
+use std::time::Duration;
+
+use hyper::Method;
+
+use crate::Error;
+
+pub const REQUESTS: &str = "google_translation_requests_total";
+pub const ERRORS: &str = "google_translation_errors_total";
+pub const LATENCY: &str = "google_translation_request_duration_seconds";
+pub const CHARACTERS: &str = "google_translation_characters_total";
+
+/// Registers the descriptions and units of the client's metrics with the installed recorder.
+#[cfg(feature = "metrics")]
+pub fn describe() {
+    use ::metrics::{Unit, describe_counter, describe_histogram};
+
+    describe_counter!(REQUESTS, Unit::Count, "HTTP requests sent to the Cloud Translation API, by endpoint and status");
+    describe_counter!(ERRORS, Unit::Count, "Failed Cloud Translation API requests, by endpoint and error code");
+    describe_histogram!(LATENCY, Unit::Seconds, "Cloud Translation API response latency, by endpoint");
+    describe_counter!(CHARACTERS, Unit::Count, "Billable characters sent to the Cloud Translation API, by endpoint");
+}
+
+/// The API method `url` calls, such as "translateText", "glossaries.list" or "v2.detect".
+pub(crate) fn endpoint(method: &Method, url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let path = path.find("://").and_then(|i| path[i + 3..].find('/').map(|j| &path[i + 3 + j..])).unwrap_or(path);
+    if let Some(i) = path.find("/language/translate/v2") {
+        return match path[i + "/language/translate/v2".len()..].trim_matches('/') {
+            "" => "v2.translate".to_string(),
+            rest => format!("v2.{}", rest),
+        };
+    }
+    let last = path.rsplit('/').next().unwrap_or_default();
+    if let Some((_, custom_method)) = last.split_once(':') {
+        return custom_method.to_string();
+    }
+    // Resource names alternate between collections and ids, starting from "projects".
+    let segments: Vec<&str> = path.split('/').skip_while(|segment| *segment != "projects").filter(|s| !s.is_empty()).collect();
+    let (collection, is_collection) = match segments.len() {
+        0 => return "unknown".to_string(),
+        n if n % 2 == 1 => (segments[n - 1], true),
+        n => (segments[n - 2], false),
+    };
+    let verb = match (method, is_collection) {
+        (_, true) if collection == "supportedLanguages" => return "getSupportedLanguages".to_string(),
+        (&Method::GET, true) => "list",
+        (&Method::POST, true) => "create",
+        (&Method::GET, false) => "get",
+        (&Method::DELETE, false) => "delete",
+        (&Method::PATCH, false) => "patch",
+        (method, _) => return format!("{}.{}", collection, method.as_str().to_lowercase()),
+    };
+    format!("{}.{}", collection, verb)
+}
+
+/// Records a request to `url` that got a response with `status` after `latency`, or no response
+/// if `status` is None, and failed with `error` if it did.
+pub(crate) fn request(method: &Method, url: &str, status: Option<u16>, latency: Duration, error: Option<&Error>) {
+    #[cfg(not(feature = "metrics"))]
+    let _ = (method, url, status, latency, error);
+    #[cfg(feature = "metrics")]
+    {
+        let endpoint = endpoint(method, url);
+        let status = status.map_or_else(|| "none".to_string(), |status| status.to_string());
+        ::metrics::counter!(REQUESTS, "endpoint" => endpoint.clone(), "status" => status).increment(1);
+        ::metrics::histogram!(LATENCY, "endpoint" => endpoint.clone()).record(latency.as_secs_f64());
+        if let Some(error) = error {
+            let code = error.code().map_or("UNKNOWN", crate::ErrorCode::name);
+            ::metrics::counter!(ERRORS, "endpoint" => endpoint, "code" => code).increment(1);
+        }
+    }
+}
+
+/// Records `count` billable characters sent to `endpoint`.
+pub(crate) fn characters(endpoint: &'static str, count: usize) {
+    #[cfg(not(feature = "metrics"))]
+    let _ = (endpoint, count);
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!(CHARACTERS, "endpoint" => endpoint).increment(count as u64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint() {
+        let base = "https://translation.googleapis.com/v3/projects/p/locations/global";
+        assert_eq!(endpoint(&Method::POST, &format!("{}:translateText", base)), "translateText");
+        assert_eq!(endpoint(&Method::GET, &format!("{}/glossaries?pageSize=10", base)), "glossaries.list");
+        assert_eq!(endpoint(&Method::POST, &format!("{}/glossaries", base)), "glossaries.create");
+        assert_eq!(endpoint(&Method::GET, &format!("{}/glossaries/g", base)), "glossaries.get");
+        assert_eq!(endpoint(&Method::DELETE, &format!("{}/adaptiveMtDatasets/d", base)), "adaptiveMtDatasets.delete");
+        assert_eq!(endpoint(&Method::POST, &format!("{}/operations/1:wait", base)), "wait");
+        assert_eq!(endpoint(&Method::GET, &format!("{}/supportedLanguages", base)), "getSupportedLanguages");
+        assert_eq!(endpoint(&Method::POST, "http://127.0.0.1:1/language/translate/v2?key=k"), "v2.translate");
+        assert_eq!(endpoint(&Method::POST, "http://127.0.0.1:1/language/translate/v2/detect?key=k"), "v2.detect");
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_recorded_metrics() {
+        use std::sync::Arc;
+
+        use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+        use crate::transport::MockTransport;
+        use crate::{RetryPolicy, TranslateTextRequest, TranslationClient};
+
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let transport = Arc::new(MockTransport::new(|request| match request.json()["contents"][0].as_str() {
+            Some("fail") => (429, r#"{"error": {"code": 429, "message": "Quota", "status": "RESOURCE_EXHAUSTED"}}"#.to_string()),
+            _ => (200, r#"{"translations": [{"translatedText": "Hallo"}]}"#.to_string()),
+        }));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport)
+            .with_retry_policy(RetryPolicy { max_attempts: 1, ..RetryPolicy::default() });
+        let request = |text: &str| TranslateTextRequest::builder().content(text).target("de").build().unwrap();
+        ::metrics::with_local_recorder(&recorder, || {
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+            runtime.block_on(async {
+                client.translate_text(&request("Hello")).await.unwrap();
+                client.translate_text(&request("fail")).await.unwrap_err();
+            });
+        });
+
+        let mut values: Vec<(String, Vec<String>, String)> = snapshotter.snapshot().into_vec().into_iter()
+            .map(|(key, _, _, value)| {
+                let labels = key.key().labels().map(|label| format!("{}={}", label.key(), label.value())).collect();
+                let value = match value {
+                    DebugValue::Counter(count) => count.to_string(),
+                    DebugValue::Histogram(samples) => format!("{} samples", samples.len()),
+                    DebugValue::Gauge(value) => value.to_string(),
+                };
+                (key.key().name().to_string(), labels, value)
+            })
+            .collect();
+        values.sort();
+        let strings = |labels: &[&str]| labels.iter().map(|label| label.to_string()).collect::<Vec<_>>();
+        assert_eq!(values, vec![
+            (CHARACTERS.to_string(), strings(&["endpoint=translateText"]), "5".to_string()),
+            (ERRORS.to_string(), strings(&["endpoint=translateText", "code=RESOURCE_EXHAUSTED"]), "1".to_string()),
+            (LATENCY.to_string(), strings(&["endpoint=translateText"]), "2 samples".to_string()),
+            (REQUESTS.to_string(), strings(&["endpoint=translateText", "status=200"]), "1".to_string()),
+            (REQUESTS.to_string(), strings(&["endpoint=translateText", "status=429"]), "1".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,356 @@
+//! An in-process mock of the Translation REST surface, with the `mock-server` feature.
+//!
+//! Unlike `transport::MockTransport`, which answers every request with whatever its closure
+//! returns, a `MockServer` implements the API well enough for code built on this crate to be tested
+//! without Google: translateText answers each string with "[target] string", detectLanguage
+//! detects "en", getSupportedLanguages returns a few languages, glossaries can be created, listed,
+//! fetched and deleted, and their long-running operations can be fetched, listed, waited on,
+//! cancelled and deleted. Any API method, named as in the `metrics` module such as "translateText"
+//! or "glossaries.get", can be given canned responses with `on` or `on_response`, and the requests
+//! received can be checked with `requests_to` and `assert_called`.
+//!
+//! ```
+//! # use std::sync::Arc;
+//! # use google_translation::*;
+//! use google_translation::mock_server::MockServer;
+//!
+//! # async fn run() -> Result<()> {
+//! let server = Arc::new(MockServer::new());
+//! let client = TranslationClient::new("my-project", "global", "token").with_transport(server.clone());
+//! let request = TranslateTextRequest::builder().content("Hello").target("de").build()?;
+//! assert_eq!(client.translate_text(&request).await?.translations[0].translated_text, "[de] Hello");
+//!
+//! server.on_response("detectLanguage", 429, r#"{"error": {"code": 429, "status": "RESOURCE_EXHAUSTED"}}"#);
+//! let request = DetectLanguageRequest::builder().content("Hallo").build()?;
+//! assert!(client.detect_language(&request).await.unwrap_err().is_quota());
+//! server.assert_called("translateText", 1);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The server can also be reached over HTTP, for code that doesn't take a transport, by passing the
+//! address `MockServer::serve` returns to `TranslationClient::with_endpoint`.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use hyper::{Body, Request, Response};
+use serde_json::{Value, json};
+
+use crate::{BoxFuture, Error, ErrorCode, Glossary, Operation, Result, compression};
+use crate::metrics::endpoint;
+use crate::transport::{HttpTransport, MockRequest};
+
+/// The languages getSupportedLanguages returns.
+const LANGUAGES: &[(&str, &str)] = &[
+    ("de", "German"), ("en", "English"), ("es", "Spanish"), ("fr", "French"), ("it", "Italian"),
+    ("ja", "Japanese"), ("ko", "Korean"), ("pt", "Portuguese"), ("zh-CN", "Chinese (Simplified)"),
+];
+
+type Respond = dyn Fn(&MockRequest) -> (u16, String) + Send + Sync;
+
+/// A mock of the Translation API, see the module documentation. Share it in an `Arc` to inspect
+/// it while clients use it.
+#[derive(Default)]
+pub struct MockServer {
+    responders: Mutex<Vec<(String, Arc<Respond>)>>,
+    requests: Mutex<Vec<(String, MockRequest)>>,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    glossaries: BTreeMap<String, Value>,
+    operations: BTreeMap<String, Value>,
+    next_operation: u64,
+}
+
+impl MockServer {
+    pub fn new() -> MockServer {
+        MockServer::default()
+    }
+
+    /// Answers requests to the API method `endpoint` with the status and body `respond` returns,
+    /// instead of the built-in behaviour. The last responder set for a method wins.
+    pub fn on<F>(&self, endpoint: &str, respond: F)
+        where F: Fn(&MockRequest) -> (u16, String) + Send + Sync + 'static
+    {
+        self.responders.lock().unwrap().push((endpoint.to_string(), Arc::new(respond)));
+    }
+
+    /// Answers every request to the API method `endpoint` with `status` and `body`.
+    pub fn on_response(&self, endpoint: &str, status: u16, body: impl Into<String>) {
+        let body = body.into();
+        self.on(endpoint, move |_| (status, body.clone()));
+    }
+
+    /// Removes the responders set with `on` and `on_response`.
+    pub fn reset_responses(&self) {
+        self.responders.lock().unwrap().clear();
+    }
+
+    /// Stores `glossary`, as if it had been created.
+    pub fn add_glossary(&self, glossary: &Glossary) {
+        let value = serde_json::to_value(glossary).unwrap();
+        self.state.lock().unwrap().glossaries.insert(glossary.name.clone(), value);
+    }
+
+    /// Stores `operation`, so it can be fetched, listed and waited on.
+    pub fn add_operation(&self, operation: &Operation) {
+        let value = serde_json::to_value(operation).unwrap();
+        self.state.lock().unwrap().operations.insert(operation.name.clone(), value);
+    }
+
+    /// The requests received so far, oldest first.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().iter().map(|(_, request)| request.clone()).collect()
+    }
+
+    /// The requests to the API method `endpoint` received so far, oldest first.
+    pub fn requests_to(&self, endpoint: &str) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().iter()
+            .filter(|(name, _)| name == endpoint)
+            .map(|(_, request)| request.clone())
+            .collect()
+    }
+
+    /// Panics unless exactly `times` requests to the API method `endpoint` were received.
+    #[track_caller]
+    pub fn assert_called(&self, endpoint: &str, times: usize) {
+        let count = self.requests_to(endpoint).len();
+        assert_eq!(count, times, "expected {} request(s) to {}, got {}", times, endpoint, count);
+    }
+
+    /// Serves the mock on a local port and returns its address, such as "http://127.0.0.1:41235".
+    /// The server runs on the current Tokio runtime until the runtime shuts down.
+    pub fn serve(self: Arc<Self>) -> String {
+        use hyper::service::{make_service_fn, service_fn};
+
+        let make_service = make_service_fn(move |_| {
+            let server = self.clone();
+            async move {
+                Ok::<_, hyper::Error>(service_fn(move |request: Request<Body>| {
+                    let server = server.clone();
+                    async move {
+                        Ok::<_, hyper::Error>(server.send(request).await.unwrap_or_else(|e| {
+                            let (status, body) = error(ErrorCode::Internal, &e.to_string());
+                            Response::builder().status(status).body(Body::from(body)).unwrap()
+                        }))
+                    }
+                }))
+            }
+        });
+        let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let address = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+        address
+    }
+
+    fn respond(&self, endpoint: &str, request: &MockRequest) -> (u16, String) {
+        let responder = self.responders.lock().unwrap().iter().rev()
+            .find(|(name, _)| name == endpoint)
+            .map(|(_, respond)| respond.clone());
+        if let Some(respond) = responder {
+            return respond(request);
+        }
+        let path = request.uri.split(['?', '#']).next().unwrap_or_default();
+        let name = match path.find("projects/") {
+            Some(i) => path[i..].split(':').next().unwrap_or_default(),
+            None => "",
+        };
+        let query: Vec<(String, String)> = serde_urlencoded::from_str(request.uri.split_once('?').map_or("", |(_, query)| query))
+            .unwrap_or_default();
+        let param = |key: &str| query.iter().find(|(name, _)| name == key).map(|(_, value)| value.as_str());
+        let mut state = self.state.lock().unwrap();
+        match endpoint {
+            "translateText" => translate_text(&request.json()),
+            "detectLanguage" => ok(json!({"languages": [{"languageCode": "en", "confidence": 1.0}]})),
+            "getSupportedLanguages" => ok(json!({"languages": LANGUAGES.iter()
+                .map(|(code, name)| json!({"languageCode": code, "displayName": name, "supportSource": true, "supportTarget": true}))
+                .collect::<Vec<_>>()})),
+            "glossaries.create" => {
+                let mut glossary = request.json();
+                let glossary_name = glossary["name"].as_str().unwrap_or_default().to_string();
+                if state.glossaries.contains_key(&glossary_name) {
+                    return error(ErrorCode::AlreadyExists, &format!("Glossary {} already exists.", glossary_name));
+                }
+                glossary["entryCount"] = json!(0);
+                state.glossaries.insert(glossary_name.clone(), glossary.clone());
+                let parent = name.trim_end_matches("/glossaries");
+                ok(state.operation(parent, json!({"name": glossary_name, "state": "SUCCEEDED"}), glossary))
+            }
+            "glossaries.get" => match state.glossaries.get(name) {
+                Some(glossary) => ok(glossary.clone()),
+                None => not_found("Glossary", name),
+            },
+            "glossaries.list" => {
+                let parent = name.trim_end_matches("/glossaries");
+                let glossaries = state.glossaries.values().filter(|g| g["name"].as_str().is_some_and(|n| n.starts_with(parent)));
+                ok(page("glossaries", glossaries, param("pageSize"), param("pageToken")))
+            }
+            "glossaries.delete" => match state.glossaries.remove(name) {
+                Some(_) => {
+                    let parent = name.rsplitn(3, '/').nth(2).unwrap_or_default();
+                    ok(state.operation(parent, json!({"name": name, "state": "SUCCEEDED"}), json!({"name": name})))
+                }
+                None => not_found("Glossary", name),
+            },
+            "operations.get" | "wait" => match state.operations.get(name) {
+                Some(operation) => ok(operation.clone()),
+                None => not_found("Operation", name),
+            },
+            "operations.list" => {
+                let parent = name.trim_end_matches("/operations");
+                let operations = state.operations.values().filter(|o| o["name"].as_str().is_some_and(|n| n.starts_with(parent)));
+                ok(page("operations", operations, param("pageSize"), param("pageToken")))
+            }
+            "operations.delete" => match state.operations.remove(name) {
+                Some(_) => ok(json!({})),
+                None => not_found("Operation", name),
+            },
+            "cancel" => match state.operations.get_mut(name) {
+                Some(operation) => {
+                    if operation["done"] != json!(true) {
+                        operation["done"] = json!(true);
+                        operation["error"] = json!({"code": 1, "message": "Operation cancelled."});
+                    }
+                    ok(json!({}))
+                }
+                None => not_found("Operation", name),
+            },
+            _ => error(ErrorCode::Unimplemented, &format!("The mock server doesn't implement {}.", endpoint)),
+        }
+    }
+}
+
+impl State {
+    /// Stores a finished operation under `parent` and returns it.
+    fn operation(&mut self, parent: &str, metadata: Value, response: Value) -> Value {
+        self.next_operation += 1;
+        let name = format!("{}/operations/{}", parent, self.next_operation);
+        let operation = json!({"name": name, "metadata": metadata, "done": true, "response": response});
+        self.operations.insert(name, operation.clone());
+        operation
+    }
+}
+
+impl HttpTransport for MockServer {
+    fn send(&self, request: Request<Body>) -> BoxFuture<'_, Result<Response<Body>>> {
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let mut body = hyper::body::to_bytes(body).await.map_err(Error::HyperError)?.to_vec();
+            if compression::is_gzip(&parts.headers) {
+                body = compression::gunzip(&body)?;
+            }
+            let request = MockRequest { method: parts.method, uri: parts.uri.to_string(), headers: parts.headers, body };
+            let endpoint = endpoint(&request.method, &request.uri);
+            let (status, body) = self.respond(&endpoint, &request);
+            self.requests.lock().unwrap().push((endpoint, request));
+            Response::builder()
+                .status(status)
+                .header(hyper::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .map_err(|e| Error::Other(format!("invalid mock response: {}", e)))
+        })
+    }
+}
+
+fn translate_text(request: &Value) -> (u16, String) {
+    let target = request["targetLanguageCode"].as_str().unwrap_or_default();
+    let translations: Vec<Value> = request["contents"].as_array().into_iter().flatten()
+        .map(|content| {
+            let mut translation = json!({"translatedText": format!("[{}] {}", target, content.as_str().unwrap_or_default())});
+            if request["sourceLanguageCode"].is_null() {
+                translation["detectedLanguageCode"] = json!("en");
+            }
+            translation
+        })
+        .collect();
+    let mut response = json!({"translations": translations});
+    if !request["glossaryConfig"].is_null() {
+        response["glossaryTranslations"] = response["translations"].clone();
+    }
+    ok(response)
+}
+
+/// A page of `items`, whose page tokens are the index of the first item.
+fn page<'a>(field: &str, items: impl Iterator<Item = &'a Value>, page_size: Option<&str>, page_token: Option<&str>) -> Value {
+    let items: Vec<&Value> = items.collect();
+    let start = page_token.and_then(|token| token.parse().ok()).unwrap_or(0).min(items.len());
+    let size = page_size.and_then(|size| size.parse().ok()).filter(|size| *size > 0).unwrap_or(items.len());
+    let end = (start + size).min(items.len());
+    let mut page = json!({ field: items[start..end] });
+    if end < items.len() {
+        page["nextPageToken"] = json!(end.to_string());
+    }
+    page
+}
+
+fn ok(body: Value) -> (u16, String) {
+    (200, body.to_string())
+}
+
+fn not_found(kind: &str, name: &str) -> (u16, String) {
+    error(ErrorCode::NotFound, &format!("{} {} not found.", kind, name))
+}
+
+fn error(code: ErrorCode, message: &str) -> (u16, String) {
+    (code.http_status(), json!({"error": {"code": code.http_status(), "message": message, "status": code.name()}}).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+
+    use super::*;
+    use crate::*;
+
+    #[tokio::test]
+    async fn test_mock_server() {
+        let server = Arc::new(MockServer::new());
+        let client = TranslationClient::new("p", "global", "token").with_transport(server.clone());
+        let request = TranslateTextRequest::builder().content("Hello").content("Bye").target("de").build().unwrap();
+        let response = client.translate_text(&request).await.unwrap();
+        assert_eq!(response.translations[1].translated_text, "[de] Bye");
+        assert_eq!(response.translations[1].detected_language_code.as_deref(), Some("en"));
+        let languages = client.get_supported_languages(&GetSupportedLanguagesQueryParams { display_language_code: None, model: None }).await.unwrap();
+        assert!(languages.languages.iter().any(|language| language.language_code == "ja"));
+
+        let input_config = GlossaryInputConfig { gcs_source: GcsSource { input_uri: "gs://b/g.tsv".to_string() } };
+        for id in ["g1", "g2", "g3"] {
+            let glossary = Glossary::new(format!("projects/p/locations/global/glossaries/{}", id), input_config.clone(),
+                LanguageCodePair::new("en", "de"));
+            let operation = client.create_glossary(&glossary).await.unwrap();
+            assert_eq!(operation.done, Some(true));
+            assert_eq!(client.get_operation(&operation.name).await.unwrap(), operation);
+        }
+        assert!(client.create_glossary(&Glossary::new("projects/p/locations/global/glossaries/g1", input_config,
+            LanguageCodePair::new("en", "de"))).await.unwrap_err().code() == Some(ErrorCode::AlreadyExists));
+        let params = ListGlossariesQueryParams { page_size: Some(2), page_token: None, filter: None };
+        let names: Vec<String> = client.list_glossaries_stream(&params).map_ok(|glossary| glossary.name).try_collect().await.unwrap();
+        assert_eq!(names.len(), 3);
+        client.delete_glossary("projects/p/locations/global/glossaries/g2").await.unwrap();
+        let error = client.delete_glossary("projects/p/locations/global/glossaries/g2").await.unwrap_err();
+        assert!(error.is_not_found());
+        let operations = client.list_operations(&ListOperationsQueryParams::default()).await.unwrap();
+        assert_eq!(operations.operations.len(), 4);
+
+        let pending: Operation = serde_json::from_value(json!({"name": "projects/p/locations/global/operations/x", "metadata": {}})).unwrap();
+        server.add_operation(&pending);
+        client.cancel_operation(&pending.name).await.unwrap();
+        assert_eq!(client.get_operation(&pending.name).await.unwrap().error.unwrap().code, 1);
+
+        server.on("translateText", |request| (200, json!({"translations": [{"translatedText": request.json()["contents"][0]}]}).to_string()));
+        assert_eq!(client.translate_text(&request).await.unwrap().translations[0].translated_text, "Hello");
+        server.reset_responses();
+        server.assert_called("translateText", 2);
+        server.assert_called("glossaries.create", 4);
+        assert_eq!(server.requests_to("cancel")[0].headers["authorization"], "Bearer token");
+
+        let endpoint = server.clone().serve();
+        let client = TranslationClient::new("p", "global", "token").with_endpoint(&endpoint);
+        let response = client.detect_language(&DetectLanguageRequest::builder().content("Hi").build().unwrap()).await.unwrap();
+        assert_eq!(response.languages[0].language_code, "en");
+        server.assert_called("detectLanguage", 1);
+    }
+}
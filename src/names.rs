@@ -0,0 +1,274 @@
+//! Typed resource names, so names don't have to be put together with `format!`.
+//!
+//! ```no_run
+//! # use google_translation::*;
+//! # fn run() -> Result<()> {
+//! let parent = ProjectLocation::new("my-project", "us-central1")?;
+//! let glossary = parent.glossary("terms")?;
+//! assert_eq!(glossary.to_string(), "projects/my-project/locations/us-central1/glossaries/terms");
+//! let parsed: GlossaryName = "projects/my-project/locations/us-central1/glossaries/terms".parse()?;
+//! assert_eq!(parsed, glossary);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The names convert into `String` and implement `AsRef<str>`, so they can be passed wherever the
+//! client takes a name, and to the request builders' `model` and glossary fields.
+
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+/// A project and location: `projects/{project-id}/locations/{location-id}`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct ProjectLocation(String);
+
+impl ProjectLocation {
+    pub fn new(project_id: &str, location_id: &str) -> Result<ProjectLocation> {
+        ProjectLocation::try_from(format!("projects/{}/locations/{}", project_id, location_id))
+    }
+
+    pub fn project_id(&self) -> &str {
+        segment(&self.0, 1)
+    }
+
+    pub fn location_id(&self) -> &str {
+        segment(&self.0, 3)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The name of the glossary `glossary_id` in this project and location.
+    pub fn glossary(&self, glossary_id: &str) -> Result<GlossaryName> {
+        GlossaryName::new(self, glossary_id)
+    }
+
+    /// The name of the model `model_id` in this project and location, for example "general/nmt".
+    pub fn model(&self, model_id: &str) -> Result<ModelName> {
+        ModelName::new(self, model_id)
+    }
+
+    /// The name of the long-running operation `operation_id` in this project and location.
+    pub fn operation(&self, operation_id: &str) -> Result<OperationName> {
+        OperationName::new(self, operation_id)
+    }
+}
+
+impl TryFrom<String> for ProjectLocation {
+    type Error = Error;
+
+    fn try_from(name: String) -> Result<ProjectLocation> {
+        if is_valid(&name, None) {
+            Ok(ProjectLocation(name))
+        } else {
+            Err(Error::InvalidRequest(format!(
+                "invalid location name {:?}, expected projects/{{project-id}}/locations/{{location-id}}", name)))
+        }
+    }
+}
+
+macro_rules! define_names {
+    ($(
+        $(#[$meta:meta])*
+        $name:ident { collection: $collection:literal, id: $id:ident, nested: $nested:literal }
+    )*) => {
+        $(
+            $(#[$meta])*
+            #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+            #[serde(try_from = "String", into = "String")]
+            pub struct $name(String);
+
+            impl $name {
+                pub fn new(parent: &ProjectLocation, $id: &str) -> Result<$name> {
+                    $name::try_from(format!("{}/{}/{}", parent, $collection, $id))
+                }
+
+                /// The project and location the resource belongs to.
+                pub fn parent(&self) -> ProjectLocation {
+                    ProjectLocation(self.0[..self.0.len() - self.$id().len() - $collection.len() - 2].to_string())
+                }
+
+                pub fn project_id(&self) -> &str {
+                    segment(&self.0, 1)
+                }
+
+                pub fn location_id(&self) -> &str {
+                    segment(&self.0, 3)
+                }
+
+                pub fn $id(&self) -> &str {
+                    self.0.splitn(6, '/').nth(5).unwrap()
+                }
+
+                pub fn as_str(&self) -> &str {
+                    &self.0
+                }
+            }
+
+            impl TryFrom<String> for $name {
+                type Error = Error;
+
+                fn try_from(name: String) -> Result<$name> {
+                    if is_valid(&name, Some(($collection, $nested))) {
+                        Ok($name(name))
+                    } else {
+                        Err(Error::InvalidRequest(format!(
+                            concat!("invalid ", stringify!($name), " {:?}, expected projects/{{project-id}}/locations/{{location-id}}/",
+                                $collection, "/{{", stringify!($id), "}}"),
+                            name)))
+                    }
+                }
+            }
+        )*
+
+        impl_common! { ProjectLocation $($name)* }
+    };
+}
+
+macro_rules! impl_common {
+    ($($name:ident)*) => {
+        $(
+            impl TryFrom<&str> for $name {
+                type Error = Error;
+
+                fn try_from(name: &str) -> Result<$name> {
+                    $name::try_from(name.to_string())
+                }
+            }
+
+            impl FromStr for $name {
+                type Err = Error;
+
+                fn from_str(name: &str) -> Result<$name> {
+                    $name::try_from(name)
+                }
+            }
+
+            impl From<$name> for String {
+                fn from(name: $name) -> String {
+                    name.0
+                }
+            }
+
+            impl AsRef<str> for $name {
+                fn as_ref(&self) -> &str {
+                    &self.0
+                }
+            }
+
+            impl std::fmt::Display for $name {
+                fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    f.write_str(&self.0)
+                }
+            }
+        )*
+    };
+}
+
+define_names! {
+    /// A glossary: `projects/{project-id}/locations/{location-id}/glossaries/{glossary-id}`.
+    GlossaryName { collection: "glossaries", id: glossary_id, nested: false }
+
+    /// A model: `projects/{project-id}/locations/{location-id}/models/{model-id}`. The model id of
+    /// the built-in models has several segments, for example "general/nmt" or "language-detection/default".
+    ModelName { collection: "models", id: model_id, nested: true }
+
+    /// A long-running operation: `projects/{project-id}/locations/{location-id}/operations/{operation-id}`.
+    OperationName { collection: "operations", id: operation_id, nested: false }
+}
+
+impl ModelName {
+    /// The built-in Neural Machine Translation model, "general/nmt".
+    pub fn general_nmt(parent: &ProjectLocation) -> ModelName {
+        ModelName(format!("{}/models/general/nmt", parent))
+    }
+
+    /// The built-in language detection model, "language-detection/default".
+    pub fn language_detection(parent: &ProjectLocation) -> ModelName {
+        ModelName(format!("{}/models/language-detection/default", parent))
+    }
+}
+
+/// Checks `name` against `projects/{project-id}/locations/{location-id}`, followed by
+/// `/{collection}/{id}` if `collection` is given. Nested ids may consist of several segments.
+fn is_valid(name: &str, collection: Option<(&str, bool)>) -> bool {
+    fn is_id(segment: Option<&str>) -> bool {
+        segment.is_some_and(|segment| !segment.is_empty() && !segment.chars().any(char::is_whitespace))
+    }
+    let mut segments = name.split('/');
+    if segments.next() != Some("projects") || !is_id(segments.next())
+        || segments.next() != Some("locations") || !is_id(segments.next())
+    {
+        return false;
+    }
+    match collection {
+        None => segments.next().is_none(),
+        Some((collection, nested)) => {
+            if segments.next() != Some(collection) {
+                return false;
+            }
+            let id: Vec<&str> = segments.collect();
+            !id.is_empty() && (nested || id.len() == 1) && id.into_iter().all(|segment| is_id(Some(segment)))
+        }
+    }
+}
+
+fn segment(name: &str, index: usize) -> &str {
+    name.split('/').nth(index).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glossary_name() {
+        let parent = ProjectLocation::new("p", "us-central1").unwrap();
+        let name = parent.glossary("terms").unwrap();
+        assert_eq!(name.as_str(), "projects/p/locations/us-central1/glossaries/terms");
+        assert_eq!(name.project_id(), "p");
+        assert_eq!(name.location_id(), "us-central1");
+        assert_eq!(name.glossary_id(), "terms");
+        assert_eq!(name.parent(), parent);
+        assert_eq!(name.to_string().parse::<GlossaryName>().unwrap(), name);
+
+        assert!(parent.glossary("").is_err());
+        assert!(parent.glossary("a/b").is_err());
+        assert!(ProjectLocation::new("p", "").is_err());
+        assert!("projects/p/locations/l/models/m".parse::<GlossaryName>().is_err());
+        assert!(matches!("glossaries/terms".parse::<GlossaryName>(), Err(Error::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn test_model_name() {
+        let parent = ProjectLocation::new("p", "global").unwrap();
+        let nmt = ModelName::general_nmt(&parent);
+        assert_eq!(nmt.to_string(), "projects/p/locations/global/models/general/nmt");
+        assert_eq!(nmt.model_id(), "general/nmt");
+        assert_eq!(nmt.parent(), parent);
+        assert_eq!(parent.model("general/nmt").unwrap(), nmt);
+        assert!(parent.model("general/").is_err());
+    }
+
+    #[test]
+    fn test_name_serde() {
+        let name: OperationName = serde_json::from_str(r#""projects/p/locations/global/operations/42""#).unwrap();
+        assert_eq!(name.operation_id(), "42");
+        assert_eq!(serde_json::to_string(&name).unwrap(), r#""projects/p/locations/global/operations/42""#);
+        assert!(serde_json::from_str::<OperationName>(r#""operations/42""#).is_err());
+
+        let request = crate::TranslateTextRequest::builder()
+            .content("Hello")
+            .target("de")
+            .model(ModelName::general_nmt(&name.parent()))
+            .build()
+            .unwrap();
+        assert_eq!(request.model.as_deref(), Some("projects/p/locations/global/models/general/nmt"));
+    }
+}
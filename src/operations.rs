@@ -0,0 +1,220 @@
+//! Working with the long-running operations of a project and location.
+//!
+//! `OperationFilter` composes the filter expressions `TranslationClient::list_operations` accepts,
+//! so they don't have to be written by hand:
+//!
+//! ```no_run
+//! # async fn run(client: google_translation::TranslationClient) -> google_translation::Result<()> {
+//! use google_translation::{ListOperationsQueryParams, OperationState};
+//! use google_translation::operations::OperationFilter;
+//!
+//! let filter = OperationFilter::new().done(false).state(OperationState::Running);
+//! let params = ListOperationsQueryParams::builder().filter(filter).build()?;
+//! let running = client.list_operations(&params).await?.operations;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Finished operations stay in the list until they are deleted. `cleanup` deletes the finished
+//! operations submitted longer ago than a given age.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::time::Duration;
+
+use futures::stream::TryStreamExt;
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::{Error, ListOperationsQueryParams, Operation, Result, TranslationClient};
+
+/// A filter for listing operations, matching the operations that meet all of its conditions. An
+/// empty filter matches every operation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OperationFilter {
+    conditions: Vec<String>,
+}
+
+impl OperationFilter {
+    pub fn new() -> OperationFilter {
+        OperationFilter::default()
+    }
+
+    /// Only operations that are done, or only those still running if `done` is false.
+    pub fn done(self, done: bool) -> OperationFilter {
+        self.condition(format!("done={}", done))
+    }
+
+    /// Only operations whose metadata reports `state`, an `OperationState` or its name such as
+    /// "RUNNING".
+    pub fn state(self, state: impl AsRef<str>) -> OperationFilter {
+        self.condition(format!("metadata.state={}", state.as_ref()))
+    }
+
+    /// Only operations whose metadata has the type URL `type_url`, for example
+    /// "type.googleapis.com/google.cloud.translation.v3beta1.BatchTranslateMetadata".
+    pub fn metadata_type(self, type_url: impl AsRef<str>) -> OperationFilter {
+        self.field("metadata.@type", type_url)
+    }
+
+    /// Only operations whose `field`, a path such as "metadata.state", equals the string `value`.
+    pub fn field(self, field: &str, value: impl AsRef<str>) -> OperationFilter {
+        let value = value.as_ref().replace('\\', "\\\\").replace('"', "\\\"");
+        self.condition(format!("{}=\"{}\"", field, value))
+    }
+
+    fn condition(mut self, condition: String) -> OperationFilter {
+        self.conditions.push(condition);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty()
+    }
+}
+
+impl fmt::Display for OperationFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.conditions.join(" AND "))
+    }
+}
+
+impl From<OperationFilter> for String {
+    fn from(filter: OperationFilter) -> String {
+        filter.to_string()
+    }
+}
+
+/// The operations `cleanup` deleted, and those it failed to delete.
+#[derive(Debug, Default)]
+pub struct CleanupReport {
+    /// The names of the deleted operations.
+    pub deleted: Vec<String>,
+    /// The names of the operations that matched but couldn't be deleted, with the errors.
+    pub failed: Vec<(String, Error)>,
+}
+
+/// Deletes the operations of the client's project and location that match `filter`, are done, and
+/// were submitted more than `older_than` ago, going through every page of the list. Running
+/// operations and those whose metadata has no submit time are left alone.
+///
+/// Deleting an operation only forgets it; it doesn't undo its effects. A failed deletion doesn't
+/// stop the cleanup and is reported in `CleanupReport::failed`.
+pub async fn cleanup(client: &TranslationClient, older_than: Duration, filter: &OperationFilter) -> Result<CleanupReport> {
+    let params = ListOperationsQueryParams {
+        filter: Some(filter.to_string()).filter(|filter| !filter.is_empty()),
+        ..ListOperationsQueryParams::default()
+    };
+    // Collect the list first so that deleting doesn't shift the pages.
+    let operations: Vec<Operation> = client.list_operations_stream(&params).try_collect().await?;
+    let now = SystemTime::now();
+    let mut report = CleanupReport::default();
+    for operation in operations {
+        let old_enough = submit_time(&operation)
+            .and_then(|submitted| now.duration_since(submitted).ok())
+            .is_some_and(|age| age > older_than);
+        if operation.done != Some(true) || !old_enough {
+            continue;
+        }
+        match client.delete_operation(&operation.name).await {
+            Ok(()) => report.deleted.push(operation.name),
+            Err(Error::Cancelled) => return Err(Error::Cancelled),
+            Err(e) => report.failed.push((operation.name, e)),
+        }
+    }
+    Ok(report)
+}
+
+/// The submit time in the metadata of `operation`, which every Translation API operation has.
+fn submit_time(operation: &Operation) -> Option<SystemTime> {
+    operation.metadata.get("submitTime").or_else(|| operation.metadata.get("createTime"))
+        .and_then(|time| time.as_str())
+        .and_then(parse_timestamp)
+}
+
+/// Parses an RFC 3339 timestamp in UTC, such as "2014-10-02T15:01:23.045123456Z".
+fn parse_timestamp(timestamp: &str) -> Option<SystemTime> {
+    let (date, time) = timestamp.strip_suffix('Z')?.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut time = time.splitn(3, ':').map(|part| part.parse::<u64>().ok());
+    let (hours, minutes, seconds) = (time.next()??, time.next()??, time.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+    // Howard Hinnant's days_from_civil.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = u64::try_from(era * 146_097 + day_of_era - 719_468).ok()?;
+    let nanos = match fraction {
+        "" => 0,
+        fraction if fraction.len() <= 9 => fraction.parse::<u32>().ok()? * 10u32.pow(9 - fraction.len() as u32),
+        _ => return None,
+    };
+    Some(UNIX_EPOCH + Duration::new(days * 86_400 + hours * 3600 + minutes * 60 + seconds, nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::transport::MockTransport;
+
+    #[test]
+    fn test_operation_filter() {
+        assert_eq!(OperationFilter::new().to_string(), "");
+        let filter = OperationFilter::new()
+            .done(true)
+            .state(crate::OperationState::Succeeded)
+            .field("metadata.note", r#"say "hi" \o/"#);
+        assert_eq!(filter.to_string(), r#"done=true AND metadata.state=SUCCEEDED AND metadata.note="say \"hi\" \\o/""#);
+
+        let params = ListOperationsQueryParams::builder().filter(OperationFilter::new().done(false)).build().unwrap();
+        assert_eq!(params.filter.as_deref(), Some("done=false"));
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Some(UNIX_EPOCH));
+        assert_eq!(parse_timestamp("2024-02-29T12:34:56.5Z"), Some(UNIX_EPOCH + Duration::new(1_709_210_096, 500_000_000)));
+        assert_eq!(parse_timestamp("2024-02-29T12:34:56+01:00"), None);
+        assert_eq!(parse_timestamp("2024-13-01T00:00:00Z"), None);
+    }
+
+    #[tokio::test]
+    async fn test_cleanup() {
+        let transport = Arc::new(MockTransport::new(|request| {
+            let operation = |id: u32, done: bool, submit_time: &str| serde_json::json!({
+                "name": format!("projects/p/locations/global/operations/{}", id),
+                "metadata": {"state": "SUCCEEDED", "submitTime": submit_time},
+                "done": done,
+            });
+            match (request.method.as_str(), request.uri.as_str()) {
+                ("GET", uri) if uri.contains("pageToken=2") => (200, serde_json::json!({
+                    "operations": [operation(4, true, "2020-01-01T00:00:00Z"), operation(5, true, "2020-01-01T00:00:00Z")],
+                }).to_string()),
+                ("GET", uri) => {
+                    assert!(uri.ends_with("/operations?filter=metadata.state%3DSUCCEEDED"));
+                    (200, serde_json::json!({
+                        "operations": [operation(1, true, "2020-01-01T00:00:00Z"), operation(2, false, "2020-01-01T00:00:00Z"),
+                            operation(3, true, "2999-01-01T00:00:00Z")],
+                        "nextPageToken": "2",
+                    }).to_string())
+                }
+                (_, uri) if uri.ends_with("/5") => (500, r#"{"error": {"code": 500, "message": "Internal error.", "status": "INTERNAL"}}"#.to_string()),
+                _ => (200, "{}".to_string()),
+            }
+        }));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone())
+            .with_retry_policy(crate::retry::RetryPolicy { max_attempts: 1, ..Default::default() });
+        let report = cleanup(&client, Duration::from_secs(86_400), &OperationFilter::new().state("SUCCEEDED")).await.unwrap();
+        assert_eq!(report.deleted, vec!["projects/p/locations/global/operations/1", "projects/p/locations/global/operations/4"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "projects/p/locations/global/operations/5");
+        assert_eq!(transport.requests().iter().filter(|request| request.method == hyper::Method::DELETE).count(), 3);
+    }
+}
@@ -0,0 +1,126 @@
+//! OpenTelemetry client spans, with the `opentelemetry` feature.
+//!
+//! Every API call runs inside a client span from the global tracer provider, named after the API
+//! method, such as "google.cloud.translation/translateText", and a child of the current context.
+//! Retries are part of the same span. The span's context is injected into the headers of each
+//! request with the global propagator, so the propagator must be set, usually to
+//! `TraceContextPropagator`, for the context to reach the API.
+
+use hyper::Method;
+use hyper::header::{HeaderMap, HeaderName, HeaderValue};
+use opentelemetry::{Context, KeyValue, global};
+use opentelemetry::propagation::Injector;
+use opentelemetry::trace::{SpanKind, Status, TraceContextExt, Tracer, get_active_span};
+
+use crate::Error;
+use crate::trace::redact_url;
+
+const PEER_SERVICE: &str = "translate.googleapis.com";
+
+/// Starts the client span of a call to `url`, returning a context with the span active.
+pub(crate) fn start_span(method: &Method, url: &str) -> Context {
+    let rpc_method = crate::metrics::endpoint(method, url);
+    let mut attributes = vec![
+        KeyValue::new("peer.service", PEER_SERVICE),
+        KeyValue::new("rpc.system", "http"),
+        KeyValue::new("rpc.service", "google.cloud.translation"),
+        KeyValue::new("rpc.method", rpc_method.clone()),
+        KeyValue::new("http.request.method", method.as_str().to_string()),
+        KeyValue::new("url.full", redact_url(url)),
+    ];
+    if let Ok(uri) = url.parse::<hyper::Uri>() {
+        attributes.extend(uri.host().map(|host| KeyValue::new("server.address", host.to_string())));
+    }
+    let tracer = global::tracer("google-translation");
+    let span = tracer.span_builder(format!("google.cloud.translation/{}", rpc_method))
+        .with_kind(SpanKind::Client)
+        .with_attributes(attributes)
+        .start(&tracer);
+    Context::current_with_span(span)
+}
+
+/// Adds the context of the active span to `headers`.
+pub(crate) fn inject(headers: &mut HeaderMap) {
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&Context::current(), &mut HeaderInjector(headers)));
+}
+
+/// Records the HTTP status of a response on the active span.
+pub(crate) fn response(status: u16) {
+    get_active_span(|span| span.set_attribute(KeyValue::new("http.response.status_code", status as i64)));
+}
+
+/// Records on the active span that the request is sent again for the `attempt`th time.
+pub(crate) fn retry(attempt: u32) {
+    get_active_span(|span| span.set_attribute(KeyValue::new("http.request.resend_count", (attempt - 1) as i64)));
+}
+
+/// Ends the span of `context`, recording `error` if the call failed.
+pub(crate) fn end_span(context: &Context, error: Option<&Error>) {
+    let span = context.span();
+    if let Some(error) = error {
+        let error_type = error.code().map_or("UNKNOWN", crate::ErrorCode::name);
+        span.set_attribute(KeyValue::new("error.type", error_type));
+        span.set_status(Status::error(error.to_string()));
+    }
+    span.end();
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(&value)) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use opentelemetry::trace::{SpanKind, Status};
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+
+    use crate::transport::MockTransport;
+    use crate::{RetryPolicy, TranslateTextRequest, TranslationClient};
+
+    #[tokio::test]
+    async fn test_client_spans() {
+        let exporter = InMemorySpanExporter::default();
+        opentelemetry::global::set_tracer_provider(SdkTracerProvider::builder().with_simple_exporter(exporter.clone()).build());
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+        let transport = Arc::new(MockTransport::new(|request| match request.json()["contents"][0].as_str() {
+            Some("fail") => (400, r#"{"error": {"code": 400, "message": "Bad", "status": "INVALID_ARGUMENT"}}"#.to_string()),
+            _ => (200, r#"{"translations": [{"translatedText": "Hallo"}]}"#.to_string()),
+        }));
+        let client = TranslationClient::new("otel-project", "global", "token").with_transport(transport.clone())
+            .with_retry_policy(RetryPolicy { max_attempts: 1, ..RetryPolicy::default() });
+        let request = |text: &str| TranslateTextRequest::builder().content(text).target("de").build().unwrap();
+        client.translate_text(&request("Hello")).await.unwrap();
+        client.translate_text(&request("fail")).await.unwrap_err();
+
+        // Other tests may create spans with the global provider too.
+        let spans: Vec<_> = exporter.get_finished_spans().unwrap().into_iter()
+            .filter(|span| span.attributes.iter().any(|kv| kv.key.as_str() == "url.full" && kv.value.as_str().contains("otel-project")))
+            .collect();
+        assert_eq!(spans.len(), 2);
+        let attribute = |i: usize, key: &str| spans[i].attributes.iter().find(|kv| kv.key.as_str() == key).map(|kv| kv.value.to_string());
+        assert_eq!(spans[0].name, "google.cloud.translation/translateText");
+        assert_eq!(spans[0].span_kind, SpanKind::Client);
+        assert_eq!(attribute(0, "rpc.method").as_deref(), Some("translateText"));
+        assert_eq!(attribute(0, "peer.service").as_deref(), Some("translate.googleapis.com"));
+        assert_eq!(attribute(0, "http.response.status_code").as_deref(), Some("200"));
+        assert_eq!(spans[0].status, Status::Unset);
+        assert_eq!(attribute(1, "http.response.status_code").as_deref(), Some("400"));
+        assert_eq!(attribute(1, "error.type").as_deref(), Some("INVALID_ARGUMENT"));
+        assert!(matches!(spans[1].status, Status::Error { .. }));
+
+        let traceparent = transport.requests()[0].headers["traceparent"].to_str().unwrap().to_string();
+        let trace_id = spans[0].span_context.trace_id().to_string();
+        let span_id = spans[0].span_context.span_id().to_string();
+        assert_eq!(traceparent, format!("00-{}-{}-01", trace_id, span_id));
+    }
+}
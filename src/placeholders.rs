@@ -0,0 +1,197 @@
+//! Keeping format string placeholders intact through translation.
+//!
+//! Machine translation tends to mangle placeholders in UI strings: `{name}` gets translated,
+//! `%s` loses its percent sign, `{{count}}` gains spaces. With
+//! `TranslationClient::with_placeholder_protection` enabled, `translate_text` replaces each
+//! placeholder before sending the text and puts the original back into the translation:
+//!
+//! - in HTML content, the default for translateText, placeholders are wrapped in
+//!   `<span translate="no" data-placeholder="0">…</span>`, which the API leaves untranslated;
+//! - in plain text, placeholders are replaced with tokens such as `__PH0__`.
+//!
+//! ```
+//! use google_translation::placeholders::Placeholders;
+//!
+//! let placeholders = Placeholders::default();
+//! let protected = placeholders.protect("Hello {name}, you have %d messages", false);
+//! assert_eq!(protected.text, "Hello __PH0__, you have __PH1__ messages");
+//! assert_eq!(protected.restore("Hallo __PH0__, Sie haben __PH1__ Nachrichten"),
+//!     "Hallo {name}, Sie haben %d Nachrichten");
+//! ```
+
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+
+use crate::*;
+
+/// The patterns `Placeholders::default` detects: `{{var}}`, `${var}`, `{name}` or `{0}`, and
+/// printf-style conversions such as `%s`, `%1$d` or `%.2f`.
+pub const DEFAULT_PATTERNS: &[&str] = &[
+    r"\{\{\s*[\w.-]+\s*\}\}",
+    r"\$\{[\w.-]+\}",
+    r"\{[\w.:-]*\}",
+    r"%(?:\d+\$)?[-+ 0#]*\d*(?:\.\d+)?[sdifuxXeEgGc@]",
+];
+
+/// Detects placeholders with a set of regular expressions, see the module documentation.
+#[derive(Clone, Debug)]
+pub struct Placeholders {
+    patterns: Vec<String>,
+    regex: Option<Regex>,
+}
+
+impl Default for Placeholders {
+    fn default() -> Placeholders {
+        DEFAULT_PATTERNS.iter().try_fold(Placeholders::empty(), |placeholders, pattern| placeholders.with_pattern(pattern))
+            .unwrap()
+    }
+}
+
+/// Text with its placeholders replaced, as returned by `Placeholders::protect`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Protected {
+    /// The text to translate.
+    pub text: String,
+    /// The placeholders, in the order they appear in the original text.
+    pub placeholders: Vec<String>,
+    html: bool,
+}
+
+impl Placeholders {
+    /// Detects no placeholders until patterns are added with `with_pattern`.
+    pub fn empty() -> Placeholders {
+        Placeholders { patterns: Vec::new(), regex: None }
+    }
+
+    /// Also detects matches of the regular expression `pattern`, for example `r":\w+"` for
+    /// `:name` placeholders. Where patterns overlap, the one added first wins.
+    pub fn with_pattern(mut self, pattern: &str) -> Result<Placeholders> {
+        Regex::new(pattern).map_err(|e| Error::InvalidRequest(format!("invalid placeholder pattern {:?}: {}", pattern, e)))?;
+        self.patterns.push(pattern.to_string());
+        let combined = self.patterns.iter().map(|pattern| format!("(?:{})", pattern)).collect::<Vec<_>>().join("|");
+        self.regex = Some(Regex::new(&combined).map_err(|e| Error::InvalidRequest(e.to_string()))?);
+        Ok(self)
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Replaces the placeholders in `text`, with notranslate spans if `html` is set, otherwise with tokens.
+    pub fn protect(&self, text: &str, html: bool) -> Protected {
+        let mut placeholders = Vec::new();
+        let text = match &self.regex {
+            Some(regex) => regex.replace_all(text, |captures: &Captures| {
+                let placeholder = &captures[0];
+                let i = placeholders.len();
+                placeholders.push(placeholder.to_string());
+                if html {
+                    format!(r#"<span translate="no" data-placeholder="{}">{}</span>"#, i, placeholder)
+                } else {
+                    format!("__PH{}__", i)
+                }
+            }).into_owned(),
+            None => text.to_string(),
+        };
+        Protected { text, placeholders, html }
+    }
+}
+
+impl Protected {
+    /// Puts the placeholders back into `translated`, the translation of `text`. Placeholders the
+    /// translation lost are logged and left out.
+    pub fn restore(&self, translated: &str) -> String {
+        if self.placeholders.is_empty() {
+            return translated.to_string();
+        }
+        let mut restored = 0;
+        let pattern = if self.html { &*HTML_PLACEHOLDER } else { &*TOKEN_PLACEHOLDER };
+        let text = pattern.replace_all(translated, |captures: &Captures| {
+            match captures[1].parse::<usize>().ok().and_then(|i| self.placeholders.get(i)) {
+                Some(placeholder) => {
+                    restored += 1;
+                    placeholder.clone()
+                }
+                None => captures[0].to_string(),
+            }
+        }).into_owned();
+        if restored < self.placeholders.len() {
+            trace::placeholders_lost(self.placeholders.len() - restored);
+        }
+        text
+    }
+}
+
+static HTML_PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)<span\b[^>]*\bdata-placeholder\s*=\s*"(\d+)"[^>]*>.*?</span>"#).unwrap()
+});
+
+static TOKEN_PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)__\s*PH\s*(\d+)\s*__").unwrap()
+});
+
+/// Translates `request` with its placeholders protected, see the module documentation.
+pub(crate) async fn translate_text_protected(client: &TranslationClient, placeholders: &Placeholders,
+        request: &TranslateTextRequest)
+    -> Result<TranslateTextResponse>
+{
+    let html = request.mime_type != Some(MimeType::Plain);
+    let protected: Vec<Protected> = request.contents.iter().map(|content| placeholders.protect(content, html)).collect();
+    let protected_request = TranslateTextRequest {
+        contents: protected.iter().map(|protected| protected.text.clone()).collect(),
+        ..request.clone()
+    };
+    let mut response = client.translate_text_unprotected(&protected_request).await?;
+    let restore = |translations: &mut Vec<Translation>| {
+        for (translation, protected) in translations.iter_mut().zip(&protected) {
+            translation.translated_text = protected.restore(&translation.translated_text);
+        }
+    };
+    restore(&mut response.translations);
+    if let Some(glossary_translations) = &mut response.glossary_translations {
+        restore(glossary_translations);
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::transport::MockTransport;
+
+    #[test]
+    fn test_protect() {
+        let placeholders = Placeholders::default();
+        let protected = placeholders.protect("{{count}} files in ${dir}, {0} of {total}: %1$s %.2f%", false);
+        assert_eq!(protected.text, "__PH0__ files in __PH1__, __PH2__ of __PH3__: __PH4__ __PH5__%");
+        assert_eq!(protected.placeholders, vec!["{{count}}", "${dir}", "{0}", "{total}", "%1$s", "%.2f"]);
+        assert_eq!(protected.restore("__PH3__ von __ph2__: __ PH4 __ __PH5__% __PH1__ __PH0__ __PH9__"),
+            "{total} von {0}: %1$s %.2f% ${dir} {{count}} __PH9__");
+
+        let protected = placeholders.protect("<b>Hello {name}</b>", true);
+        assert_eq!(protected.text, r#"<b>Hello <span translate="no" data-placeholder="0">{name}</span></b>"#);
+        assert_eq!(protected.restore(r#"<b>Hallo <span data-placeholder="0" translate="no">{Name}</span></b>"#),
+            "<b>Hallo {name}</b>");
+
+        let custom = Placeholders::empty().with_pattern(r":\w+").unwrap();
+        assert_eq!(custom.protect("Hi :user {x}", false).text, "Hi __PH0__ {x}");
+        assert!(Placeholders::empty().with_pattern("(").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_translate_text_protected() {
+        let transport = Arc::new(MockTransport::new(|request| {
+            assert_eq!(request.json()["contents"][0], "Hello __PH0__");
+            (200, r#"{"translations": [{"translatedText": "Hallo __PH0__"}]}"#.to_string())
+        }));
+        let client = TranslationClient::new("p", "global", "token")
+            .with_transport(transport)
+            .with_placeholder_protection(Placeholders::default());
+        let request = TranslateTextRequest::builder().content("Hello {name}").target("de").mime_type(MimeType::Plain).build().unwrap();
+        let response = client.translate_text(&request).await.unwrap();
+        assert_eq!(response.translations[0].translated_text, "Hallo {name}");
+    }
+}
@@ -0,0 +1,298 @@
+//! Splitting batch translations that exceed the per-request limits.
+//!
+//! A `batchTranslateText` request fails if its inputs match more than 1000 files or add up to more
+//! than 100M codepoints. `plan` lists the files the input configs match in Cloud Storage and, if
+//! the limits are exceeded, packs them into several requests that each stay within them. Each
+//! request writes to its own `part-N/` directory under the original output prefix, since the API
+//! requires an empty output location.
+//!
+//! Codepoints are estimated from file sizes in bytes, which is an upper bound for UTF-8 files, so
+//! requests are split a little earlier than strictly necessary.
+//!
+//! ```no_run
+//! # async fn run(client: google_translation::TranslationClient, request: google_translation::BatchTranslateTextRequest)
+//! #     -> google_translation::Result<()> {
+//! use google_translation::gcs::StorageClient;
+//! use google_translation::planner;
+//!
+//! let plan = planner::plan(&StorageClient::new(&client), &request).await?;
+//! let job = plan.start(&client).await?;
+//! let status = job.status(&client).await?;
+//! println!("{}/{} operations done", status.done, status.operations);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::result::Result as StdResult;
+
+use futures::future::try_join_all;
+
+use crate::*;
+use crate::gcs::StorageClient;
+
+/// The maximum number of files a batchTranslateText request may match.
+pub const MAX_FILES: usize = 1000;
+/// The maximum total number of codepoints in the files a batchTranslateText request matches.
+pub const MAX_CODEPOINTS: u64 = 100_000_000;
+
+/// The requests a batch translation was split into.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchPlan {
+    pub requests: Vec<BatchTranslateTextRequest>,
+}
+
+/// A file matched by an input config.
+struct InputFile {
+    uri: String,
+    size: u64,
+    mime_type: Option<MimeType>,
+}
+
+/// Splits `request` into requests within `MAX_FILES` and `MAX_CODEPOINTS`, listing the files its
+/// input configs match with `storage`. A request within the limits is returned as the only request
+/// of the plan, unchanged. Fails if an input config matches no files or a single file exceeds the
+/// codepoint limit.
+pub async fn plan(storage: &StorageClient, request: &BatchTranslateTextRequest) -> Result<BatchPlan> {
+    plan_with_limits(storage, request, MAX_FILES, MAX_CODEPOINTS).await
+}
+
+async fn plan_with_limits(storage: &StorageClient, request: &BatchTranslateTextRequest, max_files: usize, max_codepoints: u64)
+    -> Result<BatchPlan>
+{
+    let mut files = Vec::new();
+    for input_config in &request.input_configs {
+        let matched = list_matches(storage, &input_config.gcs_source.input_uri).await?;
+        if matched.is_empty() {
+            return Err(Error::InvalidRequest(format!("{} matches no files", input_config.gcs_source.input_uri)));
+        }
+        files.extend(matched.into_iter().map(|(uri, size)| InputFile { uri, size, mime_type: input_config.mime_type.clone() }));
+    }
+    if let Some(file) = files.iter().find(|file| file.size > max_codepoints) {
+        return Err(Error::InvalidRequest(format!("{} is larger than the limit of {} codepoints per request", file.uri, max_codepoints)));
+    }
+    if files.len() <= max_files && files.iter().map(|file| file.size).sum::<u64>() <= max_codepoints {
+        return Ok(BatchPlan { requests: vec![request.clone()] });
+    }
+
+    let mut parts: Vec<Vec<InputFile>> = vec![Vec::new()];
+    let mut codepoints = 0;
+    for file in files {
+        let part = parts.last_mut().unwrap();
+        if !part.is_empty() && (part.len() == max_files || codepoints + file.size > max_codepoints) {
+            codepoints = 0;
+            parts.push(Vec::new());
+        }
+        codepoints += file.size;
+        parts.last_mut().unwrap().push(file);
+    }
+    let output_uri_prefix = &request.output_config.gcs_destination.output_uri_prefix;
+    let requests = parts.into_iter().enumerate().map(|(i, part)| BatchTranslateTextRequest {
+        input_configs: part.into_iter()
+            .map(|file| BatchTranslateTextInputConfig { mime_type: file.mime_type, gcs_source: GcsSource { input_uri: file.uri } })
+            .collect(),
+        output_config: BatchTranslateTextOutputConfig {
+            gcs_destination: GcsDestination { output_uri_prefix: format!("{}/part-{}/", output_uri_prefix.trim_end_matches('/'), i) },
+        },
+        ..request.clone()
+    }).collect();
+    Ok(BatchPlan { requests })
+}
+
+/// The URIs and sizes of the objects `input_uri` matches. A `*` in the URI matches any sequence of characters.
+async fn list_matches(storage: &StorageClient, input_uri: &str) -> Result<Vec<(String, u64)>> {
+    let prefix = input_uri.split('*').next().unwrap_or_default();
+    Ok(storage.list(prefix).await?
+        .into_iter()
+        .map(|object| (object.uri(), object.size.max(0) as u64))
+        .filter(|(uri, _)| !uri.ends_with('/') && wildcard_match(input_uri, uri))
+        .collect())
+}
+
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match text.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<&str> = parts.collect();
+    let (last, middle) = match parts.split_last() {
+        Some((last, middle)) => (*last, middle),
+        None => return rest.is_empty(),
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+impl From<BatchTranslateTextRequest> for BatchPlan {
+    /// A plan of just `request`, for requests known to be within the limits.
+    fn from(request: BatchTranslateTextRequest) -> BatchPlan {
+        BatchPlan { requests: vec![request] }
+    }
+}
+
+impl BatchPlan {
+    /// Starts a batch translation for every request of the plan.
+    pub async fn start(&self, client: &TranslationClient) -> Result<BatchJob> {
+        let mut operations = Vec::new();
+        for request in &self.requests {
+            operations.push(client.batch_translate_text(request).await?.into_typed()?);
+        }
+        Ok(BatchJob { operations })
+    }
+}
+
+/// The operations of a started `BatchPlan`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchJob {
+    pub operations: Vec<BatchTranslateOperation>,
+}
+
+/// The combined state of the operations of a `BatchJob`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BatchJobStatus {
+    /// The number of operations.
+    pub operations: usize,
+    /// The number of operations that are done, successfully or not.
+    pub done: usize,
+    /// The errors of the operations that failed.
+    pub errors: Vec<Status>,
+    /// The sums over all operations of the counts in their metadata.
+    pub translated_characters: i64,
+    pub failed_characters: i64,
+    pub total_characters: i64,
+}
+
+impl BatchJobStatus {
+    pub fn is_done(&self) -> bool {
+        self.done == self.operations
+    }
+
+    /// The fraction of characters processed so far over all operations, see `BatchTranslateMetadata::progress`.
+    pub fn progress(&self) -> Option<f64> {
+        BatchTranslateMetadata {
+            state: None,
+            translated_characters: self.translated_characters,
+            failed_characters: self.failed_characters,
+            total_characters: self.total_characters,
+            submit_time: None,
+        }.progress()
+    }
+}
+
+impl BatchJob {
+    /// Fetches the latest state of every operation and combines them.
+    pub async fn status(&self, client: &TranslationClient) -> Result<BatchJobStatus> {
+        operations_status(client, self.operations.iter().map(|operation| operation.name.as_str())).await
+    }
+
+    /// Waits for every operation, returning their results in the order of the plan's requests.
+    pub async fn wait_until_done(&self, client: &TranslationClient, poll_config: &PollConfig)
+        -> Result<Vec<StdResult<BatchTranslateResponse, Status>>>
+    {
+        try_join_all(self.operations.iter().map(|operation| operation.wait_until_done(client, poll_config))).await
+    }
+}
+
+/// Fetches the operations named `names` and combines their states.
+pub(crate) async fn operations_status<'a>(client: &TranslationClient, names: impl Iterator<Item = &'a str>) -> Result<BatchJobStatus> {
+    let operations = try_join_all(names.map(|name| client.get_operation(name))).await?;
+    let mut status = BatchJobStatus { operations: operations.len(), ..BatchJobStatus::default() };
+    for operation in operations {
+        // Document translation metadata has the same character counts.
+        let metadata: BatchTranslateMetadata = operation.metadata_as()?;
+        status.translated_characters += metadata.translated_characters;
+        status.failed_characters += metadata.failed_characters;
+        status.total_characters += metadata.total_characters;
+        if operation.done == Some(true) {
+            status.done += 1;
+            status.errors.extend(operation.error);
+        }
+    }
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::transport::MockTransport;
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("gs://b/in/*", "gs://b/in/a/b.txt"));
+        assert!(wildcard_match("gs://b/in/*.tsv", "gs://b/in/a.tsv"));
+        assert!(!wildcard_match("gs://b/in/*.tsv", "gs://b/in/a.txt"));
+        assert!(wildcard_match("gs://b/in/a.tsv", "gs://b/in/a.tsv"));
+        assert!(!wildcard_match("gs://b/in/a.tsv", "gs://b/in/a.tsv.bak"));
+    }
+
+    #[tokio::test]
+    async fn test_plan() {
+        let transport = Arc::new(MockTransport::with_response(200, r#"{"items": [
+            {"bucket": "b", "name": "in/", "size": "0"},
+            {"bucket": "b", "name": "in/1.tsv", "size": "60"},
+            {"bucket": "b", "name": "in/2.tsv", "size": "50"},
+            {"bucket": "b", "name": "in/3.tsv", "size": "30"},
+            {"bucket": "b", "name": "in/4.txt", "size": "10"}
+        ]}"#));
+        let client = TranslationClient::new("p", "us-central1", "token").with_transport(transport);
+        let storage = StorageClient::new(&client);
+        let request = BatchTranslateTextRequest::builder()
+            .source_language_code("en")
+            .target_language("de")
+            .input_config(BatchTranslateTextInputConfig {
+                mime_type: Some(MimeType::Html),
+                gcs_source: GcsSource { input_uri: "gs://b/in/*.tsv".to_string() },
+            })
+            .output_config(BatchTranslateTextOutputConfig {
+                gcs_destination: GcsDestination { output_uri_prefix: "gs://b/out/".to_string() },
+            })
+            .build()
+            .unwrap();
+
+        let plan = plan_with_limits(&storage, &request, 2, 100).await.unwrap();
+        let inputs: Vec<Vec<&str>> = plan.requests.iter()
+            .map(|request| request.input_configs.iter().map(|config| config.gcs_source.input_uri.as_str()).collect())
+            .collect();
+        assert_eq!(inputs, vec![vec!["gs://b/in/1.tsv"], vec!["gs://b/in/2.tsv", "gs://b/in/3.tsv"]]);
+        assert_eq!(plan.requests[1].output_config.gcs_destination.output_uri_prefix, "gs://b/out/part-1/");
+        assert_eq!(plan.requests[1].input_configs[0].mime_type, Some(MimeType::Html));
+
+        assert_eq!(plan_with_limits(&storage, &request, 3, 200).await.unwrap().requests, vec![request.clone()]);
+        assert!(plan_with_limits(&storage, &request, 3, 55).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_job_status() {
+        let transport = Arc::new(MockTransport::new(|request| {
+            let body = if request.uri.ends_with("/1") {
+                r#"{"name": "projects/p/locations/l/operations/1", "done": true,
+                    "metadata": {"translatedCharacters": "10", "totalCharacters": "10"},
+                    "response": {"translatedCharacters": "10", "totalCharacters": "10"}}"#
+            } else {
+                r#"{"name": "projects/p/locations/l/operations/2", "done": true,
+                    "metadata": {"translatedCharacters": "5", "failedCharacters": "5", "totalCharacters": "10"},
+                    "error": {"code": 3, "message": "Unsupported file.", "status": "INVALID_ARGUMENT"}}"#
+            };
+            (200, body.to_string())
+        }));
+        let client = TranslationClient::new("p", "l", "token").with_transport(transport);
+        let mut job = BatchJob { operations: Vec::new() };
+        for name in ["projects/p/locations/l/operations/1", "projects/p/locations/l/operations/2"] {
+            job.operations.push(client.get_operation(name).await.unwrap().into_typed().unwrap());
+        }
+        let status = job.status(&client).await.unwrap();
+        assert!(status.is_done());
+        assert_eq!(status.progress(), Some(1.0));
+        assert_eq!(status.errors[0].message, "Unsupported file.");
+        let results = job.wait_until_done(&client, &PollConfig::default()).await.unwrap();
+        assert!(results[0].is_ok() && results[1].is_err());
+    }
+}
@@ -0,0 +1,149 @@
+//! Client-side rate limiting for the per-minute quotas.
+//!
+//! The API enforces characters-per-minute and requests-per-minute quotas per project, and exceeding
+//! them produces bursts of 429 errors. A `RateLimiter` set with `TranslationClient::with_rate_limiter`
+//! delays `translate_text` and `detect_language` requests so they stay within the configured rates.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use web_time::Instant;
+
+use crate::runtime::{DefaultRuntime, Runtime};
+
+/// The rates a `RateLimiter` enforces. `None` leaves that dimension unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RateLimit {
+    pub characters_per_minute: Option<u32>,
+    pub requests_per_minute: Option<u32>,
+}
+
+/// A pair of token buckets, one for characters and one for requests.
+///
+/// Each bucket holds up to one minute's worth of tokens and refills continuously, so short bursts
+/// up to the per-minute amount are let through immediately. A request with more characters than
+/// the whole bucket waits for a full bucket and then empties it.
+///
+/// Clones share the same buckets, so one limiter can be used by several clients of the same project.
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    characters: Option<Bucket>,
+    requests: Option<Bucket>,
+    updated_at: Instant,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    tokens: f64,
+    per_second: f64,
+}
+
+impl Bucket {
+    fn new(per_minute: u32) -> Bucket {
+        let capacity = per_minute.max(1) as f64;
+        Bucket { capacity, tokens: capacity, per_second: capacity / 60.0 }
+    }
+
+    fn refill(&mut self, elapsed: Duration) {
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.per_second).min(self.capacity);
+    }
+
+    /// How long until `cost` tokens are available.
+    fn wait(&self, cost: f64) -> Duration {
+        let missing = cost.min(self.capacity) - self.tokens;
+        if missing <= 0.0 {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_secs_f64(missing / self.per_second)
+        }
+    }
+
+    fn take(&mut self, cost: f64) {
+        self.tokens -= cost.min(self.capacity);
+    }
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> RateLimiter {
+        RateLimiter {
+            state: Arc::new(Mutex::new(State {
+                characters: limit.characters_per_minute.map(Bucket::new),
+                requests: limit.requests_per_minute.map(Bucket::new),
+                updated_at: Instant::now(),
+            })),
+        }
+    }
+
+    /// Waits until a request with `characters` characters may be sent, and accounts for it.
+    pub async fn acquire(&self, characters: usize) {
+        self.acquire_with(characters, &DefaultRuntime::default()).await
+    }
+
+    /// Like `acquire`, waiting with `runtime`.
+    pub(crate) async fn acquire_with(&self, characters: usize, runtime: &dyn Runtime) {
+        while let Err(wait) = self.try_acquire(characters, Instant::now()) {
+            runtime.sleep(wait).await;
+        }
+    }
+
+    /// Accounts for the request if the buckets allow it, otherwise returns how long to wait before trying again.
+    fn try_acquire(&self, characters: usize, now: Instant) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+        let elapsed = now.saturating_duration_since(state.updated_at);
+        state.updated_at = now;
+        let characters = characters as f64;
+        let mut wait = Duration::from_secs(0);
+        if let Some(bucket) = &mut state.characters {
+            bucket.refill(elapsed);
+            wait = wait.max(bucket.wait(characters));
+        }
+        if let Some(bucket) = &mut state.requests {
+            bucket.refill(elapsed);
+            wait = wait.max(bucket.wait(1.0));
+        }
+        if wait > Duration::from_secs(0) {
+            return Err(wait);
+        }
+        if let Some(bucket) = &mut state.characters {
+            bucket.take(characters);
+        }
+        if let Some(bucket) = &mut state.requests {
+            bucket.take(1.0);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_character_bucket() {
+        let limiter = RateLimiter::new(RateLimit { characters_per_minute: Some(600), requests_per_minute: None });
+        let start = Instant::now();
+        assert!(limiter.try_acquire(500, start).is_ok());
+        let wait = limiter.try_acquire(200, start).unwrap_err();
+        assert_eq!(wait.as_secs_f64().round(), 10.0);
+        assert!(limiter.try_acquire(200, start + Duration::from_secs(10)).is_ok());
+        // Larger than the bucket: waits for a full bucket, then goes through.
+        assert!(limiter.try_acquire(1000, start + Duration::from_secs(20)).is_err());
+        assert!(limiter.try_acquire(1000, start + Duration::from_secs(80)).is_ok());
+    }
+
+    #[test]
+    fn test_request_bucket() {
+        let limiter = RateLimiter::new(RateLimit { characters_per_minute: None, requests_per_minute: Some(2) });
+        let start = Instant::now();
+        assert!(limiter.try_acquire(10, start).is_ok());
+        assert!(limiter.try_acquire(10, start).is_ok());
+        assert_eq!(limiter.try_acquire(10, start).unwrap_err().as_secs_f64().round(), 30.0);
+        assert!(limiter.clone().try_acquire(10, start + Duration::from_secs(31)).is_ok());
+    }
+}
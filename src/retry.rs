@@ -0,0 +1,128 @@
+//! Retrying transient failures with exponential backoff.
+
+use std::time::Duration;
+
+use rand::Rng;
+
+/// How the client retries requests that fail with a retryable error (see `Error::is_retryable`).
+///
+/// The delay before retry `n` (1-based) is `base_delay * 2^(n-1)`, capped at `max_delay`.
+/// With `jitter` the actual delay is picked uniformly between zero and that value, so many
+/// clients hitting the same quota don't retry in lockstep. A `Retry-After` header sent with
+/// the failed response takes precedence when `honor_retry_after` is set.
+///
+/// Only requests that can safely be sent twice are retried by default: reads, deletes, cancels and
+/// translations. Requests that create a resource or start a long-running operation, such as
+/// createGlossary and batchTranslateText, may have succeeded even if the response was lost, so
+/// retrying them can start a duplicate operation or fail with ALREADY_EXISTS. Set
+/// `retry_non_idempotent` to retry them too.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one. 1 disables retries.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: bool,
+    pub honor_retry_after: bool,
+    /// Also retry the requests that create resources or start operations. Off by default.
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+            honor_retry_after: true,
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// The delay to wait after the `attempt`-th (1-based) attempt failed.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        if self.honor_retry_after {
+            if let Some(retry_after) = retry_after {
+                return retry_after.min(self.max_delay);
+            }
+        }
+        let exponent = attempt.saturating_sub(1).min(31);
+        let backoff = self.base_delay.saturating_mul(1 << exponent).min(self.max_delay);
+        if self.jitter {
+            backoff.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+        } else {
+            backoff
+        }
+    }
+}
+
+/// Parses the delay-seconds form of a `Retry-After` header. The HTTP-date form isn't sent by Google APIs.
+pub(crate) fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse::<u64>().ok().map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::transport::MockTransport;
+    use crate::{Glossary, TranslateTextRequest, TranslationClient};
+
+    #[test]
+    fn test_exponential_backoff() {
+        let policy = RetryPolicy {
+            jitter: false,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.delay(1, None), Duration::from_millis(500));
+        assert_eq!(policy.delay(2, None), Duration::from_secs(1));
+        assert_eq!(policy.delay(3, None), Duration::from_secs(2));
+        assert_eq!(policy.delay(20, None), Duration::from_secs(30));
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(7))), Duration::from_secs(7));
+    }
+
+    #[test]
+    fn test_jitter_stays_below_backoff() {
+        let policy = RetryPolicy::default();
+        for attempt in 1..10 {
+            assert!(policy.delay(attempt, None) <= policy.base_delay * (1 << (attempt - 1)));
+        }
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    }
+
+    #[tokio::test]
+    async fn test_non_idempotent_requests_are_not_retried() {
+        let unavailable = r#"{"error": {"code": 503, "message": "Unavailable.", "status": "UNAVAILABLE"}}"#;
+        let transport = Arc::new(MockTransport::with_response(503, unavailable));
+        let policy = RetryPolicy { base_delay: Duration::from_millis(1), ..RetryPolicy::default() };
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone()).with_retry_policy(policy.clone());
+        let glossary: Glossary = serde_json::from_str(r#"{"name": "projects/p/locations/global/glossaries/g",
+            "inputConfig": {"gcsSource": {"inputUri": "gs://b/g.tsv"}}, "languagePair": {"sourceLanguageCode": "en", "targetLanguageCode": "de"}}"#).unwrap();
+        assert!(client.create_glossary(&glossary).await.is_err());
+        assert_eq!(transport.requests().len(), 1);
+        let request = TranslateTextRequest::builder().content("Hello").target("de").build().unwrap();
+        assert!(client.translate_text(&request).await.is_err());
+        assert_eq!(transport.requests().len(), 4);
+
+        let client = client.with_retry_policy(RetryPolicy { retry_non_idempotent: true, ..policy });
+        assert!(client.create_glossary(&glossary).await.is_err());
+        assert_eq!(transport.requests().len(), 7);
+    }
+}
@@ -0,0 +1,121 @@
+//! The async runtime the client waits on.
+//!
+//! The client waits between retries, between polls of long-running operations, for the rate
+//! limiter and for `BulkTranslateConfig::max_delay`. It does so through the `Runtime` set with
+//! `TranslationClient::with_runtime`, `TokioRuntime` by default, or `WasmRuntime`, which waits on
+//! JavaScript timers, with the `wasm` feature. With the `async-std` feature,
+//! `AsyncStdRuntime` waits on async-std's timers instead, so the client can be driven by an
+//! async-std executor. The client never spawns tasks, so timers are all a runtime provides.
+//!
+//! The default `HyperTransport` and `transport::ReqwestTransport` connect with Tokio's sockets and
+//! need a Tokio reactor; under async-std, send requests through a transport built on that runtime,
+//! see the `transport` module.
+//!
+//! ```
+//! # #[cfg(feature = "async-std")]
+//! # fn run() {
+//! use std::sync::Arc;
+//!
+//! use google_translation::runtime::AsyncStdRuntime;
+//!
+//! let client = google_translation::TranslationClient::new("my-project", "global", "token")
+//!     .with_runtime(Arc::new(AsyncStdRuntime));
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use crate::BoxFuture;
+
+/// Timers for the client, see the module documentation.
+pub trait Runtime: Send + Sync {
+    /// A future that completes after `duration`.
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+/// Waits with `tokio::time`. Requires a Tokio runtime with the time driver enabled.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokioRuntime;
+
+impl Runtime for TokioRuntime {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// Waits with `async_std::task::sleep`.
+#[cfg(feature = "async-std")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AsyncStdRuntime;
+
+#[cfg(feature = "async-std")]
+impl Runtime for AsyncStdRuntime {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(async_std::task::sleep(duration))
+    }
+}
+
+/// Waits with JavaScript's `setTimeout`, in browsers and workers. The default with the `wasm` feature.
+#[cfg(feature = "wasm")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WasmRuntime;
+
+#[cfg(feature = "wasm")]
+impl Runtime for WasmRuntime {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(gloo_timers::future::sleep(duration))
+    }
+}
+
+/// The runtime used unless `TranslationClient::with_runtime` sets another one.
+#[cfg(feature = "native")]
+pub(crate) type DefaultRuntime = TokioRuntime;
+#[cfg(feature = "wasm")]
+pub(crate) type DefaultRuntime = WasmRuntime;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::*;
+    use crate::transport::MockTransport;
+
+    /// Records the requested sleeps instead of waiting.
+    #[derive(Default)]
+    struct RecordingRuntime {
+        sleeps: Mutex<Vec<Duration>>,
+    }
+
+    impl Runtime for RecordingRuntime {
+        fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+            self.sleeps.lock().unwrap().push(duration);
+            Box::pin(async {})
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_runtime() {
+        let transport = Arc::new(MockTransport::new(|_| {
+            (503, r#"{"error": {"code": 503, "message": "Unavailable", "status": "UNAVAILABLE"}}"#.to_string())
+        }));
+        let runtime = Arc::new(RecordingRuntime::default());
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone())
+            .with_retry_policy(RetryPolicy { max_attempts: 3, base_delay: Duration::from_secs(60), ..RetryPolicy::default() })
+            .with_runtime(runtime.clone());
+        let request = TranslateTextRequest::builder().content("Hello").target("de").build().unwrap();
+        client.translate_text(&request).await.unwrap_err();
+        assert_eq!(transport.requests().len(), 3);
+        assert_eq!(runtime.sleeps.lock().unwrap().len(), 2);
+    }
+
+    #[cfg(feature = "async-std")]
+    #[test]
+    fn test_async_std_runtime() {
+        async_std::task::block_on(async {
+            let started = std::time::Instant::now();
+            AsyncStdRuntime.sleep(Duration::from_millis(10)).await;
+            assert!(started.elapsed() >= Duration::from_millis(10));
+        });
+    }
+}
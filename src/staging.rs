@@ -0,0 +1,236 @@
+//! Translating local files with `batch_translate_text`, staged through Cloud Storage.
+//!
+//! `translate_files` runs the whole workflow: it uploads the files under a new job prefix in a
+//! staging location, starts a batch translation of them, waits for it to finish, downloads the
+//! translations listed in the output `index.csv` and writes them next to the originals, as
+//! `report.de.txt` for `report.txt` translated into German. The staged inputs and outputs are
+//! deleted afterwards unless `StagingConfig::keep_staged_files` is set.
+//!
+//! ```no_run
+//! # async fn run(client: google_translation::TranslationClient) -> google_translation::Result<()> {
+//! use google_translation::gcs::StorageClient;
+//! use google_translation::staging::{StagingConfig, translate_files};
+//!
+//! let config = StagingConfig::new("gs://my-bucket/translation-staging/", "en", &["de", "fr"]);
+//! let storage = StorageClient::new(&client);
+//! for file in translate_files(&client, &storage, &["docs/intro.html", "docs/faq.txt"], &config).await? {
+//!     println!("{} -> {}", file.source.display(), file.path.display());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Text (.txt), HTML (.html, .htm) and TSV (.tsv) files are supported. For TSV files the written
+//! file is the TSV output of the batch translation, see `batch::translation_records`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::Rng;
+
+use crate::*;
+use crate::batch::OutputIndex;
+use crate::gcs::{StorageClient, parse_uri};
+
+/// Options for `translate_files`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StagingConfig {
+    /// The Cloud Storage location files are staged under, for example "gs://my-bucket/staging/".
+    /// Each call uses a new job prefix below it.
+    pub staging_uri: String,
+    pub source_language_code: String,
+    /// Up to 10 target languages.
+    pub target_language_codes: Vec<String>,
+    /// The model for each target language, see `BatchTranslateTextRequest::models`.
+    pub models: Option<HashMap<String, String>>,
+    /// How the batch operation is polled.
+    pub poll_config: PollConfig,
+    /// Leaves the uploaded inputs and the batch outputs in the staging location.
+    pub keep_staged_files: bool,
+}
+
+impl StagingConfig {
+    pub fn new<S: AsRef<str>>(staging_uri: &str, source_language_code: impl AsRef<str>, target_language_codes: &[S]) -> StagingConfig {
+        StagingConfig {
+            staging_uri: staging_uri.to_string(),
+            source_language_code: source_language_code.as_ref().to_string(),
+            target_language_codes: target_language_codes.iter().map(|code| code.as_ref().to_string()).collect(),
+            models: None,
+            poll_config: PollConfig::default(),
+            keep_staged_files: false,
+        }
+    }
+}
+
+/// A translated file written by `translate_files`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TranslatedFile {
+    /// The file that was translated.
+    pub source: PathBuf,
+    pub target_language_code: String,
+    /// The file the translation was written to, next to `source`.
+    pub path: PathBuf,
+}
+
+/// Translates `files` into each of `config.target_language_codes` with a batch translation staged
+/// through `storage`, writing the translations next to the files. See the module documentation.
+///
+/// Returns the files written, in the order of `index.csv`. A failed batch operation is returned as
+/// `Error::ResponseError`; files the operation couldn't translate are missing from the result.
+pub async fn translate_files<P: AsRef<Path>>(client: &TranslationClient, storage: &StorageClient, files: &[P],
+        config: &StagingConfig)
+    -> Result<Vec<TranslatedFile>>
+{
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+    let job_uri = format!("{}/{}/", config.staging_uri.trim_end_matches('/'), job_id());
+    parse_uri(&job_uri)?;
+
+    let mut request = BatchTranslateTextRequest::builder()
+        .source_language_code(config.source_language_code.as_str())
+        .target_language_codes(config.target_language_codes.clone())
+        .output_config(BatchTranslateTextOutputConfig {
+            gcs_destination: GcsDestination { output_uri_prefix: format!("{}output/", job_uri) },
+        });
+    if let Some(models) = &config.models {
+        request = request.models(models.clone());
+    }
+    // Inputs are staged as input/{index}/{file name}, so files with the same name don't collide.
+    let mut sources = HashMap::new();
+    for (i, file) in files.iter().enumerate() {
+        let file = file.as_ref();
+        let (name, mime_type) = input_name_and_type(file)?;
+        let uri = format!("{}input/{}/{}", job_uri, i, name);
+        let data = tokio::fs::read(file).await.map_err(Error::IoError)?;
+        storage.upload(&uri, data).await?;
+        request = request.input_config(BatchTranslateTextInputConfig { mime_type, gcs_source: GcsSource { input_uri: uri.clone() } });
+        sources.insert(uri, file.to_path_buf());
+    }
+
+    let result = run_batch(client, storage, &request.build()?, &job_uri, &sources, &config.poll_config).await;
+    if !config.keep_staged_files {
+        let cleanup = async {
+            for object in storage.list(&job_uri).await? {
+                storage.delete(&object.uri()).await?;
+            }
+            Ok::<_, Error>(())
+        };
+        // A failed cleanup doesn't hide the result, the job prefix can be deleted later.
+        if let Err(e) = cleanup.await {
+            trace::staging_cleanup_failed(&job_uri, &e);
+        }
+    }
+    result
+}
+
+async fn run_batch(client: &TranslationClient, storage: &StorageClient, request: &BatchTranslateTextRequest, job_uri: &str,
+        sources: &HashMap<String, PathBuf>, poll_config: &PollConfig)
+    -> Result<Vec<TranslatedFile>>
+{
+    let operation: BatchTranslateOperation = client.batch_translate_text(request).await?.into_typed()?;
+    operation.wait_until_done(client, poll_config).await?.map_err(operation_error)?;
+
+    let index = storage.download(&format!("{}output/index.csv", job_uri)).await?;
+    let index = OutputIndex::parse(&String::from_utf8_lossy(&index))?;
+    let mut translated = Vec::new();
+    for row in index {
+        let (source, translations_uri) = match (sources.get(&row.input_file), row.translations_uri) {
+            (Some(source), Some(translations_uri)) => (source, translations_uri),
+            _ => continue,
+        };
+        let path = translated_path(source, &row.target_language_code);
+        let data = storage.download(&translations_uri).await?;
+        tokio::fs::write(&path, data).await.map_err(Error::IoError)?;
+        translated.push(TranslatedFile { source: source.clone(), target_language_code: row.target_language_code, path });
+    }
+    Ok(translated)
+}
+
+/// The file name and MIME type of an input file, rejecting formats batchTranslateText doesn't take.
+fn input_name_and_type(file: &Path) -> Result<(&str, Option<MimeType>)> {
+    let name = file.file_name().and_then(|name| name.to_str())
+        .ok_or_else(|| Error::InvalidRequest(format!("{} has no UTF-8 file name", file.display())))?;
+    match file.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase).as_deref() {
+        Some("txt") => Ok((name, Some(MimeType::Plain))),
+        Some("html") | Some("htm") => Ok((name, Some(MimeType::Html))),
+        Some("tsv") => Ok((name, None)),
+        _ => Err(Error::InvalidRequest(format!("{} is not a .txt, .html or .tsv file", file.display()))),
+    }
+}
+
+/// `dir/name.ext` translated into `language` is written to `dir/name.language.ext`.
+fn translated_path(source: &Path, target_language_code: &str) -> PathBuf {
+    let stem = source.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = match source.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, target_language_code, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, target_language_code),
+    };
+    source.with_file_name(name)
+}
+
+/// A job prefix that sorts by start time and doesn't collide between concurrent jobs.
+fn job_id() -> String {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or_default();
+    format!("job-{}-{:08x}", millis, rand::thread_rng().gen::<u32>())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::transport::MockTransport;
+
+    #[test]
+    fn test_translated_path() {
+        assert_eq!(translated_path(Path::new("docs/report.txt"), "de"), Path::new("docs/report.de.txt"));
+        assert_eq!(translated_path(Path::new("README"), "zh-CN"), Path::new("README.zh-CN"));
+        assert!(input_name_and_type(Path::new("a.pdf")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_translate_files() {
+        let dir = std::env::temp_dir().join(format!("google-translation-staging-{}", job_id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("hello.txt");
+        std::fs::write(&file, "Hello").unwrap();
+
+        // The uploaded input's URI, known once the job prefix has been chosen.
+        let input_uri = Arc::new(Mutex::new(String::new()));
+        let transport = Arc::new(MockTransport::new({
+            let input_uri = input_uri.clone();
+            move |request| {
+                let uri = request.uri.as_str();
+                if uri.contains("uploadType=media") {
+                    let name: HashMap<String, String> = serde_urlencoded::from_str(uri.split_once('?').unwrap().1).unwrap();
+                    *input_uri.lock().unwrap() = format!("gs://bucket/{}", name["name"]);
+                    (200, r#"{"bucket": "bucket", "name": "x"}"#.to_string())
+                } else if uri.ends_with(":batchTranslateText") {
+                    (200, r#"{"name": "projects/p/locations/us-central1/operations/1", "metadata": {}}"#.to_string())
+                } else if uri.contains("/operations/1") {
+                    (200, r#"{"name": "projects/p/locations/us-central1/operations/1", "metadata": {}, "done": true,
+                        "response": {"totalCharacters": "5", "translatedCharacters": "5"}}"#.to_string())
+                } else if uri.contains("index.csv") {
+                    (200, format!("{},de,gs://bucket/out/hello_de_translations.txt,\n", input_uri.lock().unwrap()))
+                } else if uri.contains("alt=media") {
+                    (200, "Hallo".to_string())
+                } else {
+                    (200, r#"{"items": []}"#.to_string())
+                }
+            }
+        }));
+        let client = TranslationClient::new("p", "us-central1", "token").with_transport(transport.clone());
+        let storage = StorageClient::new(&client);
+        let config = StagingConfig::new("gs://bucket/staging", "en", &["de"]);
+        let translated = translate_files(&client, &storage, &[&file], &config).await.unwrap();
+
+        assert_eq!(translated, vec![TranslatedFile { source: file.clone(), target_language_code: "de".to_string(), path: dir.join("hello.de.txt") }]);
+        assert_eq!(std::fs::read_to_string(dir.join("hello.de.txt")).unwrap(), "Hallo");
+        let batch = transport.requests().into_iter().find(|request| request.uri.ends_with(":batchTranslateText")).unwrap();
+        assert_eq!(batch.json()["inputConfigs"][0]["gcsSource"]["inputUri"], input_uri.lock().unwrap().as_str());
+        assert!(input_uri.lock().unwrap().starts_with("gs://bucket/staging/job-"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
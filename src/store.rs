@@ -0,0 +1,245 @@
+//! Persistent translation memory.
+//!
+//! A `TranslationStore` set through `TranslationClient::with_store` is consulted by `translate_text`
+//! before calling the API, and every new translation is written back to it, so strings translated in
+//! an earlier run are never paid for twice. The in-memory `TranslationCache`, if any, sits in front of
+//! the store.
+//!
+//! Only the translated text is stored. Requests with a `glossary_config` bypass the store, because
+//! their responses carry a second, glossary-applied translation for every string.
+//!
+//! With the `sqlite` feature, `SqliteStore` keeps the translations in a SQLite database.
+
+use sha2::{Digest, Sha256};
+
+use crate::*;
+
+/// Identifies a stored translation.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StoreKey {
+    /// The hex SHA-256 of the source text and the request options that affect its translation
+    /// (MIME type, model and transliteration), see `StoreKey::new`.
+    pub content_hash: String,
+    /// The source language, or "" if it was detected by the API.
+    pub source_language_code: String,
+    pub target_language_code: String,
+}
+
+impl StoreKey {
+    /// The key under which the translation of `text` for `request` is stored.
+    pub fn new(request: &TranslateTextRequest, text: &str) -> StoreKey {
+        let mut hasher = Sha256::new();
+        let mime_type = request.mime_type.as_ref().map_or("", MimeType::as_str);
+        for part in [mime_type, request.model.as_deref().unwrap_or(""), text] {
+            hasher.update((part.len() as u64).to_be_bytes());
+            hasher.update(part.as_bytes());
+        }
+        // Only hashed when enabled, so keys stored before transliteration existed stay valid.
+        if request.transliteration_config.as_ref().and_then(|config| config.enable_transliteration) == Some(true) {
+            hasher.update(b"transliterate");
+        }
+        StoreKey {
+            content_hash: hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect(),
+            source_language_code: request.source_language_code.clone().unwrap_or_default(),
+            target_language_code: request.target_language_code.clone(),
+        }
+    }
+}
+
+/// A persistent map from `StoreKey` to translated text.
+pub trait TranslationStore: Send + Sync {
+    fn lookup<'a>(&'a self, key: &'a StoreKey) -> BoxFuture<'a, Result<Option<String>>>;
+
+    /// Stores `translated_text` under `key`, replacing any previous translation.
+    fn insert<'a>(&'a self, key: &'a StoreKey, translated_text: &'a str) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Answers `request` from `store` where possible and translates the remaining strings with the API.
+pub(crate) async fn translate_text_stored(client: &TranslationClient, store: &dyn TranslationStore, request: &TranslateTextRequest)
+    -> Result<TranslateTextResponse>
+{
+    let keys: Vec<StoreKey> = request.contents.iter().map(|text| StoreKey::new(request, text)).collect();
+    let mut translations = Vec::with_capacity(keys.len());
+    for key in &keys {
+        translations.push(store.lookup(key).await?.map(|translated_text| Translation {
+            translated_text,
+            model: None,
+            detected_language_code: None,
+            glossary_config: None,
+        }));
+    }
+    let misses: Vec<usize> = (0..keys.len()).filter(|&i| translations[i].is_none()).collect();
+
+    if !misses.is_empty() {
+        let miss_request = TranslateTextRequest {
+            contents: misses.iter().map(|&i| request.contents[i].clone()).collect(),
+            mime_type: request.mime_type.clone(),
+            source_language_code: request.source_language_code.clone(),
+            target_language_code: request.target_language_code.clone(),
+            model: request.model.clone(),
+            glossary_config: None,
+            labels: request.labels.clone(),
+            transliteration_config: request.transliteration_config.clone(),
+        };
+        let response = client.translate_text_from_api(&miss_request).await?;
+        if response.translations.len() != misses.len() {
+            return Err(Error::Other(format!("translateText returned {} translations for {} strings",
+                response.translations.len(), misses.len())));
+        }
+        for (&i, translation) in misses.iter().zip(response.translations) {
+            store.insert(&keys[i], &translation.translated_text).await?;
+            translations[i] = Some(translation);
+        }
+    }
+
+    Ok(TranslateTextResponse {
+        translations: translations.into_iter().map(Option::unwrap).collect(),
+        glossary_translations: None,
+    })
+}
+
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::SqliteStore;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    use rusqlite::{Connection, OptionalExtension, params};
+
+    use super::{StoreKey, TranslationStore};
+    use crate::{BoxFuture, Error, Result};
+
+    /// A `TranslationStore` in a SQLite database.
+    ///
+    /// Queries run synchronously on the calling task; they are single-row lookups by primary key,
+    /// so they take microseconds on a local database.
+    pub struct SqliteStore {
+        connection: Mutex<Connection>,
+    }
+
+    fn sqlite_error(error: rusqlite::Error) -> Error {
+        Error::Other(format!("sqlite: {}", error))
+    }
+
+    impl SqliteStore {
+        /// Opens or creates the database at `path`.
+        pub fn open<P: AsRef<Path>>(path: P) -> Result<SqliteStore> {
+            SqliteStore::from_connection(Connection::open(path).map_err(sqlite_error)?)
+        }
+
+        /// A store that only lives as long as the process, mostly useful for tests.
+        pub fn open_in_memory() -> Result<SqliteStore> {
+            SqliteStore::from_connection(Connection::open_in_memory().map_err(sqlite_error)?)
+        }
+
+        fn from_connection(connection: Connection) -> Result<SqliteStore> {
+            connection.execute_batch(
+                "CREATE TABLE IF NOT EXISTS translations (
+                    content_hash TEXT NOT NULL,
+                    source_language_code TEXT NOT NULL,
+                    target_language_code TEXT NOT NULL,
+                    translated_text TEXT NOT NULL,
+                    PRIMARY KEY (content_hash, source_language_code, target_language_code)
+                )"
+            ).map_err(sqlite_error)?;
+            Ok(SqliteStore { connection: Mutex::new(connection) })
+        }
+    }
+
+    impl TranslationStore for SqliteStore {
+        fn lookup<'a>(&'a self, key: &'a StoreKey) -> BoxFuture<'a, Result<Option<String>>> {
+            Box::pin(async move {
+                self.connection.lock().unwrap()
+                    .query_row(
+                        "SELECT translated_text FROM translations
+                         WHERE content_hash = ?1 AND source_language_code = ?2 AND target_language_code = ?3",
+                        params![key.content_hash, key.source_language_code, key.target_language_code],
+                        |row| row.get(0),
+                    )
+                    .optional()
+                    .map_err(sqlite_error)
+            })
+        }
+
+        fn insert<'a>(&'a self, key: &'a StoreKey, translated_text: &'a str) -> BoxFuture<'a, Result<()>> {
+            Box::pin(async move {
+                self.connection.lock().unwrap()
+                    .execute(
+                        "INSERT OR REPLACE INTO translations
+                         (content_hash, source_language_code, target_language_code, translated_text)
+                         VALUES (?1, ?2, ?3, ?4)",
+                        params![key.content_hash, key.source_language_code, key.target_language_code, translated_text],
+                    )
+                    .map(|_| ())
+                    .map_err(sqlite_error)
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::serve;
+
+    #[derive(Default)]
+    struct MemoryStore(std::sync::Mutex<HashMap<StoreKey, String>>);
+
+    impl TranslationStore for MemoryStore {
+        fn lookup<'a>(&'a self, key: &'a StoreKey) -> BoxFuture<'a, Result<Option<String>>> {
+            Box::pin(async move { Ok(self.0.lock().unwrap().get(key).cloned()) })
+        }
+
+        fn insert<'a>(&'a self, key: &'a StoreKey, translated_text: &'a str) -> BoxFuture<'a, Result<()>> {
+            Box::pin(async move {
+                self.0.lock().unwrap().insert(key.clone(), translated_text.to_string());
+                Ok(())
+            })
+        }
+    }
+
+    #[test]
+    fn test_store_key() {
+        let request = TranslateTextRequest::builder().content("Hello").target("de").build().unwrap();
+        let key = StoreKey::new(&request, "Hello");
+        assert_eq!(key.content_hash.len(), 64);
+        assert_eq!(key.source_language_code, "");
+        assert_eq!(key, StoreKey::new(&request, "Hello"));
+        assert_ne!(key, StoreKey::new(&request, "Hello!"));
+        let html = TranslateTextRequest::builder().content("Hello").target("de").mime_type(MimeType::Html).build().unwrap();
+        assert_ne!(key, StoreKey::new(&html, "Hello"));
+        let transliterate = |enable| TranslateTextRequest::builder().content("Hello").target("de")
+            .transliteration_config(TransliterationConfig::new(enable)).build().unwrap();
+        assert_eq!(key, StoreKey::new(&transliterate(false), "Hello"));
+        assert_ne!(key, StoreKey::new(&transliterate(true), "Hello"));
+        assert_eq!(serde_json::to_value(transliterate(true)).unwrap()["transliterationConfig"],
+            serde_json::json!({"enableTransliteration": true}));
+    }
+
+    #[tokio::test]
+    async fn test_stored_translate_text() {
+        let (endpoint, requests) = serve(200, r#"{"translations": [{"translatedText": "Hallo"}]}"#).await;
+        let store = Arc::new(MemoryStore::default());
+        let client = TranslationClient::new("p", "global", "token").with_endpoint(&endpoint).with_store(store.clone());
+        let request = TranslateTextRequest::builder().content("Hello").target("de").build().unwrap();
+        for _ in 0..2 {
+            assert_eq!(client.translate_text(&request).await.unwrap().translations[0].translated_text, "Hallo");
+        }
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        assert_eq!(store.0.lock().unwrap().len(), 1);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_sqlite_store() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let request = TranslateTextRequest::builder().content("Hello").target("de").build().unwrap();
+        let key = StoreKey::new(&request, "Hello");
+        assert_eq!(store.lookup(&key).await.unwrap(), None);
+        store.insert(&key, "Hallo").await.unwrap();
+        store.insert(&key, "Hallo!").await.unwrap();
+        assert_eq!(store.lookup(&key).await.unwrap().as_deref(), Some("Hallo!"));
+    }
+}
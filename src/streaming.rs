@@ -0,0 +1,373 @@
+//! Translating large documents without buffering them.
+//!
+//! translateDocument takes the document base64-encoded inside its JSON request and returns the
+//! translation the same way, so `TranslationClient::translate_document` holds a document in memory
+//! several times over. `TranslationClient::translate_document_streaming` instead reads the document
+//! from an `AsyncRead`, encoding it while the request body is sent with chunked transfer encoding,
+//! and decodes the translation as the response arrives, writing it to an `AsyncWrite`:
+//!
+//! ```no_run
+//! # async fn run(client: google_translation::TranslationClient) -> Result<(), Box<dyn std::error::Error>> {
+//! use google_translation::{DocumentInputConfig, DocumentSource, TranslateDocumentRequest};
+//!
+//! let input_config = DocumentInputConfig {
+//!     mime_type: Some("application/pdf".to_string()),
+//!     source: DocumentSource::Content(Vec::new()),
+//! };
+//! let request = TranslateDocumentRequest::builder().target_language_code("de").document_input_config(input_config).build()?;
+//! let input = tokio::fs::File::open("manual.pdf").await?;
+//! let mut output = tokio::fs::File::create("manual.de.pdf").await?;
+//! let response = client.translate_document_streaming(&request, input, &mut output).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A streamed body can't be sent twice, so the request is sent once regardless of the client's
+//! retry policy, and it isn't gzip-compressed. Only the first of `byte_stream_outputs`, the one the
+//! API returns today, is streamed; a glossary translation is returned in the response as usual.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use futures::stream::{self, Stream, StreamExt};
+use hyper::body::HttpBody;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::*;
+
+/// How many bytes of the document are read and encoded at a time. A multiple of 3, so that only
+/// the last chunk's encoding is padded.
+const READ_CHUNK_SIZE: usize = 48 * 1024;
+
+/// Stands in for the document content in the serialized request, to find where it goes.
+const CONTENT_PLACEHOLDER: &str = "\u{0}content\u{0}";
+
+impl TranslationClient {
+    /// Translates the document read from `input` and writes the translation to `output`, see the
+    /// module documentation. The content in `request_body.document_input_config` is ignored, and
+    /// the response is returned without the streamed document.
+    pub async fn translate_document_streaming<R, W>(&self, request_body: &TranslateDocumentRequest, input: R,
+            output: &mut W)
+        -> Result<TranslateDocumentResponse>
+        where R: AsyncRead + Send + Unpin + 'static, W: AsyncWrite + Unpin
+    {
+        request_body.validate()?;
+        let (prefix, suffix) = split_request(request_body)?;
+        let access_token = self.access_token().await?;
+        let url = self.location_url(":translateDocument");
+        let http = &self.http;
+        let send = async {
+            let mut req = build_request(http, &Method::POST, &url, &access_token, None, Some("application/json"))?;
+            *req.body_mut() = Body::wrap_stream(encode_body(prefix, input, suffix));
+            // A compressed response would have to be decompressed before the document can be found in it.
+            req.headers_mut().remove(hyper::header::ACCEPT_ENCODING);
+            #[cfg(feature = "opentelemetry")]
+            otel::inject(req.headers_mut());
+            for interceptor in &http.interceptors {
+                interceptor.on_request(&mut req);
+            }
+            let started = Instant::now();
+            let result = receive(http, req, &url, output).await;
+            let status = result.as_ref().map(|(status, _)| status.as_u16()).ok();
+            crate::metrics::request(&Method::POST, &url, status, started.elapsed(), result.as_ref().err());
+            result.map(|(_, response)| response)
+        };
+        http.until_cancelled(send).await.and_then(|result| result)
+    }
+}
+
+/// Sends `req` and writes the document in the response to `output`.
+async fn receive<W>(http: &HttpClient, req: Request<Body>, url: &str, output: &mut W)
+    -> Result<(hyper::StatusCode, TranslateDocumentResponse)>
+    where W: AsyncWrite + Unpin
+{
+    let started = Instant::now();
+    let (parts, mut body) = http.transport.send(req).await?.into_parts();
+    let res = hyper::Response::from_parts(parts, ());
+    let status = res.status();
+    if status != hyper::StatusCode::OK {
+        let body = hyper::body::to_bytes(body).await.map_err(Error::HyperError)?;
+        for interceptor in &http.interceptors {
+            interceptor.on_response(&res, &body);
+        }
+        trace::response(&Method::POST, url, status, started.elapsed(), 0, body.len());
+        return Err(response_error(status.as_u16(), &body));
+    }
+    let mut extractor = OutputExtractor::default();
+    let mut decoded = Vec::new();
+    let mut received = 0;
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(Error::HyperError)?;
+        received += chunk.len();
+        extractor.feed(&chunk, &mut decoded)?;
+        output.write_all(&decoded).await.map_err(Error::IoError)?;
+        decoded.clear();
+    }
+    output.flush().await.map_err(Error::IoError)?;
+    let rest = Bytes::from(extractor.finish()?);
+    for interceptor in &http.interceptors {
+        interceptor.on_response(&res, &rest);
+    }
+    trace::response(&Method::POST, url, status, started.elapsed(), 0, received);
+    let response = parse_response(http, url, &res, &rest).map_err(Error::SerdeJsonError)?;
+    Ok((status, response))
+}
+
+/// Serializes `request_body` without its document content, split where the encoded content goes.
+fn split_request(request_body: &TranslateDocumentRequest) -> Result<(Bytes, Bytes)> {
+    let mut json = serde_json::to_value(request_body).map_err(Error::SerdeJsonError)?;
+    let input_config = &mut json["documentInputConfig"];
+    input_config.as_object_mut().map(|config| config.remove("gcsSource"));
+    input_config["content"] = CONTENT_PLACEHOLDER.into();
+    let json = json.to_string();
+    let placeholder = serde_json::to_string(CONTENT_PLACEHOLDER).map_err(Error::SerdeJsonError)?;
+    let (prefix, suffix) = json.split_once(&placeholder)
+        .ok_or_else(|| Error::Other("failed to serialize the TranslateDocumentRequest".to_string()))?;
+    Ok((format!("{}\"", prefix).into(), format!("\"{}", suffix).into()))
+}
+
+/// The request body: `prefix`, then the contents of `input` base64-encoded, then `suffix`.
+fn encode_body<R>(prefix: Bytes, input: R, suffix: Bytes) -> impl Stream<Item = std::io::Result<Bytes>>
+    where R: AsyncRead + Send + Unpin + 'static
+{
+    let content = stream::try_unfold((input, vec![0; READ_CHUNK_SIZE]), |(mut input, mut buffer)| async move {
+        // Fill the whole buffer so that the chunk encodes without padding unless it is the last.
+        let mut filled = 0;
+        while filled < buffer.len() {
+            match input.read(&mut buffer[filled..]).await? {
+                0 => break,
+                read => filled += read,
+            }
+        }
+        if filled == 0 {
+            return Ok(None);
+        }
+        let encoded = Bytes::from(STANDARD.encode(&buffer[..filled]));
+        Ok(Some((encoded, (input, buffer))))
+    });
+    stream::once(async { Ok(prefix) }).chain(content).chain(stream::once(async { Ok(suffix) }))
+}
+
+/// Scans a translateDocument response as it arrives, decoding the first of
+/// `documentTranslation.byteStreamOutputs` and keeping the rest of the JSON with an empty string in
+/// its place.
+#[derive(Default)]
+struct OutputExtractor {
+    /// The response without the document.
+    rest: Vec<u8>,
+    /// The objects and arrays enclosing the current position, outermost first.
+    containers: Vec<Container>,
+    state: State,
+    /// Base64 text of the document not decoded yet.
+    pending: Vec<u8>,
+    found: bool,
+}
+
+enum Container {
+    /// An object and the key of its current member.
+    Object { key: Vec<u8>, expecting_key: bool },
+    /// An array and the index of its current element.
+    Array { index: usize },
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Value,
+    /// In an object key, which is collected into the object's `key`.
+    Key { escaped: bool },
+    /// In a string other than the document.
+    String { escaped: bool },
+    /// In the document, after a backslash if `escape` is set, holding the escape sequence so far.
+    Output { escape: Option<Vec<u8>> },
+}
+
+impl OutputExtractor {
+    /// Scans the next part of the response, appending the decoded document in it to `output`.
+    fn feed(&mut self, data: &[u8], output: &mut Vec<u8>) -> Result<()> {
+        for &byte in data {
+            match &mut self.state {
+                State::Value => {
+                    self.rest.push(byte);
+                    match byte {
+                        b'"' => {
+                            let at_output = !self.found && self.at_output();
+                            self.state = match self.containers.last_mut() {
+                                Some(Container::Object { key, expecting_key: true }) => {
+                                    key.clear();
+                                    State::Key { escaped: false }
+                                }
+                                _ if at_output => {
+                                    self.found = true;
+                                    State::Output { escape: None }
+                                }
+                                _ => State::String { escaped: false },
+                            };
+                        }
+                        b'{' => self.containers.push(Container::Object { key: Vec::new(), expecting_key: true }),
+                        b'[' => self.containers.push(Container::Array { index: 0 }),
+                        b'}' | b']' => {
+                            self.containers.pop();
+                        }
+                        b':' => {
+                            if let Some(Container::Object { expecting_key, .. }) = self.containers.last_mut() {
+                                *expecting_key = false;
+                            }
+                        }
+                        b',' => match self.containers.last_mut() {
+                            Some(Container::Object { expecting_key, .. }) => *expecting_key = true,
+                            Some(Container::Array { index }) => *index += 1,
+                            None => {}
+                        },
+                        _ => {}
+                    }
+                }
+                State::Key { escaped } => {
+                    self.rest.push(byte);
+                    if *escaped {
+                        *escaped = false;
+                    } else if byte == b'\\' {
+                        *escaped = true;
+                    } else if byte == b'"' {
+                        self.state = State::Value;
+                        continue;
+                    }
+                    if let Some(Container::Object { key, .. }) = self.containers.last_mut() {
+                        key.push(byte);
+                    }
+                }
+                State::String { escaped } => {
+                    self.rest.push(byte);
+                    if *escaped {
+                        *escaped = false;
+                    } else if byte == b'\\' {
+                        *escaped = true;
+                    } else if byte == b'"' {
+                        self.state = State::Value;
+                    }
+                }
+                State::Output { escape: Some(sequence) } => {
+                    // Base64 is ASCII, so the only escapes are "\/" and "\u00XX".
+                    sequence.push(byte);
+                    let unescaped = match sequence.as_slice() {
+                        [b'/'] => b'/',
+                        [b'u', ..] if sequence.len() < 5 => continue,
+                        [b'u', hex @ ..] => std::str::from_utf8(hex).ok()
+                            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                            .ok_or_else(invalid_document)?,
+                        _ => return Err(invalid_document()),
+                    };
+                    self.pending.push(unescaped);
+                    self.state = State::Output { escape: None };
+                }
+                State::Output { escape: None } => match byte {
+                    b'\\' => self.state = State::Output { escape: Some(Vec::new()) },
+                    b'"' => {
+                        self.rest.push(byte);
+                        STANDARD.decode_vec(&self.pending, output).map_err(|_| invalid_document())?;
+                        self.pending.clear();
+                        self.state = State::Value;
+                    }
+                    _ => self.pending.push(byte),
+                },
+            }
+        }
+        if let State::Output { .. } = self.state {
+            let complete = self.pending.len() / 4 * 4;
+            STANDARD.decode_vec(&self.pending[..complete], output).map_err(|_| invalid_document())?;
+            self.pending.drain(..complete);
+        }
+        Ok(())
+    }
+
+    /// Whether a string starting here is the document.
+    fn at_output(&self) -> bool {
+        matches!(self.containers.as_slice(), [
+            Container::Object { key: root_key, .. },
+            Container::Object { key: translation_key, .. },
+            Container::Array { index: 0 },
+        ] if root_key == b"documentTranslation" && translation_key == b"byteStreamOutputs")
+    }
+
+    /// The response without the document, once all of it has been scanned.
+    fn finish(self) -> Result<Vec<u8>> {
+        match self.state {
+            State::Value if self.containers.is_empty() => Ok(self.rest),
+            _ => Err(Error::Other("truncated translateDocument response".to_string())),
+        }
+    }
+}
+
+fn invalid_document() -> Error {
+    Error::Other("invalid base64 document in translateDocument response".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::transport::HttpTransport;
+
+    fn extract(response: &str, chunk_size: usize) -> (Vec<u8>, String) {
+        let mut extractor = OutputExtractor::default();
+        let mut output = Vec::new();
+        for chunk in response.as_bytes().chunks(chunk_size) {
+            extractor.feed(chunk, &mut output).unwrap();
+        }
+        (output, String::from_utf8(extractor.finish().unwrap()).unwrap())
+    }
+
+    #[test]
+    fn test_output_extractor() {
+        let response = r#"{"model": "m\"}", "documentTranslation": {"mimeType": "application/pdf",
+            "byteStreamOutputs": ["JVBE\/w==", "YQ=="]},
+            "glossaryDocumentTranslation": {"byteStreamOutputs": ["Yg=="]}}"#;
+        for chunk_size in &[1, 3, 7, response.len()] {
+            let (output, rest) = extract(response, *chunk_size);
+            assert_eq!(output, b"%PD\xff");
+            let rest: TranslateDocumentResponse = serde_json::from_str(&rest).unwrap();
+            assert_eq!(rest.document_translation.byte_stream_outputs, vec![Vec::new(), b"a".to_vec()]);
+            assert_eq!(rest.glossary_document_translation.unwrap().byte_stream_outputs, vec![b"b".to_vec()]);
+            assert_eq!(rest.model.as_deref(), Some("m\"}"));
+        }
+
+        let mut extractor = OutputExtractor::default();
+        extractor.feed(br#"{"documentTranslation": {"byteStreamOutputs": ["JVB"#, &mut Vec::new()).unwrap();
+        assert!(extractor.finish().is_err());
+    }
+
+    /// Answers translateDocument with the request's document, sent back a few bytes at a time.
+    struct Echo;
+
+    impl HttpTransport for Echo {
+        fn send(&self, request: Request<Body>) -> BoxFuture<'_, Result<hyper::Response<Body>>> {
+            Box::pin(async move {
+                let body = hyper::body::to_bytes(request.into_body()).await.map_err(Error::HyperError)?;
+                let request: serde_json::Value = serde_json::from_slice(&body).map_err(Error::SerdeJsonError)?;
+                let response = serde_json::json!({
+                    "documentTranslation": {"byteStreamOutputs": [request["documentInputConfig"]["content"]]},
+                    "model": request["targetLanguageCode"],
+                }).to_string();
+                let chunks: Vec<std::io::Result<Bytes>> = response.into_bytes().chunks(5).map(|chunk| Ok(Bytes::copy_from_slice(chunk))).collect();
+                Ok(hyper::Response::new(Body::wrap_stream(stream::iter(chunks))))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_translate_document_streaming() {
+        let client = TranslationClient::new("p", "global", "token").with_transport(Arc::new(Echo));
+        let document: Vec<u8> = (0..READ_CHUNK_SIZE * 2 + 1000).map(|i| (i % 251) as u8).collect();
+        let input_config = DocumentInputConfig {
+            mime_type: Some("application/pdf".to_string()),
+            source: DocumentSource::GcsSource(GcsSource { input_uri: "gs://bucket/ignored.pdf".to_string() }),
+        };
+        let request = TranslateDocumentRequest::builder().target_language_code("de").document_input_config(input_config)
+            .build().unwrap();
+        let mut output = Vec::new();
+        let response = client.translate_document_streaming(&request, std::io::Cursor::new(document.clone()), &mut output)
+            .await.unwrap();
+        assert_eq!(output, document);
+        assert_eq!(response.model.as_deref(), Some("de"));
+        assert_eq!(response.document_translation.byte_stream_outputs, vec![Vec::<u8>::new()]);
+    }
+}
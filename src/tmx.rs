@@ -0,0 +1,195 @@
+//! Recording translations as a TMX translation memory.
+//!
+//! https://www.gala-global.org/tmx-14b
+//!
+//! A `TmxRecorder` set through `TranslationClient::with_tmx_recorder` records the source text,
+//! translation, language pair and time of every string `translate_text` returns, including those
+//! answered by the cache or store, so the output of large jobs can be reused in CAT tools.
+//! `TmxRecorder::to_tmx` writes the recorded pairs as a TMX 1.4 document.
+//!
+//! ```no_run
+//! # async fn run() -> google_translation::Result<()> {
+//! use google_translation::tmx::TmxRecorder;
+//!
+//! let recorder = TmxRecorder::new();
+//! let client = google_translation::TranslationClient::new("my-project", "global", "token")
+//!     .with_tmx_recorder(recorder.clone());
+//! // ... translate ...
+//! recorder.write_to("memory.tmx")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A pair already recorded isn't recorded again. Strings whose source language is neither set on
+//! the request nor detected by the API aren't recorded, since TMX needs the language of every
+//! segment. Glossary translations aren't recorded; the glossary-free translation is.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::*;
+use crate::html::escape;
+
+/// A recorded translation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TmxEntry {
+    pub source: String,
+    pub target: String,
+    pub source_language_code: String,
+    pub target_language_code: String,
+    pub timestamp: SystemTime,
+}
+
+/// Records translations in memory. Clones share the same entries.
+#[derive(Clone, Debug, Default)]
+pub struct TmxRecorder {
+    entries: Arc<Mutex<Vec<TmxEntry>>>,
+}
+
+impl TmxRecorder {
+    pub fn new() -> TmxRecorder {
+        TmxRecorder::default()
+    }
+
+    /// Records `entry` unless the same pair of segments and languages was recorded before.
+    pub fn record(&self, entry: TmxEntry) {
+        let mut entries = self.entries.lock().unwrap();
+        let recorded = entries.iter().any(|e| e.source == entry.source && e.target == entry.target
+            && e.source_language_code == entry.source_language_code && e.target_language_code == entry.target_language_code);
+        if !recorded {
+            entries.push(entry);
+        }
+    }
+
+    /// Records the translations of `response` to `request`.
+    pub(crate) fn record_response(&self, request: &TranslateTextRequest, response: &TranslateTextResponse) {
+        let timestamp = SystemTime::now();
+        for (source, translation) in request.contents.iter().zip(&response.translations) {
+            let source_language_code = match request.source_language_code.as_ref().or(translation.detected_language_code.as_ref()) {
+                Some(code) => code.clone(),
+                None => continue,
+            };
+            self.record(TmxEntry {
+                source: source.clone(),
+                target: translation.translated_text.clone(),
+                source_language_code,
+                target_language_code: request.target_language_code.clone(),
+                timestamp,
+            });
+        }
+    }
+
+    /// The recorded entries, oldest first.
+    pub fn entries(&self) -> Vec<TmxEntry> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// The recorded entries as a TMX 1.4 document, one `<tu>` per entry.
+    pub fn to_tmx(&self) -> String {
+        let entries = self.entries.lock().unwrap();
+        let source_language = match entries.first() {
+            Some(first) if entries.iter().all(|entry| entry.source_language_code == first.source_language_code) =>
+                first.source_language_code.as_str(),
+            _ => "*all*",
+        };
+        let mut tmx = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<tmx version=\"1.4\">\n");
+        tmx.push_str(&format!(
+            "  <header creationtool=\"google-translation\" creationtoolversion=\"{}\" segtype=\"sentence\" \
+            o-tmf=\"google-translation\" adminlang=\"en\" srclang=\"{}\" datatype=\"plaintext\"/>\n",
+            env!("CARGO_PKG_VERSION"), escape(source_language)));
+        tmx.push_str("  <body>\n");
+        for entry in entries.iter() {
+            tmx.push_str(&format!("    <tu creationdate=\"{}\">\n", tmx_date(entry.timestamp)));
+            for (language, segment) in [(&entry.source_language_code, &entry.source), (&entry.target_language_code, &entry.target)] {
+                tmx.push_str(&format!("      <tuv xml:lang=\"{}\"><seg>{}</seg></tuv>\n", escape(language), escape(segment)));
+            }
+            tmx.push_str("    </tu>\n");
+        }
+        tmx.push_str("  </body>\n</tmx>\n");
+        tmx
+    }
+
+    /// Writes `to_tmx()` to the file at `path`.
+    pub fn write_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.to_tmx()).map_err(Error::IoError)
+    }
+}
+
+/// `time` in the TMX date format, "YYYYMMDDThhmmssZ" in UTC.
+pub(crate) fn tmx_date(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (days, seconds) = ((seconds / 86_400) as i64, seconds % 86_400);
+    // Howard Hinnant's civil_from_days.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}{:02}{:02}T{:02}{:02}{:02}Z", year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::transport::MockTransport;
+
+    #[test]
+    fn test_tmx_date() {
+        assert_eq!(tmx_date(UNIX_EPOCH), "19700101T000000Z");
+        assert_eq!(tmx_date(UNIX_EPOCH + Duration::from_secs(1_709_210_096)), "20240229T123456Z");
+    }
+
+    #[tokio::test]
+    async fn test_record_translations() {
+        let transport = Arc::new(MockTransport::new(|request| {
+            let source = request.json()["sourceLanguageCode"].clone();
+            let translations: Vec<_> = request.json()["contents"].as_array().unwrap().iter()
+                .map(|content| match source.as_str() {
+                    Some(_) => serde_json::json!({"translatedText": format!("DE {}", content.as_str().unwrap())}),
+                    None => serde_json::json!({"translatedText": "Hallo & Tschüss", "detectedLanguageCode": "en"}),
+                })
+                .collect();
+            (200, serde_json::json!({"translations": translations}).to_string())
+        }));
+        let recorder = TmxRecorder::new();
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport)
+            .with_tmx_recorder(recorder.clone());
+        let translator = client.translator();
+        translator.translate_one("Hello & bye", "de").await.unwrap();
+        translator.clone().with_source_language("en").translate_many(&["<b>Yes</b>"], "de").await.unwrap();
+        translator.translate_one("Hello & bye", "de").await.unwrap();
+
+        let entries = recorder.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].source.as_str(), entries[0].target.as_str()), ("Hello & bye", "Hallo & Tschüss"));
+        assert_eq!(entries[1].source_language_code, "en");
+        assert_eq!(entries[1].target_language_code, "de");
+
+        let tmx = recorder.to_tmx();
+        let date = tmx_date(entries[0].timestamp);
+        assert!(tmx.contains(r#"srclang="en" datatype="plaintext"/>"#));
+        assert!(tmx.contains(&format!("    <tu creationdate=\"{}\">\n      <tuv xml:lang=\"en\"><seg>Hello &amp; bye</seg></tuv>\n      <tuv xml:lang=\"de\"><seg>Hallo &amp; Tschüss</seg></tuv>\n    </tu>\n", date)));
+        assert!(tmx.contains("<seg>&lt;b&gt;Yes&lt;/b&gt;</seg>"));
+        assert!(tmx.ends_with("  </body>\n</tmx>\n"));
+    }
+}
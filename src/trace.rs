@@ -0,0 +1,113 @@
+//! Diagnostics emitted by the request helpers.
+//!
+//! With the `tracing` feature every request runs inside a `google_translation.request` span and
+//! emits `tracing` events; without it the same information goes to the `log` crate at debug level.
+//! Access tokens are never recorded and API keys are redacted from URLs.
+
+use std::time::Duration;
+
+use hyper::{Method, StatusCode};
+
+const REDACTED_PARAMS: &[&str] = &["key", "access_token"];
+
+/// Replaces the values of credential-carrying query parameters with "REDACTED".
+pub(crate) fn redact_url(url: &str) -> String {
+    let (path, query) = match url.find('?') {
+        Some(i) => (&url[..i], &url[i + 1..]),
+        None => return url.to_string(),
+    };
+    let query = query.split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if REDACTED_PARAMS.contains(&name) => format!("{}=REDACTED", name),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    format!("{}?{}", path, query)
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) fn request_span(method: &Method, url: &str) -> tracing::Span {
+    tracing::debug_span!("google_translation.request", method = %method, url = %redact_url(url))
+}
+
+pub(crate) fn response(method: &Method, url: &str, status: StatusCode, latency: Duration, request_bytes: usize, response_bytes: usize) {
+    #[cfg(feature = "tracing")]
+    {
+        let _ = (method, url);
+        tracing::debug!(status = status.as_u16(), latency_ms = latency.as_millis() as u64, request_bytes, response_bytes, "response received");
+    }
+    #[cfg(not(feature = "tracing"))]
+    debug!("{} {}: {} in {:?} ({} bytes sent, {} bytes received)",
+        method, redact_url(url), status, latency, request_bytes, response_bytes);
+}
+
+pub(crate) fn retry(method: &Method, url: &str, attempt: u32, error: &crate::Error, delay: Duration) {
+    #[cfg(feature = "tracing")]
+    {
+        let _ = (method, url);
+        tracing::warn!(attempt, error = %error, delay_ms = delay.as_millis() as u64, "request failed, retrying");
+    }
+    #[cfg(not(feature = "tracing"))]
+    warn!("{} {} failed (attempt {}): {}, retrying in {:?}", method, redact_url(url), attempt, error, delay);
+}
+
+pub(crate) fn unknown_fields(url: &str, fields: &[String]) {
+    #[cfg(feature = "tracing")]
+    {
+        let _ = url;
+        tracing::warn!(fields = %fields.join(", "), "response has unknown fields");
+    }
+    #[cfg(not(feature = "tracing"))]
+    warn!("response from {} has unknown fields: {}", redact_url(url), fields.join(", "));
+}
+
+pub(crate) fn model_fallback(model: &str, fallback_model: &str, error: &crate::Error) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(model, fallback_model, error = %error, "translation failed, falling back to another model");
+    #[cfg(not(feature = "tracing"))]
+    warn!("translation with {} failed: {}, falling back to {}", model, error, fallback_model);
+}
+
+pub(crate) fn circuit_state(state: crate::circuit_breaker::CircuitState) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(state = ?state, "circuit breaker state changed");
+    #[cfg(not(feature = "tracing"))]
+    warn!("circuit breaker is now {:?}", state);
+}
+
+#[cfg(feature = "native")]
+pub(crate) fn ignored_proxy(error: &crate::Error) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(error = %error, "ignoring HTTPS_PROXY");
+    #[cfg(not(feature = "tracing"))]
+    warn!("ignoring HTTPS_PROXY: {}", error);
+}
+
+#[cfg(feature = "native")]
+pub(crate) fn staging_cleanup_failed(job_uri: &str, error: &crate::Error) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(job_uri, error = %error, "failed to delete staged files");
+    #[cfg(not(feature = "tracing"))]
+    warn!("failed to delete staged files under {}: {}", job_uri, error);
+}
+
+pub(crate) fn placeholders_lost(missing: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(missing, "translation lost placeholders");
+    #[cfg(not(feature = "tracing"))]
+    warn!("translation lost {} placeholders", missing);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_url() {
+        assert_eq!(redact_url("https://translation.googleapis.com/language/translate/v2?key=secret&q=hello"),
+            "https://translation.googleapis.com/language/translate/v2?key=REDACTED&q=hello");
+        assert_eq!(redact_url("https://translation.googleapis.com/v3/projects/p/locations/global/glossaries?pageSize=10"),
+            "https://translation.googleapis.com/v3/projects/p/locations/global/glossaries?pageSize=10");
+    }
+}
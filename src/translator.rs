@@ -0,0 +1,125 @@
+//! A convenience layer for the common case of translating a few strings.
+//!
+//! ```no_run
+//! # async fn run() -> google_translation::Result<()> {
+//! use google_translation::Translator;
+//!
+//! let translator = Translator::global("my-project", "access-token");
+//! let german = translator.translate_one("Hello world", "de").await?;
+//! let language = translator.detect_one("Bonjour").await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Text is sent as `text/plain` and the source language is detected by the API unless
+//! `with_source_language` is set. Use `TranslationClient` directly for anything else.
+
+use crate::*;
+
+/// Translates and detects plain text, returning just the strings.
+#[derive(Clone)]
+pub struct Translator {
+    client: TranslationClient,
+    source_language_code: Option<String>,
+}
+
+impl Translator {
+    pub fn new(client: TranslationClient) -> Translator {
+        Translator { client, source_language_code: None }
+    }
+
+    /// A translator for the `global` location of `project_id`.
+    pub fn global(project_id: &str, access_token: &str) -> Translator {
+        Translator::new(TranslationClient::new(project_id, "global", access_token))
+    }
+
+    /// Translates from `source_language_code` instead of detecting the source language.
+    pub fn with_source_language(mut self, source_language_code: impl AsRef<str>) -> Translator {
+        self.source_language_code = Some(source_language_code.as_ref().to_string());
+        self
+    }
+
+    pub fn client(&self) -> &TranslationClient {
+        &self.client
+    }
+
+    /// Translates `text` into `target_language_code`.
+    pub async fn translate_one(&self, text: &str, target_language_code: impl AsRef<str>) -> Result<String> {
+        let mut translations = self.translate_many(&[text], target_language_code).await?;
+        translations.pop()
+            .ok_or_else(|| Error::Other("translateText returned no translations".to_string()))
+    }
+
+    /// Translates each of `texts` into `target_language_code`, returning the translations in the same order.
+    pub async fn translate_many<S: AsRef<str>>(&self, texts: &[S], target_language_code: impl AsRef<str>) -> Result<Vec<String>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let request = TranslateTextRequest {
+            contents: texts.iter().map(|text| text.as_ref().to_string()).collect(),
+            mime_type: Some(MimeType::Plain),
+            source_language_code: self.source_language_code.clone(),
+            target_language_code: target_language_code.as_ref().to_string(),
+            model: None,
+            glossary_config: None,
+            labels: None,
+            transliteration_config: None,
+        };
+        let response = self.client.translate_text(&request).await?;
+        if response.translations.len() != texts.len() {
+            return Err(Error::Other(format!("translateText returned {} translations for {} strings",
+                response.translations.len(), texts.len())));
+        }
+        Ok(response.translations.into_iter().map(|translation| translation.translated_text).collect())
+    }
+
+    /// Returns the most likely language code of `text`.
+    pub async fn detect_one(&self, text: &str) -> Result<String> {
+        let request = DetectLanguageRequest {
+            model: None,
+            mime_type: Some(MimeType::Plain),
+            labels: None,
+            content: text.to_string(),
+        };
+        let response = self.client.detect_language(&request).await?;
+        response.languages.into_iter().next()
+            .map(|language| language.language_code)
+            .ok_or_else(|| Error::Other("detectLanguage returned no languages".to_string()))
+    }
+}
+
+impl TranslationClient {
+    /// A `Translator` sharing this client's connection pool and credentials.
+    pub fn translator(&self) -> Translator {
+        Translator::new(self.clone())
+    }
+}
+
+impl From<TranslationClient> for Translator {
+    fn from(client: TranslationClient) -> Translator {
+        Translator::new(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::serve;
+
+    #[tokio::test]
+    async fn test_translate_one() {
+        let (endpoint, requests) = serve(200, r#"{"translations": [{"translatedText": "Hallo Welt"}]}"#).await;
+        let translator = Translator::new(TranslationClient::new("p", "global", "token").with_endpoint(&endpoint))
+            .with_source_language("en");
+        assert_eq!(translator.translate_one("Hello world", "de").await.unwrap(), "Hallo Welt");
+        assert_eq!(requests.lock().unwrap()[0], "POST /v3beta1/projects/p/locations/global:translateText");
+        assert!(translator.translate_many(&["a", "b"], "de").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_detect_one() {
+        let (endpoint, _) = serve(200, r#"{"languages": [{"languageCode": "fr", "confidence": 0.98}]}"#).await;
+        let translator = TranslationClient::new("p", "global", "token").with_endpoint(&endpoint).translator();
+        assert_eq!(translator.detect_one("Bonjour").await.unwrap(), "fr");
+    }
+}
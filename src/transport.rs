@@ -0,0 +1,432 @@
+//! The HTTP layer under `TranslationClient`.
+//!
+//! Every request the client makes, after headers, retries and interceptors have been applied, is
+//! handed to an `HttpTransport`. The default is `HyperTransport`, a pooled hyper client over TLS,
+//! or with the `wasm` feature `FetchTransport`, which sends requests with the JavaScript fetch API.
+//! `TranslationClient::with_transport` swaps in another one, such as `MockTransport`, which answers
+//! requests from memory so code using the client can be tested without a network, or, with the
+//! `reqwest-backend` feature, `ReqwestTransport`, which shares an application's `reqwest::Client`.
+//! `TranslationClient::with_connection_config` tunes the pool of the default transport, for
+//! example to keep HTTP/2 connections alive or to bound the connections per host.
+//!
+//! ```
+//! # use std::sync::Arc;
+//! # use google_translation::*;
+//! use google_translation::transport::MockTransport;
+//!
+//! # async fn run() -> Result<()> {
+//! let transport = Arc::new(MockTransport::with_response(200, r#"{"translations": [{"translatedText": "Hallo"}]}"#));
+//! let client = TranslationClient::new("my-project", "global", "token").with_transport(transport.clone());
+//! let request = TranslateTextRequest::builder().content("Hello").target("de").build()?;
+//! assert_eq!(client.translate_text(&request).await?.translations[0].translated_text, "Hallo");
+//! assert_eq!(transport.requests()[0].uri,
+//!     "https://translation.googleapis.com/v3beta1/projects/my-project/locations/global:translateText");
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(feature = "native")]
+use std::collections::HashMap;
+#[cfg(feature = "native")]
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[cfg(feature = "native")]
+use futures::stream::StreamExt;
+use hyper::{Body, Method, Request, Response};
+#[cfg(feature = "native")]
+use hyper::Uri;
+use hyper::header::HeaderMap;
+#[cfg(feature = "native")]
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::{JsCast, JsValue, prelude::wasm_bindgen};
+#[cfg(feature = "wasm")]
+use wasm_bindgen_futures::JsFuture;
+
+use crate::{BoxFuture, Error, Result};
+#[cfg(feature = "native")]
+use crate::{HttpsClient, new_https_client, new_https_client_with_config};
+#[cfg(feature = "native")]
+use crate::proxy::Proxy;
+
+/// Sends a single HTTP request and returns the response. Failed requests are only retried by the
+/// client if the error is retryable, see `Error::is_retryable`.
+pub trait HttpTransport: Send + Sync {
+    fn send(&self, request: Request<Body>) -> BoxFuture<'_, Result<Response<Body>>>;
+}
+
+/// How a `HyperTransport` manages its connections. The defaults are hyper's, except that the
+/// number of connections isn't limited.
+///
+/// With the `rustls` feature HTTP/2 is negotiated with every server that supports it, as Google's
+/// do, and all requests to a host share one connection. With `native-tls` HTTP/2 is only
+/// negotiated if `http2_only` is set, and requests otherwise use HTTP/1.1 and a connection each.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnectionConfig {
+    /// Speak HTTP/2 on every connection, and offer only HTTP/2 when negotiating TLS.
+    pub http2_only: bool,
+    /// How often to send HTTP/2 pings to keep connections alive, if at all.
+    pub http2_keep_alive_interval: Option<Duration>,
+    /// How long to wait for the answer to a ping before closing the connection.
+    pub http2_keep_alive_timeout: Duration,
+    /// Whether to ping connections that have no requests in flight.
+    pub http2_keep_alive_while_idle: bool,
+    /// How long an idle connection is kept in the pool, or forever if None.
+    pub pool_idle_timeout: Option<Duration>,
+    /// The most idle connections kept per host, or no limit if None.
+    pub pool_max_idle_per_host: Option<usize>,
+    /// The most requests sent to one host at a time, or no limit if None. Further requests wait
+    /// for one to finish. Over HTTP/1.1 every request in flight needs a connection, so this also
+    /// bounds the connections per host.
+    pub max_connections_per_host: Option<usize>,
+    /// How often to send TCP keepalive probes on idle connections, if at all.
+    pub tcp_keepalive: Option<Duration>,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        ConnectionConfig {
+            http2_only: false,
+            http2_keep_alive_interval: None,
+            http2_keep_alive_timeout: Duration::from_secs(20),
+            http2_keep_alive_while_idle: false,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            pool_max_idle_per_host: None,
+            max_connections_per_host: None,
+            tcp_keepalive: None,
+        }
+    }
+}
+
+/// Sends requests with a pooled hyper client. The connection pool is shared by clones of the client
+/// the transport belongs to.
+#[cfg(feature = "native")]
+#[derive(Clone)]
+pub struct HyperTransport {
+    client: HttpsClient,
+    host_limit: Option<Arc<HostLimit>>,
+}
+
+#[cfg(feature = "native")]
+impl HyperTransport {
+    /// A transport that connects through the proxy named by HTTPS_PROXY, if any.
+    pub fn new() -> HyperTransport {
+        HyperTransport { client: new_https_client(), host_limit: None }
+    }
+
+    /// A transport that connects through `proxy`, or directly if it is None.
+    pub fn with_proxy(proxy: Option<Proxy>) -> HyperTransport {
+        HyperTransport::with_config(proxy, ConnectionConfig::default())
+    }
+
+    /// A transport that connects through `proxy`, or directly if it is None, and manages its
+    /// connections as `config` says.
+    pub fn with_config(proxy: Option<Proxy>, config: ConnectionConfig) -> HyperTransport {
+        HyperTransport {
+            client: new_https_client_with_config(proxy, &config),
+            host_limit: config.max_connections_per_host.map(|max| Arc::new(HostLimit { max, hosts: Mutex::default() })),
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl Default for HyperTransport {
+    fn default() -> Self {
+        HyperTransport::new()
+    }
+}
+
+#[cfg(feature = "native")]
+impl HttpTransport for HyperTransport {
+    fn send(&self, request: Request<Body>) -> BoxFuture<'_, Result<Response<Body>>> {
+        Box::pin(async move {
+            let permit = match &self.host_limit {
+                Some(limit) => Some(limit.acquire(request.uri()).await),
+                None => None,
+            };
+            let response = self.client.request(request).await.map_err(Error::HyperError)?;
+            Ok(match permit {
+                // The request is in flight until its response body has been read.
+                Some(permit) => response.map(|body| Body::wrap_stream(body.map(move |chunk| {
+                    let _permit = &permit;
+                    chunk
+                }))),
+                None => response,
+            })
+        })
+    }
+}
+
+/// Limits the requests in flight to each host, see `ConnectionConfig::max_connections_per_host`.
+#[cfg(feature = "native")]
+struct HostLimit {
+    max: usize,
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+#[cfg(feature = "native")]
+impl HostLimit {
+    async fn acquire(&self, uri: &Uri) -> OwnedSemaphorePermit {
+        let host = uri.authority().map(|authority| authority.to_string()).unwrap_or_default();
+        let semaphore = self.hosts.lock().unwrap().entry(host).or_insert_with(|| Arc::new(Semaphore::new(self.max))).clone();
+        // The semaphore is never closed.
+        semaphore.acquire_owned().await.unwrap()
+    }
+}
+
+/// Sends requests with a `reqwest::Client`, so an application can share its client's connection pool
+/// and configuration (proxies, timeouts, TLS roots) with the translation client.
+#[cfg(feature = "reqwest-backend")]
+#[derive(Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "reqwest-backend")]
+impl ReqwestTransport {
+    pub fn new() -> ReqwestTransport {
+        ReqwestTransport::default()
+    }
+
+    pub fn with_client(client: reqwest::Client) -> ReqwestTransport {
+        ReqwestTransport { client }
+    }
+}
+
+#[cfg(feature = "reqwest-backend")]
+impl HttpTransport for ReqwestTransport {
+    fn send(&self, request: Request<Body>) -> BoxFuture<'_, Result<Response<Body>>> {
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let body = hyper::body::to_bytes(body).await.map_err(Error::HyperError)?;
+            let res = self.client.request(parts.method, parts.uri.to_string())
+                .headers(parts.headers)
+                .body(body)
+                .send()
+                .await
+                .map_err(Error::ReqwestError)?;
+            let mut response = Response::builder().status(res.status()).version(res.version());
+            if let Some(headers) = response.headers_mut() {
+                *headers = res.headers().clone();
+            }
+            let body = res.bytes().await.map_err(Error::ReqwestError)?;
+            response.body(Body::from(body)).map_err(|e| Error::Other(format!("invalid response: {}", e)))
+        })
+    }
+}
+
+/// Sends requests with the JavaScript fetch API, in browsers and in workers such as Cloudflare
+/// Workers. The default transport with the `wasm` feature. The browser manages the connections and
+/// TLS, and decompresses responses itself.
+#[cfg(feature = "wasm")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FetchTransport;
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+extern "C" {
+    /// The global fetch function, which workers have as well as windows.
+    fn fetch(request: &web_sys::Request) -> js_sys::Promise;
+}
+
+/// Response headers fetch has already applied: it hands over the body decompressed.
+#[cfg(feature = "wasm")]
+const APPLIED_HEADERS: &[hyper::header::HeaderName] = &[hyper::header::CONTENT_ENCODING, hyper::header::CONTENT_LENGTH];
+
+#[cfg(feature = "wasm")]
+impl HttpTransport for FetchTransport {
+    fn send(&self, request: Request<Body>) -> BoxFuture<'_, Result<Response<Body>>> {
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let body = hyper::body::to_bytes(body).await.map_err(Error::HyperError)?;
+            let headers = web_sys::Headers::new().map_err(fetch_error)?;
+            for (name, value) in &parts.headers {
+                let value = value.to_str().map_err(|_| Error::InvalidRequest(format!("the {} header isn't ASCII", name)))?;
+                headers.append(name.as_str(), value).map_err(fetch_error)?;
+            }
+            let init = web_sys::RequestInit::new();
+            init.set_method(parts.method.as_str());
+            init.set_headers(&headers);
+            if !body.is_empty() {
+                init.set_body(&js_sys::Uint8Array::from(&body[..]));
+            }
+            let request = web_sys::Request::new_with_str_and_init(&parts.uri.to_string(), &init).map_err(fetch_error)?;
+            let response: web_sys::Response = JsFuture::from(fetch(&request)).await.map_err(fetch_error)?.unchecked_into();
+            let data = JsFuture::from(response.array_buffer().map_err(fetch_error)?).await.map_err(fetch_error)?;
+
+            let mut builder = Response::builder().status(response.status());
+            for entry in response.headers().entries() {
+                let entry: js_sys::Array = entry.map_err(fetch_error)?.unchecked_into();
+                if let (Some(name), Some(value)) = (entry.get(0).as_string(), entry.get(1).as_string()) {
+                    if !APPLIED_HEADERS.iter().any(|applied| name.eq_ignore_ascii_case(applied.as_str())) {
+                        builder = builder.header(name, value);
+                    }
+                }
+            }
+            builder.body(Body::from(js_sys::Uint8Array::new(&data).to_vec()))
+                .map_err(|e| Error::Other(format!("invalid response: {}", e)))
+        })
+    }
+}
+
+/// An `Error::FetchError` with the message of a JavaScript exception.
+#[cfg(feature = "wasm")]
+fn fetch_error(error: JsValue) -> Error {
+    match error.dyn_ref::<js_sys::Error>() {
+        Some(error) => Error::FetchError(String::from(error.message())),
+        None => Error::FetchError(format!("{:?}", error)),
+    }
+}
+
+/// A request received by a `MockTransport`.
+#[derive(Clone, Debug)]
+pub struct MockRequest {
+    pub method: Method,
+    pub uri: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl MockRequest {
+    /// The body parsed as JSON, or `Value::Null` if it isn't JSON.
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_slice(&self.body).unwrap_or(serde_json::Value::Null)
+    }
+}
+
+type Respond = dyn Fn(&MockRequest) -> (u16, String) + Send + Sync;
+
+/// A transport that records requests and answers them with canned responses, without any I/O.
+pub struct MockTransport {
+    respond: Box<Respond>,
+    requests: Mutex<Vec<MockRequest>>,
+}
+
+impl MockTransport {
+    /// Answers each request with the status and body `respond` returns for it.
+    pub fn new<F>(respond: F) -> MockTransport
+        where F: Fn(&MockRequest) -> (u16, String) + Send + Sync + 'static
+    {
+        MockTransport { respond: Box::new(respond), requests: Mutex::new(Vec::new()) }
+    }
+
+    /// Answers every request with `status` and `body`.
+    pub fn with_response(status: u16, body: impl Into<String>) -> MockTransport {
+        let body = body.into();
+        MockTransport::new(move |_| (status, body.clone()))
+    }
+
+    /// The requests received so far, oldest first.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl HttpTransport for MockTransport {
+    fn send(&self, request: Request<Body>) -> BoxFuture<'_, Result<Response<Body>>> {
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let body = hyper::body::to_bytes(body).await.map_err(Error::HyperError)?;
+            let request = MockRequest {
+                method: parts.method,
+                uri: parts.uri.to_string(),
+                headers: parts.headers,
+                body: body.to_vec(),
+            };
+            let (status, body) = (self.respond)(&request);
+            self.requests.lock().unwrap().push(request);
+            Response::builder()
+                .status(status)
+                .body(Body::from(body))
+                .map_err(|e| Error::Other(format!("invalid mock response: {}", e)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::*;
+
+    #[tokio::test]
+    async fn test_mock_transport() {
+        let transport = Arc::new(MockTransport::new(|request| match request.json()["content"].as_str() {
+            Some("") => (400, r#"{"error": {"code": 400, "message": "Empty content.", "status": "INVALID_ARGUMENT"}}"#.to_string()),
+            _ => (200, r#"{"languages": [{"languageCode": "de", "confidence": 1.0}]}"#.to_string()),
+        }));
+        let client = TranslationClient::new("p", "global", "token").with_transport(transport.clone());
+        let response = client.detect_language(&DetectLanguageRequest::builder().content("Hallo").build().unwrap()).await.unwrap();
+        assert_eq!(response.languages[0].language_code, "de");
+        let error = client.detect_language(&DetectLanguageRequest::builder().content("").build().unwrap()).await.unwrap_err();
+        assert_eq!(error.code(), Some(ErrorCode::InvalidArgument));
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].method, Method::POST);
+        assert_eq!(requests[0].uri, "https://translation.googleapis.com/v3beta1/projects/p/locations/global:detectLanguage");
+        assert_eq!(requests[0].headers["authorization"], "Bearer token");
+        assert_eq!(requests[0].json(), serde_json::json!({ "content": "Hallo" }));
+    }
+
+    #[tokio::test]
+    async fn test_connection_config() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use hyper::service::{make_service_fn, service_fn};
+
+        // Counts the requests in flight, which each take a moment, and records the most at once.
+        let counts = Arc::new((AtomicUsize::new(0), AtomicUsize::new(0)));
+        let server_counts = counts.clone();
+        let make_service = make_service_fn(move |_| {
+            let counts = server_counts.clone();
+            async move {
+                Ok::<_, hyper::Error>(service_fn(move |req: Request<Body>| {
+                    let counts = counts.clone();
+                    async move {
+                        assert_eq!(req.version(), hyper::Version::HTTP_2);
+                        let in_flight = counts.0.fetch_add(1, Ordering::SeqCst) + 1;
+                        counts.1.fetch_max(in_flight, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        counts.0.fetch_sub(1, Ordering::SeqCst);
+                        Ok::<_, hyper::Error>(Response::new(Body::from("{}")))
+                    }
+                }))
+            }
+        });
+        let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).http2_only(true).serve(make_service);
+        let endpoint = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        let config = ConnectionConfig {
+            http2_only: true,
+            max_connections_per_host: Some(2),
+            ..ConnectionConfig::default()
+        };
+        let client = TranslationClient::new("p", "global", "token")
+            .with_endpoint(&endpoint)
+            .with_transport(Arc::new(HyperTransport::with_config(None, config)));
+        let calls = (0..6).map(|i| {
+            let client = client.clone();
+            async move { client.cancel_operation(&format!("projects/p/locations/global/operations/{}", i)).await }
+        });
+        for result in futures::future::join_all(calls).await {
+            result.unwrap();
+        }
+        assert_eq!(counts.1.load(Ordering::SeqCst), 2);
+    }
+
+    #[cfg(feature = "reqwest-backend")]
+    #[tokio::test]
+    async fn test_reqwest_transport() {
+        let (endpoint, requests) = crate::tests::serve(404, r#"{"error": {"code": 404, "message": "Not found.", "status": "NOT_FOUND"}}"#).await;
+        let client = TranslationClient::new("p", "global", "token")
+            .with_endpoint(&endpoint)
+            .with_transport(Arc::new(ReqwestTransport::new()));
+        let error = client.get_glossary("projects/p/locations/global/glossaries/g").await.unwrap_err();
+        assert!(error.is_not_found());
+        assert_eq!(*requests.lock().unwrap(), vec!["GET /v3beta1/projects/p/locations/global/glossaries/g"]);
+    }
+}
@@ -0,0 +1,274 @@
+//! The legacy v2 API (Cloud Translation - Basic), authenticated with an API key.
+//!
+//! https://cloud.google.com/translate/docs/reference/rest/v2/translate
+//!
+//! v2 has no projects, locations, glossaries or long-running operations. It is here so programs
+//! using an API key can move to `TranslationClient` one call at a time.
+//!
+//! ```no_run
+//! # async fn run() -> google_translation::Result<()> {
+//! use google_translation::v2;
+//! let request = v2::TranslateRequest::new(vec!["Hello world".to_string()], "de");
+//! let response = v2::translate("my-api-key", &request).await?;
+//! println!("{}", response.translations[0].translated_text);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Arc;
+
+use serde::{Serialize, Deserialize};
+
+use crate::{HttpClient, Result, get_request, post_request};
+use crate::auth::SecretToken;
+use crate::transport::HttpTransport;
+
+const DEFAULT_ENDPOINT: &str = "https://translation.googleapis.com";
+
+/// A v2 client bound to an API key.
+#[derive(Clone)]
+pub struct Client {
+    http: HttpClient,
+    api_key: SecretToken,
+    endpoint: String,
+}
+
+impl Client {
+    pub fn new(api_key: &str) -> Client {
+        Client {
+            http: HttpClient::new(),
+            api_key: SecretToken::from(api_key),
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+        }
+    }
+
+    /// Sends requests to `endpoint` instead of "https://translation.googleapis.com",
+    /// see `TranslationClient::with_endpoint`.
+    pub fn with_endpoint(mut self, endpoint: &str) -> Client {
+        self.endpoint = endpoint.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sends requests with `transport`, see `TranslationClient::with_transport`.
+    pub fn with_transport(mut self, transport: Arc<dyn HttpTransport>) -> Client {
+        self.http.transport = transport;
+        self
+    }
+
+    fn url(&self, suffix: &str) -> String {
+        format!("{}/language/translate/v2{}", self.endpoint, suffix)
+    }
+
+    fn key_param(&self) -> String {
+        serde_urlencoded::to_string([("key", self.api_key.expose())]).unwrap()
+    }
+
+    /// Translates input text, returning translated text.
+    pub async fn translate(&self, request_body: &TranslateRequest) -> Result<TranslateResponse> {
+        let url = format!("{}?{}", self.url(""), self.key_param());
+        let response = post_request::<_, Data<_>>(&self.http, &url, &SecretToken::default(), request_body).await?;
+        crate::metrics::characters("v2.translate", request_body.q.iter().map(|q| q.chars().count()).sum());
+        Ok(response.data)
+    }
+
+    /// Detects the language of text within a request.
+    pub async fn detect(&self, request_body: &DetectRequest) -> Result<DetectResponse> {
+        let url = format!("{}?{}", self.url("/detect"), self.key_param());
+        let response = post_request::<_, Data<_>>(&self.http, &url, &SecretToken::default(), request_body).await?;
+        crate::metrics::characters("v2.detect", request_body.q.iter().map(|q| q.chars().count()).sum());
+        Ok(response.data)
+    }
+
+    /// Returns a list of supported languages for translation.
+    pub async fn languages(&self, query_params: &LanguagesQueryParams) -> Result<LanguagesResponse> {
+        let params = KeyedLanguagesQueryParams {
+            key: self.api_key.expose(),
+            target: query_params.target.as_deref(),
+            model: query_params.model.as_deref(),
+        };
+        get_request::<_, Data<_>>(&self.http, &self.url("/languages"), &SecretToken::default(), &params).await.map(|response| response.data)
+    }
+}
+
+/// Every v2 response wraps its payload in a "data" object.
+#[derive(Deserialize)]
+struct Data<T> {
+    data: T,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TranslateRequest {
+    /// Required. The input text to translate. Repeat this parameter to perform translation operations on multiple text inputs.
+    pub q: Vec<String>,
+    /// Required. The language to use for translation of the input text, set to one of the language codes listed in Language Support.
+    pub target: String,
+    /// Optional. The format of the source text, in either HTML (default) or plain-text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<Format>,
+    /// Optional. The language of the source text, set to one of the language codes listed in Language Support.
+    /// If the source language is not specified, the API will attempt to detect the source language automatically
+    /// and return it within the response.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Optional. The translation model. Can be either base to use the Phrase-Based Machine Translation (PBMT) model,
+    /// or nmt to use the Neural Machine Translation (NMT) model. If omitted, then nmt is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+impl TranslateRequest {
+    pub fn new(q: Vec<String>, target: impl Into<String>) -> TranslateRequest {
+        TranslateRequest { q, target: target.into(), format: None, source: None, model: None }
+    }
+}
+
+/// The format of the source text.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Html,
+    Text,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslateResponse {
+    /// Contains list of translation results of supplied text, in the order of `TranslateRequest::q`.
+    pub translations: Vec<Translation>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Translation {
+    /// Text translated into the target language.
+    pub translated_text: String,
+    /// The source language of the initial request, detected automatically, if no source language was passed
+    /// within the initial request. If the source language was passed, auto-detection of the language will not
+    /// occur and this field will be omitted.
+    pub detected_source_language: Option<String>,
+    /// The translation model used. Only present if a model was given in the request.
+    pub model: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DetectRequest {
+    /// The input text upon which to perform language detection. Repeat this parameter to perform language detection on multiple text inputs.
+    pub q: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DetectResponse {
+    /// The detected languages of each input text, in the order of `DetectRequest::q`.
+    pub detections: Vec<Vec<Detection>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Detection {
+    /// The detected language.
+    pub language: String,
+    /// Deprecated by the API. Indicates whether the language detection result is reliable.
+    #[serde(default)]
+    pub is_reliable: bool,
+    /// The confidence of the detection result for this language.
+    #[serde(default)]
+    pub confidence: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct LanguagesQueryParams {
+    /// Optional. The target language code for the results. If specified, then the language names are returned
+    /// in the name field of the response, localized in the target language.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Optional. The translation model of the supported languages. Can be either base or nmt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+#[derive(Serialize)]
+struct KeyedLanguagesQueryParams<'a> {
+    key: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<&'a str>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LanguagesResponse {
+    /// The list of supported languages.
+    pub languages: Vec<Language>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Language {
+    /// Supported language code, generally consisting of its ISO 639-1 identifier, for example 'en', 'ja'.
+    pub language: String,
+    /// Human-readable name of the language localized in the target language. Only present if a target was requested.
+    pub name: Option<String>,
+}
+
+/// Translates input text with the API key `api_key`, returning translated text.
+pub async fn translate(api_key: &str, request_body: &TranslateRequest) -> Result<TranslateResponse> {
+    Client::new(api_key).translate(request_body).await
+}
+
+/// Detects the language of text with the API key `api_key`.
+pub async fn detect(api_key: &str, request_body: &DetectRequest) -> Result<DetectResponse> {
+    Client::new(api_key).detect(request_body).await
+}
+
+/// Returns a list of supported languages for translation, using the API key `api_key`.
+pub async fn languages(api_key: &str, query_params: &LanguagesQueryParams) -> Result<LanguagesResponse> {
+    Client::new(api_key).languages(query_params).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{serve, serve_fn};
+
+    #[tokio::test]
+    async fn test_translate() {
+        let (endpoint, requests) = serve(200, r#"{"data": {"translations": [{"translatedText": "Hallo", "detectedSourceLanguage": "en"}]}}"#).await;
+        let client = Client::new("k&y").with_endpoint(&endpoint);
+        let mut request = TranslateRequest::new(vec!["Hello".to_string()], "de");
+        request.format = Some(Format::Text);
+        assert_eq!(serde_json::to_value(&request).unwrap(), serde_json::json!({ "q": ["Hello"], "target": "de", "format": "text" }));
+        let response = client.translate(&request).await.unwrap();
+        assert_eq!(response.translations[0].translated_text, "Hallo");
+        assert_eq!(response.translations[0].detected_source_language.as_deref(), Some("en"));
+        assert_eq!(*requests.lock().unwrap(), vec!["POST /language/translate/v2?key=k%26y"]);
+    }
+
+    #[tokio::test]
+    async fn test_detect_and_languages() {
+        let (endpoint, requests) = serve_fn(|uri| {
+            let body = if uri.starts_with("/language/translate/v2/detect") {
+                r#"{"data": {"detections": [[{"language": "zh-CN", "isReliable": false, "confidence": 1}]]}}"#
+            } else {
+                r#"{"data": {"languages": [{"language": "de", "name": "Deutsch"}]}}"#
+            };
+            (200, body.to_string())
+        }).await;
+        let client = Client::new("key").with_endpoint(&endpoint);
+        let response = client.detect(&DetectRequest { q: vec!["我是谁".to_string()] }).await.unwrap();
+        assert_eq!(response.detections[0][0].language, "zh-CN");
+        let params = LanguagesQueryParams { target: Some("de".to_string()), model: None };
+        let response = client.languages(&params).await.unwrap();
+        assert_eq!(response.languages[0].name.as_deref(), Some("Deutsch"));
+        assert_eq!(requests.lock().unwrap()[1], "GET /language/translate/v2/languages?key=key&target=de");
+    }
+
+    #[tokio::test]
+    async fn test_error() {
+        let (endpoint, _) = serve(400, r#"{"error": {"code": 400, "message": "API key not valid.", "errors": [], "status": "INVALID_ARGUMENT"}}"#).await;
+        let client = Client::new("bad").with_endpoint(&endpoint);
+        let error = client.detect(&DetectRequest { q: vec!["x".to_string()] }).await.unwrap_err();
+        assert_eq!(error.code(), Some(crate::ErrorCode::InvalidArgument));
+    }
+}